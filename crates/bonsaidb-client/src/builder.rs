@@ -7,7 +7,7 @@ use bonsaidb_core::api;
 use bonsaidb_core::api::ApiName;
 use bonsaidb_core::networking::CURRENT_PROTOCOL_VERSION;
 #[cfg(not(target_arch = "wasm32"))]
-use fabruic::Certificate;
+use fabruic::{Certificate, KeyPair};
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::runtime::Handle;
 use url::Url;
@@ -32,6 +32,8 @@ pub struct Builder<AsyncMode> {
     #[cfg(not(target_arch = "wasm32"))]
     certificate: Option<fabruic::Certificate>,
     #[cfg(not(target_arch = "wasm32"))]
+    client_key_pair: Option<fabruic::KeyPair>,
+    #[cfg(not(target_arch = "wasm32"))]
     tokio: Option<Handle>,
     mode: PhantomData<AsyncMode>,
 }
@@ -48,6 +50,8 @@ impl<AsyncMode> Builder<AsyncMode> {
             #[cfg(not(target_arch = "wasm32"))]
             certificate: None,
             #[cfg(not(target_arch = "wasm32"))]
+            client_key_pair: None,
+            #[cfg(not(target_arch = "wasm32"))]
             tokio: None,
             mode: PhantomData,
         }
@@ -87,6 +91,17 @@ impl<AsyncMode> Builder<AsyncMode> {
         self
     }
 
+    /// Presents the certificate of `key_pair` to the server while connecting,
+    /// allowing a server configured to verify client certificates to
+    /// authenticate this client. Only supported with BonsaiDb protocol-based
+    /// connections.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_client_certificate(mut self, key_pair: KeyPair) -> Self {
+        self.client_key_pair = Some(key_pair);
+        self
+    }
+
     /// Overrides the protocol version. Only for testing purposes.
     #[cfg(feature = "test-util")]
     #[allow(clippy::missing_const_for_fn)]
@@ -122,6 +137,8 @@ impl<AsyncMode> Builder<AsyncMode> {
             #[cfg(not(target_arch = "wasm32"))]
             self.certificate,
             #[cfg(not(target_arch = "wasm32"))]
+            self.client_key_pair,
+            #[cfg(not(target_arch = "wasm32"))]
             self.tokio.or_else(|| Handle::try_current().ok()),
         )
     }
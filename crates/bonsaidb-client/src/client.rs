@@ -7,17 +7,18 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use bonsaidb_core::admin::{Admin, ADMIN_DATABASE_NAME};
+use bonsaidb_core::admin::{Admin, DatabaseQuota, ADMIN_DATABASE_NAME};
 use bonsaidb_core::api::{self, Api, ApiName};
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::arc_bytes::OwnedBytes;
 use bonsaidb_core::connection::{
-    AsyncStorageConnection, Database, HasSession, IdentityReference, Session,
+    AsyncStorageConnection, Database, DatabaseUsage, HasSession, IdentityReference, Session,
 };
 use bonsaidb_core::networking::{
     AlterUserPermissionGroupMembership, AlterUserRoleMembership, AssumeIdentity, CreateDatabase,
-    CreateUser, DeleteDatabase, DeleteUser, ListAvailableSchemas, ListDatabases, LogOutSession,
-    MessageReceived, Payload, UnregisterSubscriber, CURRENT_PROTOCOL_VERSION,
+    CreateUser, DeleteDatabase, DeleteUser, ListAvailableSchemas, ListDatabaseUsage, ListDatabases,
+    LogOutSession, MessageReceived, Payload, SetDatabaseQuota, UnregisterSubscriber,
+    CURRENT_PROTOCOL_VERSION,
 };
 use bonsaidb_core::permissions::Permissions;
 use bonsaidb_core::schema::{Nameable, Schema, SchemaName, SchemaSummary, Schematic};
@@ -289,6 +290,8 @@ impl AsyncClient {
             #[cfg(not(target_arch = "wasm32"))]
             None,
             #[cfg(not(target_arch = "wasm32"))]
+            None,
+            #[cfg(not(target_arch = "wasm32"))]
             Handle::try_current().ok(),
         )
     }
@@ -308,6 +311,7 @@ impl AsyncClient {
     /// to recover and reconnect, each component of the apps built can adopt a
     /// "retry-to-recover" design, or "abort-and-fail" depending on how critical
     /// the database is to operation.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new_from_parts(
         url: Url,
         protocol_version: &'static str,
@@ -315,6 +319,7 @@ impl AsyncClient {
         connect_timeout: Option<Duration>,
         request_timeout: Option<Duration>,
         #[cfg(not(target_arch = "wasm32"))] certificate: Option<fabruic::Certificate>,
+        #[cfg(not(target_arch = "wasm32"))] client_key_pair: Option<fabruic::KeyPair>,
         #[cfg(not(target_arch = "wasm32"))] tokio: Option<Handle>,
     ) -> Result<Self, Error> {
         let subscribers = SubscriberMap::default();
@@ -354,6 +359,7 @@ impl AsyncClient {
                 connection,
                 protocol_version,
                 certificate,
+                client_key_pair,
                 custom_apis,
                 tokio,
             )),
@@ -374,6 +380,7 @@ impl AsyncClient {
         server: ConnectionInfo,
         protocol_version: &'static str,
         certificate: Option<fabruic::Certificate>,
+        client_key_pair: Option<fabruic::KeyPair>,
         custom_apis: HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>,
         tokio: Option<Handle>,
     ) -> Self {
@@ -387,6 +394,7 @@ impl AsyncClient {
                 server,
                 protocol_version,
                 certificate,
+                client_key_pair,
                 request_receiver,
                 Arc::new(custom_apis),
                 connection_counter.clone(),
@@ -699,6 +707,60 @@ impl AsyncStorageConnection for AsyncClient {
         Ok(self.send_api_request(&ListDatabases).await?)
     }
 
+    async fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&SetDatabaseQuota {
+            name: name.to_string(),
+            quota,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, bonsaidb_core::Error> {
+        Ok(self.send_api_request(&ListDatabaseUsage).await?)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<bonsaidb_core::connection::AuthenticationTokenSummary>, bonsaidb_core::Error>
+    {
+        Ok(self
+            .send_api_request(&bonsaidb_core::networking::ListAuthenticationTokens {
+                identity: identity.into_owned(),
+            })
+            .await?)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&bonsaidb_core::networking::RevokeAuthenticationToken { id })
+            .await?;
+        Ok(())
+    }
+
+    async fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        Ok(self
+            .send_api_request(&bonsaidb_core::networking::ListSessions)
+            .await?)
+    }
+
+    async fn revoke_session(
+        &self,
+        id: bonsaidb_core::connection::SessionId,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&bonsaidb_core::networking::RevokeSession { id })
+            .await?;
+        Ok(())
+    }
+
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         Ok(self.send_api_request(&ListAvailableSchemas).await?)
     }
@@ -736,7 +798,49 @@ impl AsyncStorageConnection for AsyncClient {
             .await?)
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    async fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<bonsaidb_core::admin::TotpSecret, bonsaidb_core::Error> {
+        Ok(self
+            .send_api_request(&bonsaidb_core::networking::EnrollUserTotp {
+                user: user.name()?.into_owned(),
+            })
+            .await?)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        Ok(self
+            .send_api_request(&bonsaidb_core::networking::ConfirmUserTotp {
+                user: user.name()?.into_owned(),
+                code,
+            })
+            .await?)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        Ok(self
+            .send_api_request(&bonsaidb_core::networking::RemoveUserTotp {
+                user: user.name()?.into_owned(),
+            })
+            .await?)
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     async fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
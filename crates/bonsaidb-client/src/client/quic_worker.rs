@@ -6,7 +6,7 @@ use std::time::Duration;
 use bonsaidb_core::api::ApiName;
 use bonsaidb_core::networking::Payload;
 use bonsaidb_utils::fast_async_lock;
-use fabruic::{self, Certificate, Endpoint};
+use fabruic::{self, Certificate, Endpoint, KeyPair};
 use flume::Receiver;
 use futures::StreamExt;
 use url::Url;
@@ -24,6 +24,7 @@ pub(super) async fn reconnecting_client_loop(
     mut server: ConnectionInfo,
     protocol_version: &'static str,
     certificate: Option<Certificate>,
+    client_key_pair: Option<KeyPair>,
     request_receiver: Receiver<PendingRequest>,
     custom_apis: Arc<HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>>,
     connection_counter: Arc<AtomicU32>,
@@ -44,6 +45,7 @@ pub(super) async fn reconnecting_client_loop(
             &server.url,
             protocol_version,
             certificate.as_ref(),
+            client_key_pair.as_ref(),
             request,
             &request_receiver,
             custom_apis.clone(),
@@ -66,19 +68,22 @@ async fn connect_and_process(
     url: &Url,
     protocol_version: &str,
     certificate: Option<&Certificate>,
+    client_key_pair: Option<&KeyPair>,
     initial_request: PendingRequest,
     request_receiver: &Receiver<PendingRequest>,
     custom_apis: Arc<HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>>,
     connect_timeout: Duration,
 ) -> Result<(), (Option<PendingRequest>, Option<Error>)> {
-    let (_connection, payload_sender, payload_receiver) =
-        match tokio::time::timeout(connect_timeout, connect(url, certificate, protocol_version))
-            .await
-        {
-            Ok(Ok(result)) => result,
-            Ok(Err(err)) => return Err((Some(initial_request), Some(err))),
-            Err(_) => return Err((Some(initial_request), Some(Error::connect_timeout()))),
-        };
+    let (_connection, payload_sender, payload_receiver) = match tokio::time::timeout(
+        connect_timeout,
+        connect(url, certificate, client_key_pair, protocol_version),
+    )
+    .await
+    {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => return Err((Some(initial_request), Some(err))),
+        Err(_) => return Err((Some(initial_request), Some(Error::connect_timeout()))),
+    };
 
     let outstanding_requests = OutstandingRequestMapHandle::default();
     let request_processor = tokio::spawn(process(
@@ -155,6 +160,7 @@ pub async fn process(
 async fn connect(
     url: &Url,
     certificate: Option<&Certificate>,
+    client_key_pair: Option<&KeyPair>,
     protocol_version: &str,
 ) -> Result<
     (
@@ -169,6 +175,7 @@ async fn connect(
         .set_max_idle_timeout(None)
         .map_err(|err| Error::Core(bonsaidb_core::Error::other("quic", err)))?;
     endpoint.set_protocols([protocol_version.as_bytes().to_vec()]);
+    endpoint.set_client_key_pair(client_key_pair.cloned());
     let endpoint = endpoint
         .build()
         .map_err(|err| Error::Core(bonsaidb_core::Error::other("quic", err)))?;
@@ -3,7 +3,8 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::networking::{
-    CreateSubscriber, Publish, PublishToAll, SubscribeTo, UnsubscribeFrom,
+    CreateSubscriber, Publish, PublishToAll, SubscribeTo, SubscribeToPattern, UnsubscribeFrom,
+    UnsubscribeFromPattern,
 };
 use bonsaidb_core::pubsub::{AsyncPubSub, AsyncSubscriber, Receiver};
 
@@ -100,6 +101,28 @@ impl AsyncSubscriber for AsyncRemoteSubscriber {
         Ok(())
     }
 
+    async fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        self.client
+            .send_api_request(&SubscribeToPattern {
+                database: self.database.to_string(),
+                subscriber_id: self.id,
+                pattern: pattern.to_string(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        self.client
+            .send_api_request(&UnsubscribeFromPattern {
+                database: self.database.to_string(),
+                subscriber_id: self.id,
+                pattern: pattern.to_string(),
+            })
+            .await?;
+        Ok(())
+    }
+
     fn receiver(&self) -> &Receiver {
         &self.receiver
     }
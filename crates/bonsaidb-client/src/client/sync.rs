@@ -16,9 +16,10 @@ use bonsaidb_core::networking::{
     AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction, AssumeIdentity,
     Compact, CompactCollection, CompactKeyValueStore, Count, CreateDatabase, CreateSubscriber,
     CreateUser, DeleteDatabase, DeleteDocs, DeleteUser, ExecuteKeyOperation, Get, GetMultiple,
-    LastTransactionId, List, ListAvailableSchemas, ListDatabases, ListExecutedTransactions,
-    ListHeaders, Publish, PublishToAll, Query, QueryWithDocs, Reduce, ReduceGrouped, SubscribeTo,
-    UnsubscribeFrom, CURRENT_PROTOCOL_VERSION,
+    LastTransactionId, List, ListAvailableSchemas, ListDatabaseUsage, ListDatabases,
+    ListExecutedTransactions, ListHeaders, Publish, PublishToAll, Query, QueryWithDocs, Reduce,
+    ReduceGrouped, SetDatabaseQuota, SubscribeTo, SubscribeToPattern, UnsubscribeFrom,
+    UnsubscribeFromPattern, CURRENT_PROTOCOL_VERSION,
 };
 use bonsaidb_core::pubsub::{AsyncSubscriber, PubSub, Receiver, Subscriber};
 use bonsaidb_core::schema::view::map;
@@ -167,6 +168,57 @@ impl StorageConnection for BlockingClient {
         Ok(self.send_api_request(&ListDatabases)?)
     }
 
+    fn set_database_quota(
+        &self,
+        name: &str,
+        quota: bonsaidb_core::admin::DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&SetDatabaseQuota {
+            name: name.to_string(),
+            quota,
+        })?;
+        Ok(())
+    }
+
+    fn list_database_usage(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::DatabaseUsage>, bonsaidb_core::Error> {
+        Ok(self.send_api_request(&ListDatabaseUsage)?)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<bonsaidb_core::connection::AuthenticationTokenSummary>, bonsaidb_core::Error>
+    {
+        Ok(
+            self.send_api_request(&bonsaidb_core::networking::ListAuthenticationTokens {
+                identity: identity.into_owned(),
+            })?,
+        )
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&bonsaidb_core::networking::RevokeAuthenticationToken { id })?;
+        Ok(())
+    }
+
+    fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        Ok(self.send_api_request(&bonsaidb_core::networking::ListSessions)?)
+    }
+
+    fn revoke_session(
+        &self,
+        id: bonsaidb_core::connection::SessionId,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.send_api_request(&bonsaidb_core::networking::RevokeSession { id })?;
+        Ok(())
+    }
+
     fn list_available_schemas(
         &self,
     ) -> Result<Vec<bonsaidb_core::schema::SchemaSummary>, bonsaidb_core::Error> {
@@ -202,7 +254,49 @@ impl StorageConnection for BlockingClient {
         })?)
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    fn enroll_user_totp<'user, U: bonsaidb_core::schema::Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<bonsaidb_core::admin::TotpSecret, bonsaidb_core::Error> {
+        use bonsaidb_core::networking::EnrollUserTotp;
+
+        Ok(self.send_api_request(&EnrollUserTotp {
+            user: user.name()?.into_owned(),
+        })?)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    fn confirm_user_totp<'user, U: bonsaidb_core::schema::Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        use bonsaidb_core::networking::ConfirmUserTotp;
+
+        Ok(self.send_api_request(&ConfirmUserTotp {
+            user: user.name()?.into_owned(),
+            code,
+        })?)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    fn remove_user_totp<'user, U: bonsaidb_core::schema::Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        use bonsaidb_core::networking::RemoveUserTotp;
+
+        Ok(self.send_api_request(&RemoveUserTotp {
+            user: user.name()?.into_owned(),
+        })?)
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
@@ -624,6 +718,28 @@ impl Subscriber for BlockingRemoteSubscriber {
         Ok(())
     }
 
+    fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        self.0
+            .client
+            .send_blocking_api_request(&SubscribeToPattern {
+                database: self.0.database.to_string(),
+                subscriber_id: self.0.id,
+                pattern: pattern.to_string(),
+            })?;
+        Ok(())
+    }
+
+    fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        self.0
+            .client
+            .send_blocking_api_request(&UnsubscribeFromPattern {
+                database: self.0.database.to_string(),
+                subscriber_id: self.0.id,
+                pattern: pattern.to_string(),
+            })?;
+        Ok(())
+    }
+
     fn receiver(&self) -> &Receiver {
         AsyncSubscriber::receiver(&self.0)
     }
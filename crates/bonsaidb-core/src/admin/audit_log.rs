@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::connection::{AsyncConnection, Connection, Identity, SessionId};
+use crate::document::{CollectionDocument, Emit};
+use crate::key::time::TimestampAsNanoseconds;
+use crate::schema::{Collection, CollectionMapReduce, View, ViewMapResult, ViewSchema};
+
+/// An action performed by a session, recorded in the admin database when the
+/// storage's audit log is enabled.
+///
+/// An entry is recorded each time an audited action's permissions are checked.
+/// The [`outcome`](Self::outcome) records whether the session was allowed to
+/// perform the action. An allowed action may still fail for other reasons,
+/// such as a document conflict.
+#[derive(Collection, Clone, Serialize, Deserialize, Debug)]
+#[collection(name = "audit-log", authority = "bonsaidb", views = [AuditLogByTimestamp, AuditLogByResource], core = crate)]
+pub struct AuditLogEntry {
+    /// The time the action was performed.
+    pub timestamp: TimestampAsNanoseconds,
+    /// The id of the session that performed the action, if the session was
+    /// assigned an id.
+    pub session_id: Option<SessionId>,
+    /// The identity the session was authenticated as, if any.
+    pub identity: Option<Identity>,
+    /// The name of the action, such as `Database.Document.Insert`.
+    pub action: String,
+    /// The name of the resource the action was performed on.
+    pub resource: String,
+    /// Whether the session was permitted to perform the action.
+    pub outcome: AuditOutcome,
+}
+
+/// The outcome of an audited permission check.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuditOutcome {
+    /// The session was allowed to perform the action.
+    Allowed,
+    /// The session was denied permission to perform the action.
+    Denied,
+}
+
+impl AuditLogEntry {
+    /// Returns up to `limit` entries recorded between `start` and `end`,
+    /// inclusive, ordered by the time they were recorded.
+    ///
+    /// Multiple entries can share a timestamp, so passing a `start` after the
+    /// last entry's timestamp to read the next page may skip entries. Instead,
+    /// pass the last entry's timestamp as the next page's `start`, and skip
+    /// the returned entries whose `(timestamp, id)` is less than or equal to
+    /// the last entry's. If a page contains no entries that haven't been seen,
+    /// more than `limit` entries share a timestamp and a larger `limit` is
+    /// needed to continue.
+    pub fn recorded_between<Database: Connection>(
+        start: TimestampAsNanoseconds,
+        end: TimestampAsNanoseconds,
+        limit: u32,
+        admin: &Database,
+    ) -> Result<Vec<CollectionDocument<Self>>, crate::Error> {
+        let mappings = admin
+            .view::<AuditLogByTimestamp>()
+            .with_key_range(start..=end)
+            .limit(limit)
+            .query_with_collection_docs()?;
+        Ok(sorted_by_timestamp(mappings.documents.into_values()))
+    }
+
+    /// Returns up to `limit` entries recorded between `start` and `end`,
+    /// inclusive, ordered by the time they were recorded.
+    ///
+    /// Multiple entries can share a timestamp, so passing a `start` after the
+    /// last entry's timestamp to read the next page may skip entries. Instead,
+    /// pass the last entry's timestamp as the next page's `start`, and skip
+    /// the returned entries whose `(timestamp, id)` is less than or equal to
+    /// the last entry's. If a page contains no entries that haven't been seen,
+    /// more than `limit` entries share a timestamp and a larger `limit` is
+    /// needed to continue.
+    pub async fn recorded_between_async<Database: AsyncConnection>(
+        start: TimestampAsNanoseconds,
+        end: TimestampAsNanoseconds,
+        limit: u32,
+        admin: &Database,
+    ) -> Result<Vec<CollectionDocument<Self>>, crate::Error> {
+        let mappings = admin
+            .view::<AuditLogByTimestamp>()
+            .with_key_range(start..=end)
+            .limit(limit)
+            .query_with_collection_docs()
+            .await?;
+        Ok(sorted_by_timestamp(mappings.documents.into_values()))
+    }
+
+    /// Returns up to `limit` entries for actions performed on `resource` that
+    /// were recorded between `start` and `end`, inclusive, ordered by the time
+    /// they were recorded.
+    pub fn for_resource<Database: Connection>(
+        resource: &str,
+        start: TimestampAsNanoseconds,
+        end: TimestampAsNanoseconds,
+        limit: u32,
+        admin: &Database,
+    ) -> Result<Vec<CollectionDocument<Self>>, crate::Error> {
+        let mappings = admin
+            .view::<AuditLogByResource>()
+            .with_key_range(resource_range(resource, start, end))
+            .limit(limit)
+            .query_with_collection_docs()?;
+        Ok(sorted_by_timestamp(mappings.documents.into_values()))
+    }
+
+    /// Returns up to `limit` entries for actions performed on `resource` that
+    /// were recorded between `start` and `end`, inclusive, ordered by the time
+    /// they were recorded.
+    pub async fn for_resource_async<Database: AsyncConnection>(
+        resource: &str,
+        start: TimestampAsNanoseconds,
+        end: TimestampAsNanoseconds,
+        limit: u32,
+        admin: &Database,
+    ) -> Result<Vec<CollectionDocument<Self>>, crate::Error> {
+        let mappings = admin
+            .view::<AuditLogByResource>()
+            .with_key_range(resource_range(resource, start, end))
+            .limit(limit)
+            .query_with_collection_docs()
+            .await?;
+        Ok(sorted_by_timestamp(mappings.documents.into_values()))
+    }
+}
+
+fn resource_range(
+    resource: &str,
+    start: TimestampAsNanoseconds,
+    end: TimestampAsNanoseconds,
+) -> std::ops::RangeInclusive<(String, TimestampAsNanoseconds)> {
+    (resource.to_string(), start)..=(resource.to_string(), end)
+}
+
+fn sorted_by_timestamp(
+    entries: impl Iterator<Item = CollectionDocument<AuditLogEntry>>,
+) -> Vec<CollectionDocument<AuditLogEntry>> {
+    let mut entries = entries.collect::<Vec<_>>();
+    entries.sort_by_key(|entry| (entry.contents.timestamp, entry.header.id));
+    entries
+}
+
+/// Indexes [`AuditLogEntry`]s by the time they were recorded.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = AuditLogEntry, key = TimestampAsNanoseconds, value = (), name = "by-timestamp", core = crate)]
+#[view_schema(core = crate)]
+pub struct AuditLogByTimestamp;
+
+impl CollectionMapReduce for AuditLogByTimestamp {
+    fn map<'doc>(&self, document: CollectionDocument<AuditLogEntry>) -> ViewMapResult<'doc, Self> {
+        document.header.emit_key(document.contents.timestamp)
+    }
+}
+
+/// Indexes [`AuditLogEntry`]s by the name of the resource acted upon and the
+/// time they were recorded.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = AuditLogEntry, key = (String, TimestampAsNanoseconds), value = (), name = "by-resource", core = crate)]
+#[view_schema(core = crate)]
+pub struct AuditLogByResource;
+
+impl CollectionMapReduce for AuditLogByResource {
+    fn map<'doc>(&self, document: CollectionDocument<AuditLogEntry>) -> ViewMapResult<'doc, Self> {
+        document
+            .header
+            .emit_key((document.contents.resource, document.contents.timestamp))
+    }
+}
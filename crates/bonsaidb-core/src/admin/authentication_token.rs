@@ -1,15 +1,96 @@
 use serde::{Deserialize, Serialize};
 
-use crate::connection::{IdentityId, SensitiveString};
+use crate::connection::{AuthenticationTokenSummary, IdentityId, SensitiveString};
+use crate::document::{CollectionDocument, Emit};
 use crate::key::time::TimestampAsNanoseconds;
-use crate::schema::Collection;
+use crate::permissions::Statement;
+use crate::schema::{Collection, CollectionMapReduce, View, ViewMapResult, ViewSchema};
 
+/// A private token that can be used to authenticate as an identity.
 #[derive(Collection, Clone, Serialize, Deserialize, Debug)]
-#[collection(name = "authentication-tokens", authority = "bonsaidb", core = crate)]
+#[collection(name = "authentication-tokens", authority = "bonsaidb", views = [AuthenticationTokensByIdentity], core = crate)]
 pub struct AuthenticationToken {
+    /// The identity this token authenticates as.
     pub identity: IdentityId,
+    /// The private token.
     pub token: SensitiveString,
+    /// The time this token was created.
     pub created_at: TimestampAsNanoseconds,
+    /// The time after which this token can no longer be used to authenticate.
+    /// If `None`, the token never expires.
+    #[serde(default)]
+    pub expires_at: Option<TimestampAsNanoseconds>,
+    /// The time this token was last used to authenticate successfully.
+    #[serde(default)]
+    pub last_used_at: Option<TimestampAsNanoseconds>,
+    /// The statements sessions authenticated with this token are restricted
+    /// to. A session authenticated with a scoped token is only allowed to
+    /// perform actions permitted by both the identity's permissions and the
+    /// scope. If `None`, the session has the identity's full permissions.
+    #[serde(default)]
+    pub scope: Option<Vec<Statement>>,
+}
+
+/// Options for creating an [`AuthenticationToken`].
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct AuthenticationTokenOptions {
+    /// The time after which the token can no longer be used to authenticate.
+    pub expires_at: Option<TimestampAsNanoseconds>,
+    /// The statements sessions authenticated with the token are restricted
+    /// to.
+    pub scope: Option<Vec<Statement>>,
+}
+
+impl AuthenticationTokenOptions {
+    /// Sets [`Self::expires_at`](Self#structfield.expires_at) to `expires_at` and returns self.
+    pub const fn expires_at(mut self, expires_at: TimestampAsNanoseconds) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Sets [`Self::scope`](Self#structfield.scope) to `statements` and returns self.
+    pub fn scope(mut self, statements: impl Into<Vec<Statement>>) -> Self {
+        self.scope = Some(statements.into());
+        self
+    }
+}
+
+impl AuthenticationToken {
+    /// Returns true if this token has expired as of `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: TimestampAsNanoseconds) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+}
+
+impl From<CollectionDocument<AuthenticationToken>> for AuthenticationTokenSummary {
+    fn from(token: CollectionDocument<AuthenticationToken>) -> Self {
+        Self {
+            id: token.header.id,
+            identity: token.contents.identity,
+            created_at: token.contents.created_at,
+            expires_at: token.contents.expires_at,
+            last_used_at: token.contents.last_used_at,
+            scope: token.contents.scope.clone(),
+        }
+    }
+}
+
+/// Indexes [`AuthenticationToken`]s by the identity they authenticate as.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = AuthenticationToken, key = IdentityId, value = (), name = "by-identity", core = crate)]
+#[view_schema(core = crate)]
+pub struct AuthenticationTokensByIdentity;
+
+impl CollectionMapReduce for AuthenticationTokensByIdentity {
+    fn map<'doc>(
+        &self,
+        document: CollectionDocument<AuthenticationToken>,
+    ) -> ViewMapResult<'doc, Self> {
+        document.header.emit_key(document.contents.identity)
+    }
 }
 
 #[cfg(feature = "token-authentication")]
@@ -18,7 +99,7 @@ mod implementation {
     use rand::{thread_rng, Rng};
     use zeroize::Zeroize;
 
-    use super::AuthenticationToken;
+    use super::{AuthenticationToken, AuthenticationTokenOptions};
     use crate::connection::{
         AsyncConnection, Connection, IdentityId, IdentityReference, SensitiveString,
         TokenChallengeAlgorithm,
@@ -28,7 +109,7 @@ mod implementation {
     use crate::schema::SerializedCollection;
 
     impl AuthenticationToken {
-        fn random(identity: IdentityId) -> (u64, Self) {
+        fn random(identity: IdentityId, options: &AuthenticationTokenOptions) -> (u64, Self) {
             const ALPHABET: &[u8] =
                 b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_-.+/#";
             let mut rng = thread_rng();
@@ -45,6 +126,9 @@ mod implementation {
                     identity,
                     token,
                     created_at: TimestampAsNanoseconds::now(),
+                    expires_at: options.expires_at,
+                    last_used_at: None,
+                    scope: options.scope.clone(),
                 },
             )
         }
@@ -52,12 +136,22 @@ mod implementation {
         pub fn create<C: Connection>(
             identity: &IdentityReference<'_>,
             database: &C,
+        ) -> Result<CollectionDocument<Self>, crate::Error> {
+            Self::create_with_options(identity, &AuthenticationTokenOptions::default(), database)
+        }
+
+        /// Creates a token for `identity` that expires and is restricted
+        /// according to `options`.
+        pub fn create_with_options<C: Connection>(
+            identity: &IdentityReference<'_>,
+            options: &AuthenticationTokenOptions,
+            database: &C,
         ) -> Result<CollectionDocument<Self>, crate::Error> {
             let identity_id = identity
                 .resolve(database)?
                 .ok_or(crate::Error::InvalidCredentials)?;
             loop {
-                let (id, token) = Self::random(identity_id);
+                let (id, token) = Self::random(identity_id, options);
                 match token.insert_into(&id, database) {
                     Err(err) if err.error.conflicting_document::<Self>().is_some() => continue,
                     other => break other.map_err(|err| err.error),
@@ -68,13 +162,28 @@ mod implementation {
         pub async fn create_async<C: AsyncConnection>(
             identity: IdentityReference<'_>,
             database: &C,
+        ) -> Result<CollectionDocument<Self>, crate::Error> {
+            Self::create_with_options_async(
+                identity,
+                &AuthenticationTokenOptions::default(),
+                database,
+            )
+            .await
+        }
+
+        /// Creates a token for `identity` that expires and is restricted
+        /// according to `options`.
+        pub async fn create_with_options_async<C: AsyncConnection>(
+            identity: IdentityReference<'_>,
+            options: &AuthenticationTokenOptions,
+            database: &C,
         ) -> Result<CollectionDocument<Self>, crate::Error> {
             let identity_id = identity
                 .resolve_async(database)
                 .await?
                 .ok_or(crate::Error::InvalidCredentials)?;
             loop {
-                let (id, token) = Self::random(identity_id);
+                let (id, token) = Self::random(identity_id, options);
                 match token.insert_into_async(&id, database).await {
                     Err(err) if err.error.conflicting_document::<Self>().is_some() => continue,
                     other => break other.map_err(|err| err.error),
@@ -151,5 +260,3 @@ mod implementation {
         }
     }
 }
-
-impl AuthenticationToken {}
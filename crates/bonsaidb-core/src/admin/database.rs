@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use serde::{Deserialize, Serialize};
 
 use crate::define_basic_unique_mapped_view;
@@ -12,6 +14,77 @@ pub struct Database {
     pub name: String,
     /// The schema defining the database.
     pub schema: SchemaName,
+    /// The limits on how much data the database can store.
+    #[serde(default)]
+    pub quota: DatabaseQuota,
+}
+
+/// Limits on how much data a database can store. A limit of `None` is
+/// unlimited.
+///
+/// Quotas are checked before a transaction is applied or a key-value entry is
+/// set. Operations that would cause the database to exceed its quota fail with
+/// [`Error::QuotaExceeded`](crate::Error::QuotaExceeded). Operations that only
+/// remove data are always allowed. The contents of updated and overwritten
+/// documents are counted as if they were new documents.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DatabaseQuota {
+    /// The maximum number of bytes of document contents and key-value entries
+    /// stored in the database. View indexes and storage overhead are not
+    /// counted.
+    pub max_bytes: Option<u64>,
+    /// The maximum number of documents stored across all collections in the
+    /// database.
+    pub max_documents: Option<u64>,
+}
+
+impl DatabaseQuota {
+    /// Returns a quota with no limits.
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_bytes: None,
+            max_documents: None,
+        }
+    }
+
+    /// Sets [`Self::max_bytes`](Self#structfield.max_bytes) to `max_bytes` and returns self.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sets [`Self::max_documents`](Self#structfield.max_documents) to `max_documents` and returns self.
+    #[must_use]
+    pub const fn with_max_documents(mut self, max_documents: u64) -> Self {
+        self.max_documents = Some(max_documents);
+        self
+    }
+
+    /// Returns true if this quota has no limits.
+    #[must_use]
+    pub const fn is_unlimited(&self) -> bool {
+        self.max_bytes.is_none() && self.max_documents.is_none()
+    }
+}
+
+/// A limit of a [`DatabaseQuota`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+pub enum QuotaLimit {
+    /// The [`DatabaseQuota::max_bytes`] limit.
+    Bytes(u64),
+    /// The [`DatabaseQuota::max_documents`] limit.
+    Documents(u64),
+}
+
+impl Display for QuotaLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bytes(bytes) => write!(f, "{bytes} bytes"),
+            Self::Documents(documents) => write!(f, "{documents} documents"),
+        }
+    }
 }
 
 define_basic_unique_mapped_view!(
@@ -1,5 +1,7 @@
 use crate::schema::Schema;
 
+#[doc(hidden)]
+pub mod audit_log;
 #[doc(hidden)]
 pub mod authentication_token;
 #[doc(hidden)]
@@ -9,17 +11,23 @@ pub mod group;
 #[doc(hidden)]
 pub mod role;
 #[doc(hidden)]
+pub mod scheduled_task;
+#[doc(hidden)]
 pub mod user;
 
-pub use self::authentication_token::AuthenticationToken;
-pub use self::database::Database;
+pub use self::audit_log::{AuditLogEntry, AuditOutcome};
+pub use self::authentication_token::{
+    AuthenticationToken, AuthenticationTokenOptions, AuthenticationTokensByIdentity,
+};
+pub use self::database::{Database, DatabaseQuota, QuotaLimit};
 pub use self::group::PermissionGroup;
 pub use self::role::Role;
-pub use self::user::User;
+pub use self::scheduled_task::ScheduledTaskRun;
+pub use self::user::{TotpSecret, User, UserTotp};
 
 /// The BonsaiDb administration schema.
 #[derive(Debug, Schema)]
-#[schema(name = "bonsaidb-admin", authority = "khonsulabs", collections = [Database, PermissionGroup, Role, User, AuthenticationToken], core = crate)]
+#[schema(name = "bonsaidb-admin", authority = "khonsulabs", collections = [Database, PermissionGroup, Role, User, AuthenticationToken, ScheduledTaskRun, AuditLogEntry], core = crate)]
 pub struct Admin;
 
 /// The name of the admin database.
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::key::time::TimestampAsNanoseconds;
+use crate::schema::Collection;
+
+/// The most recent execution of a server's scheduled task. The document's id
+/// is the name of the task.
+#[derive(Collection, Clone, Serialize, Deserialize, Debug)]
+#[collection(name = "scheduled-task-runs", authority = "bonsaidb", primary_key = String, core = crate)]
+pub struct ScheduledTaskRun {
+    /// The time the task was most recently executed.
+    pub last_run: TimestampAsNanoseconds,
+}
@@ -1,9 +1,11 @@
+use arc_bytes::serde::Bytes;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::admin::{group, role};
 use crate::connection::{
-    AsyncStorageConnection, Connection, IdentityReference, SensitiveString, StorageConnection,
+    AsyncStorageConnection, Connection, IdentityReference, SensitiveBytes, SensitiveString,
+    StorageConnection,
 };
 use crate::define_basic_unique_mapped_view;
 use crate::document::{CollectionDocument, Emit, KeyId};
@@ -29,6 +31,14 @@ pub struct User {
     /// records are updated in the meantime.
     #[serde(default)]
     pub argon_hash: Option<SensitiveString>,
+
+    /// The user's time-based one-time password (TOTP) enrollment, if any.
+    ///
+    /// Like `argon_hash`, this field is not feature gated to prevent losing
+    /// enrollments if the `totp-authentication` feature is disabled and then
+    /// re-enabled.
+    #[serde(default)]
+    pub totp: Option<UserTotp>,
 }
 
 impl User {
@@ -98,6 +108,164 @@ impl User {
     }
 }
 
+/// A time-based one-time password (TOTP) second factor enrolled for a
+/// [`User`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserTotp {
+    /// The secret shared with the user's authenticator.
+    pub secret: TotpSecret,
+    /// True once a code generated from `secret` has been verified. Password
+    /// authentication only requires a code after the enrollment has been
+    /// confirmed.
+    pub confirmed: bool,
+    /// The time step of the most recently accepted code. Codes from this time
+    /// step or earlier are rejected, preventing a code from being used twice.
+    #[serde(default)]
+    pub last_used_step: Option<u64>,
+}
+
+/// A secret used to generate time-based one-time passwords.
+///
+/// Codes are generated as specified by [RFC 6238][rfc] using HMAC-SHA1,
+/// 30-second time steps, and 6 digits, which is what most authenticator apps
+/// expect.
+///
+/// [rfc]: https://www.rfc-editor.org/rfc/rfc6238
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct TotpSecret(pub SensitiveBytes);
+
+impl TotpSecret {
+    /// The number of digits in each code.
+    pub const DIGITS: usize = 6;
+    /// The number of seconds each code is valid for.
+    pub const STEP_SECONDS: u64 = 30;
+
+    /// Returns the secret encoded using unpadded base32, the format
+    /// authenticator apps accept when a secret is entered manually.
+    #[must_use]
+    pub fn to_base32(&self) -> SensitiveString {
+        const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+        let mut encoded = String::with_capacity((self.0.len() * 8 + 4) / 5);
+        let mut buffer = 0_u16;
+        let mut bits = 0;
+        for byte in self.0.iter().copied() {
+            buffer = (buffer << 8) | u16::from(byte);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(char::from(ALPHABET[usize::from((buffer >> bits) & 0x1f)]));
+            }
+        }
+        if bits > 0 {
+            encoded.push(char::from(
+                ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)],
+            ));
+        }
+        SensitiveString(encoded)
+    }
+
+    /// Returns an `otpauth://` URI that authenticator apps can import, usually
+    /// by scanning it as a QR code.
+    #[must_use]
+    pub fn provisioning_uri(&self, issuer: &str, account: &str) -> SensitiveString {
+        let issuer = uri_encode(issuer);
+        SensitiveString(format!(
+            "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+            account = uri_encode(account),
+            secret = self.to_base32().0,
+            digits = Self::DIGITS,
+            period = Self::STEP_SECONDS,
+        ))
+    }
+
+    /// Returns the time step that contains `unix_timestamp`, measured in
+    /// seconds.
+    #[must_use]
+    pub const fn step_at(unix_timestamp: u64) -> u64 {
+        unix_timestamp / Self::STEP_SECONDS
+    }
+
+    /// Returns the code for the time step `step`.
+    #[cfg(feature = "totp-authentication")]
+    #[must_use]
+    pub fn code_at(&self, step: u64) -> String {
+        use hmac::{Hmac, Mac};
+
+        let mut mac =
+            Hmac::<sha1::Sha1>::new_from_slice(&self.0).expect("hmac accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = usize::from(hash[hash.len() - 1] & 0xf);
+        let truncated = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!("{:0width$}", truncated % 1_000_000, width = Self::DIGITS)
+    }
+
+    /// Returns the code for the current time.
+    #[cfg(feature = "totp-authentication")]
+    #[must_use]
+    pub fn current_code(&self) -> String {
+        self.code_at(Self::step_at(unix_timestamp()))
+    }
+
+    /// Verifies `code` against the current time, allowing for one time step
+    /// of clock drift in either direction. Codes from `last_used_step` or
+    /// earlier are rejected.
+    ///
+    /// Returns the time step `code` belongs to if it is valid. Every candidate
+    /// code is compared in constant time, so the time taken doesn't reveal how
+    /// closely `code` matched.
+    #[cfg(feature = "totp-authentication")]
+    #[must_use]
+    pub fn verify(&self, code: &str, last_used_step: Option<u64>) -> Option<u64> {
+        let current = Self::step_at(unix_timestamp());
+        (current.saturating_sub(1)..=current + 1)
+            .filter(|step| last_used_step.map_or(true, |last| *step > last))
+            .fold(None, |found, step| {
+                let matched = constant_time_eq(self.code_at(step).as_bytes(), code.as_bytes());
+                found.or(matched.then_some(step))
+            })
+    }
+}
+
+impl From<Vec<u8>> for TotpSecret {
+    fn from(secret: Vec<u8>) -> Self {
+        Self(SensitiveBytes(Bytes::from(secret)))
+    }
+}
+
+/// Returns true if `a` and `b` are equal, taking time that only depends on
+/// their lengths.
+#[cfg(feature = "totp-authentication")]
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(feature = "totp-authentication")]
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
 impl NamedCollection for User {
     type ByNameView = ByName;
 }
@@ -110,3 +278,32 @@ define_basic_unique_mapped_view!(
     String,
     |document: CollectionDocument<User>| { document.header.emit_key(document.contents.username) }
 );
+
+#[cfg(all(test, feature = "totp-authentication"))]
+mod tests {
+    use super::TotpSecret;
+
+    #[test]
+    fn rfc6238_test_vectors() {
+        let secret = TotpSecret::from(b"12345678901234567890".to_vec());
+        for (timestamp, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+        ] {
+            assert_eq!(secret.code_at(TotpSecret::step_at(timestamp)), code);
+        }
+    }
+
+    #[test]
+    fn base32() {
+        let secret = TotpSecret::from(b"12345678901234567890".to_vec());
+        assert_eq!(
+            secret.to_base32().as_str(),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(TotpSecret::from(b"f".to_vec()).to_base32().as_str(), "MY");
+    }
+}
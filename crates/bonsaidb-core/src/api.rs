@@ -1,10 +1,13 @@
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
+use async_trait::async_trait;
 pub use bonsaidb_macros::Api;
 use serde::{Deserialize, Serialize};
 
-use crate::schema::{Authority, Name, Qualified, QualifiedName};
+use crate::connection::AsyncStorageConnection;
+use crate::permissions::PermissionDenied;
+use crate::schema::{Authority, InsertError, InvalidNameError, Name, Qualified, QualifiedName};
 
 /// An API request type. This trait is used by BonsaiDb's server to allow a
 /// client to send a request of this type, and the server can respond with a
@@ -74,6 +77,78 @@ impl<T> ApiError for T where
 /// The result of executing a custom API call.
 pub type ApiResult<Api> = Result<<Api as self::Api>::Response, <Api as self::Api>::Error>;
 
+/// A trait that can dispatch requests for an [`Api`].
+///
+/// `Context` is the type the handler is registered with, such as
+/// `bonsaidb_server::CustomServer` or `bonsaidb_local::AsyncStorage`. A handler
+/// that only needs [`HandlerContext::as_client()`] can be implemented for any
+/// `Context`, allowing the same handler to be served over the network and
+/// invoked in-process.
+#[async_trait]
+pub trait Handler<Api: self::Api, Context: HandlerContext>: Send + Sync {
+    /// Handles `request`, returning the response to send to the caller.
+    async fn handle(session: Context::Session<'_>, request: Api) -> HandlerResult<Api>;
+}
+
+/// A storage connection that [`Handler`]s can be registered with.
+pub trait HandlerContext: AsyncStorageConnection + Clone + 'static {
+    /// The session passed to [`Handler::handle()`].
+    type Session<'a>: Send;
+
+    /// Returns the connection of `session` that rejects any operations that
+    /// the caller is not authorized to perform.
+    fn as_client<'s>(session: &'s Self::Session<'_>) -> &'s Self;
+}
+
+/// An error that can occur inside of a [`Handler`].
+#[derive(thiserror::Error, Debug)]
+pub enum HandlerError<E: ApiError = Infallible> {
+    /// An api-related error.
+    #[error("api error: {0}")]
+    Api(E),
+    /// An error from BonsaiDb occurred.
+    #[error("bonsaidb error: {0}")]
+    Core(#[from] crate::Error),
+}
+
+impl<E: ApiError> From<PermissionDenied> for HandlerError<E> {
+    fn from(permission_denied: PermissionDenied) -> Self {
+        Self::Core(crate::Error::from(permission_denied))
+    }
+}
+
+impl<E: ApiError> From<InvalidNameError> for HandlerError<E> {
+    fn from(err: InvalidNameError) -> Self {
+        Self::Core(crate::Error::from(err))
+    }
+}
+
+impl<E: ApiError> From<pot::Error> for HandlerError<E> {
+    fn from(other: pot::Error) -> Self {
+        Self::Core(crate::Error::from(other))
+    }
+}
+
+impl<E: ApiError> From<std::io::Error> for HandlerError<E> {
+    fn from(err: std::io::Error) -> Self {
+        Self::Core(crate::Error::other("io", err))
+    }
+}
+
+impl<T, E> From<InsertError<T>> for HandlerError<E>
+where
+    E: ApiError,
+{
+    fn from(error: InsertError<T>) -> Self {
+        Self::Core(error.error)
+    }
+}
+
+/// The return type from a [`Handler`]'s [`handle()`](Handler::handle)
+/// function.
+pub type HandlerResult<Api> =
+    Result<<Api as self::Api>::Response, HandlerError<<Api as self::Api>::Error>>;
+
 /// The qualified name of an [`Api`](crate::api::Api).
 #[derive(Hash, PartialEq, Eq, Deserialize, Serialize, Debug, Clone, Ord, PartialOrd)]
 #[serde(transparent)]
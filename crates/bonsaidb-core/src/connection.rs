@@ -13,12 +13,13 @@ use futures::{Future, FutureExt};
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
-use crate::admin::{Role, User};
+use crate::admin::{DatabaseQuota, Role, User};
 use crate::document::{
     CollectionDocument, CollectionHeader, Document, HasHeader, Header, OwnedDocument,
 };
+use crate::key::time::TimestampAsNanoseconds;
 use crate::key::{ByteSource, IntoPrefixRange, Key, KeyEncoding, KeyKind, KeyVisitor};
-use crate::permissions::Permissions;
+use crate::permissions::{Permissions, Statement};
 use crate::schema::view::map::{MappedDocuments, ViewMappings as ViewMappingsCurrent};
 use crate::schema::{
     self, MappedValue, Nameable, NamedReference, Schema, SchemaName, SchemaSummary,
@@ -3014,6 +3015,50 @@ pub trait StorageConnection: HasSession + Sized + Send + Sync {
     /// Lists the databases in this storage.
     fn list_databases(&self) -> Result<Vec<Database>, crate::Error>;
 
+    /// Sets the [`DatabaseQuota`] of the database named `name`.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DatabaseNotFound`]: database `name` does not exist.
+    fn set_database_quota(&self, name: &str, quota: DatabaseQuota) -> Result<(), crate::Error>;
+
+    /// Lists the current usage and quota of each database in this storage.
+    fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, crate::Error>;
+
+    /// Lists the [`AuthenticationToken`](crate::admin::AuthenticationToken)s
+    /// that authenticate as `identity`.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::UserNotFound`]: `identity` does not exist.
+    #[cfg(feature = "token-authentication")]
+    fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<AuthenticationTokenSummary>, crate::Error>;
+
+    /// Revokes the [`AuthenticationToken`](crate::admin::AuthenticationToken)
+    /// with `id`, preventing it from being used to authenticate. Sessions that
+    /// have already authenticated using the token are not affected.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentNotFound`]: no token with `id` exists.
+    #[cfg(feature = "token-authentication")]
+    fn revoke_authentication_token(&self, id: u64) -> Result<(), crate::Error>;
+
+    /// Lists the active authentication sessions.
+    fn list_sessions(&self) -> Result<Vec<SessionSummary>, crate::Error>;
+
+    /// Revokes the authentication session `id`. Clients connected to a server
+    /// using the session are logged out of it, and subsequent requests using
+    /// the session are rejected.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::SessionNotFound`]: no active session with `id` exists.
+    fn revoke_session(&self, id: SessionId) -> Result<(), crate::Error>;
+
     /// Lists the [`SchemaName`]s registered with this storage.
     fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, crate::Error>;
 
@@ -3034,10 +3079,56 @@ pub trait StorageConnection: HasSession + Sized + Send + Sync {
         password: SensitiveString,
     ) -> Result<(), crate::Error>;
 
+    /// Enrolls a user in time-based one-time password (TOTP) authentication,
+    /// returning the newly generated secret to share with the user's
+    /// authenticator. An unconfirmed enrollment is replaced, but a confirmed
+    /// enrollment must first be removed using [`Self::remove_user_totp`].
+    ///
+    /// Password authentication does not require a code until the enrollment
+    /// is confirmed using [`Self::confirm_user_totp`].
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::TotpAlreadyEnrolled`]: the user has a confirmed enrollment.
+    #[cfg(feature = "totp-authentication")]
+    fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<crate::admin::TotpSecret, crate::Error>;
+
+    /// Confirms a user's TOTP enrollment using a `code` generated from the
+    /// secret returned by [`Self::enroll_user_totp`]. Once confirmed,
+    /// authenticating as the user with a password yields a session that has
+    /// no permissions until it is completed using
+    /// [`Self::authenticate_with_totp`].
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::InvalidCredentials`]: the user is not enrolled or `code` is
+    ///   invalid.
+    #[cfg(feature = "totp-authentication")]
+    fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: SensitiveString,
+    ) -> Result<(), crate::Error>;
+
+    /// Removes a user's TOTP enrollment. Password authentication will no
+    /// longer require a code.
+    #[cfg(feature = "totp-authentication")]
+    fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), crate::Error>;
+
     /// Authenticates using the active session, returning a connection with a
     /// new session upon success. The existing connection will remain usable
     /// with the existing authentication, if any.
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     fn authenticate(
         &self,
         authentication: Authentication,
@@ -3087,6 +3178,10 @@ pub trait StorageConnection: HasSession + Sized + Send + Sync {
     /// Authenticates a [`User`](crate::admin::User) using a password. If
     ///  successful, the returned instance will have the permissions from
     ///  `identity`.
+    ///
+    /// If the user has a confirmed time-based one-time password enrollment,
+    /// the returned instance has no permissions until authentication is
+    /// completed using `authenticate_with_totp`.
     #[cfg(feature = "password-hashing")]
     fn authenticate_with_password<'name, User: Nameable<'name, u64>>(
         &self,
@@ -3096,6 +3191,31 @@ pub trait StorageConnection: HasSession + Sized + Send + Sync {
         self.authenticate(Authentication::password(user, password)?)
     }
 
+    /// Authenticates using a signed JSON Web Token. The server maps the
+    /// token's claims to a [`User`](crate::admin::User) or
+    /// [`Role`](crate::admin::Role). If successful, the returned instance will
+    /// have the permissions from that identity.
+    #[cfg(feature = "jwt-authentication")]
+    fn authenticate_with_jwt(
+        &self,
+        token: impl Into<String>,
+    ) -> Result<Self::Authenticated, crate::Error> {
+        self.authenticate(Authentication::jwt(token))
+    }
+
+    /// Completes authenticating a [`User`](crate::admin::User) whose password
+    /// has been verified but who must also provide a time-based one-time
+    /// password. This must be invoked on the pending session returned by
+    /// [`Self::authenticate_with_password`]. If successful, the returned
+    /// instance will have the permissions of the user.
+    #[cfg(feature = "totp-authentication")]
+    fn authenticate_with_totp(
+        &self,
+        code: SensitiveString,
+    ) -> Result<Self::Authenticated, crate::Error> {
+        self.authenticate(Authentication::Totp(code))
+    }
+
     /// Adds a user to a permission group.
     fn add_permission_group_to_user<
         'user,
@@ -3204,6 +3324,54 @@ pub trait AsyncStorageConnection: HasSession + Sized + Send + Sync {
     /// Lists the databases in this storage.
     async fn list_databases(&self) -> Result<Vec<Database>, crate::Error>;
 
+    /// Sets the [`DatabaseQuota`] of the database named `name`.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DatabaseNotFound`]: database `name` does not exist.
+    async fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), crate::Error>;
+
+    /// Lists the current usage and quota of each database in this storage.
+    async fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, crate::Error>;
+
+    /// Lists the [`AuthenticationToken`](crate::admin::AuthenticationToken)s
+    /// that authenticate as `identity`.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::UserNotFound`]: `identity` does not exist.
+    #[cfg(feature = "token-authentication")]
+    async fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<AuthenticationTokenSummary>, crate::Error>;
+
+    /// Revokes the [`AuthenticationToken`](crate::admin::AuthenticationToken)
+    /// with `id`, preventing it from being used to authenticate. Sessions that
+    /// have already authenticated using the token are not affected.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentNotFound`]: no token with `id` exists.
+    #[cfg(feature = "token-authentication")]
+    async fn revoke_authentication_token(&self, id: u64) -> Result<(), crate::Error>;
+
+    /// Lists the active authentication sessions.
+    async fn list_sessions(&self) -> Result<Vec<SessionSummary>, crate::Error>;
+
+    /// Revokes the authentication session `id`. Clients connected to a server
+    /// using the session are logged out of it, and subsequent requests using
+    /// the session are rejected.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::SessionNotFound`]: no active session with `id` exists.
+    async fn revoke_session(&self, id: SessionId) -> Result<(), crate::Error>;
+
     /// Lists the [`SchemaName`]s registered with this storage.
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, crate::Error>;
 
@@ -3224,11 +3392,57 @@ pub trait AsyncStorageConnection: HasSession + Sized + Send + Sync {
         password: SensitiveString,
     ) -> Result<(), crate::Error>;
 
+    /// Enrolls a user in time-based one-time password (TOTP) authentication,
+    /// returning the newly generated secret to share with the user's
+    /// authenticator. An unconfirmed enrollment is replaced, but a confirmed
+    /// enrollment must first be removed using [`Self::remove_user_totp`].
+    ///
+    /// Password authentication does not require a code until the enrollment
+    /// is confirmed using [`Self::confirm_user_totp`].
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::TotpAlreadyEnrolled`]: the user has a confirmed enrollment.
+    #[cfg(feature = "totp-authentication")]
+    async fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<crate::admin::TotpSecret, crate::Error>;
+
+    /// Confirms a user's TOTP enrollment using a `code` generated from the
+    /// secret returned by [`Self::enroll_user_totp`]. Once confirmed,
+    /// authenticating as the user with a password yields a session that has
+    /// no permissions until it is completed using
+    /// [`Self::authenticate_with_totp`].
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::InvalidCredentials`]: the user is not enrolled or `code` is
+    ///   invalid.
+    #[cfg(feature = "totp-authentication")]
+    async fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: SensitiveString,
+    ) -> Result<(), crate::Error>;
+
+    /// Removes a user's TOTP enrollment. Password authentication will no
+    /// longer require a code.
+    #[cfg(feature = "totp-authentication")]
+    async fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), crate::Error>;
+
     /// Authenticates using an
     /// [`AuthenticationToken`](crate::admin::AuthenticationToken). If
     ///  successful, the returned instance will have the permissions from
     ///  `identity`.
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     async fn authenticate(
         &self,
         authentication: Authentication,
@@ -3273,6 +3487,10 @@ pub trait AsyncStorageConnection: HasSession + Sized + Send + Sync {
     /// Authenticates a [`User`](crate::admin::User) using a password. If
     ///  successful, the returned instance will have the permissions from
     ///  `identity`.
+    ///
+    /// If the user has a confirmed time-based one-time password enrollment,
+    /// the returned instance has no permissions until authentication is
+    /// completed using `authenticate_with_totp`.
     #[cfg(feature = "password-hashing")]
     async fn authenticate_with_password<'name, User: Nameable<'name, u64> + Send>(
         &self,
@@ -3283,6 +3501,31 @@ pub trait AsyncStorageConnection: HasSession + Sized + Send + Sync {
             .await
     }
 
+    /// Authenticates using a signed JSON Web Token. The server maps the
+    /// token's claims to a [`User`](crate::admin::User) or
+    /// [`Role`](crate::admin::Role). If successful, the returned instance will
+    /// have the permissions from that identity.
+    #[cfg(feature = "jwt-authentication")]
+    async fn authenticate_with_jwt(
+        &self,
+        token: impl Into<String> + Send,
+    ) -> Result<Self::Authenticated, crate::Error> {
+        self.authenticate(Authentication::jwt(token)).await
+    }
+
+    /// Completes authenticating a [`User`](crate::admin::User) whose password
+    /// has been verified but who must also provide a time-based one-time
+    /// password. This must be invoked on the pending session returned by
+    /// [`Self::authenticate_with_password`]. If successful, the returned
+    /// instance will have the permissions of the user.
+    #[cfg(feature = "totp-authentication")]
+    async fn authenticate_with_totp(
+        &self,
+        code: SensitiveString,
+    ) -> Result<Self::Authenticated, crate::Error> {
+        self.authenticate(Authentication::Totp(code)).await
+    }
+
     /// Assumes the `identity`. If successful, the returned instance will have
     /// the merged permissions of the current authentication session and the
     /// permissions from `identity`.
@@ -3349,6 +3592,57 @@ pub struct Database {
     pub schema: SchemaName,
 }
 
+/// The amount of data stored in a database.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DatabaseUsage {
+    /// The name of the database.
+    pub name: String,
+    /// The number of bytes of document contents and key-value entries stored
+    /// in the database.
+    pub bytes: u64,
+    /// The number of documents stored across all collections in the database.
+    pub documents: u64,
+    /// The quota of the database.
+    pub quota: DatabaseQuota,
+}
+
+/// Information about an
+/// [`AuthenticationToken`](crate::admin::AuthenticationToken). The private
+/// token is not included.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthenticationTokenSummary {
+    /// The unique id of the token.
+    pub id: u64,
+    /// The identity the token authenticates as.
+    pub identity: IdentityId,
+    /// The time the token was created.
+    pub created_at: TimestampAsNanoseconds,
+    /// The time after which the token can no longer be used to authenticate.
+    pub expires_at: Option<TimestampAsNanoseconds>,
+    /// The time the token was last used to authenticate successfully.
+    pub last_used_at: Option<TimestampAsNanoseconds>,
+    /// The statements sessions authenticated with the token are restricted
+    /// to.
+    pub scope: Option<Vec<Statement>>,
+}
+
+/// Information about an active authentication [`Session`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionSummary {
+    /// The unique id of the session.
+    pub id: SessionId,
+    /// The authentication state of the session.
+    pub authentication: SessionAuthentication,
+    /// The time the session was created.
+    pub created_at: TimestampAsNanoseconds,
+    /// The address of the client using the session, if the session is in use
+    /// by a client connected to a server.
+    pub address: Option<std::net::SocketAddr>,
+    /// The name of the transport the client using the session is connected
+    /// with, if the session is in use by a client connected to a server.
+    pub transport: Option<String>,
+}
+
 /// A string containing sensitive (private) data. This struct automatically
 /// overwrites its contents with zeroes when dropped.
 #[derive(Clone, Default, Serialize, Deserialize, Zeroize, Eq, PartialEq)]
@@ -3494,6 +3788,15 @@ pub enum Authentication {
         /// The password of the user.
         password: SensitiveString,
     },
+    /// Authenticate using a signed JSON Web Token (JWT), such as an OpenID
+    /// Connect ID token. The token is verified by `bonsaidb-server` against its
+    /// configured keys.
+    #[cfg(feature = "jwt-authentication")]
+    Jwt(SensitiveString),
+    /// A time-based one-time password completing a password authentication
+    /// that is pending a second factor.
+    #[cfg(feature = "totp-authentication")]
+    Totp(SensitiveString),
 }
 
 impl Authentication {
@@ -3509,6 +3812,12 @@ impl Authentication {
         })
     }
 
+    /// Returns an authentication instance for a signed JSON Web Token.
+    #[cfg(feature = "jwt-authentication")]
+    pub fn jwt(token: impl Into<String>) -> Self {
+        Self::Jwt(SensitiveString(token.into()))
+    }
+
     /// Returns a token authentication initialization instance for this token.
     #[cfg(feature = "token-authentication")]
     pub fn token(id: u64, token: &SensitiveString) -> Result<Self, crate::Error> {
@@ -3625,6 +3934,12 @@ pub struct Session {
     pub authentication: SessionAuthentication,
     /// The effective permissions of the session.
     pub permissions: Permissions,
+    /// Additional restrictions on the session's permissions. When present, an
+    /// action is only allowed if both `permissions` and `scope` allow it. This
+    /// is set when authenticating using a scoped
+    /// [`AuthenticationToken`](crate::admin::AuthenticationToken).
+    #[serde(default)]
+    pub scope: Option<Permissions>,
 }
 
 /// The authentication state of a [`Session`].
@@ -3647,6 +3962,14 @@ pub enum SessionAuthentication {
         /// The server timestamp that is used for authenticated extra data.
         server_timestamp: crate::key::time::TimestampAsNanoseconds,
     },
+    /// The session has verified a user's password and is pending a
+    /// time-based one-time password. The session has no permissions until
+    /// authentication is completed using [`Authentication::Totp`].
+    #[cfg(feature = "totp-authentication")]
+    TotpChallenge {
+        /// The id of the user being authenticated.
+        user_id: u64,
+    },
 }
 
 impl Default for SessionAuthentication {
@@ -3701,6 +4024,11 @@ pub enum AuthenticationMethod {
     Token,
     /// Authenticate a user using password hashing (Argon2).
     PasswordHash,
+    /// Authenticate the user or role using a signed JSON Web Token.
+    Jwt,
+    /// Authenticate the user or role using a TLS client certificate verified
+    /// during the connection's handshake.
+    ClientCertificate,
 }
 
 /// A unique session ID.
@@ -3715,7 +4043,11 @@ impl Session {
         resource_name: R,
         action: &P,
     ) -> bool {
-        self.permissions.allowed_to(resource_name, action)
+        self.permissions.allowed_to(&resource_name, action)
+            && self
+                .scope
+                .as_ref()
+                .map_or(true, |scope| scope.allowed_to(&resource_name, action))
     }
 
     /// Checks if `action` is permitted against `resource_name`. If permission
@@ -3726,9 +4058,11 @@ impl Session {
         resource_name: R,
         action: &P,
     ) -> Result<(), Error> {
-        self.permissions
-            .check(resource_name, action)
-            .map_err(Error::from)
+        self.permissions.check(&resource_name, action)?;
+        if let Some(scope) = &self.scope {
+            scope.check(&resource_name, action)?;
+        }
+        Ok(())
     }
 
     /// Returns the identity that this session is authenticated as, if any.
@@ -3860,7 +4194,8 @@ impl<'name> IdentityReference<'name> {
 }
 
 /// An identity from the connected BonsaiDb instance.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Key)]
+#[key(core = crate)]
 #[non_exhaustive]
 pub enum IdentityId {
     /// A [`User`](crate::admin::User) id.
@@ -93,6 +93,13 @@ pub enum Error {
     #[error("view '{0}' was already registered")]
     ViewAlreadyRegistered(ViewName),
 
+    /// A view and [`DocumentEncryption`](schema::DocumentEncryption) were both
+    /// defined for the same collection. View entries are stored unencrypted,
+    /// so views can't be defined for collections that encrypt documents with
+    /// document-level keys.
+    #[error("collection '{0}' can't have both views and document encryption")]
+    ViewsWithDocumentEncryption(CollectionName),
+
     /// An invalid database name was specified. See
     /// [`StorageConnection::create_database()`](connection::StorageConnection::create_database)
     /// for database name requirements.
@@ -178,6 +185,15 @@ pub enum Error {
     #[error("user not found")]
     UserNotFound,
 
+    /// The user already has a confirmed time-based one-time password
+    /// enrollment, which must be removed before enrolling again.
+    #[error("user is already enrolled in totp authentication")]
+    TotpAlreadyEnrolled,
+
+    /// The session specified was not found. Contains the id of the session.
+    #[error("session {0} not found")]
+    SessionNotFound(u64),
+
     /// An error occurred converting from bytes to Utf-8.
     #[error("invalid string: {0}")]
     InvalidUnicode(String),
@@ -198,6 +214,25 @@ pub enum Error {
     #[error("time error: {0}")]
     Time(#[from] TimeError),
 
+    /// An operation would cause a database to exceed its
+    /// [`DatabaseQuota`](admin::DatabaseQuota).
+    #[error("database '{database}' would exceed its quota of {limit}")]
+    QuotaExceeded {
+        /// The name of the database.
+        database: String,
+        /// The limit that would be exceeded.
+        limit: admin::QuotaLimit,
+    },
+
+    /// A request was rejected because it exceeded a rate limit configured on
+    /// the server.
+    #[error("rate limit exceeded, retry after {retry_after:?}")]
+    RateLimited {
+        /// The amount of time to wait before the request is able to be
+        /// performed.
+        retry_after: std::time::Duration,
+    },
+
     /// An error from another crate.
     #[error("error from {origin}: {error}")]
     Other {
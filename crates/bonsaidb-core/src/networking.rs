@@ -1,9 +1,11 @@
 use arc_bytes::serde::Bytes;
 use serde::{Deserialize, Serialize};
 
+use crate::admin::DatabaseQuota;
 use crate::api::{Api, ApiName};
 use crate::connection::{
-    AccessPolicy, Database, IdentityReference, Range, SerializedQueryKey, Session, SessionId, Sort,
+    AccessPolicy, Database, DatabaseUsage, IdentityReference, Range, SerializedQueryKey, Session,
+    SessionId, Sort,
 };
 use crate::document::{DocumentId, Header, OwnedDocument};
 use crate::keyvalue::{KeyOperation, Output};
@@ -74,6 +76,102 @@ impl Api for ListDatabases {
     }
 }
 
+/// Sets the quota of a database.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct SetDatabaseQuota {
+    /// The name of the database.
+    pub name: String,
+    /// The new quota of the database.
+    pub quota: DatabaseQuota,
+}
+
+impl Api for SetDatabaseQuota {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "SetDatabaseQuota")
+    }
+}
+
+/// Lists the usage and quota of all databases.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ListDatabaseUsage;
+
+impl Api for ListDatabaseUsage {
+    type Error = crate::Error;
+    type Response = Vec<DatabaseUsage>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ListDatabaseUsage")
+    }
+}
+
+/// Lists the authentication tokens of an identity.
+#[cfg(feature = "token-authentication")]
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ListAuthenticationTokens {
+    /// The identity whose tokens to list.
+    pub identity: IdentityReference<'static>,
+}
+
+#[cfg(feature = "token-authentication")]
+impl Api for ListAuthenticationTokens {
+    type Error = crate::Error;
+    type Response = Vec<crate::connection::AuthenticationTokenSummary>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ListAuthenticationTokens")
+    }
+}
+
+/// Revokes an authentication token.
+#[cfg(feature = "token-authentication")]
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RevokeAuthenticationToken {
+    /// The id of the token to revoke.
+    pub id: u64,
+}
+
+#[cfg(feature = "token-authentication")]
+impl Api for RevokeAuthenticationToken {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RevokeAuthenticationToken")
+    }
+}
+
+/// Lists the active authentication sessions.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ListSessions;
+
+impl Api for ListSessions {
+    type Error = crate::Error;
+    type Response = Vec<crate::connection::SessionSummary>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ListSessions")
+    }
+}
+
+/// Revokes an authentication session.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RevokeSession {
+    /// The id of the session to revoke.
+    pub id: SessionId,
+}
+
+impl Api for RevokeSession {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RevokeSession")
+    }
+}
+
 /// Lists available schemas.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct ListAvailableSchemas;
@@ -139,15 +237,79 @@ impl Api for SetUserPassword {
     }
 }
 
+/// Enrolls a user in time-based one-time password authentication.
+#[cfg(feature = "totp-authentication")]
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct EnrollUserTotp {
+    /// The username or id of the user.
+    pub user: NamedReference<'static, u64>,
+}
+
+#[cfg(feature = "totp-authentication")]
+impl Api for EnrollUserTotp {
+    type Error = crate::Error;
+    type Response = crate::admin::TotpSecret;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "EnrollUserTotp")
+    }
+}
+
+/// Confirms a user's time-based one-time password enrollment.
+#[cfg(feature = "totp-authentication")]
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ConfirmUserTotp {
+    /// The username or id of the user.
+    pub user: NamedReference<'static, u64>,
+    /// A code generated from the enrolled secret.
+    pub code: crate::connection::SensitiveString,
+}
+
+#[cfg(feature = "totp-authentication")]
+impl Api for ConfirmUserTotp {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ConfirmUserTotp")
+    }
+}
+
+/// Removes a user's time-based one-time password enrollment.
+#[cfg(feature = "totp-authentication")]
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RemoveUserTotp {
+    /// The username or id of the user.
+    pub user: NamedReference<'static, u64>,
+}
+
+#[cfg(feature = "totp-authentication")]
+impl Api for RemoveUserTotp {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RemoveUserTotp")
+    }
+}
+
 /// Authenticate the current connection.
-#[cfg(any(feature = "password-hashing", feature = "token-authentication"))]
+#[cfg(any(
+    feature = "password-hashing",
+    feature = "token-authentication",
+    feature = "jwt-authentication"
+))]
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Authenticate {
     /// The method of authentication.
     pub authentication: crate::connection::Authentication,
 }
 
-#[cfg(any(feature = "password-hashing", feature = "token-authentication"))]
+#[cfg(any(
+    feature = "password-hashing",
+    feature = "token-authentication",
+    feature = "jwt-authentication"
+))]
 impl Api for Authenticate {
     type Error = crate::Error;
     type Response = Session;
@@ -590,6 +752,46 @@ impl Api for UnsubscribeFrom {
     }
 }
 
+/// Subscribes `subscriber_id` to messages for all topics matching `pattern`.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct SubscribeToPattern {
+    /// The name of the database.
+    pub database: String,
+    /// The id of the [`Subscriber`](crate::pubsub::Subscriber).
+    pub subscriber_id: u64,
+    /// The [`TopicPattern`](crate::pubsub::TopicPattern) to subscribe to.
+    pub pattern: String,
+}
+
+impl Api for SubscribeToPattern {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "SubscribeToPattern")
+    }
+}
+
+/// Unsubscribes `subscriber_id` from messages for topics matching `pattern`.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct UnsubscribeFromPattern {
+    /// The name of the database.
+    pub database: String,
+    /// The id of the [`Subscriber`](crate::pubsub::Subscriber).
+    pub subscriber_id: u64,
+    /// The [`TopicPattern`](crate::pubsub::TopicPattern) to unsubscribe from.
+    pub pattern: String,
+}
+
+impl Api for UnsubscribeFromPattern {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "UnsubscribeFromPattern")
+    }
+}
+
 /// Unregisters the subscriber.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct UnregisterSubscriber {
@@ -54,6 +54,18 @@ pub fn pubsub_topic_resource_name<'a>(database: &'a str, topic: &'a [u8]) -> Res
     database_resource_name(database).and("pubsub").and(topic)
 }
 
+/// Creates a resource name for `PubSub` topic `pattern` within `database`.
+#[must_use]
+pub fn pubsub_topic_pattern_resource_name<'a>(
+    database: &'a str,
+    pattern: &'a str,
+) -> ResourceName<'a> {
+    database_resource_name(database)
+        .and("pubsub")
+        .and("pattern")
+        .and(pattern)
+}
+
 /// Creates a resource name for the key-value store in `database`.
 #[must_use]
 pub fn kv_resource_name(database: &str) -> ResourceName<'_> {
@@ -136,6 +148,10 @@ pub enum ServerAction {
     DeleteUser,
     /// Permits [`StorageConnection::set_user_password`](crate::connection::StorageConnection::set_user_password).
     SetPassword,
+    /// Permits enrolling, confirming, and removing a user's time-based
+    /// one-time password. Checked against the [`user_resource_name()`] of the
+    /// user.
+    ModifyUserTotp,
     /// Permits the ability to log in with a password.
     Authenticate(AuthenticationMethod),
     /// Permits the ability to assume an identity without authenticating that
@@ -146,6 +162,24 @@ pub enum ServerAction {
     /// Permits .
     /// Permits [`StorageConnection::add_role_to_user`](crate::connection::StorageConnection::add_role_to_user) and [`StorageConnection::remove_role_from_user`](crate::connection::StorageConnection::remove_role_from_user).
     ModifyUserRoles,
+    /// Permits [`StorageConnection::set_database_quota`](crate::connection::StorageConnection::set_database_quota).
+    SetDatabaseQuota,
+    /// Permits [`StorageConnection::list_database_usage`](crate::connection::StorageConnection::list_database_usage).
+    ListDatabaseUsage,
+    /// Permits listing the authentication tokens of a user or role. Checked
+    /// against the [`user_resource_name()`] or [`role_resource_name()`] of the
+    /// identity.
+    ListAuthenticationTokens,
+    /// Permits revoking an authentication token. Checked against
+    /// [`authentication_token_resource_name()`].
+    RevokeAuthenticationToken,
+    /// Permits [`StorageConnection::list_sessions`](crate::connection::StorageConnection::list_sessions).
+    ListSessions,
+    /// Permits [`StorageConnection::revoke_session`](crate::connection::StorageConnection::revoke_session).
+    RevokeSession,
+    /// Permits replacing the vault key that seals the storage's master keys.
+    /// Checked against [`bonsaidb_resource_name()`].
+    RotateVaultKey,
 }
 
 /// Actions that operate on a specific database.
@@ -266,6 +300,19 @@ pub enum PubSubAction {
     /// [`pubsub_topic_resource_name()`] for the format of `PubSub` topic
     /// resource names.
     UnsubscribeFrom,
+    /// Allows subscribing to all `PubSub` topics matching a pattern with
+    /// [`Subscriber::subscribe_to_pattern()`](crate::pubsub::Subscriber::subscribe_to_pattern).
+    /// Messages delivered through a pattern subscription are only delivered
+    /// if the subscriber is also allowed [`PubSubAction::SubscribeTo`] for the
+    /// message's topic. See
+    /// [`pubsub_topic_pattern_resource_name()`] for the format of `PubSub`
+    /// pattern resource names.
+    SubscribeToPattern,
+    /// Allows unsubscribing from a `PubSub` topic pattern with
+    /// [`Subscriber::unsubscribe_from_pattern()`](crate::pubsub::Subscriber::unsubscribe_from_pattern).
+    /// See [`pubsub_topic_pattern_resource_name()`] for the format of `PubSub`
+    /// pattern resource names.
+    UnsubscribeFromPattern,
 }
 
 /// Actions that operate on the key-value store.
@@ -284,4 +331,9 @@ pub enum EncryptionKeyAction {
     Encrypt,
     /// Uses a key to decrypt data.
     Decrypt,
+    /// Deletes a key, making all data encrypted with it unreadable.
+    Delete,
+    /// Creates a new version of a key to encrypt new data with and
+    /// re-encrypts data that was encrypted with the previous version.
+    Rotate,
 }
@@ -1,9 +1,15 @@
+use std::time::Duration;
+
+use arc_bytes::OwnedBytes;
 use async_trait::async_trait;
 use circulate::{flume, Message};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::Error;
 
+pub mod durable;
+pub mod rpc;
+
 /// Publishes and Subscribes to messages on topics.
 pub trait PubSub {
     /// The Subscriber type for this `PubSub` connection.
@@ -68,6 +74,13 @@ pub trait Subscriber {
     /// Unsubscribe from [`Message`]s published to `topic`.
     fn unsubscribe_from_bytes(&self, topic: &[u8]) -> Result<(), Error>;
 
+    /// Subscribe to [`Message`]s published to any topic matching `pattern`.
+    /// See [`TopicPattern`] for the pattern syntax.
+    fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), Error>;
+
+    /// Unsubscribe from [`Message`]s published to topics matching `pattern`.
+    fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), Error>;
+
     /// Returns the receiver to receive [`Message`]s.
     fn receiver(&self) -> &Receiver;
 }
@@ -147,6 +160,13 @@ pub trait AsyncSubscriber: Send + Sync {
     /// Unsubscribe from [`Message`]s published to `topic`.
     async fn unsubscribe_from_bytes(&self, topic: &[u8]) -> Result<(), Error>;
 
+    /// Subscribe to [`Message`]s published to any topic matching `pattern`.
+    /// See [`TopicPattern`] for the pattern syntax.
+    async fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), Error>;
+
+    /// Unsubscribe from [`Message`]s published to topics matching `pattern`.
+    async fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), Error>;
+
     /// Returns the receiver to receive [`Message`]s.
     fn receiver(&self) -> &Receiver;
 }
@@ -197,6 +217,19 @@ impl Receiver {
             .map_err(|_| Disconnected)
     }
 
+    /// Receive the next [`Message`], blocking the current thread for up to
+    /// `timeout`. If no message is received before `timeout` elapses,
+    /// [`TryReceiveError::Empty`] is returned.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<Message, TryReceiveError> {
+        self.receiver
+            .recv_timeout(timeout)
+            .map(|message| self.remove_database_prefix(message))
+            .map_err(|err| match err {
+                flume::RecvTimeoutError::Timeout => TryReceiveError::Empty,
+                flume::RecvTimeoutError::Disconnected => TryReceiveError::Disconnected,
+            })
+    }
+
     /// Try to receive the next [`Message`]. This function will not block, and
     /// only returns a message if one is already available.
     pub fn try_receive(&self) -> Result<Message, TryReceiveError> {
@@ -207,6 +240,10 @@ impl Receiver {
     }
 
     fn remove_database_prefix(&self, mut message: Message) -> Message {
+        if let Some(delivered) = unwrap_pattern_delivery(&message) {
+            return delivered;
+        }
+
         if self.strip_database {
             if let Some(database_length) = message.topic.iter().position(|b| b == 0) {
                 message.topic.0.read_bytes(database_length + 1).unwrap();
@@ -265,6 +302,145 @@ pub fn database_topic(database: &str, topic: &[u8]) -> Vec<u8> {
     namespaced_topic
 }
 
+/// A pattern matching one or more `PubSub` topics.
+///
+/// Patterns only match topics that were serialized from strings, such as
+/// topics published using `publish(&"orders.created", ...)`. Topics are split
+/// into segments separated by `.`. Each segment of the pattern is matched
+/// against the topic's segments:
+///
+/// - `*` matches exactly one segment.
+/// - `#` matches zero or more segments.
+/// - Any other segment must match exactly.
+///
+/// For example, `orders.*` matches `orders.created` but not `orders` or
+/// `orders.created.eu`, while `orders.#` matches all three.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TopicPattern(String);
+
+impl TopicPattern {
+    /// Returns a new pattern from its string representation.
+    #[must_use]
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    /// Returns the string representation of this pattern.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The maximum number of `.`-separated segments a pattern may contain.
+    /// Longer patterns never match any topic, and subscribing to them returns
+    /// an error.
+    pub const MAX_SEGMENTS: usize = 32;
+
+    /// Returns true if this pattern has no more than
+    /// [`MAX_SEGMENTS`](Self::MAX_SEGMENTS) segments.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.0.split('.').count() <= Self::MAX_SEGMENTS
+    }
+
+    /// Returns true if `topic` matches this pattern.
+    #[must_use]
+    pub fn matches(&self, topic: &str) -> bool {
+        if !self.is_valid() {
+            return false;
+        }
+        let pattern = self.0.split('.').collect::<Vec<_>>();
+        let topic = topic.split('.').collect::<Vec<_>>();
+        segments_match(&pattern, &topic)
+    }
+
+    /// Returns true if `topic` is a serialized string that matches this
+    /// pattern.
+    #[must_use]
+    pub fn matches_serialized(&self, topic: &[u8]) -> bool {
+        pot::from_slice::<String>(topic).map_or(false, |topic| self.matches(&topic))
+    }
+}
+
+impl From<&str> for TopicPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+/// Matches `topic` against `pattern` in `O(pattern.len() * topic.len())`
+/// time. `matched[i]` tracks whether the pattern segments processed so far
+/// match the first `i` topic segments.
+fn segments_match(pattern: &[&str], topic: &[&str]) -> bool {
+    let mut matched = vec![false; topic.len() + 1];
+    matched[0] = true;
+    for segment in pattern {
+        match *segment {
+            "#" => {
+                // Zero or more segments: once a prefix matches, every longer
+                // prefix matches too.
+                let mut any_matched = false;
+                for matched in &mut matched {
+                    any_matched |= *matched;
+                    *matched = any_matched;
+                }
+            }
+            segment => {
+                for index in (1..=topic.len()).rev() {
+                    matched[index] =
+                        matched[index - 1] && (segment == "*" || topic[index - 1] == segment);
+                }
+                matched[0] = false;
+            }
+        }
+    }
+    matched[topic.len()]
+}
+
+const PATTERN_DELIVERY_PREFIX: &[u8] = b"\0pattern\0";
+
+/// Returns the topic used to deliver messages matching pattern subscriptions
+/// to `subscriber_id`. This is an internal API, which is why the documentation
+/// is hidden.
+#[doc(hidden)]
+#[must_use]
+pub fn pattern_delivery_topic(subscriber_id: u64) -> Vec<u8> {
+    let mut topic = PATTERN_DELIVERY_PREFIX.to_vec();
+    topic.extend(subscriber_id.to_be_bytes());
+    topic
+}
+
+/// Wraps `topic` and `payload` to be published to a
+/// [`pattern_delivery_topic()`]. This is an internal API, which is why the
+/// documentation is hidden.
+#[doc(hidden)]
+#[must_use]
+pub fn pattern_delivery_payload(topic: &[u8], payload: &[u8]) -> Vec<u8> {
+    let topic_length = u32::try_from(topic.len()).expect("topic too large");
+    let mut delivery = Vec::with_capacity(4 + topic.len() + payload.len());
+    delivery.extend(topic_length.to_be_bytes());
+    delivery.extend(topic);
+    delivery.extend(payload);
+    delivery
+}
+
+fn unwrap_pattern_delivery(message: &Message) -> Option<Message> {
+    if !message.topic.starts_with(PATTERN_DELIVERY_PREFIX) || message.payload.len() < 4 {
+        return None;
+    }
+
+    let (topic_length, delivery) = message.payload[..].split_at(4);
+    let topic_length = usize::try_from(u32::from_be_bytes(topic_length.try_into().ok()?)).ok()?;
+    if delivery.len() < topic_length {
+        return None;
+    }
+    let (topic, payload) = delivery.split_at(topic_length);
+    Some(Message {
+        topic: OwnedBytes::from(topic.to_vec()),
+        payload: OwnedBytes::from(payload.to_vec()),
+    })
+}
+
 /// Expands into a suite of pubsub unit tests using the passed type as the test harness.
 #[cfg(feature = "test-util")]
 #[macro_export]
@@ -445,6 +621,89 @@ macro_rules! define_async_pubsub_test_suite {
 
                 Ok(())
             }
+
+            #[tokio::test]
+            async fn pattern_subscription_test() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::PubSubPatterns).await?;
+                let pubsub = harness.connect().await?;
+                let single = AsyncPubSub::create_subscriber(&pubsub).await?;
+                let multiple = AsyncPubSub::create_subscriber(&pubsub).await?;
+                AsyncSubscriber::subscribe_to_pattern(&single, "orders.*").await?;
+                AsyncSubscriber::subscribe_to_pattern(&multiple, "orders.#").await?;
+
+                AsyncPubSub::publish(&pubsub, &"orders", &1_u32).await?;
+                AsyncPubSub::publish(&pubsub, &"orders.created", &2_u32).await?;
+                AsyncPubSub::publish(&pubsub, &"orders.created.eu", &3_u32).await?;
+                AsyncPubSub::publish(&pubsub, &"invoices.created", &4_u32).await?;
+
+                // `*` only matches a single segment.
+                let message = single.receiver().receive_async().await?;
+                assert_eq!(message.topic::<String>()?, "orders.created");
+                assert_eq!(message.payload::<u32>()?, 2);
+
+                // `#` matches any number of segments, including none.
+                for expected in 1_u32..=3 {
+                    let message = multiple.receiver().receive_async().await?;
+                    assert_eq!(message.payload::<u32>()?, expected);
+                }
+
+                AsyncSubscriber::unsubscribe_from_pattern(&multiple, "orders.#").await?;
+                AsyncPubSub::publish(&pubsub, &"orders.deleted", &5_u32).await?;
+                let message = single.receiver().receive_async().await?;
+                assert_eq!(message.payload::<u32>()?, 5);
+                assert!(matches!(
+                    single.receiver().try_receive(),
+                    Err($crate::pubsub::TryReceiveError::Empty)
+                ));
+                assert!(matches!(
+                    multiple.receiver().try_receive(),
+                    Err($crate::pubsub::TryReceiveError::Empty)
+                ));
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn request_reply_test() -> anyhow::Result<()> {
+                use $crate::pubsub::rpc::AsyncPubSubRpc;
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::PubSubRequestReply).await?;
+                let pubsub = harness.connect().await?;
+                let responder = AsyncPubSubRpc::respond_to(&pubsub, &"double").await?;
+                let responder_task = tokio::spawn(async move {
+                    responder
+                        .respond(|value: u32| async move { Ok::<_, String>(value * 2) })
+                        .await
+                });
+
+                let response: u32 = AsyncPubSubRpc::request(
+                    &pubsub,
+                    &"double",
+                    &21_u32,
+                    std::time::Duration::from_secs(10),
+                )
+                .await?;
+                assert_eq!(response, 42);
+                responder_task.await??;
+
+                // Without a responder, the request should time out.
+                let result: Result<u32, _> = AsyncPubSubRpc::request(
+                    &pubsub,
+                    &"double",
+                    &21_u32,
+                    std::time::Duration::from_millis(100),
+                )
+                .await;
+                assert!(matches!(
+                    result,
+                    Err($crate::Error::Networking(
+                        $crate::networking::Error::RequestTimeout
+                    ))
+                ));
+
+                Ok(())
+            }
         }
     };
 }
@@ -594,6 +853,109 @@ macro_rules! define_blocking_pubsub_test_suite {
 
                 Ok(())
             }
+
+            #[test]
+            fn pattern_subscription_test() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::PubSubPatterns)?;
+                let pubsub = harness.connect()?;
+                let single = PubSub::create_subscriber(&pubsub)?;
+                let multiple = PubSub::create_subscriber(&pubsub)?;
+                Subscriber::subscribe_to_pattern(&single, "orders.*")?;
+                Subscriber::subscribe_to_pattern(&multiple, "orders.#")?;
+
+                PubSub::publish(&pubsub, &"orders", &1_u32)?;
+                PubSub::publish(&pubsub, &"orders.created", &2_u32)?;
+                PubSub::publish(&pubsub, &"orders.created.eu", &3_u32)?;
+                PubSub::publish(&pubsub, &"invoices.created", &4_u32)?;
+
+                // `*` only matches a single segment.
+                let message = single.receiver().receive()?;
+                assert_eq!(message.topic::<String>()?, "orders.created");
+                assert_eq!(message.payload::<u32>()?, 2);
+
+                // `#` matches any number of segments, including none.
+                for expected in 1_u32..=3 {
+                    let message = multiple.receiver().receive()?;
+                    assert_eq!(message.payload::<u32>()?, expected);
+                }
+
+                Subscriber::unsubscribe_from_pattern(&multiple, "orders.#")?;
+                PubSub::publish(&pubsub, &"orders.deleted", &5_u32)?;
+                let message = single.receiver().receive()?;
+                assert_eq!(message.payload::<u32>()?, 5);
+                assert!(matches!(
+                    single.receiver().try_receive(),
+                    Err($crate::pubsub::TryReceiveError::Empty)
+                ));
+                assert!(matches!(
+                    multiple.receiver().try_receive(),
+                    Err($crate::pubsub::TryReceiveError::Empty)
+                ));
+
+                Ok(())
+            }
+
+            #[test]
+            fn request_reply_test() -> anyhow::Result<()> {
+                use $crate::pubsub::rpc::PubSubRpc;
+
+                let harness = $harness::new($crate::test_util::HarnessTest::PubSubRequestReply)?;
+                let pubsub = harness.connect()?;
+                let responder = PubSubRpc::respond_to(&pubsub, &"double")?;
+                let responder_thread = std::thread::spawn(move || {
+                    responder.respond(|value: u32| Ok::<_, String>(value * 2))
+                });
+
+                let response: u32 = PubSubRpc::request(
+                    &pubsub,
+                    &"double",
+                    &21_u32,
+                    std::time::Duration::from_secs(10),
+                )?;
+                assert_eq!(response, 42);
+                responder_thread.join().unwrap()?;
+
+                // Without a responder, the request should time out.
+                let result: Result<u32, _> = PubSubRpc::request(
+                    &pubsub,
+                    &"double",
+                    &21_u32,
+                    std::time::Duration::from_millis(100),
+                );
+                assert!(matches!(
+                    result,
+                    Err($crate::Error::Networking(
+                        $crate::networking::Error::RequestTimeout
+                    ))
+                ));
+
+                Ok(())
+            }
         }
     };
 }
+
+#[test]
+fn topic_pattern_matching() {
+    let pattern = TopicPattern::from("orders.*");
+    assert!(pattern.matches("orders.created"));
+    assert!(!pattern.matches("orders"));
+    assert!(!pattern.matches("orders.created.eu"));
+
+    let pattern = TopicPattern::from("orders.#");
+    assert!(pattern.matches("orders"));
+    assert!(pattern.matches("orders.created.eu"));
+    assert!(!pattern.matches("customers.created"));
+
+    assert!(TopicPattern::from("#.created.#").matches("orders.created.eu"));
+    assert!(TopicPattern::from("a.#.c").matches("a.c"));
+
+    // Repeated `#` segments must not cause exponential backtracking.
+    let pattern = TopicPattern::new(vec!["#"; TopicPattern::MAX_SEGMENTS - 1].join(".") + ".x");
+    let topic = vec!["a"; 1000].join(".");
+    assert!(!pattern.matches(&topic));
+
+    let pattern = TopicPattern::new(vec!["*"; TopicPattern::MAX_SEGMENTS + 1].join("."));
+    assert!(!pattern.is_valid());
+    assert!(!pattern.matches(&vec!["a"; TopicPattern::MAX_SEGMENTS + 1].join(".")));
+}
@@ -0,0 +1,513 @@
+//! Durable topics that persist published messages, allowing subscribers to
+//! replay messages they missed while disconnected.
+//!
+//! Regular [`PubSub`] messages are only delivered to subscribers connected at
+//! the time of publishing. Messages published using
+//! [`DurablePubSub::publish_durable`] are first stored in the
+//! [`DurableMessage`] collection, and then published to the topic as usual.
+//! Each stored message is assigned an increasing offset, which can be used to
+//! resume consuming a topic using [`DurablePubSub::subscribe_from`].
+//!
+//! Consumers can record their progress with
+//! [`DurablePubSub::acknowledge_durable`]. The acknowledged offset is stored
+//! in the [`ConsumerOffset`] collection, and
+//! [`DurablePubSub::subscribe_as_consumer`] resumes from the message following
+//! the last acknowledged offset.
+//!
+//! The collections used by this module must be registered in the database's
+//! schema using [`define_collections`]. No additional permissions exist for
+//! durable topics: accessing them requires the permissions for the underlying
+//! collections and for the pubsub operations.
+
+use std::collections::VecDeque;
+
+use arc_bytes::serde::Bytes;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::connection::{AsyncConnection, Connection};
+use crate::document::{CollectionDocument, Emit};
+use crate::key::time::TimestampAsNanoseconds;
+use crate::pubsub::{AsyncPubSub, AsyncSubscriber, PubSub, Subscriber};
+use crate::schema::{
+    Collection, CollectionMapReduce, Schematic, SerializedCollection, View, ViewMapResult,
+    ViewSchema,
+};
+use crate::{networking, Error};
+
+/// The maximum number of messages loaded from the database at once while
+/// replaying a durable topic.
+const REPLAY_BATCH_SIZE: u32 = 100;
+
+/// Registers the collections needed for durable topics into `schema`.
+pub fn define_collections(schema: &mut Schematic) -> Result<(), Error> {
+    schema.define_collection::<DurableMessage>()?;
+    schema.define_collection::<ConsumerOffset>()?;
+
+    Ok(())
+}
+
+/// A message published to a durable topic. The document's id is the message's
+/// offset.
+#[derive(Collection, Clone, Debug, Serialize, Deserialize)]
+#[collection(name = "durable-messages", authority = "bonsaidb", views = [MessagesByTopic], core = crate)]
+pub struct DurableMessage {
+    /// The serialized topic this message was published to.
+    pub topic: Bytes,
+    /// The serialized payload of this message.
+    pub payload: Bytes,
+    /// The time this message was published.
+    pub published_at: TimestampAsNanoseconds,
+}
+
+/// Indexes [`DurableMessage`]s by their topic and offset.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = DurableMessage, key = (Bytes, u64), value = (), name = "by-topic", core = crate)]
+#[view_schema(core = crate)]
+pub struct MessagesByTopic;
+
+impl CollectionMapReduce for MessagesByTopic {
+    fn map<'doc>(&self, document: CollectionDocument<DurableMessage>) -> ViewMapResult<'doc, Self> {
+        document
+            .header
+            .emit_key((document.contents.topic, document.header.id))
+    }
+}
+
+/// The last offset acknowledged by a consumer of a durable topic. The
+/// document's id is the consumer's name and the serialized topic.
+#[derive(Collection, Clone, Debug, Serialize, Deserialize)]
+#[collection(name = "durable-consumer-offsets", authority = "bonsaidb", primary_key = (String, Bytes), core = crate)]
+pub struct ConsumerOffset {
+    /// The offset of the last message acknowledged.
+    pub offset: u64,
+    /// The time the offset was acknowledged.
+    pub acknowledged_at: TimestampAsNanoseconds,
+}
+
+/// A message stored in a durable topic.
+#[derive(Clone, Debug)]
+pub struct StoredMessage {
+    /// The offset of this message within the database.
+    pub offset: u64,
+    /// The serialized topic this message was published to.
+    pub topic: Bytes,
+    /// The serialized payload of this message.
+    pub payload: Bytes,
+    /// The time this message was published.
+    pub published_at: TimestampAsNanoseconds,
+}
+
+impl StoredMessage {
+    /// Deserialize the payload as `Payload` using the same format as
+    /// [`PubSub::publish`].
+    pub fn payload<Payload: DeserializeOwned>(&self) -> Result<Payload, pot::Error> {
+        pot::from_slice(&self.payload)
+    }
+}
+
+impl From<CollectionDocument<DurableMessage>> for StoredMessage {
+    fn from(document: CollectionDocument<DurableMessage>) -> Self {
+        Self {
+            offset: document.header.id,
+            topic: document.contents.topic,
+            payload: document.contents.payload,
+            published_at: document.contents.published_at,
+        }
+    }
+}
+
+fn topic_range(topic: Vec<u8>, offset: u64) -> std::ops::Range<(Bytes, u64)> {
+    let topic = Bytes::from(topic);
+    (topic.clone(), offset)..(topic, u64::MAX)
+}
+
+fn consumer_offset_id(consumer: &str, topic: Vec<u8>) -> (String, Bytes) {
+    (consumer.to_string(), Bytes::from(topic))
+}
+
+/// Publishes and replays messages on durable topics. This trait is
+/// automatically implemented for all types that implement both [`Connection`]
+/// and [`PubSub`].
+pub trait DurablePubSub: Connection + PubSub {
+    /// Stores `payload` in `topic` and publishes it to all current subscribers
+    /// of `topic`. Returns the offset of the stored message.
+    fn publish_durable<Topic: Serialize, Payload: Serialize>(
+        &self,
+        topic: &Topic,
+        payload: &Payload,
+    ) -> Result<u64, Error> {
+        self.publish_durable_bytes(pot::to_vec(topic)?, pot::to_vec(payload)?)
+    }
+
+    /// Stores `payload` in `topic` and publishes it to all current subscribers
+    /// of `topic`. Returns the offset of the stored message.
+    fn publish_durable_bytes(&self, topic: Vec<u8>, payload: Vec<u8>) -> Result<u64, Error> {
+        let message = DurableMessage {
+            topic: Bytes::from(topic.clone()),
+            payload: Bytes::from(payload.clone()),
+            published_at: TimestampAsNanoseconds::now(),
+        }
+        .push_into(self)?;
+        self.publish_bytes(topic, payload)?;
+        Ok(message.header.id)
+    }
+
+    /// Returns up to `limit` messages stored in `topic` with an offset greater
+    /// than or equal to `offset`, in the order they were published.
+    fn durable_messages_since<Topic: Serialize>(
+        &self,
+        topic: &Topic,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<StoredMessage>, Error> {
+        self.durable_messages_since_bytes(pot::to_vec(topic)?, offset, limit)
+    }
+
+    /// Returns up to `limit` messages stored in `topic` with an offset greater
+    /// than or equal to `offset`, in the order they were published.
+    fn durable_messages_since_bytes(
+        &self,
+        topic: Vec<u8>,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<StoredMessage>, Error> {
+        let messages = self
+            .view::<MessagesByTopic>()
+            .with_key_range(topic_range(topic, offset))
+            .limit(limit)
+            .query_with_collection_docs()?;
+        Ok(messages
+            .documents
+            .into_values()
+            .map(StoredMessage::from)
+            .collect())
+    }
+
+    /// Records that `consumer` has processed all messages in `topic` up to and
+    /// including `offset`.
+    fn acknowledge_durable<Topic: Serialize>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+        offset: u64,
+    ) -> Result<(), Error> {
+        ConsumerOffset {
+            offset,
+            acknowledged_at: TimestampAsNanoseconds::now(),
+        }
+        .overwrite_into(&consumer_offset_id(consumer, pot::to_vec(topic)?), self)?;
+        Ok(())
+    }
+
+    /// Returns the last offset `consumer` acknowledged for `topic`, if any.
+    fn durable_consumer_offset<Topic: Serialize>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+    ) -> Result<Option<u64>, Error> {
+        let offset = ConsumerOffset::get(&consumer_offset_id(consumer, pot::to_vec(topic)?), self)?;
+        Ok(offset.map(|offset| offset.contents.offset))
+    }
+
+    /// Subscribes to `topic`, first replaying all stored messages with an
+    /// offset greater than or equal to `offset`.
+    fn subscribe_from<Topic: Serialize>(
+        &self,
+        topic: &Topic,
+        offset: u64,
+    ) -> Result<DurableSubscriber<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let topic = pot::to_vec(topic)?;
+        let subscriber = self.create_subscriber()?;
+        subscriber.subscribe_to_bytes(topic.clone())?;
+        Ok(DurableSubscriber {
+            database: self.clone(),
+            subscriber,
+            topic,
+            next_offset: offset,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Subscribes to `topic`, resuming after the last offset acknowledged by
+    /// `consumer`. If `consumer` has never acknowledged a message in this
+    /// topic, all stored messages are replayed.
+    fn subscribe_as_consumer<Topic: Serialize>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+    ) -> Result<DurableSubscriber<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let offset = self
+            .durable_consumer_offset(consumer, topic)?
+            .map_or(0, |offset| offset + 1);
+        self.subscribe_from(topic, offset)
+    }
+}
+
+impl<T> DurablePubSub for T where T: Connection + PubSub {}
+
+/// A subscriber of a durable topic. Stored messages are returned in the order
+/// they were published, followed by new messages as they are published.
+#[must_use]
+pub struct DurableSubscriber<Database: PubSub> {
+    database: Database,
+    subscriber: Database::Subscriber,
+    topic: Vec<u8>,
+    next_offset: u64,
+    pending: VecDeque<StoredMessage>,
+}
+
+impl<Database> DurableSubscriber<Database>
+where
+    Database: DurablePubSub,
+{
+    /// Returns the next message in the topic. Blocks the current thread until
+    /// a message is available. If the underlying subscriber becomes
+    /// disconnected, an error will be returned. A new subscriber can be
+    /// created using [`Self::next_offset()`] to resume consuming the topic.
+    pub fn receive(&mut self) -> Result<StoredMessage, Error> {
+        loop {
+            if let Some(message) = self.try_receive()? {
+                return Ok(message);
+            }
+
+            self.subscriber
+                .receiver()
+                .receive()
+                .map_err(|_| Error::Networking(networking::Error::Disconnected))?;
+        }
+    }
+
+    /// Returns the next message in the topic, if one has been stored. This
+    /// function does not wait for new messages to be published.
+    pub fn try_receive(&mut self) -> Result<Option<StoredMessage>, Error> {
+        if self.pending.is_empty() {
+            // Any notifications received before loading the next batch are
+            // covered by the query.
+            while self.subscriber.receiver().try_receive().is_ok() {}
+            let messages = self.database.durable_messages_since_bytes(
+                self.topic.clone(),
+                self.next_offset,
+                REPLAY_BATCH_SIZE,
+            )?;
+            self.pending.extend(messages);
+        }
+
+        Ok(self.pending.pop_front().map(|message| {
+            self.next_offset = message.offset + 1;
+            message
+        }))
+    }
+
+    /// Returns the offset of the next message this subscriber will return.
+    #[must_use]
+    pub const fn next_offset(&self) -> u64 {
+        self.next_offset
+    }
+}
+
+/// Publishes and replays messages on durable topics. This trait is
+/// automatically implemented for all types that implement both
+/// [`AsyncConnection`] and [`AsyncPubSub`].
+#[async_trait]
+pub trait AsyncDurablePubSub: AsyncConnection + AsyncPubSub {
+    /// Stores `payload` in `topic` and publishes it to all current subscribers
+    /// of `topic`. Returns the offset of the stored message.
+    async fn publish_durable<Topic: Serialize + Send + Sync, Payload: Serialize + Send + Sync>(
+        &self,
+        topic: &Topic,
+        payload: &Payload,
+    ) -> Result<u64, Error> {
+        self.publish_durable_bytes(pot::to_vec(topic)?, pot::to_vec(payload)?)
+            .await
+    }
+
+    /// Stores `payload` in `topic` and publishes it to all current subscribers
+    /// of `topic`. Returns the offset of the stored message.
+    async fn publish_durable_bytes(&self, topic: Vec<u8>, payload: Vec<u8>) -> Result<u64, Error> {
+        let message = DurableMessage {
+            topic: Bytes::from(topic.clone()),
+            payload: Bytes::from(payload.clone()),
+            published_at: TimestampAsNanoseconds::now(),
+        }
+        .push_into_async(self)
+        .await?;
+        self.publish_bytes(topic, payload).await?;
+        Ok(message.header.id)
+    }
+
+    /// Returns up to `limit` messages stored in `topic` with an offset greater
+    /// than or equal to `offset`, in the order they were published.
+    async fn durable_messages_since<Topic: Serialize + Send + Sync>(
+        &self,
+        topic: &Topic,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<StoredMessage>, Error> {
+        self.durable_messages_since_bytes(pot::to_vec(topic)?, offset, limit)
+            .await
+    }
+
+    /// Returns up to `limit` messages stored in `topic` with an offset greater
+    /// than or equal to `offset`, in the order they were published.
+    async fn durable_messages_since_bytes(
+        &self,
+        topic: Vec<u8>,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<StoredMessage>, Error> {
+        let messages = self
+            .view::<MessagesByTopic>()
+            .with_key_range(topic_range(topic, offset))
+            .limit(limit)
+            .query_with_collection_docs()
+            .await?;
+        Ok(messages
+            .documents
+            .into_values()
+            .map(StoredMessage::from)
+            .collect())
+    }
+
+    /// Records that `consumer` has processed all messages in `topic` up to and
+    /// including `offset`.
+    async fn acknowledge_durable<Topic: Serialize + Send + Sync>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+        offset: u64,
+    ) -> Result<(), Error> {
+        ConsumerOffset {
+            offset,
+            acknowledged_at: TimestampAsNanoseconds::now(),
+        }
+        .overwrite_into_async(&consumer_offset_id(consumer, pot::to_vec(topic)?), self)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the last offset `consumer` acknowledged for `topic`, if any.
+    async fn durable_consumer_offset<Topic: Serialize + Send + Sync>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+    ) -> Result<Option<u64>, Error> {
+        let offset =
+            ConsumerOffset::get_async(&consumer_offset_id(consumer, pot::to_vec(topic)?), self)
+                .await?;
+        Ok(offset.map(|offset| offset.contents.offset))
+    }
+
+    /// Subscribes to `topic`, first replaying all stored messages with an
+    /// offset greater than or equal to `offset`.
+    async fn subscribe_from<Topic: Serialize + Send + Sync>(
+        &self,
+        topic: &Topic,
+        offset: u64,
+    ) -> Result<AsyncDurableSubscriber<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let topic = pot::to_vec(topic)?;
+        let subscriber = self.create_subscriber().await?;
+        subscriber.subscribe_to_bytes(topic.clone()).await?;
+        Ok(AsyncDurableSubscriber {
+            database: self.clone(),
+            subscriber,
+            topic,
+            next_offset: offset,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Subscribes to `topic`, resuming after the last offset acknowledged by
+    /// `consumer`. If `consumer` has never acknowledged a message in this
+    /// topic, all stored messages are replayed.
+    async fn subscribe_as_consumer<Topic: Serialize + Send + Sync>(
+        &self,
+        consumer: &str,
+        topic: &Topic,
+    ) -> Result<AsyncDurableSubscriber<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let offset = self
+            .durable_consumer_offset(consumer, topic)
+            .await?
+            .map_or(0, |offset| offset + 1);
+        self.subscribe_from(topic, offset).await
+    }
+}
+
+impl<T> AsyncDurablePubSub for T where T: AsyncConnection + AsyncPubSub {}
+
+/// A subscriber of a durable topic. Stored messages are returned in the order
+/// they were published, followed by new messages as they are published.
+#[must_use]
+pub struct AsyncDurableSubscriber<Database: AsyncPubSub> {
+    database: Database,
+    subscriber: Database::Subscriber,
+    topic: Vec<u8>,
+    next_offset: u64,
+    pending: VecDeque<StoredMessage>,
+}
+
+impl<Database> AsyncDurableSubscriber<Database>
+where
+    Database: AsyncDurablePubSub,
+{
+    /// Returns the next message in the topic. Waits until a message is
+    /// available. If the underlying subscriber becomes disconnected, an error
+    /// will be returned. A new subscriber can be created using
+    /// [`Self::next_offset()`] to resume consuming the topic.
+    pub async fn receive(&mut self) -> Result<StoredMessage, Error> {
+        loop {
+            if let Some(message) = self.try_receive().await? {
+                return Ok(message);
+            }
+
+            self.subscriber
+                .receiver()
+                .receive_async()
+                .await
+                .map_err(|_| Error::Networking(networking::Error::Disconnected))?;
+        }
+    }
+
+    /// Returns the next message in the topic, if one has been stored. This
+    /// function does not wait for new messages to be published.
+    pub async fn try_receive(&mut self) -> Result<Option<StoredMessage>, Error> {
+        if self.pending.is_empty() {
+            // Any notifications received before loading the next batch are
+            // covered by the query.
+            while self.subscriber.receiver().try_receive().is_ok() {}
+            let messages = self
+                .database
+                .durable_messages_since_bytes(
+                    self.topic.clone(),
+                    self.next_offset,
+                    REPLAY_BATCH_SIZE,
+                )
+                .await?;
+            self.pending.extend(messages);
+        }
+
+        Ok(self.pending.pop_front().map(|message| {
+            self.next_offset = message.offset + 1;
+            message
+        }))
+    }
+
+    /// Returns the offset of the next message this subscriber will return.
+    #[must_use]
+    pub const fn next_offset(&self) -> u64 {
+        self.next_offset
+    }
+}
@@ -0,0 +1,398 @@
+//! Request/reply messaging built on top of [`PubSub`].
+//!
+//! [`PubSubRpc::request()`] publishes a request to a topic and waits for a
+//! single response. Responders are created using
+//! [`PubSubRpc::respond_to()`], and answer one request at a time using
+//! [`Responder::respond()`].
+//!
+//! Each request is published with a unique correlation id and a reply topic
+//! that only the requester is subscribed to. When multiple responders are
+//! subscribed to the same topic, each responder attempts to claim the request
+//! using the key-value store before invoking its handler, ensuring that only
+//! one responder handles each request.
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use arc_bytes::serde::Bytes;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::key::time::TimestampAsNanoseconds;
+use crate::keyvalue::{AsyncKeyValue, KeyStatus, KeyValue};
+use crate::pubsub::{AsyncPubSub, AsyncSubscriber, PubSub, Receiver, Subscriber, TryReceiveError};
+use crate::{networking, Error};
+
+/// The key-value namespace used to claim requests.
+const RPC_NAMESPACE: &str = "bonsaidb.rpc";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RpcRequest {
+    correlation_id: String,
+    reply_topic: String,
+    timeout: Duration,
+    payload: Bytes,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RpcResponse {
+    correlation_id: String,
+    result: Result<Bytes, String>,
+}
+
+impl RpcResponse {
+    fn into_result(self) -> Result<Vec<u8>, Error> {
+        self.result
+            .map(Bytes::into_vec)
+            .map_err(|err| Error::other("bonsaidb-core pubsub rpc", err))
+    }
+}
+
+fn new_correlation_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{}-{:x}", TimestampAsNanoseconds::now(), hasher.finish())
+}
+
+fn new_request(topic_payload: Vec<u8>, timeout: Duration) -> RpcRequest {
+    let correlation_id = new_correlation_id();
+    RpcRequest {
+        reply_topic: format!("bonsaidb.rpc-reply.{correlation_id}"),
+        correlation_id,
+        timeout,
+        payload: Bytes::from(topic_payload),
+    }
+}
+
+fn parse_response(correlation_id: &str, payload: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let response = pot::from_slice::<RpcResponse>(payload)?;
+    if response.correlation_id == correlation_id {
+        response.into_result().map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn response_for<Response: Serialize, E: Display>(
+    correlation_id: String,
+    result: Result<Response, E>,
+) -> Result<Vec<u8>, Error> {
+    let result = match result {
+        Ok(response) => Ok(Bytes::from(pot::to_vec(&response)?)),
+        Err(err) => Err(err.to_string()),
+    };
+    Ok(pot::to_vec(&RpcResponse {
+        correlation_id,
+        result,
+    })?)
+}
+
+/// Waits up to `timeout` for `future` to complete, returning
+/// [`networking::Error::RequestTimeout`] if it doesn't.
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl Future<Output = Result<T, Error>> + Send,
+) -> Result<T, Error> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| Error::Networking(networking::Error::RequestTimeout))?
+}
+
+/// Waits for `future` to complete. No timer is available to enforce
+/// `timeout` in this configuration.
+#[cfg(not(all(feature = "async", not(target_arch = "wasm32"))))]
+async fn with_timeout<T>(
+    _timeout: Duration,
+    future: impl Future<Output = Result<T, Error>> + Send,
+) -> Result<T, Error> {
+    future.await
+}
+
+/// Sends requests and responds to requests over [`PubSub`]. This trait is
+/// automatically implemented for all types that implement both [`PubSub`] and
+/// [`KeyValue`].
+pub trait PubSubRpc: PubSub + KeyValue {
+    /// Publishes `request` to `topic` and waits up to `timeout` for a
+    /// response. If no responder answers within `timeout`,
+    /// [`networking::Error::RequestTimeout`] is returned.
+    fn request<Topic: Serialize, Request: Serialize, Response: DeserializeOwned>(
+        &self,
+        topic: &Topic,
+        request: &Request,
+        timeout: Duration,
+    ) -> Result<Response, Error> {
+        let response = self.request_bytes(pot::to_vec(topic)?, pot::to_vec(request)?, timeout)?;
+        Ok(pot::from_slice(&response)?)
+    }
+
+    /// Publishes `request` to `topic` and waits up to `timeout` for a
+    /// response. If no responder answers within `timeout`,
+    /// [`networking::Error::RequestTimeout`] is returned.
+    fn request_bytes(
+        &self,
+        topic: Vec<u8>,
+        request: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, Error> {
+        let deadline = Instant::now() + timeout;
+        let request = new_request(request, timeout);
+        let subscriber = self.create_subscriber()?;
+        subscriber.subscribe_to(&request.reply_topic)?;
+        self.publish_bytes(topic, pot::to_vec(&request)?)?;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match subscriber.receiver().receive_timeout(remaining) {
+                Ok(message) => {
+                    if let Some(response) =
+                        parse_response(&request.correlation_id, &message.payload[..])?
+                    {
+                        return Ok(response);
+                    }
+                }
+                Err(TryReceiveError::Empty) => {
+                    return Err(Error::Networking(networking::Error::RequestTimeout))
+                }
+                Err(TryReceiveError::Disconnected) => {
+                    return Err(Error::Networking(networking::Error::Disconnected))
+                }
+            }
+        }
+    }
+
+    /// Returns a [`Responder`] that answers requests published to `topic`.
+    fn respond_to<Topic: Serialize>(&self, topic: &Topic) -> Result<Responder<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let subscriber = self.create_subscriber()?;
+        subscriber.subscribe_to(topic)?;
+        Ok(Responder {
+            database: self.clone(),
+            subscriber,
+        })
+    }
+}
+
+impl<T> PubSubRpc for T where T: PubSub + KeyValue {}
+
+/// Answers requests sent using [`PubSubRpc::request()`].
+#[must_use]
+pub struct Responder<Database: PubSub> {
+    database: Database,
+    subscriber: Database::Subscriber,
+}
+
+impl<Database> Responder<Database>
+where
+    Database: PubSubRpc,
+{
+    /// Waits for the next request and replies with the result of `handler`.
+    /// Requests handled by another responder are skipped. If `handler`
+    /// returns an error, the requester receives an [`Error::Other`]
+    /// containing the error's message.
+    pub fn respond<Request, Response, E, Handler>(&self, handler: Handler) -> Result<(), Error>
+    where
+        Request: DeserializeOwned,
+        Response: Serialize,
+        E: Display,
+        Handler: FnOnce(Request) -> Result<Response, E>,
+    {
+        loop {
+            let message = self
+                .subscriber
+                .receiver()
+                .receive()
+                .map_err(|_| Error::Networking(networking::Error::Disconnected))?;
+            let Ok(request) = pot::from_slice::<RpcRequest>(&message.payload[..]) else {
+                continue;
+            };
+            if !self.claim(&request)? {
+                continue;
+            }
+
+            let result = pot::from_slice::<Request>(&request.payload)
+                .map_err(|err| err.to_string())
+                .and_then(|request| handler(request).map_err(|err| err.to_string()));
+            let response = response_for(request.correlation_id, result)?;
+            self.database
+                .publish_bytes(pot::to_vec(&request.reply_topic)?, response)?;
+            return Ok(());
+        }
+    }
+
+    fn claim(&self, request: &RpcRequest) -> Result<bool, Error> {
+        let status = self
+            .database
+            .with_key_namespace(RPC_NAMESPACE)
+            .set_key(request.correlation_id.clone(), &())
+            .only_if_vacant()
+            .expire_in(request.timeout)
+            .execute()?;
+        Ok(status == KeyStatus::Inserted)
+    }
+
+    /// Returns the receiver of the underlying subscriber.
+    pub fn receiver(&self) -> &Receiver {
+        self.subscriber.receiver()
+    }
+}
+
+/// Sends requests and responds to requests over [`AsyncPubSub`]. This trait
+/// is automatically implemented for all types that implement both
+/// [`AsyncPubSub`] and [`AsyncKeyValue`].
+#[async_trait]
+pub trait AsyncPubSubRpc: AsyncPubSub + AsyncKeyValue {
+    /// Publishes `request` to `topic` and waits up to `timeout` for a
+    /// response. If no responder answers within `timeout`,
+    /// [`networking::Error::RequestTimeout`] is returned.
+    ///
+    /// Enforcing `timeout` requires the `async` feature and isn't supported
+    /// on WebAssembly. Otherwise, this function waits until a response is
+    /// received or the subscriber is disconnected.
+    async fn request<
+        Topic: Serialize + Send + Sync,
+        Request: Serialize + Send + Sync,
+        Response: DeserializeOwned,
+    >(
+        &self,
+        topic: &Topic,
+        request: &Request,
+        timeout: Duration,
+    ) -> Result<Response, Error> {
+        let response = self
+            .request_bytes(pot::to_vec(topic)?, pot::to_vec(request)?, timeout)
+            .await?;
+        Ok(pot::from_slice(&response)?)
+    }
+
+    /// Publishes `request` to `topic` and waits up to `timeout` for a
+    /// response. If no responder answers within `timeout`,
+    /// [`networking::Error::RequestTimeout`] is returned.
+    ///
+    /// Enforcing `timeout` requires the `async` feature and isn't supported
+    /// on WebAssembly. Otherwise, this function waits until a response is
+    /// received or the subscriber is disconnected.
+    async fn request_bytes(
+        &self,
+        topic: Vec<u8>,
+        request: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, Error> {
+        let request = new_request(request, timeout);
+        let subscriber = self.create_subscriber().await?;
+        subscriber.subscribe_to(&request.reply_topic).await?;
+        self.publish_bytes(topic, pot::to_vec(&request)?).await?;
+
+        with_timeout(timeout, async {
+            loop {
+                let message = subscriber
+                    .receiver()
+                    .receive_async()
+                    .await
+                    .map_err(|_| Error::Networking(networking::Error::Disconnected))?;
+                if let Some(response) =
+                    parse_response(&request.correlation_id, &message.payload[..])?
+                {
+                    return Ok(response);
+                }
+            }
+        })
+        .await
+    }
+
+    /// Returns an [`AsyncResponder`] that answers requests published to
+    /// `topic`.
+    async fn respond_to<Topic: Serialize + Send + Sync>(
+        &self,
+        topic: &Topic,
+    ) -> Result<AsyncResponder<Self>, Error>
+    where
+        Self: Clone,
+    {
+        let subscriber = self.create_subscriber().await?;
+        subscriber.subscribe_to(topic).await?;
+        Ok(AsyncResponder {
+            database: self.clone(),
+            subscriber,
+        })
+    }
+}
+
+impl<T> AsyncPubSubRpc for T where T: AsyncPubSub + AsyncKeyValue {}
+
+/// Answers requests sent using [`AsyncPubSubRpc::request()`].
+#[must_use]
+pub struct AsyncResponder<Database: AsyncPubSub> {
+    database: Database,
+    subscriber: Database::Subscriber,
+}
+
+impl<Database> AsyncResponder<Database>
+where
+    Database: AsyncPubSubRpc,
+{
+    /// Waits for the next request and replies with the result of `handler`.
+    /// Requests handled by another responder are skipped. If `handler`
+    /// returns an error, the requester receives an [`Error::Other`]
+    /// containing the error's message.
+    pub async fn respond<Request, Response, E, Handler, HandlerFuture>(
+        &self,
+        handler: Handler,
+    ) -> Result<(), Error>
+    where
+        Request: DeserializeOwned,
+        Response: Serialize,
+        E: Display,
+        Handler: FnOnce(Request) -> HandlerFuture,
+        HandlerFuture: Future<Output = Result<Response, E>>,
+    {
+        loop {
+            let message = self
+                .subscriber
+                .receiver()
+                .receive_async()
+                .await
+                .map_err(|_| Error::Networking(networking::Error::Disconnected))?;
+            let Ok(request) = pot::from_slice::<RpcRequest>(&message.payload[..]) else {
+                continue;
+            };
+            if !self.claim(&request).await? {
+                continue;
+            }
+
+            let result = match pot::from_slice::<Request>(&request.payload) {
+                Ok(request) => handler(request).await.map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            let response = response_for(request.correlation_id, result)?;
+            self.database
+                .publish_bytes(pot::to_vec(&request.reply_topic)?, response)
+                .await?;
+            return Ok(());
+        }
+    }
+
+    async fn claim(&self, request: &RpcRequest) -> Result<bool, Error> {
+        let status = self
+            .database
+            .with_key_namespace(RPC_NAMESPACE)
+            .set_key(request.correlation_id.clone(), &())
+            .only_if_vacant()
+            .expire_in(request.timeout)
+            .await?;
+        Ok(status == KeyStatus::Inserted)
+    }
+
+    /// Returns the receiver of the underlying subscriber.
+    pub fn receiver(&self) -> &Receiver {
+        self.subscriber.receiver()
+    }
+}
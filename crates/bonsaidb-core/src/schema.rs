@@ -1,5 +1,9 @@
 mod collection;
+/// Types for defining document-level encryption keys.
+pub mod encryption;
 mod names;
+/// Types for defining document-level access policies.
+pub mod policy;
 mod schematic;
 mod summary;
 /// Types for defining map/reduce-powered `View`s.
@@ -11,10 +15,12 @@ pub use self::collection::{
     AsyncEntry, AsyncList, Collection, DefaultSerialization, InsertError, List, Nameable,
     NamedCollection, NamedReference, SerializedCollection,
 };
+pub use self::encryption::DocumentEncryption;
 pub use self::names::{
     Authority, CollectionName, InvalidNameError, Name, Qualified, QualifiedName, SchemaName,
     ViewName,
 };
+pub use self::policy::DocumentPolicy;
 pub use self::schematic::Schematic;
 pub use self::summary::{CollectionSummary, SchemaSummary, ViewSummary};
 pub use self::view::map::{Map, MappedValue, ViewMappedValue};
@@ -0,0 +1,82 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use derive_where::derive_where;
+
+use crate::document::{BorrowedDocument, CollectionDocument, KeyId};
+use crate::schema::{CollectionName, SerializedCollection};
+use crate::Error;
+
+/// Document-level encryption keys for a [`Collection`](super::Collection).
+///
+/// [`Collection::encryption_key`](super::Collection::encryption_key) chooses a
+/// single key that encrypts every document in a collection. Implementing this
+/// trait allows each document's contents to also be encrypted with a key chosen
+/// based on the document, such as a key named after the user or tenant that
+/// owns it. The keys must be registered using
+/// [`Schematic::define_document_encryption`](crate::schema::Schematic::define_document_encryption),
+/// typically from
+/// [`Collection::define_views`](super::Collection::define_views) or
+/// [`Schema::define_collections`](crate::schema::Schema::define_collections).
+///
+/// Document keys are stored in the vault of the storage and are created the
+/// first time they are used. Deleting a key makes every document encrypted
+/// with it permanently unreadable, while documents encrypted with other keys
+/// in the same collection are unaffected. Documents whose key has been deleted
+/// are omitted from results as if they did not exist, and can still be
+/// deleted.
+///
+/// View entries are not encrypted with document-level keys, so views can't be
+/// defined for collections that use document-level encryption.
+///
+/// Backups made by `bonsaidb-local` contain these documents still encrypted,
+/// and restoring a backup skips documents whose key has been deleted. Because
+/// the keys are stored in the vault rather than the backup, these documents
+/// can only be restored to a storage whose vault contains their keys. Copies
+/// of the storage's files, including its vault, are not affected by deleting
+/// a key.
+///
+/// Documents in a collection using document-level encryption are stored with
+/// a header indicating whether they are encrypted. Document encryption must be
+/// registered before any documents are stored in the collection.
+///
+/// Only [`KeyId::Id`] keys can be deleted. Returning [`KeyId::Master`] is
+/// allowed, but the master key can't be deleted.
+pub trait DocumentEncryption: SerializedCollection + Sized + 'static {
+    /// Returns the key to encrypt `document`'s contents with, or `None` if the
+    /// contents should not be encrypted with a document-level key.
+    fn document_encryption_key(document: &CollectionDocument<Self>) -> Option<KeyId>;
+}
+
+/// A [`DocumentEncryption`] implementation that operates on serialized
+/// documents.
+pub trait Serialized: Send + Sync + Debug {
+    /// Returns the name of the collection this applies to.
+    fn collection(&self) -> CollectionName;
+
+    /// Returns the key to encrypt `document`'s contents with, if any.
+    fn document_encryption_key(
+        &self,
+        document: &BorrowedDocument<'_>,
+    ) -> Result<Option<KeyId>, Error>;
+}
+
+#[derive_where(Default, Debug)]
+pub(crate) struct DocumentEncryptionInstance<C>(PhantomData<C>);
+
+impl<C> Serialized for DocumentEncryptionInstance<C>
+where
+    C: DocumentEncryption,
+{
+    fn collection(&self) -> CollectionName {
+        C::collection_name()
+    }
+
+    fn document_encryption_key(
+        &self,
+        document: &BorrowedDocument<'_>,
+    ) -> Result<Option<KeyId>, Error> {
+        let document = CollectionDocument::<C>::try_from(document)?;
+        Ok(C::document_encryption_key(&document))
+    }
+}
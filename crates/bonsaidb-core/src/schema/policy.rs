@@ -0,0 +1,167 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use derive_where::derive_where;
+
+use crate::connection::Session;
+use crate::document::{CollectionDocument, DocumentId, OwnedDocument};
+use crate::schema::{CollectionName, SerializedCollection};
+use crate::Error;
+
+/// Document-level access rules for a [`Collection`](super::Collection).
+///
+/// Permission statements can only grant or deny actions on an entire
+/// collection or on a document by its id. Implementing this trait allows a
+/// collection to make decisions based on the contents of each document and the
+/// identity of the [`Session`] accessing it, such as only allowing users to
+/// update documents they own. The policy must be registered using
+/// [`Schematic::define_policy`](crate::schema::Schematic::define_policy),
+/// typically from
+/// [`Collection::define_views`](super::Collection::define_views) or
+/// [`Schema::define_collections`](crate::schema::Schema::define_collections).
+///
+/// Policies are evaluated in addition to the session's permissions. They are
+/// only evaluated for connections that have a [`Session`], which includes all
+/// networked connections. Accessing a database without a session bypasses all
+/// policies in the same way that it bypasses all permission checks.
+///
+/// ## Reading
+///
+/// [`DocumentPolicy::can_read`] is evaluated for each document before it is
+/// returned from `get`, `list`, and view queries. Documents that can't be read
+/// are omitted from results as if they did not exist. Because limits are applied
+/// before policies are evaluated, a limited request may return fewer results
+/// than requested even when more readable documents exist. View entries whose
+/// source document can't be read are also omitted, and reductions only include
+/// the entries from readable documents.
+///
+/// The policy is given mutable access to the document, allowing individual
+/// fields to be redacted before the document is returned. Redacted documents
+/// should not be used to update the stored document, as the redacted contents
+/// would overwrite the stored contents.
+///
+/// ## Writing
+///
+/// [`DocumentPolicy::can_insert`], [`DocumentPolicy::can_update`], and
+/// [`DocumentPolicy::can_delete`] are evaluated for each operation in a
+/// transaction. If any operation is rejected, the entire transaction is
+/// rejected with a [`PermissionDenied`](Error::PermissionDenied) error. An
+/// overwrite is evaluated while the transaction is executing: as an update when
+/// a document already exists, and as an insert otherwise. Field-level restrictions can be implemented by
+/// comparing the existing document's contents to the updated contents.
+pub trait DocumentPolicy: SerializedCollection + Sized + 'static {
+    /// Returns true if `session` may read `document`. The document may be
+    /// modified to hide fields from `session`.
+    #[allow(unused_variables)]
+    fn can_read(session: &Session, document: &mut CollectionDocument<Self>) -> bool {
+        true
+    }
+
+    /// Returns true if `session` may insert a new document containing
+    /// `contents`. `id` is the id requested for the new document, if one was
+    /// specified.
+    #[allow(unused_variables)]
+    fn can_insert(
+        session: &Session,
+        id: Option<&Self::PrimaryKey>,
+        contents: &Self::Contents,
+    ) -> bool {
+        true
+    }
+
+    /// Returns true if `session` may replace `existing`'s contents with
+    /// `updated`.
+    #[allow(unused_variables)]
+    fn can_update(
+        session: &Session,
+        existing: &CollectionDocument<Self>,
+        updated: &Self::Contents,
+    ) -> bool {
+        true
+    }
+
+    /// Returns true if `session` may delete `existing`.
+    #[allow(unused_variables)]
+    fn can_delete(session: &Session, existing: &CollectionDocument<Self>) -> bool {
+        true
+    }
+}
+
+/// A [`DocumentPolicy`] that operates on serialized documents.
+pub trait Serialized: Send + Sync + Debug {
+    /// Returns the name of the collection this policy applies to.
+    fn collection(&self) -> CollectionName;
+
+    /// Returns true if `session` may read `document`. The document's contents
+    /// may be modified to hide fields from `session`.
+    fn can_read(&self, session: &Session, document: &mut OwnedDocument) -> Result<bool, Error>;
+
+    /// Returns true if `session` may insert a document containing `contents`.
+    fn can_insert(
+        &self,
+        session: &Session,
+        id: Option<&DocumentId>,
+        contents: &[u8],
+    ) -> Result<bool, Error>;
+
+    /// Returns true if `session` may replace the contents of `existing` with
+    /// `updated`.
+    fn can_update(
+        &self,
+        session: &Session,
+        existing: &OwnedDocument,
+        updated: &[u8],
+    ) -> Result<bool, Error>;
+
+    /// Returns true if `session` may delete `existing`.
+    fn can_delete(&self, session: &Session, existing: &OwnedDocument) -> Result<bool, Error>;
+}
+
+#[derive_where(Default, Debug)]
+pub(crate) struct PolicyInstance<C>(PhantomData<C>);
+
+impl<C> Serialized for PolicyInstance<C>
+where
+    C: DocumentPolicy,
+{
+    fn collection(&self) -> CollectionName {
+        C::collection_name()
+    }
+
+    fn can_read(&self, session: &Session, document: &mut OwnedDocument) -> Result<bool, Error> {
+        let mut collection_document = CollectionDocument::<C>::try_from(&*document)?;
+        if C::can_read(session, &mut collection_document) {
+            document.contents = C::serialize(&collection_document.contents)?.into();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn can_insert(
+        &self,
+        session: &Session,
+        id: Option<&DocumentId>,
+        contents: &[u8],
+    ) -> Result<bool, Error> {
+        let id = id.map(|id| id.deserialize::<C::PrimaryKey>()).transpose()?;
+        let contents = C::deserialize(contents)?;
+        Ok(C::can_insert(session, id.as_ref(), &contents))
+    }
+
+    fn can_update(
+        &self,
+        session: &Session,
+        existing: &OwnedDocument,
+        updated: &[u8],
+    ) -> Result<bool, Error> {
+        let existing = CollectionDocument::<C>::try_from(existing)?;
+        let updated = C::deserialize(updated)?;
+        Ok(C::can_update(session, &existing, &updated))
+    }
+
+    fn can_delete(&self, session: &Session, existing: &OwnedDocument) -> Result<bool, Error> {
+        let existing = CollectionDocument::<C>::try_from(existing)?;
+        Ok(C::can_delete(session, &existing))
+    }
+}
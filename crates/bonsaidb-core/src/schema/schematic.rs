@@ -8,6 +8,8 @@ use derive_where::derive_where;
 use crate::document::{BorrowedDocument, DocumentId, KeyId};
 use crate::key::{ByteSource, Key, KeyDescription};
 use crate::schema::collection::Collection;
+use crate::schema::encryption::{self, DocumentEncryption, DocumentEncryptionInstance};
+use crate::schema::policy::{self, DocumentPolicy, PolicyInstance};
 use crate::schema::view::map::{self, MappedValue};
 use crate::schema::view::{
     self, MapReduce, Serialized, SerializedView, ViewSchema, ViewUpdatePolicy,
@@ -27,6 +29,8 @@ pub struct Schematic {
     views_by_name: HashMap<ViewName, TypeId>,
     views_by_collection: HashMap<CollectionName, Vec<TypeId>>,
     eager_views_by_collection: HashMap<CollectionName, Vec<TypeId>>,
+    policies: HashMap<CollectionName, Box<dyn policy::Serialized>>,
+    document_encryption: HashMap<CollectionName, Box<dyn encryption::Serialized>>,
 }
 
 impl Schematic {
@@ -42,6 +46,8 @@ impl Schematic {
             views_by_name: HashMap::new(),
             views_by_collection: HashMap::new(),
             eager_views_by_collection: HashMap::new(),
+            policies: HashMap::new(),
+            document_encryption: HashMap::new(),
         };
         S::define_collections(&mut schematic)?;
         Ok(schematic)
@@ -90,6 +96,9 @@ impl Schematic {
         }
 
         let collection = instance.collection();
+        if self.document_encryption.contains_key(&collection) {
+            return Err(Error::ViewsWithDocumentEncryption(collection));
+        }
         let eager = instance.update_policy().is_eager();
         self.views.insert(TypeId::of::<V>(), Box::new(instance));
         self.views_by_name.insert(name, TypeId::of::<V>());
@@ -110,6 +119,47 @@ impl Schematic {
         Ok(())
     }
 
+    /// Registers `C`'s [`DocumentPolicy`], replacing any previously registered
+    /// policy for the collection.
+    pub fn define_policy<C: DocumentPolicy>(&mut self) {
+        self.policies
+            .insert(C::collection_name(), Box::<PolicyInstance<C>>::default());
+    }
+
+    /// Returns the [`DocumentPolicy`] registered for `collection`, if any.
+    #[must_use]
+    pub fn policy_for(&self, collection: &CollectionName) -> Option<&'_ dyn policy::Serialized> {
+        self.policies.get(collection).map(AsRef::as_ref)
+    }
+
+    /// Registers `C`'s [`DocumentEncryption`], replacing any previously
+    /// registered document encryption for the collection.
+    ///
+    /// Returns [`Error::ViewsWithDocumentEncryption`] if any views have been
+    /// defined for `C`.
+    pub fn define_document_encryption<C: DocumentEncryption>(&mut self) -> Result<(), Error> {
+        let collection = C::collection_name();
+        if self
+            .views_by_collection
+            .get(&collection)
+            .map_or(false, |views| !views.is_empty())
+        {
+            return Err(Error::ViewsWithDocumentEncryption(collection));
+        }
+        self.document_encryption
+            .insert(collection, Box::<DocumentEncryptionInstance<C>>::default());
+        Ok(())
+    }
+
+    /// Returns the [`DocumentEncryption`] registered for `collection`, if any.
+    #[must_use]
+    pub fn document_encryption_for(
+        &self,
+        collection: &CollectionName,
+    ) -> Option<&'_ dyn encryption::Serialized> {
+        self.document_encryption.get(collection).map(AsRef::as_ref)
+    }
+
     /// Returns `true` if this schema contains the collection `C`.
     #[must_use]
     pub fn contains_collection<C: Collection + 'static>(&self) -> bool {
@@ -227,6 +277,8 @@ impl Debug for Schematic {
             .field("views_by_name", &self.views_by_name)
             .field("views_by_collection", &self.views_by_collection)
             .field("eager_views_by_collection", &self.eager_views_by_collection)
+            .field("policies", &self.policies)
+            .field("document_encryption", &self.document_encryption)
             .finish()
     }
 }
@@ -32,8 +32,11 @@ use crate::transaction::{Operation, OperationResult, Transaction};
 use crate::Error;
 #[cfg(feature = "token-authentication")]
 use crate::{
-    admin::AuthenticationToken,
+    admin::{AuthenticationToken, AuthenticationTokenOptions},
     connection::{HasSession, Identity, IdentityReference, Session},
+    key::time::TimestampAsNanoseconds,
+    permissions::bonsai::{BonsaiAction, ServerAction},
+    permissions::Statement,
 };
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default, Clone, Collection)]
@@ -495,6 +498,8 @@ pub enum HarnessTest {
     KvExpiration,
     KvDeleteExpire,
     KvTransactions,
+    PubSubPatterns,
+    PubSubRequestReply,
 }
 
 impl HarnessTest {
@@ -2927,6 +2932,81 @@ pub async fn token_authentication_tests<C: AsyncConnection, S: AsyncStorageConne
         assert_eq!(*id, role.header.id);
     }
 
+    // Active sessions can be listed and revoked.
+    let role_session_id = as_role.session().and_then(|session| session.id).unwrap();
+    assert!(server
+        .list_sessions()
+        .await?
+        .iter()
+        .any(|session| session.id == role_session_id));
+    server.revoke_session(role_session_id).await?;
+    assert!(!server
+        .list_sessions()
+        .await?
+        .iter()
+        .any(|session| session.id == role_session_id));
+    assert!(matches!(
+        server.revoke_session(role_session_id).await,
+        Err(Error::SessionNotFound(_))
+    ));
+
+    // Tokens record when they were last used, and can be listed and revoked.
+    let tokens = server
+        .list_authentication_tokens(IdentityReference::user(&username)?)
+        .await?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].id, user_token.header.id);
+    assert!(tokens[0].last_used_at.is_some());
+    server
+        .revoke_authentication_token(user_token.header.id)
+        .await?;
+    assert!(matches!(
+        server
+            .authenticate_with_token(user_token.header.id, &user_token.contents.token)
+            .await,
+        Err(Error::InvalidCredentials)
+    ));
+    assert!(server
+        .list_authentication_tokens(IdentityReference::user(&username)?)
+        .await?
+        .is_empty());
+
+    // Expired tokens can't be used to authenticate.
+    let expired_token = AuthenticationToken::create_with_options_async(
+        IdentityReference::user(&username)?,
+        &AuthenticationTokenOptions::default().expires_at(TimestampAsNanoseconds::try_from(
+            std::time::SystemTime::now() - Duration::from_secs(60),
+        )?),
+        admin,
+    )
+    .await?;
+    assert!(matches!(
+        server
+            .authenticate_with_token(expired_token.header.id, &expired_token.contents.token)
+            .await,
+        Err(Error::InvalidCredentials)
+    ));
+
+    // Sessions authenticated with a scoped token are limited to the scope.
+    let scoped_token = AuthenticationToken::create_with_options_async(
+        IdentityReference::user(&username)?,
+        &AuthenticationTokenOptions::default().scope(vec![
+            Statement::for_any().allowing(&BonsaiAction::Server(ServerAction::ListDatabases))
+        ]),
+        admin,
+    )
+    .await?;
+    let as_scoped = server
+        .authenticate_with_token(scoped_token.header.id, &scoped_token.contents.token)
+        .await?;
+    assert!(as_scoped
+        .session()
+        .map_or(false, |session| session.scope.is_some()));
+    assert!(matches!(
+        as_scoped.create_user(&format!("{username}-scoped")).await,
+        Err(Error::PermissionDenied(_))
+    ));
+
     Ok(())
 }
 
@@ -2959,6 +3039,67 @@ pub fn blocking_token_authentication_tests<C: Connection, S: StorageConnection>(
         assert_eq!(*id, role.header.id);
     }
 
+    // Active sessions can be listed and revoked.
+    let role_session_id = as_role.session().and_then(|session| session.id).unwrap();
+    assert!(server
+        .list_sessions()?
+        .iter()
+        .any(|session| session.id == role_session_id));
+    server.revoke_session(role_session_id)?;
+    assert!(!server
+        .list_sessions()?
+        .iter()
+        .any(|session| session.id == role_session_id));
+    assert!(matches!(
+        server.revoke_session(role_session_id),
+        Err(Error::SessionNotFound(_))
+    ));
+
+    // Tokens record when they were last used, and can be listed and revoked.
+    let tokens = server.list_authentication_tokens(IdentityReference::user(&username)?)?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].id, user_token.header.id);
+    assert!(tokens[0].last_used_at.is_some());
+    server.revoke_authentication_token(user_token.header.id)?;
+    assert!(matches!(
+        server.authenticate_with_token(user_token.header.id, &user_token.contents.token),
+        Err(Error::InvalidCredentials)
+    ));
+    assert!(server
+        .list_authentication_tokens(IdentityReference::user(&username)?)?
+        .is_empty());
+
+    // Expired tokens can't be used to authenticate.
+    let expired_token = AuthenticationToken::create_with_options(
+        &IdentityReference::user(&username)?,
+        &AuthenticationTokenOptions::default().expires_at(TimestampAsNanoseconds::try_from(
+            std::time::SystemTime::now() - Duration::from_secs(60),
+        )?),
+        admin,
+    )?;
+    assert!(matches!(
+        server.authenticate_with_token(expired_token.header.id, &expired_token.contents.token),
+        Err(Error::InvalidCredentials)
+    ));
+
+    // Sessions authenticated with a scoped token are limited to the scope.
+    let scoped_token = AuthenticationToken::create_with_options(
+        &IdentityReference::user(&username)?,
+        &AuthenticationTokenOptions::default().scope(vec![
+            Statement::for_any().allowing(&BonsaiAction::Server(ServerAction::ListDatabases))
+        ]),
+        admin,
+    )?;
+    let as_scoped =
+        server.authenticate_with_token(scoped_token.header.id, &scoped_token.contents.token)?;
+    assert!(as_scoped
+        .session()
+        .map_or(false, |session| session.scope.is_some()));
+    assert!(matches!(
+        as_scoped.create_user(&format!("{username}-scoped")),
+        Err(Error::PermissionDenied(_))
+    ));
+
     Ok(())
 }
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bonsaidb_core::schema::{Qualified, Schema, SchemaName, Schematic};
 use bonsaidb_jobs::queue::{Queue, QueueOwner};
 use bonsaidb_local::{
@@ -20,6 +22,21 @@ impl Schema for TestSchema {
 
 fn main() {
     let db = Database::open::<TestSchema>(StorageConfiguration::new("jobs-test.bonsaidb")).unwrap();
-    let queue = Queue::create(QueueOwner::Backend, "hello-world", &db).unwrap();
+    let queue = Queue::find_or_create(QueueOwner::Backend, "hello-world", &db).unwrap();
     println!("Queue: {queue:?}");
+
+    let job_id = queue.enqueue(&"Hello, World!", &db).unwrap();
+    println!("Enqueued job {job_id}");
+
+    while let Some(job) = queue
+        .lease("example", Duration::from_secs(30), &db)
+        .unwrap()
+    {
+        println!(
+            "Leased job {}: {}",
+            job.id(),
+            job.payload::<String>().unwrap()
+        );
+        job.complete(&db).unwrap();
+    }
 }
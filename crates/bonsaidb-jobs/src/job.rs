@@ -0,0 +1,339 @@
+use std::fmt::Display;
+use std::time::{Duration, SystemTime};
+
+use bonsaidb_core::arc_bytes::serde::Bytes;
+#[cfg(feature = "async")]
+use bonsaidb_core::connection::AsyncConnection;
+use bonsaidb_core::connection::Connection;
+use bonsaidb_core::document::{CollectionDocument, Emit};
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
+use bonsaidb_core::key::Key;
+use bonsaidb_core::schema::view::map::Mappings;
+use bonsaidb_core::schema::{Collection, CollectionMapReduce, View, ViewMapResult, ViewSchema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// A job stored in a [`Queue`](crate::queue::Queue).
+#[derive(Collection, Serialize, Deserialize, Clone, Debug)]
+#[collection(name = "jobs", authority = "bonsaidb", views = [AvailableJobs, JobsByStatus], core = bonsaidb_core)]
+pub struct Job {
+    /// The id of the queue this job belongs to.
+    pub queue_id: u64,
+    /// The serialized payload of this job.
+    pub payload: Bytes,
+    /// The priority of this job. Higher priorities are leased first.
+    pub priority: i32,
+    /// The current status of this job.
+    pub status: JobStatus,
+    /// The time this job was enqueued.
+    pub enqueued_at: TimestampAsNanoseconds,
+    /// When [`JobStatus::Pending`], the time this job can be leased. When
+    /// [`JobStatus::Leased`], the time the current lease expires.
+    pub available_at: TimestampAsNanoseconds,
+    /// The number of times this job has been leased.
+    pub attempts: u32,
+    /// The policy used to retry this job after a failure.
+    pub retry_policy: RetryPolicy,
+    /// The worker that most recently leased this job.
+    pub leased_by: Option<String>,
+    /// The error from the most recent failed attempt.
+    pub last_error: Option<String>,
+    /// The time this job was completed or dead-lettered.
+    pub finished_at: Option<TimestampAsNanoseconds>,
+}
+
+impl Job {
+    /// Deserializes the payload as `Payload` using `pot`.
+    pub fn payload<Payload: DeserializeOwned>(&self) -> Result<Payload, Error> {
+        Ok(pot::from_slice(&self.payload)?)
+    }
+
+    pub(crate) fn is_available(&self, now: TimestampAsNanoseconds) -> bool {
+        matches!(self.status, JobStatus::Pending | JobStatus::Leased) && self.available_at <= now
+    }
+
+    /// Leases this job to `worker`. If this job's previous lease expired and
+    /// the job has no attempts remaining, the job is dead-lettered instead
+    /// and `false` is returned.
+    pub(crate) fn lease(
+        &mut self,
+        worker: &str,
+        visibility_timeout: Duration,
+    ) -> Result<bool, Error> {
+        if self.status == JobStatus::Leased && self.attempts >= self.retry_policy.max_attempts {
+            self.dead_letter(String::from("lease expired"));
+            return Ok(false);
+        }
+
+        self.status = JobStatus::Leased;
+        self.available_at = timestamp_after(visibility_timeout)?;
+        self.attempts = self.attempts.saturating_add(1);
+        self.leased_by = Some(worker.to_string());
+        Ok(true)
+    }
+
+    fn complete(&mut self) {
+        self.status = JobStatus::Completed;
+        self.finished_at = Some(TimestampAsNanoseconds::now());
+    }
+
+    fn fail(&mut self, error: String) -> Result<(), Error> {
+        if self.attempts >= self.retry_policy.max_attempts {
+            self.dead_letter(error);
+        } else {
+            self.status = JobStatus::Pending;
+            self.available_at = timestamp_after(self.retry_policy.backoff(self.attempts))?;
+            self.last_error = Some(error);
+        }
+        Ok(())
+    }
+
+    fn dead_letter(&mut self, error: String) {
+        self.status = JobStatus::DeadLettered;
+        self.last_error = Some(error);
+        self.finished_at = Some(TimestampAsNanoseconds::now());
+    }
+
+    pub(crate) fn requeue(&mut self) -> Result<(), Error> {
+        if self.status != JobStatus::DeadLettered {
+            return Err(Error::InvalidStatus);
+        }
+
+        self.status = JobStatus::Pending;
+        self.available_at = TimestampAsNanoseconds::now();
+        self.attempts = 0;
+        self.finished_at = None;
+        Ok(())
+    }
+}
+
+/// The status of a [`Job`].
+#[derive(Key, Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[key(core = bonsaidb_core)]
+pub enum JobStatus {
+    /// The job is waiting to be leased.
+    Pending,
+    /// The job is leased by a worker.
+    Leased,
+    /// The job was completed successfully.
+    Completed,
+    /// The job exhausted its attempts without being completed.
+    DeadLettered,
+}
+
+/// Controls how a [`Job`] is retried after failing.
+///
+/// After each failed attempt, the job becomes available again after a delay
+/// of `initial_backoff * 2^(attempt - 1)`, limited to `max_backoff`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of times the job will be leased before being
+    /// dead-lettered.
+    pub max_attempts: u32,
+    /// The delay before retrying after the first failure.
+    pub initial_backoff: Duration,
+    /// The maximum delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy allowing 5 attempts with a backoff starting at 1 second
+    /// and limited to 5 minutes.
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before retrying after `attempt` has failed.
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .checked_mul(1 << exponent)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// A [`Job`] leased by a worker. Returned from
+/// [`Queue::lease`](crate::queue::Queue::lease).
+///
+/// The job must be completed or failed before its lease expires. Otherwise,
+/// the job may be leased by another worker, and this lease will return
+/// [`Error::LeaseLost`].
+#[derive(Debug, Clone)]
+pub struct LeasedJob {
+    document: CollectionDocument<Job>,
+}
+
+impl From<CollectionDocument<Job>> for LeasedJob {
+    fn from(document: CollectionDocument<Job>) -> Self {
+        Self { document }
+    }
+}
+
+impl LeasedJob {
+    /// Returns the unique id of this job.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.document.header.id
+    }
+
+    /// Returns the attempt number of this lease, starting at 1.
+    #[must_use]
+    pub const fn attempt(&self) -> u32 {
+        self.document.contents.attempts
+    }
+
+    /// Returns the time this lease expires.
+    #[must_use]
+    pub const fn lease_expires_at(&self) -> TimestampAsNanoseconds {
+        self.document.contents.available_at
+    }
+
+    /// Returns the job.
+    #[must_use]
+    pub const fn job(&self) -> &Job {
+        &self.document.contents
+    }
+
+    /// Deserializes the payload as `Payload` using `pot`.
+    pub fn payload<Payload: DeserializeOwned>(&self) -> Result<Payload, Error> {
+        self.document.contents.payload()
+    }
+
+    /// Returns the serialized payload.
+    #[must_use]
+    pub fn payload_bytes(&self) -> &[u8] {
+        &self.document.contents.payload
+    }
+
+    /// Extends this lease so that it expires after `visibility_timeout`.
+    pub fn extend<Database: Connection>(
+        &mut self,
+        visibility_timeout: Duration,
+        database: &Database,
+    ) -> Result<(), Error> {
+        self.document.contents.available_at = timestamp_after(visibility_timeout)?;
+        self.document.update(database).map_err(lease_error)
+    }
+
+    /// Extends this lease so that it expires after `visibility_timeout`.
+    #[cfg(feature = "async")]
+    pub async fn extend_async<Database: AsyncConnection>(
+        &mut self,
+        visibility_timeout: Duration,
+        database: &Database,
+    ) -> Result<(), Error> {
+        self.document.contents.available_at = timestamp_after(visibility_timeout)?;
+        self.document
+            .update_async(database)
+            .await
+            .map_err(lease_error)
+    }
+
+    /// Marks this job as completed.
+    pub fn complete<Database: Connection>(mut self, database: &Database) -> Result<(), Error> {
+        self.document.contents.complete();
+        self.document.update(database).map_err(lease_error)
+    }
+
+    /// Marks this job as completed.
+    #[cfg(feature = "async")]
+    pub async fn complete_async<Database: AsyncConnection>(
+        mut self,
+        database: &Database,
+    ) -> Result<(), Error> {
+        self.document.contents.complete();
+        self.document
+            .update_async(database)
+            .await
+            .map_err(lease_error)
+    }
+
+    /// Records that this attempt failed with `error`. If the job has attempts
+    /// remaining, it will be retried after the delay specified by its
+    /// [`RetryPolicy`]. Otherwise, the job is dead-lettered. Returns the new
+    /// status of the job.
+    pub fn fail<Database: Connection>(
+        mut self,
+        error: impl Display,
+        database: &Database,
+    ) -> Result<JobStatus, Error> {
+        self.document.contents.fail(error.to_string())?;
+        self.document.update(database).map_err(lease_error)?;
+        Ok(self.document.contents.status)
+    }
+
+    /// Records that this attempt failed with `error`. If the job has attempts
+    /// remaining, it will be retried after the delay specified by its
+    /// [`RetryPolicy`]. Otherwise, the job is dead-lettered. Returns the new
+    /// status of the job.
+    #[cfg(feature = "async")]
+    pub async fn fail_async<Database: AsyncConnection>(
+        mut self,
+        error: impl Display + Send,
+        database: &Database,
+    ) -> Result<JobStatus, Error> {
+        self.document.contents.fail(error.to_string())?;
+        self.document
+            .update_async(database)
+            .await
+            .map_err(lease_error)?;
+        Ok(self.document.contents.status)
+    }
+}
+
+fn lease_error(error: bonsaidb_core::Error) -> Error {
+    if error.conflicting_document::<Job>().is_some() {
+        Error::LeaseLost
+    } else {
+        Error::from(error)
+    }
+}
+
+pub(crate) fn timestamp_after(delay: Duration) -> Result<TimestampAsNanoseconds, Error> {
+    Ok(TimestampAsNanoseconds::try_from(SystemTime::now() + delay)?)
+}
+
+/// Indexes [`Job`]s that are pending or leased by their queue, their negated
+/// priority, and the time they become available. Jobs are ordered in the order
+/// they should be leased: highest priority first, and then by the time they
+/// become available.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = Job, key = (u64, i64, TimestampAsNanoseconds), value = (), name = "available", core = bonsaidb_core)]
+#[view_schema(core = bonsaidb_core)]
+pub struct AvailableJobs;
+
+impl CollectionMapReduce for AvailableJobs {
+    fn map<'doc>(&self, document: CollectionDocument<Job>) -> ViewMapResult<'doc, Self> {
+        match document.contents.status {
+            JobStatus::Pending | JobStatus::Leased => document.header.emit_key((
+                document.contents.queue_id,
+                -i64::from(document.contents.priority),
+                document.contents.available_at,
+            )),
+            JobStatus::Completed | JobStatus::DeadLettered => Ok(Mappings::none()),
+        }
+    }
+}
+
+/// Indexes [`Job`]s by their queue and status.
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = Job, key = (u64, JobStatus), value = (), name = "by-status", core = bonsaidb_core)]
+#[view_schema(core = bonsaidb_core)]
+pub struct JobsByStatus;
+
+impl CollectionMapReduce for JobsByStatus {
+    fn map<'doc>(&self, document: CollectionDocument<Job>) -> ViewMapResult<'doc, Self> {
+        document
+            .header
+            .emit_key((document.contents.queue_id, document.contents.status))
+    }
+}
@@ -0,0 +1,122 @@
+//! Persistent background job queues for BonsaiDb.
+//!
+//! This crate stores jobs in collections, allowing queued work to survive
+//! restarts and to be shared between any number of workers connected to the
+//! same database. Because all operations are implemented using the
+//! [`Connection`](bonsaidb_core::connection::Connection) and
+//! [`AsyncConnection`](bonsaidb_core::connection::AsyncConnection) traits,
+//! queues can be used from an embedded database as well as through
+//! `bonsaidb-client`.
+//!
+//! # Queues and Jobs
+//!
+//! A [`Queue`](queue::Queue) is identified by its [owner](queue::QueueOwner)
+//! and its name. Jobs are enqueued using [`Queue::enqueue`](queue::Queue::enqueue)
+//! or, to customize the priority, delay, or retry policy, using
+//! [`Queue::build_job`](queue::Queue::build_job).
+//!
+//! Workers call [`Queue::lease`](queue::Queue::lease) to claim the next
+//! available job. The returned [`LeasedJob`](job::LeasedJob) is hidden from
+//! other workers until its visibility timeout elapses. Once the work is done,
+//! the worker calls [`LeasedJob::complete`](job::LeasedJob::complete). If the
+//! work fails, [`LeasedJob::fail`](job::LeasedJob::fail) schedules a retry
+//! using the job's [`RetryPolicy`](job::RetryPolicy). Jobs that exhaust their
+//! attempts, either by failing or by having their lease expire too many times,
+//! are [dead-lettered](job::JobStatus::DeadLettered).
+//!
+//! The collections used by this crate must be registered in the database's
+//! schema using [`define_collections`].
+//!
+//! # Async Support
+//!
+//! Enabling the `async` feature flag adds `_async` variants of each function
+//! that accept an [`AsyncConnection`](bonsaidb_core::connection::AsyncConnection).
+#![forbid(unsafe_code)]
+#![warn(
+    clippy::cargo,
+    missing_docs,
+    // clippy::missing_docs_in_private_items,
+    clippy::pedantic,
+    future_incompatible,
+    rust_2018_idioms,
+)]
+#![allow(
+    clippy::missing_errors_doc, // TODO clippy::missing_errors_doc
+    clippy::option_if_let_else,
+    clippy::module_name_repetitions,
+)]
+
+use bonsaidb_core::schema::{InsertError, Schematic};
+
+/// Types for managing jobs.
+pub mod job;
+/// Types for managing queues.
+pub mod queue;
+
+/// Registers the collections needed for job queues into `schema`.
+pub fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+    schema.define_collection::<queue::JobQueue>()?;
+    schema.define_collection::<job::Job>()?;
+
+    Ok(())
+}
+
+/// Errors that can be returned when interacting with job queues.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// An underlying database error was returned.
+    #[error("database error: {0}")]
+    Database(bonsaidb_core::Error),
+    /// An attempt at creating a queue failed because a queue with the same
+    /// owner and name already exists.
+    #[error("a queue already exists with the owner and name provided")]
+    AlreadyExists,
+    /// The job could not be found in the queue.
+    #[error("the job was not found")]
+    JobNotFound,
+    /// The job's lease expired and the job was modified by another worker.
+    #[error("the job's lease was lost")]
+    LeaseLost,
+    /// The job is not in a state that allows the requested operation.
+    #[error("the job's status does not allow this operation")]
+    InvalidStatus,
+}
+
+impl<T> From<InsertError<T>> for Error {
+    fn from(err: InsertError<T>) -> Self {
+        Self::from(err.error)
+    }
+}
+
+impl From<bonsaidb_core::Error> for Error {
+    fn from(err: bonsaidb_core::Error) -> Self {
+        match err {
+            bonsaidb_core::Error::UniqueKeyViolation { .. } => Self::AlreadyExists,
+            other => Self::Database(other),
+        }
+    }
+}
+
+impl From<pot::Error> for Error {
+    fn from(err: pot::Error) -> Self {
+        Self::Database(bonsaidb_core::Error::from(err))
+    }
+}
+
+impl From<bonsaidb_core::key::time::TimeError> for Error {
+    fn from(err: bonsaidb_core::key::time::TimeError) -> Self {
+        Self::Database(bonsaidb_core::Error::from(err))
+    }
+}
+
+impl From<Error> for bonsaidb_core::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Database(err) => err,
+            other => Self::other("bonsaidb-jobs", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
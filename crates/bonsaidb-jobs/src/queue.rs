@@ -0,0 +1,608 @@
+use std::time::Duration;
+
+use bonsaidb_core::arc_bytes::serde::Bytes;
+#[cfg(feature = "async")]
+use bonsaidb_core::connection::AsyncConnection;
+use bonsaidb_core::connection::Connection;
+use bonsaidb_core::document::{CollectionDocument, Emit};
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
+use bonsaidb_core::key::Key;
+use bonsaidb_core::schema::view::map::ViewMappings;
+use bonsaidb_core::schema::{
+    Collection, CollectionMapReduce, SerializedCollection, View, ViewMapResult, ViewSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::job::{
+    timestamp_after, AvailableJobs, Job, JobStatus, JobsByStatus, LeasedJob, RetryPolicy,
+};
+use crate::Error;
+
+/// The maximum number of entries loaded from [`AvailableJobs`] at once while
+/// leasing a job.
+pub(crate) const LEASE_BATCH_SIZE: u32 = 32;
+
+/// The owner of a [`Queue`].
+#[derive(Key, Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[key(core = bonsaidb_core)]
+pub enum QueueOwner {
+    /// The queue is owned by the server's backend.
+    Backend,
+    /// The queue is owned by the user with the given id.
+    User(u64),
+}
+
+/// A named queue of jobs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Queue {
+    id: u64,
+    owner: QueueOwner,
+    name: String,
+}
+
+impl Queue {
+    /// Creates a new queue named `name` owned by `owner`. Returns
+    /// [`Error::AlreadyExists`] if a queue with the same owner and name
+    /// already exists.
+    pub fn create<Database: Connection>(
+        owner: QueueOwner,
+        name: impl Into<String>,
+        database: &Database,
+    ) -> Result<Self, Error> {
+        let document = JobQueue {
+            owner,
+            name: name.into(),
+        }
+        .push_into(database)?;
+        Ok(Self::from(document))
+    }
+
+    /// Creates a new queue named `name` owned by `owner`. Returns
+    /// [`Error::AlreadyExists`] if a queue with the same owner and name
+    /// already exists.
+    #[cfg(feature = "async")]
+    pub async fn create_async<Database: AsyncConnection>(
+        owner: QueueOwner,
+        name: impl Into<String>,
+        database: &Database,
+    ) -> Result<Self, Error> {
+        let document = JobQueue {
+            owner,
+            name: name.into(),
+        }
+        .push_into_async(database)
+        .await?;
+        Ok(Self::from(document))
+    }
+
+    /// Returns the queue named `name` owned by `owner`, if found.
+    pub fn find<Database: Connection>(
+        owner: QueueOwner,
+        name: &str,
+        database: &Database,
+    ) -> Result<Option<Self>, Error> {
+        let key = (owner, name.to_string());
+        let mappings = database
+            .view::<QueuesByName>()
+            .with_key(&key)
+            .query_with_collection_docs()?;
+        Ok(mappings.documents.into_values().next().map(Self::from))
+    }
+
+    /// Returns the queue named `name` owned by `owner`, if found.
+    #[cfg(feature = "async")]
+    pub async fn find_async<Database: AsyncConnection>(
+        owner: QueueOwner,
+        name: &str,
+        database: &Database,
+    ) -> Result<Option<Self>, Error> {
+        let key = (owner, name.to_string());
+        let mappings = database
+            .view::<QueuesByName>()
+            .with_key(&key)
+            .query_with_collection_docs()
+            .await?;
+        Ok(mappings.documents.into_values().next().map(Self::from))
+    }
+
+    /// Returns the queue named `name` owned by `owner`, creating it if it does
+    /// not exist.
+    pub fn find_or_create<Database: Connection>(
+        owner: QueueOwner,
+        name: &str,
+        database: &Database,
+    ) -> Result<Self, Error> {
+        if let Some(queue) = Self::find(owner.clone(), name, database)? {
+            return Ok(queue);
+        }
+
+        match Self::create(owner.clone(), name, database) {
+            Ok(queue) => Ok(queue),
+            Err(Error::AlreadyExists) => {
+                Self::find(owner, name, database)?.ok_or(Error::AlreadyExists)
+            }
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Returns the queue named `name` owned by `owner`, creating it if it does
+    /// not exist.
+    #[cfg(feature = "async")]
+    pub async fn find_or_create_async<Database: AsyncConnection>(
+        owner: QueueOwner,
+        name: &str,
+        database: &Database,
+    ) -> Result<Self, Error> {
+        if let Some(queue) = Self::find_async(owner.clone(), name, database).await? {
+            return Ok(queue);
+        }
+
+        match Self::create_async(owner.clone(), name, database).await {
+            Ok(queue) => Ok(queue),
+            Err(Error::AlreadyExists) => Self::find_async(owner, name, database)
+                .await?
+                .ok_or(Error::AlreadyExists),
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Returns the unique id of this queue.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the owner of this queue.
+    #[must_use]
+    pub const fn owner(&self) -> &QueueOwner {
+        &self.owner
+    }
+
+    /// Returns the name of this queue.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Enqueues a job with the default priority, no delay, and the default
+    /// [`RetryPolicy`]. `payload` is serialized using `pot`. Returns the id of
+    /// the new job.
+    pub fn enqueue<Payload: Serialize, Database: Connection>(
+        &self,
+        payload: &Payload,
+        database: &Database,
+    ) -> Result<u64, Error> {
+        self.build_job(payload)?.enqueue(database)
+    }
+
+    /// Enqueues a job with the default priority, no delay, and the default
+    /// [`RetryPolicy`]. `payload` is serialized using `pot`. Returns the id of
+    /// the new job.
+    #[cfg(feature = "async")]
+    pub async fn enqueue_async<Payload: Serialize, Database: AsyncConnection>(
+        &self,
+        payload: &Payload,
+        database: &Database,
+    ) -> Result<u64, Error> {
+        self.build_job(payload)?.enqueue_async(database).await
+    }
+
+    /// Returns a builder for a job containing `payload` serialized using
+    /// `pot`.
+    pub fn build_job<Payload: Serialize>(
+        &self,
+        payload: &Payload,
+    ) -> Result<JobBuilder<'_>, Error> {
+        Ok(self.build_job_bytes(pot::to_vec(payload)?))
+    }
+
+    /// Returns a builder for a job containing `payload`.
+    #[must_use]
+    pub fn build_job_bytes(&self, payload: impl Into<Bytes>) -> JobBuilder<'_> {
+        JobBuilder {
+            queue: self,
+            payload: payload.into(),
+            priority: 0,
+            delay: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Leases the next available job for `worker`. Jobs with a higher
+    /// priority are leased first, and jobs of equal priority are leased in the
+    /// order they became available.
+    ///
+    /// The leased job will not be returned to any other worker until
+    /// `visibility_timeout` elapses. If the job has not been completed or
+    /// failed by that time, it becomes available to be leased again. Returns
+    /// `None` if no jobs are currently available.
+    pub fn lease<Database: Connection>(
+        &self,
+        worker: &str,
+        visibility_timeout: Duration,
+        database: &Database,
+    ) -> Result<Option<LeasedJob>, Error> {
+        let mut scan = AvailableScan::new(self.id);
+        loop {
+            let batch = database
+                .view::<AvailableJobs>()
+                .with_key_range(scan.range())
+                .limit(LEASE_BATCH_SIZE)
+                .query()?;
+            let Some(candidates) = scan.next_candidates(batch) else {
+                return Ok(None);
+            };
+            for job_id in candidates {
+                let Some(mut job) = Job::get(&job_id, database)? else {
+                    continue;
+                };
+                if job.contents.queue_id != self.id || !job.contents.is_available(scan.now) {
+                    continue;
+                }
+
+                let leased = job.contents.lease(worker, visibility_timeout)?;
+                match job.update(database) {
+                    Ok(()) if leased => return Ok(Some(LeasedJob::from(job))),
+                    Ok(()) => {}
+                    // Another worker leased this job first.
+                    Err(err) if err.conflicting_document::<Job>().is_some() => {}
+                    Err(err) => return Err(Error::from(err)),
+                }
+            }
+        }
+    }
+
+    /// Leases the next available job for `worker`. Jobs with a higher
+    /// priority are leased first, and jobs of equal priority are leased in the
+    /// order they became available.
+    ///
+    /// The leased job will not be returned to any other worker until
+    /// `visibility_timeout` elapses. If the job has not been completed or
+    /// failed by that time, it becomes available to be leased again. Returns
+    /// `None` if no jobs are currently available.
+    #[cfg(feature = "async")]
+    pub async fn lease_async<Database: AsyncConnection>(
+        &self,
+        worker: &str,
+        visibility_timeout: Duration,
+        database: &Database,
+    ) -> Result<Option<LeasedJob>, Error> {
+        let mut scan = AvailableScan::new(self.id);
+        loop {
+            let batch = database
+                .view::<AvailableJobs>()
+                .with_key_range(scan.range())
+                .limit(LEASE_BATCH_SIZE)
+                .query()
+                .await?;
+            let Some(candidates) = scan.next_candidates(batch) else {
+                return Ok(None);
+            };
+            for job_id in candidates {
+                let Some(mut job) = Job::get_async(&job_id, database).await? else {
+                    continue;
+                };
+                if job.contents.queue_id != self.id || !job.contents.is_available(scan.now) {
+                    continue;
+                }
+
+                let leased = job.contents.lease(worker, visibility_timeout)?;
+                match job.update_async(database).await {
+                    Ok(()) if leased => return Ok(Some(LeasedJob::from(job))),
+                    Ok(()) => {}
+                    // Another worker leased this job first.
+                    Err(err) if err.conflicting_document::<Job>().is_some() => {}
+                    Err(err) => return Err(Error::from(err)),
+                }
+            }
+        }
+    }
+
+    /// Returns the job with `job_id`, if it belongs to this queue.
+    pub fn job<Database: Connection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<Option<CollectionDocument<Job>>, Error> {
+        Ok(Job::get(&job_id, database)?.filter(|job| job.contents.queue_id == self.id))
+    }
+
+    /// Returns the job with `job_id`, if it belongs to this queue.
+    #[cfg(feature = "async")]
+    pub async fn job_async<Database: AsyncConnection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<Option<CollectionDocument<Job>>, Error> {
+        Ok(Job::get_async(&job_id, database)
+            .await?
+            .filter(|job| job.contents.queue_id == self.id))
+    }
+
+    /// Returns the status of the job with `job_id`, if it belongs to this
+    /// queue.
+    pub fn job_status<Database: Connection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<Option<JobStatus>, Error> {
+        Ok(self.job(job_id, database)?.map(|job| job.contents.status))
+    }
+
+    /// Returns the status of the job with `job_id`, if it belongs to this
+    /// queue.
+    #[cfg(feature = "async")]
+    pub async fn job_status_async<Database: AsyncConnection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<Option<JobStatus>, Error> {
+        Ok(self
+            .job_async(job_id, database)
+            .await?
+            .map(|job| job.contents.status))
+    }
+
+    /// Returns all jobs in this queue with `status`.
+    pub fn jobs_with_status<Database: Connection>(
+        &self,
+        status: JobStatus,
+        database: &Database,
+    ) -> Result<Vec<CollectionDocument<Job>>, Error> {
+        let key = (self.id, status);
+        let mappings = database
+            .view::<JobsByStatus>()
+            .with_key(&key)
+            .query_with_collection_docs()?;
+        Ok(mappings.documents.into_values().collect())
+    }
+
+    /// Returns all jobs in this queue with `status`.
+    #[cfg(feature = "async")]
+    pub async fn jobs_with_status_async<Database: AsyncConnection>(
+        &self,
+        status: JobStatus,
+        database: &Database,
+    ) -> Result<Vec<CollectionDocument<Job>>, Error> {
+        let key = (self.id, status);
+        let mappings = database
+            .view::<JobsByStatus>()
+            .with_key(&key)
+            .query_with_collection_docs()
+            .await?;
+        Ok(mappings.documents.into_values().collect())
+    }
+
+    /// Returns all jobs in this queue that have exhausted their attempts.
+    pub fn dead_letters<Database: Connection>(
+        &self,
+        database: &Database,
+    ) -> Result<Vec<CollectionDocument<Job>>, Error> {
+        self.jobs_with_status(JobStatus::DeadLettered, database)
+    }
+
+    /// Returns all jobs in this queue that have exhausted their attempts.
+    #[cfg(feature = "async")]
+    pub async fn dead_letters_async<Database: AsyncConnection>(
+        &self,
+        database: &Database,
+    ) -> Result<Vec<CollectionDocument<Job>>, Error> {
+        self.jobs_with_status_async(JobStatus::DeadLettered, database)
+            .await
+    }
+
+    /// Makes the dead-lettered job with `job_id` available to be leased
+    /// again, resetting its attempts.
+    pub fn requeue<Database: Connection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<(), Error> {
+        let mut job = self.job(job_id, database)?.ok_or(Error::JobNotFound)?;
+        job.contents.requeue()?;
+        job.update(database)?;
+        Ok(())
+    }
+
+    /// Makes the dead-lettered job with `job_id` available to be leased
+    /// again, resetting its attempts.
+    #[cfg(feature = "async")]
+    pub async fn requeue_async<Database: AsyncConnection>(
+        &self,
+        job_id: u64,
+        database: &Database,
+    ) -> Result<(), Error> {
+        let mut job = self
+            .job_async(job_id, database)
+            .await?
+            .ok_or(Error::JobNotFound)?;
+        job.contents.requeue()?;
+        job.update_async(database).await?;
+        Ok(())
+    }
+}
+
+impl From<CollectionDocument<JobQueue>> for Queue {
+    fn from(document: CollectionDocument<JobQueue>) -> Self {
+        Self {
+            id: document.header.id,
+            owner: document.contents.owner,
+            name: document.contents.name,
+        }
+    }
+}
+
+type AvailableKey = (u64, i64, TimestampAsNanoseconds);
+
+/// Scans a queue's entries in [`AvailableJobs`] in batches, in the order jobs
+/// should be leased.
+///
+/// Entries of the same priority are ordered by the time their jobs become
+/// available. Once a job that isn't available yet is scanned, the remaining
+/// entries of its priority are skipped by starting the next batch at the next
+/// priority, ensuring delayed jobs are not loaded in batches.
+struct AvailableScan {
+    queue_id: u64,
+    now: TimestampAsNanoseconds,
+    /// The first key of the next batch.
+    start: AvailableKey,
+    last: Option<(AvailableKey, u64)>,
+}
+
+impl AvailableScan {
+    fn new(queue_id: u64) -> Self {
+        Self {
+            queue_id,
+            now: TimestampAsNanoseconds::now(),
+            start: (
+                queue_id,
+                i64::MIN,
+                TimestampAsNanoseconds::from_representation(i64::MIN),
+            ),
+            last: None,
+        }
+    }
+
+    /// Returns the range of keys that haven't been scanned yet. The last key
+    /// scanned may be included, because other jobs may share the same key.
+    fn range(&self) -> std::ops::RangeInclusive<AvailableKey> {
+        self.start
+            ..=(
+                self.queue_id,
+                i64::MAX,
+                TimestampAsNanoseconds::from_representation(i64::MAX),
+            )
+    }
+
+    /// Returns the ids of the jobs in `batch` that are available and haven't
+    /// been scanned yet, or `None` if `batch` contained no new entries.
+    fn next_candidates(&mut self, batch: ViewMappings<AvailableJobs>) -> Option<Vec<u64>> {
+        let mut scanned_any = false;
+        let mut candidates = Vec::new();
+        let mut delayed_priority = None;
+        for mapping in batch {
+            let position = (mapping.key, mapping.source.id);
+            if self.last.map_or(false, |last| position <= last) {
+                continue;
+            }
+
+            scanned_any = true;
+            self.last = Some(position);
+            let (_, priority, available_at) = position.0;
+            // Once a job isn't available, the rest of its priority's jobs
+            // aren't available either.
+            if delayed_priority != Some(priority) {
+                if available_at <= self.now {
+                    candidates.push(position.1);
+                } else {
+                    delayed_priority = Some(priority);
+                }
+            }
+        }
+
+        if let Some((key, _)) = self.last {
+            self.start = if delayed_priority == Some(key.1) {
+                (
+                    self.queue_id,
+                    key.1.saturating_add(1),
+                    TimestampAsNanoseconds::from_representation(i64::MIN),
+                )
+            } else {
+                key
+            };
+        }
+
+        scanned_any.then_some(candidates)
+    }
+}
+
+/// Builds a new job. Returned from [`Queue::build_job`] and
+/// [`Queue::build_job_bytes`].
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct JobBuilder<'a> {
+    queue: &'a Queue,
+    payload: Bytes,
+    priority: i32,
+    delay: Option<Duration>,
+    retry_policy: RetryPolicy,
+}
+
+impl<'a> JobBuilder<'a> {
+    /// Sets the priority of the job. Jobs with higher priorities are leased
+    /// before jobs with lower priorities. The default priority is `0`.
+    pub const fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Delays the job from being leased until `delay` has elapsed.
+    pub const fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Sets the policy used when retrying the job after a failure.
+    pub const fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Enqueues the job. Returns the id of the new job.
+    pub fn enqueue<Database: Connection>(self, database: &Database) -> Result<u64, Error> {
+        let job = self.into_job()?.push_into(database)?;
+        Ok(job.header.id)
+    }
+
+    /// Enqueues the job. Returns the id of the new job.
+    #[cfg(feature = "async")]
+    pub async fn enqueue_async<Database: AsyncConnection>(
+        self,
+        database: &Database,
+    ) -> Result<u64, Error> {
+        let job = self.into_job()?.push_into_async(database).await?;
+        Ok(job.header.id)
+    }
+
+    fn into_job(self) -> Result<Job, Error> {
+        let enqueued_at = TimestampAsNanoseconds::now();
+        let available_at = match self.delay {
+            Some(delay) => timestamp_after(delay)?,
+            None => enqueued_at,
+        };
+        Ok(Job {
+            queue_id: self.queue.id,
+            payload: self.payload,
+            priority: self.priority,
+            status: JobStatus::Pending,
+            enqueued_at,
+            available_at,
+            attempts: 0,
+            retry_policy: self.retry_policy,
+            leased_by: None,
+            last_error: None,
+            finished_at: None,
+        })
+    }
+}
+
+/// The stored representation of a [`Queue`].
+#[derive(Collection, Serialize, Deserialize, Clone, Debug)]
+#[collection(name = "job-queues", authority = "bonsaidb", views = [QueuesByName], core = bonsaidb_core)]
+pub(crate) struct JobQueue {
+    owner: QueueOwner,
+    name: String,
+}
+
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = JobQueue, key = (QueueOwner, String), value = (), name = "by-name", core = bonsaidb_core)]
+#[view_schema(policy = Unique, core = bonsaidb_core)]
+struct QueuesByName;
+
+impl CollectionMapReduce for QueuesByName {
+    fn map<'doc>(&self, document: CollectionDocument<JobQueue>) -> ViewMapResult<'doc, Self> {
+        document
+            .header
+            .emit_key((document.contents.owner, document.contents.name))
+    }
+}
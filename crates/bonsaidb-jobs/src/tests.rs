@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use bonsaidb_core::schema::{Qualified, Schema, SchemaName, Schematic};
+use bonsaidb_core::test_util::TestDirectory;
+use bonsaidb_local::config::{Builder, StorageConfiguration};
+#[cfg(feature = "async")]
+use bonsaidb_local::AsyncDatabase;
+use bonsaidb_local::Database;
+
+use crate::job::{JobStatus, RetryPolicy};
+use crate::queue::{Queue, QueueOwner, LEASE_BATCH_SIZE};
+use crate::Error;
+
+#[derive(Debug)]
+struct JobsSchema;
+
+impl Schema for JobsSchema {
+    fn schema_name() -> SchemaName {
+        SchemaName::private("jobs")
+    }
+
+    fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+        crate::define_collections(schema)
+    }
+}
+
+const VISIBILITY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[test]
+fn queue_lifecycle_test() {
+    let directory = TestDirectory::new("queue-lifecycle");
+    let database = Database::open::<JobsSchema>(StorageConfiguration::new(&directory)).unwrap();
+
+    let queue = Queue::create(QueueOwner::Backend, "lifecycle", &database).unwrap();
+    assert!(matches!(
+        Queue::create(QueueOwner::Backend, "lifecycle", &database),
+        Err(Error::AlreadyExists)
+    ));
+    assert_eq!(
+        Queue::find(QueueOwner::Backend, "lifecycle", &database).unwrap(),
+        Some(queue.clone())
+    );
+    assert_eq!(
+        Queue::find(QueueOwner::User(1), "lifecycle", &database).unwrap(),
+        None
+    );
+
+    let low = queue.enqueue(&"low", &database).unwrap();
+    let high = queue
+        .build_job(&"high")
+        .unwrap()
+        .priority(10)
+        .enqueue(&database)
+        .unwrap();
+    let delayed = queue
+        .build_job(&"delayed")
+        .unwrap()
+        .priority(100)
+        .delay(Duration::from_secs(3600))
+        .enqueue(&database)
+        .unwrap();
+
+    // The highest priority job that is available is leased first.
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.id(), high);
+    assert_eq!(job.attempt(), 1);
+    assert_eq!(job.payload::<String>().unwrap(), "high");
+    assert_eq!(
+        queue.job_status(high, &database).unwrap(),
+        Some(JobStatus::Leased)
+    );
+    job.complete(&database).unwrap();
+    assert_eq!(
+        queue.job_status(high, &database).unwrap(),
+        Some(JobStatus::Completed)
+    );
+
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.id(), low);
+
+    // The remaining job is leased, and the delayed job isn't available yet.
+    assert!(queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .is_none());
+    let pending = queue
+        .jobs_with_status(JobStatus::Pending, &database)
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].header.id, delayed);
+}
+
+#[test]
+fn lease_scans_in_batches_test() {
+    let directory = TestDirectory::new("queue-lease-batches");
+    let database = Database::open::<JobsSchema>(StorageConfiguration::new(&directory)).unwrap();
+
+    let queue = Queue::create(QueueOwner::Backend, "batches", &database).unwrap();
+    // More delayed high priority jobs than are loaded in a single batch.
+    for _ in 0..LEASE_BATCH_SIZE * 2 {
+        queue
+            .build_job(&"delayed")
+            .unwrap()
+            .priority(10)
+            .delay(Duration::from_secs(3600))
+            .enqueue(&database)
+            .unwrap();
+    }
+    let available = queue.enqueue(&"available", &database).unwrap();
+    let high_priority = queue
+        .build_job(&"high priority")
+        .unwrap()
+        .priority(10)
+        .enqueue(&database)
+        .unwrap();
+
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.id(), high_priority);
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.id(), available);
+    assert!(queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn retries_and_dead_letters_test() {
+    let directory = TestDirectory::new("queue-retries");
+    let database = Database::open::<JobsSchema>(StorageConfiguration::new(&directory)).unwrap();
+
+    let queue = Queue::create(QueueOwner::User(1), "retries", &database).unwrap();
+    let job_id = queue
+        .build_job(&42_u32)
+        .unwrap()
+        .retry_policy(RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        })
+        .enqueue(&database)
+        .unwrap();
+
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.fail("oops", &database).unwrap(), JobStatus::Pending);
+
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.attempt(), 2);
+    assert_eq!(job.job().last_error.as_deref(), Some("oops"));
+    assert_eq!(
+        job.fail("oops again", &database).unwrap(),
+        JobStatus::DeadLettered
+    );
+
+    assert!(queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .is_none());
+    let dead_letters = queue.dead_letters(&database).unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].header.id, job_id);
+    assert_eq!(
+        dead_letters[0].contents.last_error.as_deref(),
+        Some("oops again")
+    );
+
+    queue.requeue(job_id, &database).unwrap();
+    assert!(matches!(
+        queue.requeue(job_id, &database),
+        Err(Error::InvalidStatus)
+    ));
+    let job = queue
+        .lease("worker", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.payload::<u32>().unwrap(), 42);
+    assert_eq!(job.attempt(), 1);
+}
+
+#[test]
+fn expired_lease_test() {
+    let directory = TestDirectory::new("queue-expired-lease");
+    let database = Database::open::<JobsSchema>(StorageConfiguration::new(&directory)).unwrap();
+
+    let queue = Queue::create(QueueOwner::Backend, "expired", &database).unwrap();
+    let job_id = queue.enqueue(&(), &database).unwrap();
+
+    let first = queue
+        .lease("first", Duration::ZERO, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.id(), job_id);
+
+    // The first lease has expired, allowing another worker to lease the job.
+    let second = queue
+        .lease("second", VISIBILITY_TIMEOUT, &database)
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.id(), job_id);
+    assert_eq!(second.attempt(), 2);
+    assert_eq!(second.job().leased_by.as_deref(), Some("second"));
+
+    assert!(matches!(first.complete(&database), Err(Error::LeaseLost)));
+    second.complete(&database).unwrap();
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_queue_test() {
+    let directory = TestDirectory::new("queue-async");
+    let database = AsyncDatabase::open::<JobsSchema>(StorageConfiguration::new(&directory))
+        .await
+        .unwrap();
+
+    let queue = Queue::find_or_create_async(QueueOwner::Backend, "async", &database)
+        .await
+        .unwrap();
+    assert_eq!(
+        Queue::find_or_create_async(QueueOwner::Backend, "async", &database)
+            .await
+            .unwrap(),
+        queue
+    );
+
+    let job_id = queue.enqueue_async(&"hello", &database).await.unwrap();
+    let job = queue
+        .lease_async("worker", VISIBILITY_TIMEOUT, &database)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.id(), job_id);
+    assert_eq!(job.payload::<String>().unwrap(), "hello");
+    job.complete_async(&database).await.unwrap();
+    assert_eq!(
+        queue.job_status_async(job_id, &database).await.unwrap(),
+        Some(JobStatus::Completed)
+    );
+}
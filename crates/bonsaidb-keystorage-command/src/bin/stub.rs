@@ -0,0 +1,50 @@
+//! A filesystem-backed implementation of the `CommandVaultKeyStorage`
+//! protocol, intended for testing only.
+//!
+//! Usage: `bonsaidb-keystorage-stub <directory> <get|set> <storage-id>`
+
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: bonsaidb-keystorage-stub <directory> <get|set> <storage-id>";
+
+fn main() -> ExitCode {
+    match run(std::env::args_os().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<OsString>) -> Result<(), String> {
+    let [directory, operation, storage_id] =
+        <[OsString; 3]>::try_from(args).map_err(|_| String::from(USAGE))?;
+    let storage_id = storage_id
+        .into_string()
+        .ok()
+        .filter(|id| !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .ok_or_else(|| String::from("invalid storage id"))?;
+    let directory = PathBuf::from(directory);
+    let key_path = directory.join(storage_id);
+
+    match operation.to_str() {
+        Some("get") => match std::fs::read(&key_path) {
+            Ok(key) => io::stdout().write_all(&key),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        },
+        Some("set") => {
+            let mut key = Vec::new();
+            io::stdin()
+                .read_to_end(&mut key)
+                .and_then(|_| std::fs::create_dir_all(&directory))
+                .and_then(|()| std::fs::write(&key_path, key))
+        }
+        _ => return Err(String::from(USAGE)),
+    }
+    .map_err(|err| err.to_string())
+}
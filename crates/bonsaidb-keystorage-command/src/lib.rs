@@ -0,0 +1,297 @@
+//! [`VaultKeyStorage`] that delegates to an external command.
+//!
+//! This allows integrating BonsaiDb with a secret manager or key management
+//! service without writing Rust: any executable that implements the protocol
+//! below can store the vault keys.
+//!
+//! To use this, specify the `vault_key_storage` configuration parameter:
+//!
+//! ```rust
+//! # use bonsaidb_keystorage_command::CommandVaultKeyStorage;
+//! # use bonsaidb_core::{document::KeyId, test_util::TestDirectory};
+//! # use bonsaidb_local::config::{StorageConfiguration, Builder};
+//! #
+//! # fn test() {
+//! let directory = TestDirectory::new("bonsaidb-keystorage-command-basic");
+//! let configuration = StorageConfiguration::new(&directory)
+//!     .vault_key_storage(
+//!         CommandVaultKeyStorage::new("/usr/local/bin/vault-keys").arg("--profile=bonsaidb"),
+//!     )
+//!     .default_encryption_key(KeyId::Master);
+//! # }
+//! ```
+//!
+//! ## Protocol
+//!
+//! The command is executed with its configured arguments followed by an
+//! operation and the [`StorageId`] the key belongs to, formatted as 16
+//! hexadecimal digits:
+//!
+//! - `get <storage-id>`: The command must write the previously stored key to
+//!   stdout. If no key is stored for `storage-id`, the command must write
+//!   nothing.
+//! - `set <storage-id>`: The command must store the key read from stdin,
+//!   replacing any existing key for `storage-id`.
+//!
+//! Keys are exchanged as a single line of lowercase hexadecimal text. Leading
+//! and trailing whitespace is ignored when reading a key from stdout. A
+//! non-zero exit status is treated as an error, and the contents of stderr are
+//! included in the returned error. Commands that don't exit within the
+//! configured [timeout](CommandVaultKeyStorage::timeout) are killed.
+//!
+//! The `bonsaidb-keystorage-stub` executable included with this crate
+//! implements this protocol by storing keys as files in a directory passed as
+//! its first argument. It is only built when the `test-stub` feature is
+//! enabled. It offers no more security than
+//! [`LocalVaultKeyStorage`](bonsaidb_local::vault::LocalVaultKeyStorage) and
+//! is intended for testing only.
+
+#![forbid(unsafe_code)]
+#![warn(
+    clippy::cargo,
+    missing_docs,
+    // clippy::missing_docs_in_private_items,
+    clippy::pedantic,
+    future_incompatible,
+    rust_2018_idioms,
+)]
+#![allow(
+    clippy::missing_errors_doc, // TODO clippy::missing_errors_doc
+    clippy::missing_panics_doc, // TODO clippy::missing_panics_doc
+    clippy::option_if_let_else,
+    clippy::module_name_repetitions,
+)]
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use bonsaidb_local::vault::{self, KeyPair, VaultKeyStorage};
+use bonsaidb_local::StorageId;
+use zeroize::Zeroizing;
+
+/// The default amount of time a command is allowed to run.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running command is checked for having exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// [`VaultKeyStorage`] implementor that invokes an external command.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct CommandVaultKeyStorage {
+    program: PathBuf,
+    args: Vec<OsString>,
+    timeout: Duration,
+}
+
+impl CommandVaultKeyStorage {
+    /// Creates a new key storage instance that invokes `program`.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the amount of time the command is allowed to run before it is
+    /// killed and [`CommandVaultKeyStorageError::TimedOut`] is returned. The
+    /// default timeout is [`DEFAULT_TIMEOUT`].
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds an argument to pass to the command before the operation.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds arguments to pass to the command before the operation.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    fn execute(
+        &self,
+        operation: &str,
+        storage_id: StorageId,
+        input: Option<&[u8]>,
+    ) -> Result<Zeroizing<Vec<u8>>, CommandVaultKeyStorageError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(operation)
+            .arg(storage_id.to_string())
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // The input is written and the output is read on separate threads, so
+        // that a command that writes before it has read all of its input
+        // can't cause a deadlock. Dropping stdin after writing closes it,
+        // signaling the end of the input.
+        let writer = match input {
+            Some(input) => {
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                let input = Zeroizing::new(input.to_vec());
+                Some(spawn_pipe_thread(move || stdin.write_all(&input))?)
+            }
+            None => None,
+        };
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let stdout = spawn_pipe_thread(move || {
+            let mut output = Zeroizing::new(Vec::new());
+            stdout.read_to_end(&mut output).map(|_| output)
+        })?;
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr = spawn_pipe_thread(move || {
+            let mut output = Vec::new();
+            stderr.read_to_end(&mut output).map(|_| output)
+        })?;
+
+        let status = self.wait(&mut child)?;
+        // If the command exits early, its status and stderr are more useful
+        // than the error from writing.
+        let written = writer.map_or(Ok(()), join_pipe_thread);
+        let stdout = join_pipe_thread(stdout)?;
+        let stderr = join_pipe_thread(stderr)?;
+        if status.success() {
+            written?;
+            Ok(stdout)
+        } else {
+            Err(CommandVaultKeyStorageError::CommandFailed {
+                status,
+                stderr: String::from_utf8_lossy(&stderr).trim().to_string(),
+            })
+        }
+    }
+
+    /// Waits for `child` to exit, killing it if it runs longer than the
+    /// configured timeout.
+    fn wait(&self, child: &mut Child) -> Result<ExitStatus, CommandVaultKeyStorageError> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+
+            if started.elapsed() >= self.timeout {
+                // Killing the command closes its pipes, which allows the
+                // threads communicating with it to exit.
+                drop(child.kill());
+                drop(child.wait());
+                return Err(CommandVaultKeyStorageError::TimedOut(self.timeout));
+            }
+
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+}
+
+fn spawn_pipe_thread<T: Send + 'static>(
+    communicate: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> std::io::Result<JoinHandle<std::io::Result<T>>> {
+    std::thread::Builder::new()
+        .name(String::from("keystorage-command"))
+        .spawn(communicate)
+}
+
+fn join_pipe_thread<T>(thread: JoinHandle<std::io::Result<T>>) -> std::io::Result<T> {
+    thread.join().unwrap_or_else(|_| {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "command pipe thread panicked",
+        ))
+    })
+}
+
+impl VaultKeyStorage for CommandVaultKeyStorage {
+    type Error = CommandVaultKeyStorageError;
+
+    fn set_vault_key_for(&self, storage_id: StorageId, key: KeyPair) -> Result<(), Self::Error> {
+        let key = key.to_bytes()?;
+        let mut encoded = Zeroizing::new(String::with_capacity(key.len() * 2 + 1));
+        for byte in key.iter() {
+            write!(encoded, "{byte:02x}").expect("writing to a string can't fail");
+        }
+        encoded.push('\n');
+
+        self.execute("set", storage_id, Some(encoded.as_bytes()))?;
+        Ok(())
+    }
+
+    fn vault_key_for(&self, storage_id: StorageId) -> Result<Option<KeyPair>, Self::Error> {
+        let output = self.execute("get", storage_id, None)?;
+        let encoded = std::str::from_utf8(&output)
+            .map_err(|_| CommandVaultKeyStorageError::InvalidKey)?
+            .trim();
+        if encoded.is_empty() {
+            return Ok(None);
+        }
+
+        let key = decode_hex(encoded).ok_or(CommandVaultKeyStorageError::InvalidKey)?;
+        Ok(Some(KeyPair::from_bytes(&key)?))
+    }
+}
+
+fn decode_hex(encoded: &str) -> Option<Zeroizing<Vec<u8>>> {
+    fn nibble(digit: u8) -> Option<u8> {
+        char::from(digit)
+            .to_digit(16)
+            .and_then(|value| u8::try_from(value).ok())
+    }
+
+    if encoded.len() % 2 != 0 {
+        return None;
+    }
+    let mut decoded = Zeroizing::new(Vec::with_capacity(encoded.len() / 2));
+    for pair in encoded.as_bytes().chunks_exact(2) {
+        decoded.push(nibble(pair[0])? << 4 | nibble(pair[1])?);
+    }
+    Some(decoded)
+}
+
+/// Errors from [`CommandVaultKeyStorage`].
+#[derive(thiserror::Error, Debug)]
+pub enum CommandVaultKeyStorageError {
+    /// An error occurred executing the command or communicating with it.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The command exited with a non-zero status.
+    #[error("command failed with {status}: {stderr}")]
+    CommandFailed {
+        /// The exit status of the command.
+        status: ExitStatus,
+        /// The contents the command wrote to stderr.
+        stderr: String,
+    },
+
+    /// The command did not exit within the configured timeout, and was killed.
+    #[error("command did not exit within {0:?}")]
+    TimedOut(Duration),
+
+    /// The command returned a key that was not valid hexadecimal.
+    #[error("command returned an invalid key")]
+    InvalidKey,
+
+    /// An error serializing or deserializing the key.
+    #[error("key serialization error: {0}")]
+    Serialization(#[from] vault::Error),
+}
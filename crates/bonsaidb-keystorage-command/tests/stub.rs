@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use bonsaidb_core::connection::StorageConnection;
+use bonsaidb_core::document::KeyId;
+use bonsaidb_core::schema::SerializedCollection;
+use bonsaidb_core::test_util::{Basic, BasicSchema, TestDirectory};
+use bonsaidb_keystorage_command::{CommandVaultKeyStorage, CommandVaultKeyStorageError};
+use bonsaidb_local::config::{Builder, StorageConfiguration};
+use bonsaidb_local::vault::VaultKeyStorage;
+use bonsaidb_local::Storage;
+
+fn stub(keys: &TestDirectory) -> CommandVaultKeyStorage {
+    CommandVaultKeyStorage::new(env!("CARGO_BIN_EXE_bonsaidb-keystorage-stub")).arg(&keys.0)
+}
+
+#[test]
+fn stub_test() {
+    let directory = TestDirectory::new("bonsaidb-keystorage-command-stub");
+    let keys = TestDirectory::new("bonsaidb-keystorage-command-stub-keys");
+    let other_keys = TestDirectory::new("bonsaidb-keystorage-command-stub-other-keys");
+
+    let configuration = |keys: &TestDirectory| {
+        StorageConfiguration::new(&directory)
+            .vault_key_storage(stub(keys))
+            .default_encryption_key(KeyId::Master)
+            .with_schema::<BasicSchema>()
+            .unwrap()
+    };
+    let document = {
+        let bonsai = Storage::open(configuration(&keys)).unwrap();
+        let db = bonsai
+            .create_database::<BasicSchema>("test", false)
+            .unwrap();
+        Basic::new("test").push_into(&db).unwrap()
+    };
+
+    {
+        // Should be able to access the storage again
+        let bonsai = Storage::open(configuration(&keys)).unwrap();
+
+        let db = bonsai.database::<BasicSchema>("test").unwrap();
+        let retrieved = Basic::get(&document.header.id, &db)
+            .unwrap()
+            .expect("document not found");
+        assert_eq!(document, retrieved);
+    }
+
+    // Verify that we can't access the storage again without the vault
+    assert!(Storage::open(configuration(&other_keys)).is_err());
+}
+
+#[test]
+fn command_failure_test() {
+    let directory = TestDirectory::new("bonsaidb-keystorage-command-failure");
+    let bonsai = Storage::open(StorageConfiguration::new(&directory)).unwrap();
+
+    // The stub requires a directory argument, so omitting it causes it to
+    // exit with an error.
+    let storage = CommandVaultKeyStorage::new(env!("CARGO_BIN_EXE_bonsaidb-keystorage-stub"));
+    let stderr = match storage.vault_key_for(bonsai.unique_id()) {
+        Err(CommandVaultKeyStorageError::CommandFailed { stderr, .. }) => stderr,
+        Err(err) => unreachable!("unexpected error: {err}"),
+        Ok(_) => unreachable!("the command should have failed"),
+    };
+    assert!(stderr.contains("usage"));
+}
+
+#[test]
+#[cfg(unix)]
+fn command_timeout_test() {
+    let directory = TestDirectory::new("bonsaidb-keystorage-command-timeout");
+    let bonsai = Storage::open(StorageConfiguration::new(&directory)).unwrap();
+
+    // The operation and storage id are passed as positional parameters to the
+    // script, which never exits on its own.
+    let storage = CommandVaultKeyStorage::new("sh")
+        .args(["-c", "sleep 60"])
+        .timeout(Duration::from_millis(100));
+    let started = Instant::now();
+    match storage.vault_key_for(bonsai.unique_id()) {
+        Err(CommandVaultKeyStorageError::TimedOut(_)) => {}
+        Err(err) => unreachable!("unexpected error: {err}"),
+        Ok(_) => unreachable!("the command should have timed out"),
+    }
+    assert!(started.elapsed() < Duration::from_secs(60));
+}
@@ -0,0 +1,218 @@
+//! Handling custom [`Api`](api::Api)s in-process.
+//!
+//! Custom Apis are most commonly served by `bonsaidb-server` to networked
+//! clients. Handlers can also be registered with a
+//! [`StorageConfiguration`](crate::config::StorageConfiguration) using
+//! [`register_custom_api()`](crate::config::StorageConfiguration::register_custom_api),
+//! allowing requests to be sent directly to an [`AsyncStorage`] using
+//! [`AsyncStorage::send_api_request()`] or [`Storage::send_api_request()`].
+//! This allows code written against a networked client to be tested
+//! in-process, or to be deployed in a single executable without networking.
+//!
+//! Handlers implement the same [`Handler`] trait used by `bonsaidb-server`,
+//! with [`AsyncStorage`] as the handler's context. A handler that is
+//! implemented for any [`HandlerContext`] can be registered with both.
+//!
+//! Requests and responses are serialized exactly as they would be when sent
+//! over the network.
+//!
+//! ```rust
+//! use bonsaidb_core::api::{Api, ApiName, Handler, HandlerContext, HandlerResult, Infallible};
+//! use bonsaidb_core::async_trait::async_trait;
+//! use bonsaidb_core::connection::AsyncStorageConnection;
+//! use bonsaidb_local::config::{Builder, StorageConfiguration};
+//! use bonsaidb_local::AsyncStorage;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Serialize, Deserialize)]
+//! struct ListDatabaseNames;
+//!
+//! impl Api for ListDatabaseNames {
+//!     type Error = Infallible;
+//!     type Response = Vec<String>;
+//!
+//!     fn name() -> ApiName {
+//!         ApiName::private("list-database-names")
+//!     }
+//! }
+//!
+//! #[derive(Debug)]
+//! struct ListDatabaseNamesHandler;
+//!
+//! #[async_trait]
+//! impl<Context: HandlerContext> Handler<ListDatabaseNames, Context> for ListDatabaseNamesHandler {
+//!     async fn handle(
+//!         session: Context::Session<'_>,
+//!         _request: ListDatabaseNames,
+//!     ) -> HandlerResult<ListDatabaseNames> {
+//!         let databases = Context::as_client(&session).list_databases().await?;
+//!         Ok(databases.into_iter().map(|db| db.name).collect())
+//!     }
+//! }
+//!
+//! # async fn test() -> anyhow::Result<()> {
+//! let storage = AsyncStorage::open(
+//!     StorageConfiguration::new("my-db.bonsaidb")
+//!         .with_api::<ListDatabaseNamesHandler, ListDatabaseNames>(),
+//! )
+//! .await?;
+//! let names = storage.send_api_request(&ListDatabaseNames).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use bonsaidb_core::api::{self, Api, ApiError as ApiErrorTrait, HandlerContext};
+pub use bonsaidb_core::api::{Handler, HandlerError, HandlerResult};
+use bonsaidb_core::arc_bytes::serde::Bytes;
+
+use crate::{AsyncStorage, Error, Storage};
+
+/// A session for a [`Handler`], providing access to the storage.
+pub struct HandlerSession<'a> {
+    /// The storage the request was sent to. This storage instance is not
+    /// limited to the permissions of the caller.
+    pub storage: &'a AsyncStorage,
+    /// The storage the request was sent from. This storage instance will
+    /// reject any operations that the caller is not authorized to perform
+    /// based on its authentication state.
+    pub as_client: AsyncStorage,
+}
+
+impl HandlerContext for AsyncStorage {
+    type Session<'a> = HandlerSession<'a>;
+
+    fn as_client<'s>(session: &'s Self::Session<'_>) -> &'s Self {
+        &session.as_client
+    }
+}
+
+#[async_trait]
+pub(crate) trait AnyHandler: Send + Sync + Debug {
+    async fn handle(&self, session: HandlerSession<'_>, request: &[u8]) -> Result<Bytes, Error>;
+}
+
+pub(crate) struct AnyWrapper<D: Handler<A, AsyncStorage>, A: Api>(pub(crate) PhantomData<(D, A)>);
+
+impl<D, A> Debug for AnyWrapper<D, A>
+where
+    D: Handler<A, AsyncStorage>,
+    A: Api,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnyWrapper").finish()
+    }
+}
+
+#[async_trait]
+impl<T, A> AnyHandler for AnyWrapper<T, A>
+where
+    T: Handler<A, AsyncStorage>,
+    A: Api,
+{
+    async fn handle(&self, session: HandlerSession<'_>, request: &[u8]) -> Result<Bytes, Error> {
+        let request = pot::from_slice(request)?;
+        let response = match T::handle(session, request).await {
+            Ok(response) => Ok(response),
+            Err(HandlerError::Api(err)) => Err(err),
+            Err(HandlerError::Core(err)) => return Err(Error::from(err)),
+        };
+        Ok(Bytes::from(pot::to_vec(&response)?))
+    }
+}
+
+impl<E: ApiErrorTrait> From<Error> for HandlerError<E> {
+    fn from(err: Error) -> Self {
+        Self::Core(bonsaidb_core::Error::from(err))
+    }
+}
+
+/// An error returned from [`AsyncStorage::send_api_request()`] and
+/// [`Storage::send_api_request()`].
+#[derive(thiserror::Error, Debug)]
+pub enum ApiError<T> {
+    /// The API returned its own error type.
+    #[error("api error: {0}")]
+    Api(T),
+    /// An error from BonsaiDb occurred.
+    #[error("storage error: {0}")]
+    Storage(#[from] Error),
+}
+
+impl From<ApiError<Self>> for bonsaidb_core::Error {
+    fn from(error: ApiError<Self>) -> Self {
+        match error {
+            ApiError::Api(err) => err,
+            ApiError::Storage(err) => Self::from(err),
+        }
+    }
+}
+
+impl AsyncStorage {
+    /// Sends an api `request` to the handler registered using
+    /// [`StorageConfiguration::register_custom_api()`](crate::config::StorageConfiguration::register_custom_api).
+    /// The handler is invoked with the permissions of this instance's
+    /// session.
+    pub async fn send_api_request<Api: api::Api>(
+        &self,
+        request: &Api,
+    ) -> Result<Api::Response, ApiError<Api::Error>> {
+        let name = Api::name();
+        let handler = self
+            .storage
+            .instance
+            .custom_api_handler(&name)
+            .ok_or_else(|| Error::from(bonsaidb_core::Error::ApiNotFound(name)))?;
+        let request = pot::to_vec(request).map_err(Error::from)?;
+        let storage = AsyncStorage {
+            storage: Storage::from(self.storage.instance.clone()),
+            runtime: self.runtime.clone(),
+        };
+        let response = handler
+            .handle(
+                HandlerSession {
+                    storage: &storage,
+                    as_client: self.clone(),
+                },
+                &request,
+            )
+            .await?;
+        let response =
+            pot::from_slice::<Result<Api::Response, Api::Error>>(&response).map_err(Error::from)?;
+        response.map_err(ApiError::Api)
+    }
+}
+
+impl Storage {
+    /// Sends an api `request` to the handler registered using
+    /// [`StorageConfiguration::register_custom_api()`](crate::config::StorageConfiguration::register_custom_api),
+    /// blocking the current thread until the handler completes. The handler is
+    /// invoked with the permissions of this instance's session.
+    ///
+    /// Handlers are asynchronous, so each request is executed on a dedicated
+    /// thread using a single-threaded Tokio runtime. This function can be
+    /// called both inside and outside of an existing Tokio runtime, although
+    /// [`AsyncStorage::send_api_request()`] should be preferred from async
+    /// code.
+    pub fn send_api_request<Api: api::Api>(
+        &self,
+        request: &Api,
+    ) -> Result<Api::Response, ApiError<Api::Error>> {
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .map_err(Error::from)?;
+                    let storage = self.to_async_with_runtime(runtime.handle().clone());
+                    runtime.block_on(storage.send_api_request(request))
+                })
+                .join()
+                .unwrap_or_else(std::panic::resume_unwind)
+        })
+    }
+}
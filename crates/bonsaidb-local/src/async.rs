@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use bonsaidb_core::admin::DatabaseQuota;
 use bonsaidb_core::connection::{
     self, AccessPolicy, AsyncConnection, AsyncLowLevelConnection, AsyncStorageConnection,
-    Connection, HasSchema, HasSession, IdentityReference, LowLevelConnection, Range,
+    Connection, DatabaseUsage, HasSchema, HasSession, IdentityReference, LowLevelConnection, Range,
     SerializedQueryKey, Session, Sort, StorageConnection,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
@@ -151,6 +152,111 @@ impl AsyncStorage {
             .await?
     }
 
+    /// Restores all data from a backup stored using
+    /// [`AsyncStorage::backup_encrypted()`]. See
+    /// [`Storage::restore_encrypted()`] for more information.
+    #[cfg(feature = "encryption")]
+    pub async fn restore_encrypted<L: AnyBackupLocation + 'static>(
+        &self,
+        location: L,
+        encryption: crate::BackupEncryption,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.restore_encrypted(&location, &encryption))
+            .await?
+    }
+
+    /// Stores a copy of all data in this instance to `location`, encrypting
+    /// the contents of the backup as specified by `encryption`.
+    #[cfg(feature = "encryption")]
+    pub async fn backup_encrypted<L: AnyBackupLocation + 'static>(
+        &self,
+        location: L,
+        encryption: crate::BackupEncryption,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.backup_encrypted(&location, &encryption))
+            .await?
+    }
+
+    /// Deletes the vault's encryption key named `name`. All data encrypted
+    /// with the key can no longer be decrypted. See
+    /// [`Storage::delete_encryption_key`] for more information.
+    #[cfg(feature = "encryption")]
+    pub async fn delete_encryption_key(&self, name: &str) -> Result<(), Error> {
+        let task_self = self.clone();
+        let name = name.to_string();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.delete_encryption_key(&name))
+            .await?
+    }
+
+    /// Replaces the vault key with a newly generated key, re-sealing the
+    /// master keys with it. See [`Storage::rotate_vault_key`] for more
+    /// information.
+    #[cfg(feature = "encryption")]
+    pub async fn rotate_vault_key(&self) -> Result<(), Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.rotate_vault_key())
+            .await?
+    }
+
+    /// Creates a new version of the encryption key `key`. See
+    /// [`Storage::rotate_encryption_key`] for more information.
+    #[cfg(feature = "encryption")]
+    pub async fn rotate_encryption_key(
+        &self,
+        key: bonsaidb_core::document::KeyId,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.rotate_encryption_key(&key))
+            .await?
+    }
+
+    /// Removes the versions of the encryption key named `name` that are no
+    /// longer used by any collection. See
+    /// [`Storage::retire_encryption_key_versions`] for more information.
+    #[cfg(feature = "encryption")]
+    pub async fn retire_encryption_key_versions(&self, name: &str) -> Result<usize, Error> {
+        let task_self = self.clone();
+        let name = name.to_string();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.retire_encryption_key_versions(&name))
+            .await?
+    }
+
+    /// Rotates the encryption key of `collection` in the database named
+    /// `database`. See [`Database::rotate_collection_encryption_key`] for more
+    /// information.
+    #[cfg(feature = "encryption")]
+    pub async fn rotate_collection_encryption_key(
+        &self,
+        database: &str,
+        collection: CollectionName,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        let database = database.to_string();
+        self.runtime
+            .spawn_blocking(move || {
+                task_self
+                    .storage
+                    .rotate_collection_encryption_key(&database, &collection)
+            })
+            .await?
+    }
+
+    /// Returns the collections that are still being re-encrypted after their
+    /// encryption key was rotated.
+    #[must_use]
+    #[cfg(feature = "encryption")]
+    pub fn pending_key_rotations(&self) -> Vec<crate::PendingKeyRotation> {
+        self.storage.pending_key_rotations()
+    }
+
     /// Restricts an unauthenticated instance to having `effective_permissions`.
     /// Returns `None` if a session has already been established.
     #[must_use]
@@ -178,6 +284,26 @@ impl AsyncStorage {
             .await?
     }
 
+    #[cfg(feature = "internal-apis")]
+    #[doc(hidden)]
+    pub async fn authenticate_as(
+        &self,
+        identity: IdentityReference<'_>,
+        method: bonsaidb_core::connection::AuthenticationMethod,
+    ) -> Result<Self, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let identity = identity.into_owned();
+        self.runtime
+            .spawn_blocking(move || {
+                task_self
+                    .storage
+                    .authenticate_as(identity, method)
+                    .map(Storage::into_async)
+            })
+            .await
+            .map_err(Error::from)?
+    }
+
     /// Converts this instance into its blocking version, which is able to be
     /// used without async.
     pub fn into_blocking(self) -> Storage {
@@ -289,6 +415,24 @@ impl AsyncDatabase {
         .await?
     }
 
+    /// Rotates the encryption key used by `collection` and re-encrypts the
+    /// collection in the background. See
+    /// [`Database::rotate_collection_encryption_key`] for more information.
+    #[cfg(feature = "encryption")]
+    pub async fn rotate_collection_encryption_key(
+        &self,
+        collection: CollectionName,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || {
+                task_self
+                    .database
+                    .rotate_collection_encryption_key(&collection)
+            })
+            .await?
+    }
+
     /// Restricts an unauthenticated instance to having `effective_permissions`.
     /// Returns `None` if a session has already been established.
     #[must_use]
@@ -417,6 +561,71 @@ impl AsyncStorageConnection for AsyncStorage {
             .map_err(Error::from)?
     }
 
+    async fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let name = name.to_owned();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.set_database_quota(&name, quota))
+            .await
+            .map_err(Error::from)?
+    }
+
+    async fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.list_database_usage())
+            .await
+            .map_err(Error::from)?
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<bonsaidb_core::connection::AuthenticationTokenSummary>, bonsaidb_core::Error>
+    {
+        let task_self = self.clone();
+        let identity = identity.into_owned();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.list_authentication_tokens(identity))
+            .await
+            .map_err(Error::from)?
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.revoke_authentication_token(id))
+            .await
+            .map_err(Error::from)?
+    }
+
+    async fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.list_sessions())
+            .await
+            .map_err(Error::from)?
+    }
+
+    async fn revoke_session(
+        &self,
+        id: bonsaidb_core::connection::SessionId,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.revoke_session(id))
+            .await
+            .map_err(Error::from)?
+    }
+
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         let task_self = self.clone();
         self.runtime
@@ -460,7 +669,51 @@ impl AsyncStorageConnection for AsyncStorage {
             .map_err(Error::from)?
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    async fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<bonsaidb_core::admin::TotpSecret, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let user = user.name()?.into_owned();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.enroll_user_totp(user))
+            .await
+            .map_err(Error::from)?
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let user = user.name()?.into_owned();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.confirm_user_totp(user, code))
+            .await
+            .map_err(Error::from)?
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let user = user.name()?.into_owned();
+        self.runtime
+            .spawn_blocking(move || task_self.storage.remove_user_totp(user))
+            .await
+            .map_err(Error::from)?
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     async fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
@@ -690,6 +943,14 @@ impl AsyncSubscriber for Subscriber {
         pubsub::Subscriber::unsubscribe_from_bytes(self, topic)
     }
 
+    async fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        pubsub::Subscriber::subscribe_to_pattern(self, pattern)
+    }
+
+    async fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), bonsaidb_core::Error> {
+        pubsub::Subscriber::unsubscribe_from_pattern(self, pattern)
+    }
+
     fn receiver(&self) -> &Receiver {
         pubsub::Subscriber::receiver(self)
     }
@@ -10,6 +10,9 @@ use crate::{Error, Storage};
 pub mod admin;
 /// Commands for querying the schemas.
 pub mod schema;
+/// Commands for managing encryption keys.
+#[cfg(feature = "encryption")]
+pub mod vault;
 
 /// Commands operating on local database storage.
 #[derive(Subcommand, Debug)]
@@ -25,6 +28,10 @@ pub enum StorageCommand {
     Admin(admin::Command),
     /// Executes a schema query.
     Schema(schema::Command),
+    /// Executes a command operating on the vault.
+    #[cfg(feature = "encryption")]
+    #[clap(subcommand)]
+    Vault(vault::Command),
 }
 
 /// A backup location.
@@ -34,6 +41,15 @@ pub enum Location {
     Path {
         /// The path to the backup directory.
         path: PathBuf,
+        /// Encrypts the backup using the vault's master key. The backup can
+        /// only be restored by a storage using the same vault keys.
+        #[cfg(feature = "encryption")]
+        #[clap(long)]
+        vault: bool,
+        /// Encrypts the backup using a passphrase read from stdin.
+        #[cfg(all(feature = "encryption", feature = "password-hashing"))]
+        #[clap(long, conflicts_with = "vault")]
+        passphrase: bool,
     },
 }
 
@@ -51,6 +67,8 @@ impl StorageCommand {
             StorageCommand::Restore(location) => location.restore(storage),
             StorageCommand::Admin(admin) => admin.execute(storage),
             StorageCommand::Schema(schema) => schema.execute(storage),
+            #[cfg(feature = "encryption")]
+            StorageCommand::Vault(vault) => vault.execute(storage),
         }
     }
 
@@ -62,6 +80,8 @@ impl StorageCommand {
             StorageCommand::Restore(location) => location.restore_async(storage).await,
             StorageCommand::Admin(admin) => admin.execute_async(storage).await,
             StorageCommand::Schema(schema) => schema.execute_async(storage).await,
+            #[cfg(feature = "encryption")]
+            StorageCommand::Vault(vault) => vault.execute_async(storage).await,
         }
     }
 }
@@ -69,31 +89,82 @@ impl StorageCommand {
 impl Location {
     /// Backs-up `storage` to `self`.
     pub fn backup(&self, storage: &Storage) -> Result<(), Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = self.encryption(true)? {
+            return match self {
+                Location::Path { path, .. } => storage.backup_encrypted(path, &encryption),
+            };
+        }
+
         match self {
-            Location::Path { path } => storage.backup(path),
+            Location::Path { path, .. } => storage.backup(path),
         }
     }
 
     /// Restores `storage` from `self`.
     pub fn restore(&self, storage: &Storage) -> Result<(), Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = self.encryption(false)? {
+            return match self {
+                Location::Path { path, .. } => storage.restore_encrypted(path, &encryption),
+            };
+        }
+
         match self {
-            Location::Path { path } => storage.restore(path),
+            Location::Path { path, .. } => storage.restore(path),
         }
     }
 
     /// Backs-up `storage` to `self`.
     #[cfg(feature = "async")]
     pub async fn backup_async(&self, storage: &crate::AsyncStorage) -> Result<(), Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = self.encryption(true)? {
+            return match self {
+                Location::Path { path, .. } => {
+                    storage.backup_encrypted(path.clone(), encryption).await
+                }
+            };
+        }
+
         match self {
-            Location::Path { path } => storage.backup(path.clone()).await,
+            Location::Path { path, .. } => storage.backup(path.clone()).await,
         }
     }
 
     /// Restores `storage` from `self`.
     #[cfg(feature = "async")]
     pub async fn restore_async(&self, storage: &crate::AsyncStorage) -> Result<(), Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = self.encryption(false)? {
+            return match self {
+                Location::Path { path, .. } => {
+                    storage.restore_encrypted(path.clone(), encryption).await
+                }
+            };
+        }
+
+        match self {
+            Location::Path { path, .. } => storage.restore(path.clone()).await,
+        }
+    }
+
+    /// Returns how the backup should be encrypted, reading the passphrase from
+    /// stdin if needed.
+    #[cfg(feature = "encryption")]
+    #[cfg_attr(not(feature = "password-hashing"), allow(unused_variables))]
+    fn encryption(&self, confirm: bool) -> Result<Option<crate::BackupEncryption>, Error> {
         match self {
-            Location::Path { path } => storage.restore(path.clone()).await,
+            Location::Path { vault: true, .. } => Ok(Some(crate::BackupEncryption::Vault(
+                bonsaidb_core::document::KeyId::Master,
+            ))),
+            #[cfg(feature = "password-hashing")]
+            Location::Path {
+                passphrase: true, ..
+            } => Ok(Some(crate::BackupEncryption::Passphrase(
+                read_password_from_stdin(confirm)?,
+            ))),
+            Location::Path { .. } => Ok(None),
         }
     }
 }
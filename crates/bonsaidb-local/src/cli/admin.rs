@@ -1,4 +1,10 @@
-use bonsaidb_core::connection::{AsyncStorageConnection, StorageConnection};
+use bonsaidb_core::admin::{AuditLogEntry, AuditOutcome};
+use bonsaidb_core::connection::{
+    AsyncStorageConnection, Identity, SessionAuthentication, SessionId, SessionSummary,
+    StorageConnection,
+};
+use bonsaidb_core::document::CollectionDocument;
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
 use clap::Subcommand;
 
 /// An administrative command-line command.
@@ -7,6 +13,12 @@ pub enum Command {
     /// A command operating on [`User`s](bonsaidb_core::admin::User).
     #[clap(subcommand)]
     User(UserCommand),
+    /// A command operating on the [audit log](bonsaidb_core::admin::AuditLogEntry).
+    #[clap(subcommand)]
+    AuditLog(AuditLogCommand),
+    /// A command operating on active authentication sessions.
+    #[clap(subcommand)]
+    Session(SessionCommand),
 }
 
 /// A command operating on [`User`s](bonsaidb_core::admin::User).
@@ -59,6 +71,37 @@ pub enum UserCommand {
     },
 }
 
+/// A command operating on the [audit log](bonsaidb_core::admin::AuditLogEntry).
+#[derive(Subcommand, Debug)]
+pub enum AuditLogCommand {
+    /// Exports audit log entries to stdout as tab-separated values.
+    Export {
+        /// Only export entries recorded at or after this time, in seconds
+        /// since the Unix epoch.
+        #[clap(long)]
+        since: Option<TimestampAsNanoseconds>,
+        /// Only export entries recorded at or before this time, in seconds
+        /// since the Unix epoch.
+        #[clap(long)]
+        until: Option<TimestampAsNanoseconds>,
+        /// Only export entries for actions performed on this resource.
+        #[clap(long)]
+        resource: Option<String>,
+    },
+}
+
+/// A command operating on active authentication sessions.
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    /// Lists the active sessions to stdout as tab-separated values.
+    List,
+    /// Revokes a session, logging out any clients using it.
+    Revoke {
+        /// The id of the session to revoke.
+        id: u64,
+    },
+}
+
 impl Command {
     /// Executes the command on `storage`.
     pub fn execute<SC: StorageConnection>(self, storage: &SC) -> Result<(), crate::Error> {
@@ -109,6 +152,45 @@ impl Command {
                     Ok(())
                 }
             },
+            Command::AuditLog(AuditLogCommand::Export {
+                since,
+                until,
+                resource,
+            }) => {
+                let admin = storage.admin();
+                let mut export = AuditLogExport::new(since, until);
+                loop {
+                    let page = if let Some(resource) = &resource {
+                        AuditLogEntry::for_resource(
+                            resource,
+                            export.start,
+                            export.end,
+                            AUDIT_LOG_EXPORT_PAGE_SIZE,
+                            &admin,
+                        )?
+                    } else {
+                        AuditLogEntry::recorded_between(
+                            export.start,
+                            export.end,
+                            AUDIT_LOG_EXPORT_PAGE_SIZE,
+                            &admin,
+                        )?
+                    };
+                    if !export.print_page(page) {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            Command::Session(SessionCommand::List) => {
+                print_sessions(storage.list_sessions()?);
+                Ok(())
+            }
+            Command::Session(SessionCommand::Revoke { id }) => {
+                storage.revoke_session(SessionId(id))?;
+                println!("Session {id} revoked");
+                Ok(())
+            }
         }
     }
 
@@ -168,6 +250,138 @@ impl Command {
                     Ok(())
                 }
             },
+            Command::AuditLog(AuditLogCommand::Export {
+                since,
+                until,
+                resource,
+            }) => {
+                let admin = storage.admin().await;
+                let mut export = AuditLogExport::new(since, until);
+                loop {
+                    let page = if let Some(resource) = &resource {
+                        AuditLogEntry::for_resource_async(
+                            resource,
+                            export.start,
+                            export.end,
+                            AUDIT_LOG_EXPORT_PAGE_SIZE,
+                            &admin,
+                        )
+                        .await?
+                    } else {
+                        AuditLogEntry::recorded_between_async(
+                            export.start,
+                            export.end,
+                            AUDIT_LOG_EXPORT_PAGE_SIZE,
+                            &admin,
+                        )
+                        .await?
+                    };
+                    if !export.print_page(page) {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            Command::Session(SessionCommand::List) => {
+                print_sessions(storage.list_sessions().await?);
+                Ok(())
+            }
+            Command::Session(SessionCommand::Revoke { id }) => {
+                storage.revoke_session(SessionId(id)).await?;
+                println!("Session {id} revoked");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn print_sessions(sessions: Vec<SessionSummary>) {
+    let now = TimestampAsNanoseconds::now();
+    println!("id\tuser\taddress\ttransport\tage");
+    for session in sessions {
+        let user = match &session.authentication {
+            SessionAuthentication::Identity(identity) => match identity.as_ref() {
+                Identity::User { id, username } => format!("user:{id}:{username}"),
+                Identity::Role { id, name } => format!("role:{id}:{name}"),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+        let address = session
+            .address
+            .map_or_else(String::new, |address| address.to_string());
+        let transport = session.transport.unwrap_or_default();
+        let age = now
+            .duration_since(&session.created_at)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        println!(
+            "{}\t{user}\t{address}\t{transport}\t{}s",
+            session.id.0,
+            age.as_secs()
+        );
+    }
+}
+
+/// The number of audit log entries loaded at a time while exporting.
+const AUDIT_LOG_EXPORT_PAGE_SIZE: u32 = 1_000;
+
+/// Tracks the progress of exporting the audit log one page at a time.
+struct AuditLogExport {
+    start: TimestampAsNanoseconds,
+    end: TimestampAsNanoseconds,
+    last_exported: Option<(TimestampAsNanoseconds, u64)>,
+}
+
+impl AuditLogExport {
+    fn new(since: Option<TimestampAsNanoseconds>, until: Option<TimestampAsNanoseconds>) -> Self {
+        println!("timestamp\tsession\tidentity\taction\tresource\toutcome");
+        Self {
+            start: since.unwrap_or(TimestampAsNanoseconds::from_representation(i64::MIN)),
+            end: until.unwrap_or(TimestampAsNanoseconds::from_representation(i64::MAX)),
+            last_exported: None,
+        }
+    }
+
+    /// Prints the entries in `page` that haven't already been exported, and
+    /// returns true if another page should be requested.
+    fn print_page(&mut self, page: Vec<CollectionDocument<AuditLogEntry>>) -> bool {
+        let full_page =
+            u32::try_from(page.len()).map_or(true, |len| len >= AUDIT_LOG_EXPORT_PAGE_SIZE);
+        let mut exported_any = false;
+        for entry in page {
+            // Pages start at the timestamp of the last entry exported, which
+            // allows entries sharing a timestamp to span pages.
+            let position = (entry.contents.timestamp, entry.header.id);
+            if self.last_exported.map_or(false, |last| position <= last) {
+                continue;
+            }
+            self.last_exported = Some(position);
+            exported_any = true;
+
+            let entry = entry.contents;
+            let session = entry
+                .session_id
+                .map_or_else(String::new, |id| id.0.to_string());
+            let identity = match &entry.identity {
+                Some(Identity::User { id, username }) => format!("user:{id}:{username}"),
+                Some(Identity::Role { id, name }) => format!("role:{id}:{name}"),
+                Some(_) | None => String::new(),
+            };
+            let outcome = match entry.outcome {
+                AuditOutcome::Allowed => "allowed",
+                AuditOutcome::Denied => "denied",
+            };
+            println!(
+                "{}\t{session}\t{identity}\t{}\t{}\t{outcome}",
+                entry.timestamp, entry.action, entry.resource
+            );
+        }
+
+        if let Some((timestamp, _)) = self.last_exported {
+            self.start = timestamp;
         }
+        full_page && exported_any
     }
 }
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+
+use bonsaidb_core::document::KeyId;
+use bonsaidb_core::schema::CollectionName;
+use clap::Subcommand;
+
+use crate::{Error, Storage};
+
+/// A command operating on the [vault](crate::vault).
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Replaces the vault key and re-seals the master keys with it.
+    RotateVaultKey,
+    /// Creates a new version of an encryption key. Data encrypted with
+    /// previous versions remains readable.
+    RotateKey {
+        /// The name of the key to rotate. If not provided, the master key is
+        /// rotated.
+        #[clap(long)]
+        name: Option<String>,
+    },
+    /// Rotates a collection's encryption key and re-encrypts the collection's
+    /// data, waiting for re-encryption to finish.
+    RotateCollectionKey {
+        /// The name of the database containing the collection.
+        database: String,
+        /// The name of the collection to rotate the key of.
+        collection: CollectionName,
+    },
+    /// Lists the collections that are still being re-encrypted.
+    PendingRotations,
+    /// Removes the versions of a named encryption key that are no longer used
+    /// by any collection. Backups sealed with the removed versions can no
+    /// longer be restored.
+    RetireKeyVersions {
+        /// The name of the key to remove unused versions of.
+        name: String,
+    },
+}
+
+impl Command {
+    /// Executes the command on `storage`.
+    pub fn execute(self, storage: &Storage) -> Result<(), Error> {
+        match self {
+            Command::RotateVaultKey => storage.rotate_vault_key(),
+            Command::RotateKey { name } => {
+                let key = name.map_or(KeyId::Master, |name| KeyId::Id(Cow::Owned(name)));
+                storage.rotate_encryption_key(&key)
+            }
+            Command::RotateCollectionKey {
+                database,
+                collection,
+            } => {
+                let database =
+                    storage
+                        .instance
+                        .database_without_schema(&database, Some(storage), None)?;
+                database.rotate_collection_encryption_key(&collection)?;
+                // The storage is closed when the command exits, so wait for
+                // re-encryption rather than resuming it the next time the
+                // database is opened.
+                storage
+                    .instance
+                    .tasks()
+                    .spawn_reencryption(&database, collection)
+                    .receive()??;
+                Ok(())
+            }
+            Command::PendingRotations => {
+                for rotation in storage.pending_key_rotations() {
+                    println!(
+                        "{}\t{}\t{}",
+                        rotation.database, rotation.collection, rotation.remaining_trees
+                    );
+                }
+                Ok(())
+            }
+            Command::RetireKeyVersions { name } => {
+                let retired = storage.retire_encryption_key_versions(&name)?;
+                println!("retired {retired} version(s) of {name}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Executes the command on `storage`.
+    #[cfg(feature = "async")]
+    pub async fn execute_async(self, storage: &crate::AsyncStorage) -> Result<(), Error> {
+        let storage = storage.to_blocking();
+        tokio::task::spawn_blocking(move || self.execute(&storage)).await?
+    }
+}
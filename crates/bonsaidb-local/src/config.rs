@@ -1,17 +1,28 @@
 use std::collections::HashMap;
+#[cfg(feature = "async")]
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
+#[cfg(feature = "async")]
+use bonsaidb_core::api::{self, ApiName};
 #[cfg(feature = "encryption")]
 use bonsaidb_core::document::KeyId;
-use bonsaidb_core::permissions::Permissions;
+use bonsaidb_core::permissions::bonsai::{
+    BonsaiAction, DatabaseAction, DocumentAction, ServerAction, ViewAction,
+};
+use bonsaidb_core::permissions::{Action, ActionName, Permissions};
 use bonsaidb_core::schema::{Schema, SchemaName};
 use sysinfo::{CpuRefreshKind, RefreshKind, System, SystemExt};
 
+#[cfg(feature = "async")]
+use crate::api::{AnyHandler, AnyWrapper, Handler};
 use crate::storage::{DatabaseOpener, StorageSchemaOpener};
 #[cfg(feature = "encryption")]
 use crate::vault::AnyVaultKeyStorage;
+#[cfg(feature = "async")]
+use crate::AsyncStorage;
 use crate::Error;
 
 #[cfg(feature = "password-hashing")]
@@ -46,6 +57,10 @@ pub struct StorageConfiguration {
     /// location that is separate from the database. If the keys are on the same
     /// hardware as the encrypted content, anyone with access to the disk will
     /// be able to decrypt the stored data.
+    ///
+    /// To avoid relying on a single location, use
+    /// [`ShamirVaultKeyStorage`](crate::vault::ShamirVaultKeyStorage) to split
+    /// the vault key across multiple storages.
     #[cfg(feature = "encryption")]
     pub vault_key_storage: Option<Arc<dyn AnyVaultKeyStorage>>,
 
@@ -76,7 +91,13 @@ pub struct StorageConfiguration {
     #[cfg(feature = "password-hashing")]
     pub argon: ArgonConfiguration,
 
+    /// Controls which actions are recorded in the audit log. The audit log is
+    /// disabled by default.
+    pub audit_log: AuditLog,
+
     pub(crate) initial_schemas: HashMap<SchemaName, Arc<dyn DatabaseOpener>>,
+    #[cfg(feature = "async")]
+    pub(crate) custom_apis: HashMap<ApiName, Arc<dyn AnyHandler>>,
 }
 
 impl Default for StorageConfiguration {
@@ -102,7 +123,10 @@ impl Default for StorageConfiguration {
             authenticated_permissions: Permissions::default(),
             #[cfg(feature = "password-hashing")]
             argon: ArgonConfiguration::default_for(&system),
+            audit_log: AuditLog::default(),
             initial_schemas: HashMap::default(),
+            #[cfg(feature = "async")]
+            custom_apis: HashMap::default(),
         }
     }
 }
@@ -119,6 +143,7 @@ impl std::fmt::Debug for StorageConfiguration {
             .field("views", &self.views)
             .field("key_value_persistence", &self.key_value_persistence)
             .field("authenticated_permissions", &self.authenticated_permissions)
+            .field("audit_log", &self.audit_log)
             .field("initial_schemas", &schemas);
 
         #[cfg(feature = "encryption")]
@@ -143,6 +168,30 @@ impl StorageConfiguration {
             .insert(S::schema_name(), Arc::new(StorageSchemaOpener::<S>::new()?));
         Ok(())
     }
+
+    /// Registers a `Dispatcher` to handle requests for `Api` sent using
+    /// [`AsyncStorage::send_api_request()`] or
+    /// [`Storage::send_api_request()`](crate::Storage::send_api_request). Any
+    /// existing handler for `Api` is replaced.
+    #[cfg(feature = "async")]
+    pub fn register_custom_api<Dispatcher: Handler<Api, AsyncStorage> + 'static, Api: api::Api>(
+        &mut self,
+    ) {
+        self.custom_apis.insert(
+            Api::name(),
+            Arc::new(AnyWrapper::<Dispatcher, Api>(PhantomData)),
+        );
+    }
+
+    /// Registers a `Dispatcher` to handle requests for `Api` and returns self.
+    #[cfg(feature = "async")]
+    #[must_use]
+    pub fn with_api<Dispatcher: Handler<Api, AsyncStorage> + 'static, Api: api::Api>(
+        mut self,
+    ) -> Self {
+        self.register_custom_api::<Dispatcher, Api>();
+        self
+    }
 }
 
 /// Configuration options for background tasks.
@@ -184,6 +233,76 @@ pub struct Views {
     pub check_integrity_on_open: bool,
 }
 
+/// Controls which actions are recorded in the audit log.
+///
+/// When an audited action's permissions are checked for a connection that has
+/// a [`Session`](bonsaidb_core::connection::Session), an
+/// [`AuditLogEntry`](bonsaidb_core::admin::AuditLogEntry) is stored in the
+/// admin database. Connections without a session bypass permission checks and
+/// are not audited.
+#[derive(Debug, Clone, Default)]
+pub enum AuditLog {
+    /// No actions are recorded.
+    #[default]
+    Disabled,
+    /// Administrative actions and actions that modify documents are recorded.
+    Modifications,
+    /// All actions are recorded.
+    All,
+    /// Only the listed actions are recorded.
+    Actions(Vec<ActionName>),
+}
+
+impl AuditLog {
+    /// Returns a configuration that records each action in `actions`.
+    pub fn actions<'a, A: Action + 'a>(actions: impl IntoIterator<Item = &'a A>) -> Self {
+        Self::Actions(actions.into_iter().map(Action::name).collect())
+    }
+
+    /// Returns true if `action` should be recorded.
+    #[must_use]
+    pub fn includes(&self, action: &ActionName) -> bool {
+        match self {
+            Self::Disabled => false,
+            Self::Modifications => modification_actions()
+                .iter()
+                .any(|modification| modification.0 == action.0),
+            Self::All => true,
+            Self::Actions(actions) => actions.iter().any(|audited| audited.0 == action.0),
+        }
+    }
+}
+
+fn modification_actions() -> &'static [ActionName] {
+    static ACTIONS: OnceLock<Vec<ActionName>> = OnceLock::new();
+    ACTIONS.get_or_init(|| {
+        [
+            BonsaiAction::Server(ServerAction::CreateDatabase),
+            BonsaiAction::Server(ServerAction::DeleteDatabase),
+            BonsaiAction::Server(ServerAction::CreateUser),
+            BonsaiAction::Server(ServerAction::DeleteUser),
+            BonsaiAction::Server(ServerAction::SetPassword),
+            BonsaiAction::Server(ServerAction::ModifyUserTotp),
+            BonsaiAction::Server(ServerAction::AssumeIdentity),
+            BonsaiAction::Server(ServerAction::ModifyUserPermissionGroups),
+            BonsaiAction::Server(ServerAction::ModifyUserRoles),
+            BonsaiAction::Server(ServerAction::SetDatabaseQuota),
+            BonsaiAction::Server(ServerAction::RevokeAuthenticationToken),
+            BonsaiAction::Server(ServerAction::RevokeSession),
+            BonsaiAction::Server(ServerAction::RotateVaultKey),
+            BonsaiAction::Database(DatabaseAction::Compact),
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Insert)),
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Update)),
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Overwrite)),
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Delete)),
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::DeleteDocs)),
+        ]
+        .iter()
+        .map(Action::name)
+        .collect()
+    })
+}
+
 /// Rules for persisting key-value changes. Default persistence is to
 /// immediately persist all changes. While this ensures data integrity, the
 /// overhead of the key-value store can be significantly reduced by utilizing
@@ -394,6 +513,9 @@ pub trait Builder: Sized {
     #[cfg(feature = "password-hashing")]
     #[must_use]
     fn argon(self, argon: ArgonConfiguration) -> Self;
+    /// Sets [`StorageConfiguration::audit_log`](StorageConfiguration#structfield.audit_log) to `audit_log` and returns self.
+    #[must_use]
+    fn audit_log(self, audit_log: AuditLog) -> Self;
 }
 
 impl Builder for StorageConfiguration {
@@ -471,6 +593,11 @@ impl Builder for StorageConfiguration {
         self.argon = argon;
         self
     }
+
+    fn audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
 }
 
 pub(crate) trait SystemDefault: Sized {
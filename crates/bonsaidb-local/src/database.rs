@@ -3,9 +3,11 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::Infallible;
 use std::ops::{self, Deref};
 use std::sync::Arc;
+use std::time::Instant;
 use std::u8;
 
-use bonsaidb_core::arc_bytes::serde::CowBytes;
+use bonsaidb_core::admin::{DatabaseQuota, QuotaLimit};
+use bonsaidb_core::arc_bytes::serde::{Bytes, CowBytes};
 use bonsaidb_core::arc_bytes::ArcBytes;
 use bonsaidb_core::connection::{
     self, AccessPolicy, Connection, HasSchema, HasSession, LowLevelConnection, Range,
@@ -23,7 +25,7 @@ use bonsaidb_core::permissions::bonsai::{
     view_resource_name, BonsaiAction, DatabaseAction, DocumentAction, TransactionAction,
     ViewAction,
 };
-use bonsaidb_core::permissions::Permissions;
+use bonsaidb_core::permissions::{Action, Identifier, PermissionDenied, Permissions};
 use bonsaidb_core::schema::view::map::MappedSerializedValue;
 use bonsaidb_core::schema::view::{self};
 use bonsaidb_core::schema::{self, CollectionName, Schema, Schematic, ViewName};
@@ -43,7 +45,7 @@ use serde::{Deserialize, Serialize};
 use watchable::Watchable;
 
 use crate::config::{Builder, KeyValuePersistence, StorageConfiguration};
-use crate::database::keyvalue::BackgroundWorkerProcessTarget;
+use crate::database::keyvalue::{BackgroundWorkerProcessTarget, KEY_TREE};
 use crate::error::Error;
 use crate::open_trees::OpenTrees;
 use crate::storage::StorageLock;
@@ -141,6 +143,11 @@ impl Database {
             }),
         };
 
+        db.data
+            .context
+            .usage
+            .count_if_needed(|| db.count_storage_usage())?;
+
         if storage.instance.check_view_integrity_on_database_open() {
             for view in db.data.schema.views() {
                 storage.instance.tasks().spawn_integrity_check(view, &db);
@@ -152,6 +159,9 @@ impl Database {
             .tasks()
             .spawn_key_value_expiration_loader(&db);
 
+        #[cfg(feature = "encryption")]
+        storage.instance.tasks().spawn_pending_reencryptions(&db);
+
         Ok(db)
     }
 
@@ -319,11 +329,13 @@ impl Database {
         let mut changed_documents = Vec::new();
         let mut collection_indexes = HashMap::new();
         let mut collections = Vec::new();
+        let mut usage = UsageChange::default();
         for op in &transaction.operations {
             let result = self.execute_operation(
                 op,
                 &mut roots_transaction,
                 &open_trees.trees_index_by_name,
+                &mut usage,
             )?;
 
             if let Some((collection, id, deleted)) = match &result {
@@ -373,6 +385,7 @@ impl Database {
             )?)?;
 
         roots_transaction.commit()?;
+        self.data.context.usage.record_documents(&usage);
 
         Ok(results)
     }
@@ -417,11 +430,17 @@ impl Database {
         operation: &Operation,
         transaction: &mut ExecutingTransaction<AnyFile>,
         tree_index_map: &HashMap<String, usize>,
+        usage: &mut UsageChange,
     ) -> Result<OperationResult, Error> {
         match &operation.command {
-            Command::Insert { id, contents } => {
-                self.execute_insert(operation, transaction, tree_index_map, id.clone(), contents)
-            }
+            Command::Insert { id, contents } => self.execute_insert(
+                operation,
+                transaction,
+                tree_index_map,
+                id.clone(),
+                contents,
+                usage,
+            ),
             Command::Update { header, contents } => self.execute_update(
                 operation,
                 transaction,
@@ -429,12 +448,19 @@ impl Database {
                 &header.id,
                 Some(&header.revision),
                 contents,
+                usage,
+            ),
+            Command::Overwrite { id, contents } => self.execute_update(
+                operation,
+                transaction,
+                tree_index_map,
+                id,
+                None,
+                contents,
+                usage,
             ),
-            Command::Overwrite { id, contents } => {
-                self.execute_update(operation, transaction, tree_index_map, id, None, contents)
-            }
             Command::Delete { header } => {
-                self.execute_delete(operation, transaction, tree_index_map, header)
+                self.execute_delete(operation, transaction, tree_index_map, header, usage)
             }
             Command::Check { id, revision } => Self::execute_check(
                 operation,
@@ -450,7 +476,7 @@ impl Database {
         feature = "tracing",
         tracing::instrument(
             level = "trace",
-            skip(self, operation, transaction, tree_index_map, contents),
+            skip(self, operation, transaction, tree_index_map, contents, usage),
             fields(
                 database = self.name(),
                 collection.name = operation.collection.name.as_ref(),
@@ -458,6 +484,7 @@ impl Database {
             )
         )
     )]
+    #[allow(clippy::too_many_arguments)]
     fn execute_update(
         &self,
         operation: &Operation,
@@ -466,6 +493,7 @@ impl Database {
         id: &DocumentId,
         check_revision: Option<&Revision>,
         contents: &[u8],
+        usage: &mut UsageChange,
     ) -> Result<OperationResult, crate::Error> {
         let mut documents = transaction
             .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
@@ -479,6 +507,18 @@ impl Database {
                                                                         value: Option<
                 ArcBytes<'_>,
             >| {
+                if check_revision.is_none() {
+                    if let Err(err) = self.check_overwrite_policy(
+                        &operation.collection,
+                        id,
+                        value.as_deref(),
+                        contents,
+                    ) {
+                        result = Some(Err(err));
+                        return nebari::tree::KeyOperation::Skip;
+                    }
+                }
+
                 if let Some(old) = value {
                     let doc = match deserialize_document(&old) {
                         Ok(doc) => doc,
@@ -494,13 +534,16 @@ impl Database {
                                 id: id.clone(),
                                 revision: updated_revision,
                             };
-                            let serialized_doc = match serialize_document(&BorrowedDocument {
-                                header: updated_header.clone(),
-                                contents: CowBytes::from(contents),
-                            }) {
+                            let serialized_doc = match self.serialize_document_for(
+                                &operation.collection,
+                                &BorrowedDocument {
+                                    header: updated_header.clone(),
+                                    contents: CowBytes::from(contents),
+                                },
+                            ) {
                                 Ok(bytes) => bytes,
                                 Err(err) => {
-                                    result = Some(Err(Error::from(err)));
+                                    result = Some(Err(err));
                                     return nebari::tree::KeyOperation::Skip;
                                 }
                             };
@@ -509,6 +552,8 @@ impl Database {
                                 header: updated_header,
                             }));
                             updated = true;
+                            usage.added_bytes += serialized_doc.len() as u64;
+                            usage.removed_bytes += old.len() as u64;
                             return nebari::tree::KeyOperation::Set(ArcBytes::from(serialized_doc));
                         }
 
@@ -528,17 +573,22 @@ impl Database {
                     }
                 } else if check_revision.is_none() {
                     let doc = BorrowedDocument::new(id.clone(), contents);
-                    match serialize_document(&doc).map(|bytes| (doc, bytes)) {
+                    match self
+                        .serialize_document_for(&operation.collection, &doc)
+                        .map(|bytes| (doc, bytes))
+                    {
                         Ok((doc, serialized)) => {
                             result = Some(Ok(OperationResult::DocumentUpdated {
                                 collection: operation.collection.clone(),
                                 header: doc.header,
                             }));
                             updated = true;
+                            usage.added_bytes += serialized.len() as u64;
+                            usage.added_documents += 1;
                             return nebari::tree::KeyOperation::Set(ArcBytes::from(serialized));
                         }
                         Err(err) => {
-                            result = Some(Err(Error::from(err)));
+                            result = Some(Err(err));
                         }
                     }
                 } else {
@@ -563,7 +613,7 @@ impl Database {
         feature = "tracing",
         tracing::instrument(
             level = "trace",
-            skip(self, operation, transaction, tree_index_map, contents),
+            skip(self, operation, transaction, tree_index_map, contents, usage),
             fields(
                 database = self.name(),
                 collection.name = operation.collection.name.as_ref(),
@@ -578,6 +628,7 @@ impl Database {
         tree_index_map: &HashMap<String, usize>,
         id: Option<DocumentId>,
         contents: &[u8],
+        usage: &mut UsageChange,
     ) -> Result<OperationResult, Error> {
         let mut documents = transaction
             .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
@@ -596,7 +647,8 @@ impl Database {
         };
 
         let doc = BorrowedDocument::new(id, contents);
-        let serialized: Vec<u8> = serialize_document(&doc)?;
+        let serialized: Vec<u8> = self.serialize_document_for(&operation.collection, &doc)?;
+        let serialized_length = serialized.len() as u64;
         let document_id = ArcBytes::from(doc.header.id.as_ref().to_vec());
         if let Some(document) = documents.replace(document_id.clone(), serialized)? {
             let doc = deserialize_document(&document)?;
@@ -607,6 +659,8 @@ impl Database {
         } else {
             drop(documents);
             self.update_eager_views(&document_id, operation, transaction, tree_index_map)?;
+            usage.added_bytes += serialized_length;
+            usage.added_documents += 1;
 
             Ok(OperationResult::DocumentUpdated {
                 collection: operation.collection.clone(),
@@ -630,6 +684,7 @@ impl Database {
         transaction: &mut ExecutingTransaction<AnyFile>,
         tree_index_map: &HashMap<String, usize>,
         header: &Header,
+        usage: &mut UsageChange,
     ) -> Result<OperationResult, Error> {
         let mut documents = transaction
             .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
@@ -644,6 +699,8 @@ impl Database {
                     transaction,
                     tree_index_map,
                 )?;
+                usage.removed_bytes += vec.len() as u64;
+                usage.removed_documents += 1;
 
                 Ok(OperationResult::DocumentDeleted {
                     collection: operation.collection.clone(),
@@ -831,6 +888,391 @@ impl Database {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    fn get_document(
+        &self,
+        id: &DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        let tree = self
+            .data
+            .context
+            .roots
+            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
+            .map_err(Error::from)?;
+        if let Some(vec) = tree.get(id.as_ref()).map_err(Error::from)? {
+            Ok(self
+                .decrypt_document(collection, deserialize_document(&vec)?)?
+                .map(BorrowedDocument::into_owned))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_documents(
+        &self,
+        ids: &[DocumentId],
+        collection: &CollectionName,
+    ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
+        let mut ids = ids.to_vec();
+        let tree = self
+            .data
+            .context
+            .roots
+            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
+            .map_err(Error::from)?;
+        ids.sort();
+        let keys_and_values = tree
+            .get_multiple(ids.iter().map(|id| id.as_ref()))
+            .map_err(Error::from)?;
+
+        let mut documents = Vec::with_capacity(keys_and_values.len());
+        for (_, value) in keys_and_values {
+            if let Some(document) =
+                self.decrypt_document(collection, deserialize_document(&value)?)?
+            {
+                documents.push(document.into_owned());
+            }
+        }
+        Ok(documents)
+    }
+
+    fn list_documents(
+        &self,
+        ids: Range<DocumentId>,
+        sort: Sort,
+        limit: Option<u32>,
+        collection: &CollectionName,
+    ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
+        self.scan_documents(ids, sort, limit, collection, true)
+    }
+
+    /// Returns every document in `collection` as it is stored, without
+    /// decrypting contents encrypted with a document-level key. The contents
+    /// can be decrypted using [`Self::decrypt_stored_contents()`].
+    pub(crate) fn list_stored_documents(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
+        self.check_permission(
+            collection_resource_name(self.name(), collection),
+            &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::List)),
+        )?;
+        self.scan_documents(Range::from(..), Sort::Ascending, None, collection, false)
+    }
+
+    fn scan_documents(
+        &self,
+        ids: Range<DocumentId>,
+        sort: Sort,
+        limit: Option<u32>,
+        collection: &CollectionName,
+        decrypt: bool,
+    ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
+        let tree = self
+            .data
+            .context
+            .roots
+            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
+            .map_err(Error::from)?;
+        let mut found_docs = Vec::new();
+        let mut keys_read = 0;
+        let ids = DocumentIdRange(ids);
+        tree.scan(
+            &ids.borrow_as_bytes(),
+            match sort {
+                Sort::Ascending => true,
+                Sort::Descending => false,
+            },
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| {
+                if let Some(limit) = limit {
+                    if keys_read >= limit {
+                        return ScanEvaluation::Stop;
+                    }
+
+                    keys_read += 1;
+                }
+                ScanEvaluation::ReadData
+            },
+            |_, _, doc| {
+                let doc = deserialize_document(&doc)
+                    .and_then(|doc| {
+                        if decrypt {
+                            self.decrypt_document(collection, doc)
+                        } else {
+                            Ok(Some(doc))
+                        }
+                    })
+                    .map_err(AbortError::Other)?;
+                if let Some(doc) = doc {
+                    found_docs.push(doc.into_owned());
+                }
+                Ok(())
+            },
+        )
+        .map_err(|err| match err {
+            AbortError::Other(err) => err,
+            AbortError::Nebari(err) => crate::Error::from(err),
+        })?;
+
+        Ok(found_docs)
+    }
+
+    /// Returns the current session and the [`DocumentPolicy`] for
+    /// `collection`, if the policy should be evaluated for this connection.
+    ///
+    /// [`DocumentPolicy`]: bonsaidb_core::schema::DocumentPolicy
+    fn document_policy(
+        &self,
+        collection: &CollectionName,
+    ) -> Option<(&Session, &dyn schema::policy::Serialized)> {
+        let session = self.session()?;
+        let policy = self.data.schema.policy_for(collection)?;
+        Some((session, policy))
+    }
+
+    /// Removes the documents that can't be read by the current session, and
+    /// allows the policy to redact the remaining documents.
+    fn readable_documents(
+        &self,
+        collection: &CollectionName,
+        documents: Vec<OwnedDocument>,
+    ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
+        let Some((session, policy)) = self.document_policy(collection) else {
+            return Ok(documents);
+        };
+
+        let mut readable = Vec::with_capacity(documents.len());
+        for mut document in documents {
+            if policy.can_read(session, &mut document)? {
+                readable.push(document);
+            }
+        }
+        Ok(readable)
+    }
+
+    fn readable_document_ids<'a>(
+        &self,
+        ids: impl Iterator<Item = &'a DocumentId>,
+        collection: &CollectionName,
+    ) -> Result<HashSet<DocumentId>, bonsaidb_core::Error> {
+        let ids = ids
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let documents = self.get_documents(&ids, collection)?;
+        Ok(self
+            .readable_documents(collection, documents)?
+            .into_iter()
+            .map(|document| document.header.id)
+            .collect())
+    }
+
+    /// Reduces each key in `view` using only the mappings whose source
+    /// documents can be read by the current session.
+    fn reduce_readable_grouped(
+        &self,
+        view: &dyn view::Serialized,
+        collection: &CollectionName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedValue>, bonsaidb_core::Error> {
+        let mut entries = Vec::new();
+        self.for_each_in_view(view, key, Sort::Ascending, None, access_policy, |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+
+        let readable = self.readable_document_ids(
+            entries
+                .iter()
+                .flat_map(|entry| entry.mappings.iter().map(|mapping| &mapping.source.id)),
+            collection,
+        )?;
+
+        let mut mappings = Vec::new();
+        for entry in entries {
+            let values = entry
+                .mappings
+                .iter()
+                .filter(|mapping| readable.contains(&mapping.source.id))
+                .map(|mapping| (entry.key.as_ref(), mapping.value.as_ref()))
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                continue;
+            }
+
+            let value = view.reduce(&values, false).map_err(Error::from)?;
+            mappings.push(MappedSerializedValue {
+                key: entry.key,
+                value: Bytes::from(value),
+            });
+        }
+
+        Ok(mappings)
+    }
+
+    /// Evaluates the [`DocumentPolicy`] for `operation`'s collection, if
+    /// one applies to the current session.
+    ///
+    /// [`DocumentPolicy`]: bonsaidb_core::schema::DocumentPolicy
+    fn check_document_policy(&self, operation: &Operation) -> Result<(), bonsaidb_core::Error> {
+        let Some((session, policy)) = self.document_policy(&operation.collection) else {
+            return Ok(());
+        };
+
+        let (allowed, resource, action) = match &operation.command {
+            Command::Insert { id, contents } => (
+                policy.can_insert(session, id.as_ref(), contents)?,
+                collection_resource_name(self.name(), &operation.collection),
+                DocumentAction::Insert,
+            ),
+            Command::Update { header, contents } => {
+                // Missing documents are reported by the transaction itself.
+                let Some(existing) = self.get_document(&header.id, &operation.collection)? else {
+                    return Ok(());
+                };
+                (
+                    policy.can_update(session, &existing, contents)?,
+                    document_resource_name(self.name(), &operation.collection, &header.id),
+                    DocumentAction::Update,
+                )
+            }
+            // Whether an overwrite is an insert or an update depends on the
+            // document stored when the transaction executes, so the policy is
+            // evaluated by `execute_update()` instead.
+            Command::Overwrite { .. } | Command::Check { .. } => return Ok(()),
+            Command::Delete { header } => {
+                let Some(existing) = self.get_document(&header.id, &operation.collection)? else {
+                    return Ok(());
+                };
+                (
+                    policy.can_delete(session, &existing)?,
+                    document_resource_name(self.name(), &operation.collection, &header.id),
+                    DocumentAction::Delete,
+                )
+            }
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(bonsaidb_core::Error::PermissionDenied(PermissionDenied {
+                resource: resource.to_owned(),
+                action: BonsaiAction::Database(DatabaseAction::Document(action)).name(),
+            }))
+        }
+    }
+
+    /// Evaluates the [`DocumentPolicy`] for overwriting `id` with `contents`.
+    /// `existing` is the currently stored document, read within the
+    /// transaction performing the overwrite.
+    ///
+    /// [`DocumentPolicy`]: bonsaidb_core::schema::DocumentPolicy
+    fn check_overwrite_policy(
+        &self,
+        collection: &CollectionName,
+        id: &DocumentId,
+        existing: Option<&[u8]>,
+        contents: &[u8],
+    ) -> Result<(), Error> {
+        let Some((session, policy)) = self.document_policy(collection) else {
+            return Ok(());
+        };
+
+        let existing = match existing {
+            Some(existing) => self.decrypt_document(collection, deserialize_document(existing)?)?,
+            None => None,
+        };
+        let allowed = match existing {
+            Some(existing) => policy.can_update(session, &existing.into_owned(), contents)?,
+            None => policy.can_insert(session, Some(id), contents)?,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::Core(bonsaidb_core::Error::PermissionDenied(
+                PermissionDenied {
+                    resource: document_resource_name(self.name(), collection, id).to_owned(),
+                    action: BonsaiAction::Database(DatabaseAction::Document(
+                        DocumentAction::Overwrite,
+                    ))
+                    .name(),
+                },
+            )))
+        }
+    }
+
+    /// Returns the number of bytes of document contents and key-value entries
+    /// and the number of documents stored in this database.
+    pub(crate) fn storage_usage(&self) -> (u64, u64) {
+        // Key-value writes that haven't been persisted yet are counted in
+        // addition to any stored entries they replace.
+        let pending_key_value_bytes = self.data.context.pending_key_value_bytes();
+        self.data.context.usage.totals(pending_key_value_bytes)
+    }
+
+    /// Reads the number of bytes and documents stored in this database from
+    /// its trees.
+    pub(crate) fn count_storage_usage(&self) -> Result<UsageCounts, Error> {
+        let mut counts = UsageCounts::default();
+        for collection in self.schematic().collections() {
+            let stats =
+                self.roots()
+                    .tree(self.collection_tree::<Versioned, _>(
+                        collection,
+                        document_tree_name(collection),
+                    )?)?
+                    .reduce(&(..))?;
+            counts.document_bytes += stats.total_indexed_bytes;
+            counts.documents += stats.alive_keys;
+        }
+
+        counts.key_value_bytes = self
+            .roots()
+            .tree(Unversioned::tree(KEY_TREE))?
+            .reduce(&(..))?
+            .total_indexed_bytes;
+
+        Ok(counts)
+    }
+
+    /// Executes `write` if storing an additional `bytes` and `documents` would
+    /// not exceed this database's
+    /// [`DatabaseQuota`](bonsaidb_core::admin::DatabaseQuota).
+    ///
+    /// The additional `bytes` and `documents` are reserved until `write`
+    /// returns, ensuring that concurrent writes can't each pass the quota
+    /// check and together exceed the quota.
+    pub(crate) fn write_within_quota<T>(
+        &self,
+        bytes: u64,
+        documents: u64,
+        write: impl FnOnce() -> Result<T, bonsaidb_core::Error>,
+    ) -> Result<T, bonsaidb_core::Error> {
+        let Some(quota) = self.storage.instance.database_quota(self.name()) else {
+            return write();
+        };
+        if bytes == 0 && documents == 0 {
+            return write();
+        }
+
+        let pending_key_value_bytes = self.data.context.pending_key_value_bytes();
+        self.data
+            .context
+            .usage
+            .reserve(bytes, documents, pending_key_value_bytes, &quota)
+            .map_err(|limit| bonsaidb_core::Error::QuotaExceeded {
+                database: self.name().to_string(),
+                limit,
+            })?;
+        let result = write();
+        self.data.context.usage.release(bytes, documents);
+        result
+    }
+
     #[cfg(any(feature = "encryption", feature = "compression"))]
     pub(crate) fn collection_encryption_key(&self, collection: &CollectionName) -> Option<&KeyId> {
         self.schematic()
@@ -838,6 +1280,161 @@ impl Database {
             .or_else(|| self.storage.default_encryption_key())
     }
 
+    /// Rotates the encryption key used by `collection` and re-encrypts the
+    /// collection's documents and views with the new version of the key.
+    ///
+    /// The key is rotated before this function returns, and re-encryption
+    /// continues in the background. Progress is recorded after each tree is
+    /// re-encrypted, and is available from
+    /// [`Storage::pending_key_rotations()`]. If the storage is closed before
+    /// re-encryption finishes, it resumes the next time this database is
+    /// opened.
+    ///
+    /// Rotating the master key ([`KeyId::Master`]) does not re-encrypt other
+    /// collections that use it. Data encrypted with previous versions of the
+    /// key remains readable.
+    ///
+    /// Previous versions of the key are kept in the vault, allowing other data
+    /// encrypted with them, such as backups sealed using
+    /// [`BackupEncryption::Vault`](crate::BackupEncryption::Vault), to remain
+    /// readable. Once every collection using a named key ([`KeyId::Id`]) has
+    /// been re-encrypted, the versions that are no longer used can be removed
+    /// using [`Storage::retire_encryption_key_versions()`].
+    ///
+    /// This requires the [`EncryptionKeyAction::Rotate`](bonsaidb_core::permissions::bonsai::EncryptionKeyAction::Rotate)
+    /// permission for the key's
+    /// [`encryption_key_resource_name()`](bonsaidb_core::permissions::bonsai::encryption_key_resource_name).
+    #[cfg(feature = "encryption")]
+    pub fn rotate_collection_encryption_key(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<(), Error> {
+        if !self
+            .schematic()
+            .collections()
+            .any(|name| name == collection)
+        {
+            return Err(Error::Core(bonsaidb_core::Error::CollectionNotFound));
+        }
+        let Some(key) = self.collection_encryption_key(collection).cloned() else {
+            return Err(Error::other(
+                "bonsaidb-local",
+                format!("collection {collection} is not encrypted"),
+            ));
+        };
+        self.storage.rotate_encryption_key(&key)?;
+
+        let storage = &self.storage.instance;
+        storage.key_rotations().begin(self, collection)?;
+        storage.tasks().spawn_reencryption(self, collection.clone());
+        Ok(())
+    }
+
+    /// Serializes `document`, encrypting its contents if its collection has
+    /// registered [`DocumentEncryption`](schema::DocumentEncryption).
+    ///
+    /// Every document stored in a collection with document encryption is
+    /// prefixed with [`PLAINTEXT_DOCUMENT`] or [`ENCRYPTED_DOCUMENT`], which
+    /// allows the contents to be interpreted without guessing.
+    fn serialize_document_for(
+        &self,
+        collection: &CollectionName,
+        document: &BorrowedDocument<'_>,
+    ) -> Result<Vec<u8>, Error> {
+        let Some(encryption) = self.data.schema.document_encryption_for(collection) else {
+            return serialize_document(document).map_err(Error::from);
+        };
+
+        let contents = match encryption.document_encryption_key(document)? {
+            #[cfg(feature = "encryption")]
+            Some(key) => {
+                let mut contents = vec![ENCRYPTED_DOCUMENT];
+                contents.extend(self.storage().vault().encrypt_payload(
+                    &key,
+                    &document.contents,
+                    None,
+                )?);
+                contents
+            }
+            #[cfg(not(feature = "encryption"))]
+            Some(_) => return Err(Error::EncryptionDisabled),
+            None => {
+                let mut contents = Vec::with_capacity(document.contents.len() + 1);
+                contents.push(PLAINTEXT_DOCUMENT);
+                contents.extend_from_slice(&document.contents);
+                contents
+            }
+        };
+        serialize_document(&BorrowedDocument {
+            header: document.header.clone(),
+            contents: CowBytes::from(contents),
+        })
+        .map_err(Error::from)
+    }
+
+    /// Decrypts `document`'s contents if its collection has registered
+    /// [`DocumentEncryption`](schema::DocumentEncryption). Returns `None` if
+    /// the document's key has been deleted.
+    pub(crate) fn decrypt_document<'a>(
+        &self,
+        collection: &CollectionName,
+        mut document: BorrowedDocument<'a>,
+    ) -> Result<Option<BorrowedDocument<'a>>, Error> {
+        if self
+            .data
+            .schema
+            .document_encryption_for(collection)
+            .is_none()
+        {
+            return Ok(Some(document));
+        }
+        let Some(contents) = self.decrypt_document_contents(&document.contents)? else {
+            return Ok(None);
+        };
+        document.contents = CowBytes::from(contents);
+        Ok(Some(document))
+    }
+
+    /// Decrypts `contents` returned from [`Self::list_stored_documents()`].
+    /// Returns `None` if the document's key has been deleted.
+    pub(crate) fn decrypt_stored_contents(
+        &self,
+        collection: &CollectionName,
+        contents: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        if self
+            .data
+            .schema
+            .document_encryption_for(collection)
+            .is_none()
+        {
+            Ok(Some(contents))
+        } else {
+            self.decrypt_document_contents(&contents)
+        }
+    }
+
+    #[cfg_attr(not(feature = "encryption"), allow(clippy::unused_self))]
+    fn decrypt_document_contents(&self, contents: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match contents.split_first() {
+            Some((&PLAINTEXT_DOCUMENT, contents)) => Ok(Some(contents.to_vec())),
+            #[cfg(feature = "encryption")]
+            Some((&ENCRYPTED_DOCUMENT, encrypted)) => {
+                match self.storage().vault().decrypt_payload(encrypted, None) {
+                    Ok(contents) => Ok(Some(contents)),
+                    Err(Error::Vault(crate::vault::Error::EncryptionKeyNotFound(_))) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            }
+            #[cfg(not(feature = "encryption"))]
+            Some((&ENCRYPTED_DOCUMENT, _)) => Err(Error::EncryptionDisabled),
+            _ => Err(Error::other(
+                "bonsaidb-local",
+                "document is missing its document encryption header",
+            )),
+        }
+    }
+
     #[cfg_attr(
         not(feature = "encryption"),
         allow(
@@ -965,6 +1562,13 @@ struct LegacyDocument<'a> {
     contents: &'a [u8],
 }
 
+/// The first byte of each document's contents in a collection with document
+/// encryption when the document is stored unencrypted.
+const PLAINTEXT_DOCUMENT: u8 = 0;
+/// The first byte of each document's contents in a collection with document
+/// encryption when the document is encrypted with a document-level key.
+const ENCRYPTED_DOCUMENT: u8 = 1;
+
 pub(crate) fn deserialize_document(bytes: &[u8]) -> Result<BorrowedDocument<'_>, Error> {
     match pot::from_slice::<BorrowedDocument<'_>>(bytes) {
         Ok(document) => Ok(document),
@@ -991,6 +1595,14 @@ impl HasSession for Database {
     fn session(&self) -> Option<&Session> {
         self.storage.session()
     }
+
+    fn check_permission<'a, R: AsRef<[Identifier<'a>]>, P: Action>(
+        &self,
+        resource_name: R,
+        action: &P,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.storage.check_permission(resource_name, action)
+    }
 }
 
 impl Connection for Database {
@@ -1155,6 +1767,24 @@ impl LowLevelConnection for Database {
             self.check_permission(resource, &action)?;
         }
 
+        for op in &transaction.operations {
+            self.check_document_policy(op)?;
+        }
+
+        // Updated and overwritten documents are counted as if they were new,
+        // as the size of the contents being replaced isn't known yet.
+        let (bytes, documents) =
+            transaction
+                .operations
+                .iter()
+                .fold((0, 0), |(bytes, documents), op| match &op.command {
+                    Command::Insert { contents, .. } | Command::Overwrite { contents, .. } => {
+                        (bytes + contents.len() as u64, documents + 1)
+                    }
+                    Command::Update { contents, .. } => (bytes + contents.len() as u64, documents),
+                    Command::Delete { .. } | Command::Check { .. } => (bytes, documents),
+                });
+
         let mut eager_view_tasks = Vec::new();
         for collection_name in transaction
             .operations
@@ -1188,8 +1818,23 @@ impl LowLevelConnection for Database {
             }
         }
 
-        self.apply_transaction_to_roots(&transaction)
-            .map_err(bonsaidb_core::Error::from)
+        self.write_within_quota(bytes, documents, || {
+            let metrics = self.storage.instance.metrics();
+            let started_at = Instant::now();
+            let result = self.apply_transaction_to_roots(&transaction);
+            metrics.transaction_duration.observe(started_at.elapsed());
+            match &result {
+                Ok(_) => {
+                    metrics.transactions.increment();
+                    metrics
+                        .transaction_operations
+                        .add(transaction.operations.len() as u64);
+                }
+                Err(_) => metrics.transaction_errors.increment(),
+            }
+
+            result.map_err(bonsaidb_core::Error::from)
+        })
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
@@ -1210,17 +1855,10 @@ impl LowLevelConnection for Database {
             document_resource_name(self.name(), collection, &id),
             &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
         )?;
-        let tree = self
-            .data
-            .context
-            .roots
-            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
-            .map_err(Error::from)?;
-        if let Some(vec) = tree.get(id.as_ref()).map_err(Error::from)? {
-            Ok(Some(deserialize_document(&vec)?.into_owned()))
-        } else {
-            Ok(None)
-        }
+        let document = self.get_document(&id, collection)?;
+        Ok(self
+            .readable_documents(collection, document.into_iter().collect())?
+            .pop())
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
@@ -1243,47 +1881,8 @@ impl LowLevelConnection for Database {
             collection_resource_name(self.name(), collection),
             &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::List)),
         )?;
-        let tree = self
-            .data
-            .context
-            .roots
-            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
-            .map_err(Error::from)?;
-        let mut found_docs = Vec::new();
-        let mut keys_read = 0;
-        let ids = DocumentIdRange(ids);
-        tree.scan(
-            &ids.borrow_as_bytes(),
-            match sort {
-                Sort::Ascending => true,
-                Sort::Descending => false,
-            },
-            |_, _, _| ScanEvaluation::ReadData,
-            |_, _| {
-                if let Some(limit) = limit {
-                    if keys_read >= limit {
-                        return ScanEvaluation::Stop;
-                    }
-
-                    keys_read += 1;
-                }
-                ScanEvaluation::ReadData
-            },
-            |_, _, doc| {
-                found_docs.push(
-                    deserialize_document(&doc)
-                        .map(BorrowedDocument::into_owned)
-                        .map_err(AbortError::Other)?,
-                );
-                Ok(())
-            },
-        )
-        .map_err(|err| match err {
-            AbortError::Other(err) => err,
-            AbortError::Nebari(err) => crate::Error::from(err),
-        })?;
-
-        Ok(found_docs)
+        let documents = self.list_documents(ids, sort, limit, collection)?;
+        self.readable_documents(collection, documents)
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
@@ -1306,6 +1905,15 @@ impl LowLevelConnection for Database {
             collection_resource_name(self.name(), collection),
             &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::ListHeaders)),
         )?;
+        if self.document_policy(collection).is_some() {
+            // Policies are evaluated against the documents' contents.
+            let documents = self.list_documents(ids, sort, limit, collection)?;
+            return Ok(self
+                .readable_documents(collection, documents)?
+                .into_iter()
+                .map(|document| document.header)
+                .collect());
+        }
         let tree = self
             .data
             .context
@@ -1367,6 +1975,11 @@ impl LowLevelConnection for Database {
             collection_resource_name(self.name(), collection),
             &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Count)),
         )?;
+        if self.document_policy(collection).is_some() {
+            // Only documents readable by the session are counted.
+            let documents = self.list_documents(ids, Sort::Ascending, None, collection)?;
+            return Ok(self.readable_documents(collection, documents)?.len() as u64);
+        }
         let tree = self
             .data
             .context
@@ -1399,26 +2012,8 @@ impl LowLevelConnection for Database {
                 &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
             )?;
         }
-        let mut ids = ids.to_vec();
-        let collection = collection.clone();
-        let tree = self
-            .data
-            .context
-            .roots
-            .tree(
-                self.collection_tree::<Versioned, _>(&collection, document_tree_name(&collection))?,
-            )
-            .map_err(Error::from)?;
-        ids.sort();
-        let keys_and_values = tree
-            .get_multiple(ids.iter().map(|id| id.as_ref()))
-            .map_err(Error::from)?;
-
-        keys_and_values
-            .into_iter()
-            .map(|(_, value)| deserialize_document(&value).map(BorrowedDocument::into_owned))
-            .collect::<Result<Vec<_>, Error>>()
-            .map_err(bonsaidb_core::Error::from)
+        let documents = self.get_documents(ids, collection)?;
+        self.readable_documents(collection, documents)
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
@@ -1480,6 +2075,15 @@ impl LowLevelConnection for Database {
             Ok(())
         })?;
 
+        let collection = view.collection();
+        if self.document_policy(&collection).is_some() {
+            let readable = self.readable_document_ids(
+                results.iter().map(|mapping| &mapping.source.id),
+                &collection,
+            )?;
+            results.retain(|mapping| readable.contains(&mapping.source.id));
+        }
+
         Ok(results)
     }
 
@@ -1580,6 +2184,11 @@ impl LowLevelConnection for Database {
             view_resource_name(self.name(), &view.view_name()),
             &BonsaiAction::Database(DatabaseAction::View(ViewAction::Reduce)),
         )?;
+        let collection = view.collection();
+        if self.document_policy(&collection).is_some() {
+            return self.reduce_readable_grouped(view, &collection, key, access_policy);
+        }
+
         let mut mappings = Vec::new();
         self.for_each_in_view(view, key, Sort::Ascending, None, access_policy, |entry| {
             mappings.push(MappedSerializedValue {
@@ -1609,6 +2218,10 @@ impl LowLevelConnection for Database {
         access_policy: AccessPolicy,
     ) -> Result<u64, bonsaidb_core::Error> {
         let view = self.data.schema.view_by_name(view)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::DeleteDocs)),
+        )?;
         let collection = view.collection();
         let mut transaction = Transaction::default();
         self.for_each_in_view(view, key, Sort::Ascending, None, access_policy, |entry| {
@@ -1666,6 +2279,121 @@ impl Deref for Context {
 pub(crate) struct ContextData {
     pub(crate) roots: Roots<AnyFile>,
     key_value_state: Arc<Mutex<keyvalue::KeyValueState>>,
+    usage: Arc<StorageUsage>,
+}
+
+/// The number of bytes and documents stored in a database, which are checked
+/// against its [`DatabaseQuota`](bonsaidb_core::admin::DatabaseQuota).
+///
+/// The counts are read from the database's trees when the database is opened,
+/// and are updated as transactions and key-value entries are committed.
+#[derive(Debug, Default)]
+pub(crate) struct StorageUsage {
+    counts: Mutex<Option<UsageCounts>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct UsageCounts {
+    document_bytes: u64,
+    documents: u64,
+    key_value_bytes: u64,
+    /// Bytes and documents of writes that have passed the quota check but
+    /// haven't finished.
+    reserved_bytes: u64,
+    reserved_documents: u64,
+}
+
+impl UsageCounts {
+    pub(crate) fn totals(&self, pending_key_value_bytes: u64) -> (u64, u64) {
+        (
+            self.document_bytes + self.key_value_bytes + pending_key_value_bytes,
+            self.documents,
+        )
+    }
+}
+
+/// The change in a database's [`StorageUsage`] caused by a write.
+#[derive(Debug, Default)]
+pub(crate) struct UsageChange {
+    pub(crate) added_bytes: u64,
+    pub(crate) removed_bytes: u64,
+    pub(crate) added_documents: u64,
+    pub(crate) removed_documents: u64,
+}
+
+impl StorageUsage {
+    fn count_if_needed(
+        &self,
+        count: impl FnOnce() -> Result<UsageCounts, Error>,
+    ) -> Result<(), Error> {
+        let mut counts = self.counts.lock();
+        if counts.is_none() {
+            *counts = Some(count()?);
+        }
+        Ok(())
+    }
+
+    fn totals(&self, pending_key_value_bytes: u64) -> (u64, u64) {
+        self.counts
+            .lock()
+            .map(|counts| counts.totals(pending_key_value_bytes))
+            .unwrap_or_default()
+    }
+
+    fn reserve(
+        &self,
+        bytes: u64,
+        documents: u64,
+        pending_key_value_bytes: u64,
+        quota: &DatabaseQuota,
+    ) -> Result<(), QuotaLimit> {
+        let mut counts = self.counts.lock();
+        let Some(counts) = counts.as_mut() else {
+            return Ok(());
+        };
+        let (used_bytes, used_documents) = counts.totals(pending_key_value_bytes);
+        let used_bytes = used_bytes.saturating_add(counts.reserved_bytes);
+        let used_documents = used_documents.saturating_add(counts.reserved_documents);
+        match (quota.max_bytes, quota.max_documents) {
+            (Some(max_bytes), _) if used_bytes.saturating_add(bytes) > max_bytes => {
+                return Err(QuotaLimit::Bytes(max_bytes));
+            }
+            (_, Some(max_documents))
+                if used_documents.saturating_add(documents) > max_documents =>
+            {
+                return Err(QuotaLimit::Documents(max_documents));
+            }
+            _ => {}
+        }
+        counts.reserved_bytes += bytes;
+        counts.reserved_documents += documents;
+        Ok(())
+    }
+
+    fn release(&self, bytes: u64, documents: u64) {
+        if let Some(counts) = self.counts.lock().as_mut() {
+            counts.reserved_bytes -= bytes;
+            counts.reserved_documents -= documents;
+        }
+    }
+
+    /// Records the documents written by a committed transaction.
+    fn record_documents(&self, change: &UsageChange) {
+        if let Some(counts) = self.counts.lock().as_mut() {
+            counts.document_bytes =
+                (counts.document_bytes + change.added_bytes).saturating_sub(change.removed_bytes);
+            counts.documents = (counts.documents + change.added_documents)
+                .saturating_sub(change.removed_documents);
+        }
+    }
+
+    /// Records the key-value entries written by a committed transaction.
+    pub(crate) fn record_key_value_entries(&self, change: &UsageChange) {
+        if let Some(counts) = self.counts.lock().as_mut() {
+            counts.key_value_bytes =
+                (counts.key_value_bytes + change.added_bytes).saturating_sub(change.removed_bytes);
+        }
+    }
 }
 
 impl Borrow<Roots<AnyFile>> for Context {
@@ -1682,16 +2410,19 @@ impl Context {
     ) -> Self {
         let background_worker_target = Watchable::new(BackgroundWorkerProcessTarget::Never);
         let mut background_worker_target_watcher = background_worker_target.watch();
+        let usage = Arc::<StorageUsage>::default();
         let key_value_state = Arc::new(Mutex::new(keyvalue::KeyValueState::new(
             key_value_persistence,
             roots.clone(),
             background_worker_target,
+            usage.clone(),
         )));
         let background_worker_state = Arc::downgrade(&key_value_state);
         let context = Self {
             data: Arc::new(ContextData {
                 roots,
                 key_value_state,
+                usage,
             }),
         };
         std::thread::Builder::new()
@@ -1715,6 +2446,11 @@ impl Context {
         state.perform_kv_operation(op, &self.data.key_value_state)
     }
 
+    pub(crate) fn pending_key_value_bytes(&self) -> u64 {
+        let state = self.data.key_value_state.lock();
+        state.pending_bytes()
+    }
+
     pub(crate) fn update_key_expiration<'key>(
         &self,
         tree_key: impl Into<Cow<'key, str>>,
@@ -20,7 +20,7 @@ use serde::{Deserialize, Serialize};
 use watchable::{Watchable, Watcher};
 
 use crate::config::KeyValuePersistence;
-use crate::database::compat;
+use crate::database::{compat, StorageUsage, UsageChange};
 use crate::storage::StorageLock;
 use crate::tasks::{Job, Keyed, Task};
 use crate::{Database, DatabaseNonBlocking, Error};
@@ -61,7 +61,12 @@ impl KeyValue for Database {
             keyvalue_key_resource_name(self.name(), op.namespace.as_deref(), &op.key),
             &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
         )?;
-        self.data.context.perform_kv_operation(op)
+        if let Command::Set(set) = &op.command {
+            let bytes = entry_size(&op.key, &set.value);
+            self.write_within_quota(bytes, 0, || self.data.context.perform_kv_operation(op))
+        } else {
+            self.data.context.perform_kv_operation(op)
+        }
     }
 }
 
@@ -124,6 +129,16 @@ impl Database {
 
 pub(crate) const KEY_TREE: &str = "kv";
 
+/// Returns the number of bytes counted towards a database's quota for storing
+/// `value` in `key`.
+fn entry_size(key: &str, value: &Value) -> u64 {
+    let value_bytes = match value {
+        Value::Bytes(bytes) => bytes.len(),
+        Value::Numeric(_) => std::mem::size_of::<u64>(),
+    };
+    (key.len() + value_bytes) as u64
+}
+
 fn full_key(namespace: Option<&str>, key: &str) -> String {
     let full_length = namespace.map_or_else(|| 0, str::len) + key.len() + 1;
     let mut full_key = String::with_capacity(full_length);
@@ -216,6 +231,7 @@ pub struct KeyValueState {
     keys_being_persisted: Option<Arc<BTreeMap<String, Option<Entry>>>>,
     last_persistence: Watchable<Timestamp>,
     shutdown: Option<flume::Sender<()>>,
+    usage: Arc<StorageUsage>,
 }
 
 impl KeyValueState {
@@ -223,6 +239,7 @@ impl KeyValueState {
         persistence: KeyValuePersistence,
         roots: Roots<AnyFile>,
         background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
+        usage: Arc<StorageUsage>,
     ) -> Self {
         Self {
             roots,
@@ -235,6 +252,7 @@ impl KeyValueState {
             keys_being_persisted: None,
             last_persistence: Watchable::new(Timestamp::MIN),
             shutdown: None,
+            usage,
         }
     }
 
@@ -248,6 +266,17 @@ impl KeyValueState {
         }
     }
 
+    /// Returns the number of bytes of entries that have been set but not yet
+    /// persisted.
+    pub fn pending_bytes(&self) -> u64 {
+        self.keys_being_persisted
+            .iter()
+            .flat_map(|keys| keys.iter())
+            .chain(&self.dirty_keys)
+            .filter_map(|(key, entry)| entry.as_ref().map(|entry| entry_size(key, &entry.value)))
+            .sum()
+    }
+
     pub fn perform_kv_operation(
         &mut self,
         op: KeyOperation,
@@ -663,10 +692,11 @@ impl KeyValueState {
     pub fn commit_dirty_keys(&mut self, state: &Arc<Mutex<KeyValueState>>) -> bool {
         if let Some(keys) = self.stage_dirty_keys() {
             let roots = self.roots.clone();
+            let usage = self.usage.clone();
             let state = state.clone();
             std::thread::Builder::new()
                 .name(String::from("keyvalue-persist"))
-                .spawn(move || Self::persist_keys(&state, &roots, &keys))
+                .spawn(move || Self::persist_keys(&state, &roots, &usage, &keys))
                 .unwrap();
             self.last_commit = Timestamp::now();
             true
@@ -684,6 +714,7 @@ impl KeyValueState {
     fn persist_keys(
         key_value_state: &Arc<Mutex<KeyValueState>>,
         roots: &Roots<AnyFile>,
+        usage: &StorageUsage,
         keys: &BTreeMap<String, Option<Entry>>,
     ) -> Result<(), bonsaidb_core::Error> {
         let mut transaction = roots
@@ -694,6 +725,7 @@ impl KeyValueState {
             .map(|key| ArcBytes::from(key.as_bytes().to_vec()))
             .collect();
         let mut changed_keys = Vec::new();
+        let mut usage_change = UsageChange::default();
         transaction
             .tree::<Unversioned>(0)
             .unwrap()
@@ -702,6 +734,9 @@ impl KeyValueState {
                 Operation::CompareSwap(CompareSwap::new(&mut |key, existing_value| {
                     let full_key = std::str::from_utf8(key).unwrap();
                     let (namespace, key) = split_key(full_key).unwrap();
+                    if let Some(existing_value) = &existing_value {
+                        usage_change.removed_bytes += existing_value.len() as u64;
+                    }
 
                     if let Some(new_value) = keys.get(full_key).unwrap() {
                         changed_keys.push(ChangedKey {
@@ -710,6 +745,7 @@ impl KeyValueState {
                             deleted: false,
                         });
                         let bytes = bincode::serialize(new_value).unwrap();
+                        usage_change.added_bytes += bytes.len() as u64;
                         nebari::tree::KeyOperation::Set(ArcBytes::from(bytes))
                     } else if existing_value.is_some() {
                         changed_keys.push(ChangedKey {
@@ -738,6 +774,10 @@ impl KeyValueState {
         // If we are shutting down, check if we still have dirty keys.
         let final_keys = {
             let mut state = key_value_state.lock();
+            // The committed entries are recorded while the keys are still
+            // being persisted, ensuring they aren't counted twice or missed
+            // by `pending_key_value_bytes`.
+            usage.record_key_value_entries(&usage_change);
             state.last_persistence.replace(Timestamp::now());
             state.keys_being_persisted = None;
             state.update_background_worker_target();
@@ -758,7 +798,7 @@ impl KeyValueState {
             }
         };
         if let Some(final_keys) = final_keys {
-            Self::persist_keys(key_value_state, roots, &final_keys)?;
+            Self::persist_keys(key_value_state, roots, usage, &final_keys)?;
         }
         Ok(())
     }
@@ -867,6 +907,10 @@ impl Job for ExpirationLoader {
 
         Ok(())
     }
+
+    fn kind(&self) -> &'static str {
+        "expiration_loader"
+    }
 }
 
 #[cfg(test)]
@@ -2,9 +2,10 @@ use bonsaidb_core::arc_bytes::OwnedBytes;
 pub use bonsaidb_core::circulate::Relay;
 use bonsaidb_core::connection::{Connection, HasSession};
 use bonsaidb_core::permissions::bonsai::{
-    database_resource_name, pubsub_topic_resource_name, BonsaiAction, DatabaseAction, PubSubAction,
+    database_resource_name, pubsub_topic_pattern_resource_name, pubsub_topic_resource_name,
+    BonsaiAction, DatabaseAction, PubSubAction,
 };
-use bonsaidb_core::pubsub::{self, database_topic, PubSub, Receiver};
+use bonsaidb_core::pubsub::{self, database_topic, PubSub, Receiver, TopicPattern};
 use bonsaidb_core::{circulate, Error};
 
 use crate::{Database, DatabaseNonBlocking};
@@ -28,6 +29,9 @@ impl PubSub for super::Database {
             pubsub_topic_resource_name(self.name(), &topic),
             &BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::Publish)),
         )?;
+        self.storage
+            .instance
+            .publish_to_patterns(&self.data.name, &topic, &payload);
         self.storage
             .instance
             .relay()
@@ -40,17 +44,25 @@ impl PubSub for super::Database {
         topics: impl IntoIterator<Item = Vec<u8>> + Send,
         payload: Vec<u8>,
     ) -> Result<(), bonsaidb_core::Error> {
+        let topics = topics
+            .into_iter()
+            .map(|topic| {
+                self.check_permission(
+                    pubsub_topic_resource_name(self.name(), &topic),
+                    &BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::Publish)),
+                )
+                .map(|_| topic)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for topic in &topics {
+            self.storage
+                .instance
+                .publish_to_patterns(&self.data.name, topic, &payload);
+        }
         self.storage.instance.relay().publish_raw_to_all(
             topics
                 .into_iter()
-                .map(|topic| {
-                    self.check_permission(
-                        pubsub_topic_resource_name(self.name(), &topic),
-                        &BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::Publish)),
-                    )
-                    .map(|_| OwnedBytes::from(database_topic(&self.data.name, &topic)))
-                })
-                .collect::<Result<Vec<_>, _>>()?,
+                .map(|topic| OwnedBytes::from(database_topic(&self.data.name, &topic))),
             payload,
         );
         Ok(())
@@ -101,6 +113,40 @@ impl pubsub::Subscriber for Subscriber {
         Ok(())
     }
 
+    fn subscribe_to_pattern(&self, pattern: &str) -> Result<(), Error> {
+        let pattern = TopicPattern::from(pattern);
+        if !pattern.is_valid() {
+            return Err(Error::other(
+                "bonsaidb-local pubsub",
+                format!(
+                    "topic patterns are limited to {} segments",
+                    TopicPattern::MAX_SEGMENTS
+                ),
+            ));
+        }
+        self.database.check_permission(
+            pubsub_topic_pattern_resource_name(self.database.name(), pattern.as_str()),
+            &BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::SubscribeToPattern)),
+        )?;
+        self.database
+            .storage()
+            .instance
+            .subscribe_to_pattern(self.id, pattern);
+        Ok(())
+    }
+
+    fn unsubscribe_from_pattern(&self, pattern: &str) -> Result<(), Error> {
+        self.database.check_permission(
+            pubsub_topic_pattern_resource_name(self.database.name(), pattern),
+            &BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::UnsubscribeFromPattern)),
+        )?;
+        self.database
+            .storage()
+            .instance
+            .unsubscribe_from_pattern(self.id, &TopicPattern::from(pattern));
+        Ok(())
+    }
+
     fn receiver(&self) -> &Receiver {
         &self.receiver
     }
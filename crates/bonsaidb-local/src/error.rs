@@ -71,6 +71,11 @@ pub enum Error {
     #[error("a backup error: {0}")]
     Backup(Box<dyn AnyError>),
 
+    /// A backup's encryption did not match how it was requested to be
+    /// restored, or its contents could not be decrypted.
+    #[error("a backup encryption error: {0}")]
+    BackupEncryption(String),
+
     /// An error occurred with a password hash.
     #[cfg(all(feature = "password-hashing", feature = "cli"))]
     #[error("error reading password: {0}")]
@@ -15,6 +15,8 @@
     clippy::module_name_repetitions,
 )]
 
+#[cfg(feature = "async")]
+pub mod api;
 /// Command-line interface helpers.
 #[cfg(feature = "cli")]
 pub mod cli;
@@ -22,6 +24,7 @@ pub mod cli;
 pub mod config;
 mod database;
 mod error;
+pub mod metrics;
 mod open_trees;
 mod storage;
 mod tasks;
@@ -37,7 +40,11 @@ pub use bonsaidb_core as core;
 pub use self::database::pubsub::Subscriber;
 pub use self::database::{Database, DatabaseNonBlocking};
 pub use self::error::Error;
+#[cfg(feature = "encryption")]
+pub use self::storage::BackupEncryption;
 pub use self::storage::{BackupLocation, Storage, StorageId, StorageNonBlocking};
+#[cfg(feature = "encryption")]
+pub use self::tasks::PendingKeyRotation;
 
 #[cfg(feature = "async")]
 mod r#async;
@@ -0,0 +1,353 @@
+//! Metrics collected while operating a [`Storage`](crate::Storage).
+//!
+//! Metrics can be exported in the [Prometheus text exposition
+//! format](https://prometheus.io/docs/instrumenting/exposition_formats/) using
+//! [`StorageMetrics::encode()`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+
+/// A metric that can be encoded in the Prometheus text format.
+pub trait Metric: Default + Send + Sync + 'static {
+    /// The Prometheus metric type, such as `counter`.
+    const TYPE: &'static str;
+
+    /// Writes the samples of this metric named `name` to `encoder`. `labels`
+    /// are the already-formatted labels to include with each sample, or an
+    /// empty string.
+    fn encode(&self, name: &str, labels: &str, encoder: &mut PrometheusEncoder<'_>);
+}
+
+/// A value that only increases.
+#[derive(Default, Debug)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// Increments this counter by one.
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Increments this counter by `amount`.
+    pub fn add(&self, amount: u64) {
+        self.0.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Returns the current value of this counter.
+    #[must_use]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Counter {
+    const TYPE: &'static str = "counter";
+
+    fn encode(&self, name: &str, labels: &str, encoder: &mut PrometheusEncoder<'_>) {
+        encoder.sample(name, "", labels, self.get());
+    }
+}
+
+/// A value that can increase and decrease.
+#[derive(Default, Debug)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    /// Increments this gauge by one.
+    pub fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Decrements this gauge by one.
+    pub fn decrement(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Sets this gauge to `value`.
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// Returns the current value of this gauge.
+    #[must_use]
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    const TYPE: &'static str = "gauge";
+
+    fn encode(&self, name: &str, labels: &str, encoder: &mut PrometheusEncoder<'_>) {
+        encoder.sample(name, "", labels, self.get());
+    }
+}
+
+/// The upper bounds, in seconds, of the buckets a [`Histogram`] sorts
+/// durations into.
+pub const HISTOGRAM_BUCKETS: [f64; 12] = [
+    0.000_1, 0.000_5, 0.001, 0.002_5, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1., 5.,
+];
+
+/// A distribution of observed durations.
+#[derive(Default, Debug)]
+pub struct Histogram {
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    /// Records `duration` in this histogram.
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|upper_bound| seconds <= *upper_bound)
+        {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(
+            u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Returns the number of durations observed.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of all durations observed.
+    #[must_use]
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl Metric for Histogram {
+    const TYPE: &'static str = "histogram";
+
+    fn encode(&self, name: &str, labels: &str, encoder: &mut PrometheusEncoder<'_>) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (upper_bound, count) in HISTOGRAM_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            encoder.sample(
+                name,
+                "_bucket",
+                &format!("{labels}{separator}le=\"{upper_bound}\""),
+                cumulative,
+            );
+        }
+        let count = self.count();
+        encoder.sample(
+            name,
+            "_bucket",
+            &format!("{labels}{separator}le=\"+Inf\""),
+            count,
+        );
+        encoder.sample(name, "_sum", labels, self.sum().as_secs_f64());
+        encoder.sample(name, "_count", labels, count);
+    }
+}
+
+/// A set of metrics of the same kind, distinguished by the value of a single
+/// label.
+#[derive(Debug)]
+pub struct Family<M> {
+    label: &'static str,
+    metrics: RwLock<BTreeMap<String, Arc<M>>>,
+}
+
+impl<M: Metric> Family<M> {
+    /// Returns a new family whose metrics are distinguished by `label`.
+    #[must_use]
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            metrics: RwLock::default(),
+        }
+    }
+
+    /// Returns the metric for `value`, creating it if it doesn't exist.
+    #[must_use]
+    pub fn get(&self, value: &str) -> Arc<M> {
+        if let Some(metric) = self.metrics.read().get(value) {
+            return metric.clone();
+        }
+
+        self.metrics
+            .write()
+            .entry(value.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// Writes metrics in the Prometheus text exposition format.
+pub struct PrometheusEncoder<'a> {
+    output: &'a mut String,
+}
+
+impl<'a> PrometheusEncoder<'a> {
+    /// Returns an encoder that appends to `output`.
+    #[must_use]
+    pub fn new(output: &'a mut String) -> Self {
+        Self { output }
+    }
+
+    /// Encodes `metric` as `name`, described by `help`.
+    pub fn metric<M: Metric>(&mut self, name: &str, help: &str, metric: &M) {
+        self.header::<M>(name, help);
+        metric.encode(name, "", self);
+    }
+
+    /// Encodes each metric in `family` as `name`, described by `help`.
+    pub fn family<M: Metric>(&mut self, name: &str, help: &str, family: &Family<M>) {
+        self.header::<M>(name, help);
+        for (value, metric) in family.metrics.read().iter() {
+            let labels = format!("{}=\"{}\"", family.label, escape_label_value(value));
+            metric.encode(name, &labels, self);
+        }
+    }
+
+    /// Encodes a gauge named `name` whose current value is `value`, described
+    /// by `help`. This is useful for values that are computed when encoding
+    /// rather than tracked as they change.
+    pub fn gauge_value(&mut self, name: &str, help: &str, value: i64) {
+        self.header::<Gauge>(name, help);
+        self.sample(name, "", "", value);
+    }
+
+    fn header<M: Metric>(&mut self, name: &str, help: &str) {
+        // Writing to a String can't fail.
+        let _ = writeln!(self.output, "# HELP {name} {help}");
+        let _ = writeln!(self.output, "# TYPE {name} {}", M::TYPE);
+    }
+
+    fn sample(&mut self, name: &str, suffix: &str, labels: &str, value: impl std::fmt::Display) {
+        let _ = if labels.is_empty() {
+            writeln!(self.output, "{name}{suffix} {value}")
+        } else {
+            writeln!(self.output, "{name}{suffix}{{{labels}}} {value}")
+        };
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Metrics collected by background tasks.
+#[derive(Debug)]
+pub(crate) struct TaskMetrics {
+    pub queued: Family<Gauge>,
+    pub running: Family<Gauge>,
+    pub completed: Family<Counter>,
+    pub errors: Family<Counter>,
+    pub duration: Family<Histogram>,
+}
+
+impl Default for TaskMetrics {
+    fn default() -> Self {
+        Self {
+            queued: Family::new("task"),
+            running: Family::new("task"),
+            completed: Family::new("task"),
+            errors: Family::new("task"),
+            duration: Family::new("task"),
+        }
+    }
+}
+
+/// Metrics collected by a [`Storage`](crate::Storage) instance.
+#[derive(Debug, Default)]
+pub struct StorageMetrics {
+    pub(crate) transactions: Counter,
+    pub(crate) transaction_errors: Counter,
+    pub(crate) transaction_operations: Counter,
+    pub(crate) transaction_duration: Histogram,
+    pub(crate) tasks: Arc<TaskMetrics>,
+}
+
+impl StorageMetrics {
+    /// Returns the number of transactions successfully applied.
+    #[must_use]
+    pub fn transactions_applied(&self) -> u64 {
+        self.transactions.get()
+    }
+
+    /// Returns the number of queued background tasks of kind `task`, such as
+    /// `view_map`.
+    #[must_use]
+    pub fn tasks_queued(&self, task: &str) -> i64 {
+        self.tasks.queued.get(task).get()
+    }
+
+    /// Returns the number of background tasks of kind `task` that have
+    /// finished executing.
+    #[must_use]
+    pub fn tasks_completed(&self, task: &str) -> u64 {
+        self.tasks.completed.get(task).get()
+    }
+
+    /// Writes all metrics to `encoder`.
+    pub fn encode(&self, encoder: &mut PrometheusEncoder<'_>) {
+        encoder.metric(
+            "bonsaidb_transactions_total",
+            "Transactions successfully applied.",
+            &self.transactions,
+        );
+        encoder.metric(
+            "bonsaidb_transaction_errors_total",
+            "Transactions that failed to apply.",
+            &self.transaction_errors,
+        );
+        encoder.metric(
+            "bonsaidb_transaction_operations_total",
+            "Operations in transactions successfully applied.",
+            &self.transaction_operations,
+        );
+        encoder.metric(
+            "bonsaidb_transaction_duration_seconds",
+            "Time spent applying transactions.",
+            &self.transaction_duration,
+        );
+        encoder.family(
+            "bonsaidb_tasks_queued",
+            "Background tasks waiting to be executed.",
+            &self.tasks.queued,
+        );
+        encoder.family(
+            "bonsaidb_tasks_running",
+            "Background tasks currently executing.",
+            &self.tasks.running,
+        );
+        encoder.family(
+            "bonsaidb_tasks_completed_total",
+            "Background tasks that finished executing.",
+            &self.tasks.completed,
+        );
+        encoder.family(
+            "bonsaidb_task_errors_total",
+            "Background tasks that finished with an error.",
+            &self.tasks.errors,
+        );
+        encoder.family(
+            "bonsaidb_task_duration_seconds",
+            "Time spent executing background tasks.",
+            &self.tasks.duration,
+        );
+    }
+}
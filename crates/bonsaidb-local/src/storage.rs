@@ -5,43 +5,62 @@ use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
-use bonsaidb_core::admin::database::{self, ByName, Database as DatabaseRecord};
+use bonsaidb_core::admin::database::{self, Database as DatabaseRecord};
 use bonsaidb_core::admin::user::User;
-use bonsaidb_core::admin::{self, Admin, PermissionGroup, Role, ADMIN_DATABASE_NAME};
+use bonsaidb_core::admin::{
+    self, Admin, AuditLogEntry, AuditOutcome, DatabaseQuota, PermissionGroup, Role,
+    ADMIN_DATABASE_NAME,
+};
+#[cfg(feature = "async")]
+use bonsaidb_core::api::ApiName;
 use bonsaidb_core::circulate;
 pub use bonsaidb_core::circulate::Relay;
 use bonsaidb_core::connection::{
-    self, Connection, HasSession, Identity, IdentityReference, LowLevelConnection, Session,
-    SessionAuthentication, SessionId, StorageConnection,
+    self, Connection, DatabaseUsage, HasSession, Identity, IdentityReference, LowLevelConnection,
+    Session, SessionAuthentication, SessionId, StorageConnection,
 };
 use bonsaidb_core::document::CollectionDocument;
 #[cfg(any(feature = "encryption", feature = "compression"))]
 use bonsaidb_core::document::KeyId;
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
 use bonsaidb_core::permissions::bonsai::{
     bonsaidb_resource_name, database_resource_name, role_resource_name, user_resource_name,
     BonsaiAction, ServerAction,
 };
-use bonsaidb_core::permissions::Permissions;
+#[cfg(feature = "encryption")]
+use bonsaidb_core::permissions::bonsai::{encryption_key_resource_name, EncryptionKeyAction};
+use bonsaidb_core::permissions::{Action, Identifier, Permissions};
+use bonsaidb_core::pubsub::TopicPattern;
+#[cfg(feature = "encryption")]
+use bonsaidb_core::schema::CollectionName;
 use bonsaidb_core::schema::{
-    Nameable, NamedCollection, Schema, SchemaName, SchemaSummary, Schematic,
+    Nameable, NamedCollection, Schema, SchemaName, SchemaSummary, Schematic, SerializedCollection,
 };
+use bonsaidb_core::transaction::{Operation, Transaction};
 use fs2::FileExt;
 use itertools::Itertools;
 use nebari::io::any::{AnyFile, AnyFileManager};
 use nebari::io::FileManager;
 use nebari::{ChunkCache, ThreadPool};
-use parking_lot::{Mutex, RwLock};
+use parking_lot::{Condvar, Mutex, RwLock};
 use rand::{thread_rng, Rng};
 
+#[cfg(feature = "async")]
+use crate::api::AnyHandler;
 #[cfg(feature = "compression")]
 use crate::config::Compression;
-use crate::config::{KeyValuePersistence, StorageConfiguration};
+use crate::config::{AuditLog, KeyValuePersistence, StorageConfiguration};
 use crate::database::Context;
+use crate::metrics::StorageMetrics;
 use crate::tasks::manager::Manager;
 use crate::tasks::TaskManager;
 #[cfg(feature = "encryption")]
+use crate::tasks::{KeyRotations, PendingKeyRotation};
+#[cfg(feature = "encryption")]
 use crate::vault::{self, LocalVaultKeyStorage, Vault};
 use crate::{Database, Error};
 
@@ -49,11 +68,23 @@ use crate::{Database, Error};
 mod argon;
 #[cfg(feature = "token-authentication")]
 mod token_authentication;
+#[cfg(feature = "totp-authentication")]
+mod totp_authentication;
 
 mod backup;
 mod pubsub;
+#[cfg(feature = "encryption")]
+pub use backup::BackupEncryption;
 pub use backup::{AnyBackupLocation, BackupLocation};
 
+/// The amount of time to wait before retrying to write audit log entries that
+/// could not be written to the admin database.
+const AUDIT_LOG_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum number of audit log entries that can be waiting to be written.
+/// Once reached, recording an entry blocks until queued entries are written.
+const MAX_QUEUED_AUDIT_LOG_ENTRIES: usize = 10_000;
+
 /// A file-based, multi-database, multi-user database engine. This type blocks
 /// the current thread when used. See [`AsyncStorage`](crate::AsyncStorage) for
 /// this type's async counterpart.
@@ -159,24 +190,62 @@ pub struct AuthenticatedSession {
     // TODO: client_data,
     storage: Weak<Data>,
     pub session: Mutex<Session>,
+    created_at: TimestampAsNanoseconds,
+    /// Set when the session is revoked. Handles still referencing a revoked
+    /// session are denied all actions.
+    revoked: AtomicBool,
+}
+
+impl AuthenticatedSession {
+    fn check_revoked(&self) -> Result<(), bonsaidb_core::Error> {
+        if self.revoked.load(Ordering::Acquire) {
+            let id = self.session.lock().id.map_or(0, |id| id.0);
+            Err(bonsaidb_core::Error::SessionNotFound(id))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct SessionSubscribers {
     pub subscribers: HashMap<u64, SessionSubscriber>,
     pub subscribers_by_session: HashMap<SessionId, HashSet<u64>>,
+    pub pattern_subscribers: HashMap<String, HashSet<u64>>,
     pub last_id: u64,
 }
 
 impl SessionSubscribers {
     pub fn unregister(&mut self, subscriber_id: u64) {
-        if let Some(session_id) = self
-            .subscribers
-            .remove(&subscriber_id)
-            .and_then(|sub| sub.session_id)
+        if let Some(subscriber) = self.subscribers.remove(&subscriber_id) {
+            if !subscriber.patterns.is_empty() {
+                self.remove_pattern_subscriber(&subscriber.database, subscriber_id);
+            }
+            if let Some(session_id) = subscriber.session_id {
+                if let Some(session_subscribers) = self.subscribers_by_session.get_mut(&session_id)
+                {
+                    session_subscribers.remove(&subscriber_id);
+                }
+            }
+        }
+    }
+
+    pub fn unregister_session(&mut self, session_id: SessionId) {
+        for id in self
+            .subscribers_by_session
+            .remove(&session_id)
+            .into_iter()
+            .flatten()
         {
-            if let Some(session_subscribers) = self.subscribers_by_session.get_mut(&session_id) {
-                session_subscribers.remove(&subscriber_id);
+            self.unregister(id);
+        }
+    }
+
+    pub fn remove_pattern_subscriber(&mut self, database: &str, subscriber_id: u64) {
+        if let Some(subscribers) = self.pattern_subscribers.get_mut(database) {
+            subscribers.remove(&subscriber_id);
+            if subscribers.is_empty() {
+                self.pattern_subscribers.remove(database);
             }
         }
     }
@@ -185,7 +254,12 @@ impl SessionSubscribers {
 #[derive(Debug)]
 pub struct SessionSubscriber {
     pub session_id: Option<SessionId>,
+    /// The session whose permissions are checked before delivering messages
+    /// matching `patterns`.
+    pub session: Option<Session>,
+    pub database: String,
     pub subscriber: circulate::Subscriber,
+    pub patterns: HashSet<TopicPattern>,
 }
 
 impl Drop for AuthenticatedSession {
@@ -198,15 +272,7 @@ impl Drop for AuthenticatedSession {
                 sessions.sessions.remove(&id);
 
                 // Remove all subscribers.
-                let mut sessions = storage.subscribers.write();
-                for id in sessions
-                    .subscribers_by_session
-                    .remove(&id)
-                    .into_iter()
-                    .flatten()
-                {
-                    sessions.subscribers.remove(&id);
-                }
+                storage.subscribers.write().unregister_session(id);
             }
         }
     }
@@ -216,6 +282,10 @@ impl Drop for AuthenticatedSession {
 struct AuthenticatedSessions {
     sessions: HashMap<SessionId, Arc<AuthenticatedSession>>,
     last_session_id: u64,
+    /// The ids of sessions pending a time-based one-time password, in the
+    /// order they were created.
+    #[cfg(feature = "totp-authentication")]
+    totp_challenges: std::collections::VecDeque<SessionId>,
 }
 
 #[derive(Debug, Clone)]
@@ -233,6 +303,23 @@ impl From<StorageInstance> for Storage {
     }
 }
 
+/// [Audit log](AuditLog) entries waiting to be written to the admin database.
+#[derive(Default)]
+struct AuditLogQueue {
+    queued: Mutex<QueuedAuditLogEntries>,
+    /// Notified when entries are queued and when queued entries are written.
+    changed: Condvar,
+    /// Entries taken from `queued` that are being written.
+    writing: Mutex<Vec<AuditLogEntry>>,
+}
+
+#[derive(Default)]
+struct QueuedAuditLogEntries {
+    entries: Vec<AuditLogEntry>,
+    /// The number of entries in `entries` and `AuditLogQueue::writing`.
+    unwritten: usize,
+}
+
 struct Data {
     lock: StorageLock,
     path: PathBuf,
@@ -240,8 +327,10 @@ struct Data {
     threadpool: ThreadPool<AnyFile>,
     file_manager: AnyFileManager,
     pub(crate) tasks: TaskManager,
+    metrics: StorageMetrics,
     schemas: RwLock<HashMap<SchemaName, Arc<dyn DatabaseOpener>>>,
     available_databases: RwLock<HashMap<String, SchemaName>>,
+    database_quotas: RwLock<HashMap<String, DatabaseQuota>>,
     open_roots: Mutex<HashMap<String, Context>>,
     // cfg check matches `Connection::authenticate`
     authenticated_permissions: Permissions,
@@ -253,12 +342,18 @@ struct Data {
     pub(crate) vault: Arc<Vault>,
     #[cfg(feature = "encryption")]
     default_encryption_key: Option<KeyId>,
+    #[cfg(feature = "encryption")]
+    key_rotations: KeyRotations,
     #[cfg(any(feature = "compression", feature = "encryption"))]
     tree_vault: Option<TreeVault>,
     pub(crate) key_value_persistence: KeyValuePersistence,
     chunk_cache: ChunkCache,
     pub(crate) check_view_integrity_on_database_open: bool,
     relay: Relay,
+    audit_log: AuditLog,
+    audit_log_queue: Arc<AuditLogQueue>,
+    #[cfg(feature = "async")]
+    custom_apis: HashMap<ApiName, Arc<dyn AnyHandler>>,
 }
 
 impl Storage {
@@ -278,6 +373,10 @@ impl Storage {
         for _ in 0..configuration.workers.worker_count {
             manager.spawn_worker();
         }
+        let metrics = StorageMetrics {
+            tasks: manager.metrics.clone(),
+            ..StorageMetrics::default()
+        };
         let tasks = TaskManager::new(manager);
 
         fs::create_dir_all(&owned_path)?;
@@ -300,6 +399,8 @@ impl Storage {
                 vault_key_storage,
             )?)
         };
+        #[cfg(feature = "encryption")]
+        let key_rotations = KeyRotations::load(owned_path.join("key-rotations"))?;
 
         let parallelization = configuration.workers.parallelization;
         let check_view_integrity_on_database_open = configuration.views.check_integrity_on_open;
@@ -320,12 +421,14 @@ impl Storage {
         let tree_vault = TreeVault::new_if_needed(configuration.default_compression);
 
         let authenticated_permissions = configuration.authenticated_permissions;
+        let audit_log = configuration.audit_log;
 
         let storage = Self {
             instance: StorageInstance {
                 data: Arc::new(Data {
                     lock: storage_lock,
                     tasks,
+                    metrics,
                     parallelization,
                     subscribers: Arc::default(),
                     authenticated_permissions,
@@ -336,6 +439,8 @@ impl Storage {
                     vault,
                     #[cfg(feature = "encryption")]
                     default_encryption_key,
+                    #[cfg(feature = "encryption")]
+                    key_rotations,
                     #[cfg(any(feature = "compression", feature = "encryption"))]
                     tree_vault,
                     path: owned_path,
@@ -343,11 +448,16 @@ impl Storage {
                     chunk_cache: ChunkCache::new(2000, 160_384),
                     threadpool: ThreadPool::new(parallelization),
                     schemas: RwLock::new(configuration.initial_schemas),
+                    #[cfg(feature = "async")]
+                    custom_apis: configuration.custom_apis,
                     available_databases: RwLock::default(),
+                    database_quotas: RwLock::default(),
                     open_roots: Mutex::default(),
                     key_value_persistence,
                     check_view_integrity_on_database_open,
                     relay: Relay::default(),
+                    audit_log,
+                    audit_log_queue: Arc::default(),
                 }),
             },
             authentication: None,
@@ -358,6 +468,15 @@ impl Storage {
 
         storage.create_admin_database_if_needed()?;
 
+        if !matches!(storage.instance.data.audit_log, AuditLog::Disabled) {
+            let queue = storage.instance.data.audit_log_queue.clone();
+            let data = Arc::downgrade(&storage.instance.data);
+            std::thread::Builder::new()
+                .name(String::from("audit-log-writer"))
+                .spawn(move || StorageInstance::write_audit_log_in_background(&queue, &data))
+                .unwrap();
+        }
+
         Ok(storage)
     }
 
@@ -369,6 +488,40 @@ impl Storage {
             .database_without_schema(&name, Some(self), None)
     }
 
+    /// Authenticates as `identity` using an authentication `method` that has
+    /// already been verified by the caller, such as a JSON Web Token or TLS
+    /// client certificate verified by `bonsaidb-server`.
+    #[cfg(feature = "internal-apis")]
+    #[doc(hidden)]
+    pub fn authenticate_as(
+        &self,
+        identity: IdentityReference<'_>,
+        method: connection::AuthenticationMethod,
+    ) -> Result<Self, bonsaidb_core::Error> {
+        let admin = self.instance.admin();
+        match identity {
+            IdentityReference::User(user) => {
+                let user =
+                    User::load(user, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+                self.check_permission(
+                    user_resource_name(user.header.id),
+                    &BonsaiAction::Server(ServerAction::Authenticate(method)),
+                )?;
+                self.instance.assume_user(user, None, &admin)
+            }
+            IdentityReference::Role(role) => {
+                let role =
+                    Role::load(role, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+                self.check_permission(
+                    role_resource_name(role.header.id),
+                    &BonsaiAction::Server(ServerAction::Authenticate(method)),
+                )?;
+                self.instance.assume_role(role, None, &admin)
+            }
+            _ => Err(bonsaidb_core::Error::InvalidCredentials),
+        }
+    }
+
     fn lookup_or_create_id(
         configuration: &StorageConfiguration,
         path: &Path,
@@ -427,15 +580,19 @@ impl Storage {
     }
 
     fn cache_available_databases(&self) -> Result<(), Error> {
-        let available_databases = self
-            .admin()
-            .view::<ByName>()
-            .query()?
-            .into_iter()
-            .map(|map| (map.key, map.value))
-            .collect();
+        let mut available_databases = HashMap::new();
+        let mut database_quotas = HashMap::new();
+        for database in DatabaseRecord::all(&self.instance.admin()).query()? {
+            let database = database.contents;
+            if !database.quota.is_unlimited() {
+                database_quotas.insert(database.name.clone(), database.quota);
+            }
+            available_databases.insert(database.name, database.schema);
+        }
         let mut storage_databases = self.instance.data.available_databases.write();
         *storage_databases = available_databases;
+        let mut storage_quotas = self.instance.data.database_quotas.write();
+        *storage_quotas = database_quotas;
         Ok(())
     }
 
@@ -464,6 +621,27 @@ impl Storage {
         self.instance.data.lock.id()
     }
 
+    /// Returns the metrics collected by this instance, which include the
+    /// transactions applied and the background tasks executed for all
+    /// databases.
+    #[must_use]
+    pub fn metrics(&self) -> &StorageMetrics {
+        self.instance.metrics()
+    }
+
+    /// Writes any pending [audit log](crate::config::AuditLog) entries to the
+    /// admin database.
+    ///
+    /// Audit log entries are queued when the audited action's permission is
+    /// checked, and are written in batches by a background thread. If entries
+    /// can't be written, they are kept in memory and writing them is retried
+    /// once per second. Calling this function writes the queued entries
+    /// immediately, returning the error if they can't be written. Entries
+    /// that haven't been written when the storage is dropped are lost.
+    pub fn flush_audit_log(&self) -> Result<(), bonsaidb_core::Error> {
+        self.instance.flush_audit_log()
+    }
+
     #[must_use]
     pub(crate) fn parallelization(&self) -> usize {
         self.instance.data.parallelization
@@ -475,6 +653,188 @@ impl Storage {
         &self.instance.data.vault
     }
 
+    /// Deletes the vault's encryption key named `name`. All data encrypted
+    /// using [`KeyId::Id`] with this name can no longer be decrypted. Documents
+    /// encrypted using
+    /// [`DocumentEncryption`](bonsaidb_core::schema::DocumentEncryption) with
+    /// this key are omitted from results as if they did not exist.
+    ///
+    /// The storage's default encryption key and keys used by a collection's
+    /// [`encryption_key()`](bonsaidb_core::schema::Collection::encryption_key)
+    /// can't be deleted, as every document in the storage or collection would
+    /// become unreadable. Attempting to delete them returns
+    /// [`vault::Error::EncryptionKeyInUse`].
+    ///
+    /// This requires the [`EncryptionKeyAction::Delete`] permission for the
+    /// key's [`encryption_key_resource_name()`].
+    #[cfg(feature = "encryption")]
+    pub fn delete_encryption_key(&self, name: &str) -> Result<(), Error> {
+        let key_id = KeyId::Id(Cow::Owned(name.to_string()));
+        self.check_permission(
+            encryption_key_resource_name(&key_id),
+            &EncryptionKeyAction::Delete,
+        )?;
+        if self.is_encryption_key_in_use(&key_id)? {
+            return Err(Error::Vault(vault::Error::EncryptionKeyInUse(
+                name.to_string(),
+            )));
+        }
+        self.vault().delete_named_key(name)?;
+        Ok(())
+    }
+
+    /// Returns true if `key` is the storage's default encryption key or the
+    /// encryption key of any collection in this storage.
+    #[cfg(feature = "encryption")]
+    fn is_encryption_key_in_use(&self, key: &KeyId) -> Result<bool, Error> {
+        if self.default_encryption_key() == Some(key) {
+            return Ok(true);
+        }
+
+        for database_name in self.instance.available_database_names() {
+            let database = self
+                .instance
+                .database_without_schema(&database_name, None, None)?;
+            let schematic = database.schematic();
+            if schematic
+                .collections()
+                .any(|collection| schematic.encryption_key_for_collection(collection) == Some(key))
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Replaces the vault key with a newly generated key, re-sealing the
+    /// master keys with it. The new key is stored using the configured
+    /// [`VaultKeyStorage`](crate::vault::VaultKeyStorage). Data encrypted by
+    /// the vault is unaffected.
+    ///
+    /// This requires the [`ServerAction::RotateVaultKey`] permission for
+    /// [`bonsaidb_resource_name()`].
+    #[cfg(feature = "encryption")]
+    pub fn rotate_vault_key(&self) -> Result<(), Error> {
+        self.check_permission(
+            bonsaidb_resource_name(),
+            &BonsaiAction::Server(ServerAction::RotateVaultKey),
+        )?;
+        self.vault().rotate_vault_key(self.unique_id())?;
+        Ok(())
+    }
+
+    /// Creates a new version of the encryption key `key`, which will be used
+    /// to encrypt all data written from now on. Previous versions of the key
+    /// are kept so that existing data can still be decrypted. To re-encrypt a
+    /// collection's existing data, use
+    /// [`Database::rotate_collection_encryption_key()`].
+    ///
+    /// This requires the [`EncryptionKeyAction::Rotate`] permission for the
+    /// key's [`encryption_key_resource_name()`].
+    #[cfg(feature = "encryption")]
+    pub fn rotate_encryption_key(&self, key: &KeyId) -> Result<(), Error> {
+        self.check_permission(
+            encryption_key_resource_name(key),
+            &EncryptionKeyAction::Rotate,
+        )?;
+        self.vault().rotate_key(key)?;
+        Ok(())
+    }
+
+    /// Removes the versions of the encryption key named `name` that are no
+    /// longer used by any collection, returning the number of versions
+    /// removed.
+    ///
+    /// Rotating a key never removes its previous versions. Once every
+    /// collection using the key has been re-encrypted using
+    /// [`Database::rotate_collection_encryption_key()`], this function removes
+    /// the versions older than the oldest version still in use. Any other data
+    /// encrypted with the removed versions, such as backups sealed using
+    /// [`BackupEncryption::Vault`](crate::BackupEncryption::Vault), can no
+    /// longer be decrypted.
+    ///
+    /// No versions are removed from the storage's default encryption key, as
+    /// it also protects data that isn't re-encrypted, such as key-value
+    /// stores. No versions are removed if a collection using the key hasn't
+    /// been re-encrypted since the key was rotated, or if any collection uses
+    /// [`DocumentEncryption`](bonsaidb_core::schema::DocumentEncryption),
+    /// whose keys are chosen for each document.
+    ///
+    /// This requires the [`EncryptionKeyAction::Rotate`] permission for the
+    /// key's [`encryption_key_resource_name()`].
+    #[cfg(feature = "encryption")]
+    pub fn retire_encryption_key_versions(&self, name: &str) -> Result<usize, Error> {
+        let key = KeyId::Id(Cow::Owned(name.to_string()));
+        self.check_permission(
+            encryption_key_resource_name(&key),
+            &EncryptionKeyAction::Rotate,
+        )?;
+        if self.default_encryption_key() == Some(&key) {
+            return Ok(0);
+        }
+
+        let mut oldest_in_use = self.vault().current_version(&key)?;
+        for database_name in self.instance.available_database_names() {
+            let database = self
+                .instance
+                .database_without_schema(&database_name, None, None)?;
+            for collection in database.schematic().collections() {
+                if database
+                    .schematic()
+                    .document_encryption_for(collection)
+                    .is_some()
+                {
+                    return Ok(0);
+                }
+                if database.collection_encryption_key(collection) != Some(&key) {
+                    continue;
+                }
+                match self.instance.key_rotations().reencrypted_version(
+                    &database_name,
+                    collection,
+                    &key,
+                ) {
+                    Some(version) => oldest_in_use = oldest_in_use.min(version),
+                    None => return Ok(0),
+                }
+            }
+        }
+
+        Ok(self
+            .vault()
+            .retire_named_key_versions_before(name, oldest_in_use)?)
+    }
+
+    /// Rotates the encryption key of `collection` in the database named
+    /// `database`. See [`Database::rotate_collection_encryption_key()`] for
+    /// more information.
+    #[cfg(feature = "encryption")]
+    pub fn rotate_collection_encryption_key(
+        &self,
+        database: &str,
+        collection: &CollectionName,
+    ) -> Result<(), Error> {
+        self.instance
+            .database_without_schema(database, Some(self), None)?
+            .rotate_collection_encryption_key(collection)
+    }
+
+    /// Returns the collections that are still being re-encrypted after their
+    /// encryption key was rotated.
+    #[must_use]
+    #[cfg(feature = "encryption")]
+    pub fn pending_key_rotations(&self) -> Vec<PendingKeyRotation> {
+        self.instance.key_rotations().progress()
+    }
+
+    /// Returns the scope of this instance's session, if any. Identities
+    /// assumed by a scoped session are restricted to the same scope, ensuring
+    /// that assuming an identity can't be used to escape a token's scope.
+    fn scope(&self) -> Option<Permissions> {
+        self.session().and_then(|session| session.scope.clone())
+    }
+
     #[must_use]
     #[cfg(any(feature = "encryption", feature = "compression"))]
     pub(crate) fn tree_vault(&self) -> Option<&TreeVault> {
@@ -540,6 +900,7 @@ impl Storage {
                     id: None,
                     authentication: SessionAuthentication::None,
                     permissions: effective_permissions,
+                    scope: None,
                 })),
             })
         }
@@ -599,6 +960,7 @@ impl Debug for Data {
             .field("file_manager", &self.file_manager)
             .field("tasks", &self.tasks)
             .field("available_databases", &self.available_databases)
+            .field("database_quotas", &self.database_quotas)
             .field("open_roots", &self.open_roots)
             .field("authenticated_permissions", &self.authenticated_permissions)
             .field("sessions", &self.sessions)
@@ -624,7 +986,8 @@ impl Debug for Data {
         #[cfg(feature = "encryption")]
         {
             f.field("vault", &self.vault)
-                .field("default_encryption_key", &self.default_encryption_key);
+                .field("default_encryption_key", &self.default_encryption_key)
+                .field("key_rotations", &self.key_rotations);
         }
         #[cfg(any(feature = "compression", feature = "encryption"))]
         f.field("tree_vault", &self.tree_vault);
@@ -634,6 +997,125 @@ impl Debug for Data {
 }
 
 impl StorageInstance {
+    /// Returns the names of all databases in this storage.
+    #[cfg(feature = "encryption")]
+    fn available_database_names(&self) -> Vec<String> {
+        self.data
+            .available_databases
+            .read()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn custom_api_handler(&self, name: &ApiName) -> Option<Arc<dyn AnyHandler>> {
+        self.data.custom_apis.get(name).cloned()
+    }
+
+    /// Records the outcome of checking `session`'s permission to perform
+    /// `action` on `resource_name`, if the action is included in the audit log.
+    ///
+    /// Entries are queued to be written by a background thread. If
+    /// [`MAX_QUEUED_AUDIT_LOG_ENTRIES`] entries are waiting to be written, this
+    /// function blocks until the background thread has written them.
+    fn audit<P: Action>(
+        &self,
+        session: &Session,
+        resource_name: &[Identifier<'_>],
+        action: &P,
+        allowed: bool,
+    ) {
+        let action = action.name();
+        if !self.data.audit_log.includes(&action) {
+            return;
+        }
+
+        let entry = AuditLogEntry {
+            timestamp: TimestampAsNanoseconds::now(),
+            session_id: session.id,
+            identity: session.identity().cloned(),
+            action: action.to_string(),
+            resource: resource_name.iter().join("."),
+            outcome: if allowed {
+                AuditOutcome::Allowed
+            } else {
+                AuditOutcome::Denied
+            },
+        };
+
+        let queue = &self.data.audit_log_queue;
+        let mut queued = queue.queued.lock();
+        while queued.unwritten >= MAX_QUEUED_AUDIT_LOG_ENTRIES {
+            queue.changed.wait(&mut queued);
+        }
+        queued.entries.push(entry);
+        queued.unwritten += 1;
+        queue.changed.notify_all();
+    }
+
+    /// Writes all queued audit log entries to the admin database.
+    pub(crate) fn flush_audit_log(&self) -> Result<(), bonsaidb_core::Error> {
+        let queue = &self.data.audit_log_queue;
+        // The lock on the entries being written is held while writing to
+        // ensure entries are written in the order they were recorded.
+        let mut writing = queue.writing.lock();
+        writing.append(&mut queue.queued.lock().entries);
+        if writing.is_empty() {
+            return Ok(());
+        }
+
+        // Entries are only removed once they have been written.
+        self.write_audit_log_entries(&writing)?;
+        let written = writing.len();
+        writing.clear();
+        drop(writing);
+
+        let mut queued = queue.queued.lock();
+        queued.unwritten -= written;
+        queue.changed.notify_all();
+        Ok(())
+    }
+
+    fn write_audit_log_entries(
+        &self,
+        entries: &[AuditLogEntry],
+    ) -> Result<(), bonsaidb_core::Error> {
+        let mut transaction = Transaction::new();
+        for entry in entries {
+            transaction.push(Operation::push_serialized::<AuditLogEntry>(entry)?);
+        }
+        self.admin().apply_transaction(transaction)?;
+        Ok(())
+    }
+
+    fn write_audit_log_in_background(queue: &AuditLogQueue, data: &Weak<Data>) {
+        let mut failed = false;
+        loop {
+            if !failed {
+                let mut queued = queue.queued.lock();
+                if queued.entries.is_empty() {
+                    queue
+                        .changed
+                        .wait_for(&mut queued, AUDIT_LOG_RETRY_INTERVAL);
+                }
+            }
+
+            // The storage is only kept alive while writing.
+            let Some(data) = data.upgrade() else {
+                break;
+            };
+            let result = Self { data }.flush_audit_log();
+            failed = if let Err(err) = result {
+                log::error!("[storage] error writing audit log: {err}");
+                std::thread::sleep(AUDIT_LOG_RETRY_INTERVAL);
+                true
+            } else {
+                false
+            };
+        }
+    }
+
     #[cfg_attr(
         not(any(feature = "encryption", feature = "compression")),
         allow(unused_mut)
@@ -672,6 +1154,20 @@ impl StorageInstance {
         &self.data.tasks
     }
 
+    pub(crate) fn metrics(&self) -> &'_ StorageMetrics {
+        &self.data.metrics
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn key_rotations(&self) -> &'_ KeyRotations {
+        &self.data.key_rotations
+    }
+
+    /// Returns the quota of the database named `name`, if it has one.
+    pub(crate) fn database_quota(&self, name: &str) -> Option<DatabaseQuota> {
+        self.data.database_quotas.read().get(name).copied()
+    }
+
     pub(crate) fn check_view_integrity_on_database_open(&self) -> bool {
         self.data.check_view_integrity_on_database_open
     }
@@ -752,7 +1248,11 @@ impl StorageInstance {
         }
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     #[cfg_attr(
         any(
             not(feature = "token-authentication"),
@@ -799,14 +1299,29 @@ impl StorageInstance {
                 self.data
                     .argon
                     .verify(user.header.id, password, saved_hash)?;
-                self.assume_user(user, admin)
+                #[cfg(feature = "totp-authentication")]
+                if Self::requires_totp(&user) {
+                    return Ok(self.begin_totp_authentication(&user));
+                }
+                self.assume_user(user, None, admin)
+            }
+            #[cfg(feature = "totp-authentication")]
+            Authentication::Totp(code) => {
+                let session_id =
+                    current_session_id.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+                self.finish_totp_authentication(session_id, &code, admin)
             }
+            // JSON Web Tokens are verified by bonsaidb-server, which
+            // authenticates using the identity the token maps to.
+            #[cfg(feature = "jwt-authentication")]
+            Authentication::Jwt(_) => Err(bonsaidb_core::Error::InvalidCredentials),
         }
     }
 
     fn assume_user(
         &self,
         user: CollectionDocument<User>,
+        scope: Option<Permissions>,
         admin: &Database,
     ) -> Result<Storage, bonsaidb_core::Error> {
         let permissions = user.contents.effective_permissions(
@@ -824,10 +1339,13 @@ impl StorageInstance {
                 username: user.contents.username,
             })),
             permissions,
+            scope,
         };
         let authentication = Arc::new(AuthenticatedSession {
             storage: Arc::downgrade(&self.data),
             session: Mutex::new(session.clone()),
+            created_at: TimestampAsNanoseconds::now(),
+            revoked: AtomicBool::new(false),
         });
         sessions.sessions.insert(session_id, authentication.clone());
 
@@ -841,6 +1359,7 @@ impl StorageInstance {
     fn assume_role(
         &self,
         role: CollectionDocument<Role>,
+        scope: Option<Permissions>,
         admin: &Database,
     ) -> Result<Storage, bonsaidb_core::Error> {
         let permissions = role.contents.effective_permissions(
@@ -858,10 +1377,13 @@ impl StorageInstance {
                 name: role.contents.name,
             })),
             permissions,
+            scope,
         };
         let authentication = Arc::new(AuthenticatedSession {
             storage: Arc::downgrade(&self.data),
             session: Mutex::new(session.clone()),
+            created_at: TimestampAsNanoseconds::now(),
+            revoked: AtomicBool::new(false),
         });
         sessions.sessions.insert(session_id, authentication.clone());
 
@@ -997,6 +1519,7 @@ impl StorageConnection for StorageInstance {
                 .push(&admin::Database {
                     name: name.to_string(),
                     schema: schema.clone(),
+                    quota: DatabaseQuota::unlimited(),
                 })?;
             available_databases.insert(name.to_string(), schema);
         } else if !only_if_needed {
@@ -1018,10 +1541,14 @@ impl StorageConnection for StorageInstance {
         let admin = self.admin();
         let mut available_databases = self.data.available_databases.write();
         available_databases.remove(name);
+        self.data.database_quotas.write().remove(name);
 
         let mut open_roots = self.data.open_roots.lock();
         open_roots.remove(name);
 
+        #[cfg(feature = "encryption")]
+        self.data.key_rotations.remove_database(name)?;
+
         let database_folder = self.data.path.join(name);
         if database_folder.exists() {
             let file_manager = self.data.file_manager.clone();
@@ -1056,6 +1583,130 @@ impl StorageConnection for StorageInstance {
             .collect())
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let admin = self.admin();
+        let mut database = DatabaseRecord::load(name, &admin)?
+            .ok_or_else(|| bonsaidb_core::Error::DatabaseNotFound(name.to_string()))?;
+        database.contents.quota = quota;
+        database.update(&admin)?;
+
+        let mut database_quotas = self.data.database_quotas.write();
+        if quota.is_unlimited() {
+            database_quotas.remove(name);
+        } else {
+            database_quotas.insert(name.to_string(), quota);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, bonsaidb_core::Error> {
+        let databases = self
+            .data
+            .available_databases
+            .read()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut usage = Vec::with_capacity(databases.len());
+        for name in databases {
+            let database = match self.database_without_schema(&name, None, None) {
+                Ok(database) => database,
+                // The database was deleted while gathering usage.
+                Err(Error::Core(bonsaidb_core::Error::DatabaseNotFound(_))) => continue,
+                Err(err) => return Err(bonsaidb_core::Error::from(err)),
+            };
+            let (bytes, documents) = database.storage_usage();
+            usage.push(DatabaseUsage {
+                quota: self.database_quota(&name).unwrap_or_default(),
+                name,
+                bytes,
+                documents,
+            });
+        }
+        usage.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(usage)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<bonsaidb_core::connection::AuthenticationTokenSummary>, bonsaidb_core::Error>
+    {
+        let admin = self.admin();
+        let identity = identity
+            .resolve(&admin)?
+            .ok_or(bonsaidb_core::Error::UserNotFound)?;
+        let mut tokens = admin
+            .view::<admin::AuthenticationTokensByIdentity>()
+            .with_key(&identity)
+            .query_with_collection_docs()?
+            .documents
+            .into_values()
+            .map(bonsaidb_core::connection::AuthenticationTokenSummary::from)
+            .collect::<Vec<_>>();
+        tokens.sort_by_key(|token| (token.created_at, token.id));
+        Ok(tokens)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        use bonsaidb_core::schema::Collection;
+
+        let admin = self.admin();
+        let token = admin::AuthenticationToken::get(&id, &admin)?.ok_or_else(|| {
+            bonsaidb_core::Error::DocumentNotFound(
+                admin::AuthenticationToken::collection_name(),
+                Box::new(bonsaidb_core::document::DocumentId::from_u64(id)),
+            )
+        })?;
+        token.delete(&admin)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        let sessions = self.data.sessions.read();
+        let mut summaries = sessions
+            .sessions
+            .iter()
+            .map(
+                |(id, authentication)| bonsaidb_core::connection::SessionSummary {
+                    id: *id,
+                    authentication: authentication.session.lock().authentication.clone(),
+                    created_at: authentication.created_at,
+                    address: None,
+                    transport: None,
+                },
+            )
+            .collect::<Vec<_>>();
+        summaries.sort_by_key(|session| session.id.0);
+        Ok(summaries)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn revoke_session(&self, id: SessionId) -> Result<(), bonsaidb_core::Error> {
+        // The session must be dropped after the lock is released, as dropping
+        // the last reference to a session acquires the lock to clean it up.
+        let revoked = self.data.sessions.write().sessions.remove(&id);
+        if let Some(revoked) = revoked {
+            // Other handles to the session may outlive it being removed, so
+            // mark it revoked to deny any further use.
+            revoked.revoked.store(true, Ordering::Release);
+            self.data.subscribers.write().unregister_session(id);
+            Ok(())
+        } else {
+            Err(bonsaidb_core::Error::SessionNotFound(id.0))
+        }
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
     fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         let available_databases = self.data.available_databases.read();
@@ -1106,8 +1757,40 @@ impl StorageConnection for StorageInstance {
         user.update(&admin)
     }
 
+    #[cfg(feature = "totp-authentication")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<admin::TotpSecret, bonsaidb_core::Error> {
+        Self::enroll_totp(user, &self.admin())
+    }
+
+    #[cfg(feature = "totp-authentication")]
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        Self::confirm_totp(user, &code, &self.admin())
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        Self::remove_totp(user, &self.admin())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
@@ -1127,12 +1810,12 @@ impl StorageConnection for StorageInstance {
             IdentityReference::User(user) => {
                 let user =
                     User::load(user, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
-                self.assume_user(user, &admin).map(Storage::from)
+                self.assume_user(user, None, &admin).map(Storage::from)
             }
             IdentityReference::Role(role) => {
                 let role =
                     Role::load(role, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
-                self.assume_role(role, &admin).map(Storage::from)
+                self.assume_role(role, None, &admin).map(Storage::from)
             }
             _ => Err(bonsaidb_core::Error::InvalidCredentials),
         }
@@ -1221,6 +1904,24 @@ impl HasSession for Storage {
     fn session(&self) -> Option<&Session> {
         self.effective_session.as_deref()
     }
+
+    fn check_permission<'a, R: AsRef<[Identifier<'a>]>, P: Action>(
+        &self,
+        resource_name: R,
+        action: &P,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let Some(session) = self.session() else {
+            return Ok(());
+        };
+        if let Some(authentication) = &self.authentication {
+            authentication.check_revoked()?;
+        }
+
+        let result = session.check_permission(resource_name.as_ref(), action);
+        self.instance
+            .audit(session, resource_name.as_ref(), action, result.is_ok());
+        result
+    }
 }
 
 impl StorageConnection for Storage {
@@ -1228,7 +1929,12 @@ impl StorageConnection for Storage {
     type Database = Database;
 
     fn admin(&self) -> Self::Database {
-        self.instance.admin()
+        Database::new::<Admin, _>(
+            ADMIN_DATABASE_NAME,
+            self.instance.open_roots(ADMIN_DATABASE_NAME).unwrap(),
+            self,
+        )
+        .unwrap()
     }
 
     fn create_database_with_schema(
@@ -1246,7 +1952,9 @@ impl StorageConnection for Storage {
     }
 
     fn database<DB: Schema>(&self, name: &str) -> Result<Self::Database, bonsaidb_core::Error> {
-        self.instance.database::<DB>(name)
+        self.instance
+            .database_without_schema(name, Some(self), Some(DB::schema_name()))
+            .map_err(bonsaidb_core::Error::from)
     }
 
     fn delete_database(&self, name: &str) -> Result<(), bonsaidb_core::Error> {
@@ -1265,6 +1973,75 @@ impl StorageConnection for Storage {
         self.instance.list_databases()
     }
 
+    fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.check_permission(
+            database_resource_name(name),
+            &BonsaiAction::Server(ServerAction::SetDatabaseQuota),
+        )?;
+        self.instance.set_database_quota(name, quota)
+    }
+
+    fn list_database_usage(&self) -> Result<Vec<DatabaseUsage>, bonsaidb_core::Error> {
+        self.check_permission(
+            bonsaidb_resource_name(),
+            &BonsaiAction::Server(ServerAction::ListDatabaseUsage),
+        )?;
+        self.instance.list_database_usage()
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<bonsaidb_core::connection::AuthenticationTokenSummary>, bonsaidb_core::Error>
+    {
+        let admin = self.instance.admin();
+        let resource_name = match identity
+            .resolve(&admin)?
+            .ok_or(bonsaidb_core::Error::UserNotFound)?
+        {
+            bonsaidb_core::connection::IdentityId::User(id) => user_resource_name(id),
+            bonsaidb_core::connection::IdentityId::Role(id) => role_resource_name(id),
+            _ => return Err(bonsaidb_core::Error::UserNotFound),
+        };
+        self.check_permission(
+            resource_name,
+            &BonsaiAction::Server(ServerAction::ListAuthenticationTokens),
+        )?;
+        self.instance.list_authentication_tokens(identity)
+    }
+
+    #[cfg(feature = "token-authentication")]
+    fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        self.check_permission(
+            bonsaidb_core::permissions::bonsai::authentication_token_resource_name(id),
+            &BonsaiAction::Server(ServerAction::RevokeAuthenticationToken),
+        )?;
+        self.instance.revoke_authentication_token(id)
+    }
+
+    fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        self.check_permission(
+            bonsaidb_resource_name(),
+            &BonsaiAction::Server(ServerAction::ListSessions),
+        )?;
+        self.instance.list_sessions()
+    }
+
+    fn revoke_session(&self, id: SessionId) -> Result<(), bonsaidb_core::Error> {
+        self.check_permission(
+            bonsaidb_resource_name(),
+            &BonsaiAction::Server(ServerAction::RevokeSession),
+        )?;
+        self.instance.revoke_session(id)
+    }
+
     fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         self.check_permission(
             bonsaidb_resource_name(),
@@ -1285,7 +2062,7 @@ impl StorageConnection for Storage {
         &self,
         user: U,
     ) -> Result<(), bonsaidb_core::Error> {
-        let admin = self.admin();
+        let admin = self.instance.admin();
         let user = user.name()?;
         let user_id = user
             .id::<User, _>(&admin)?
@@ -1303,7 +2080,7 @@ impl StorageConnection for Storage {
         user: U,
         password: bonsaidb_core::connection::SensitiveString,
     ) -> Result<(), bonsaidb_core::Error> {
-        let admin = self.admin();
+        let admin = self.instance.admin();
         let user = user.name()?;
         let user_id = user
             .id::<User, _>(&admin)?
@@ -1315,14 +2092,70 @@ impl StorageConnection for Storage {
         self.instance.set_user_password(user, password)
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<admin::TotpSecret, bonsaidb_core::Error> {
+        let admin = self.instance.admin();
+        let user = user.name()?;
+        let user_id = user
+            .id::<User, _>(&admin)?
+            .ok_or(bonsaidb_core::Error::UserNotFound)?;
+        self.check_permission(
+            user_resource_name(user_id),
+            &BonsaiAction::Server(ServerAction::ModifyUserTotp),
+        )?;
+        self.instance.enroll_user_totp(user)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let admin = self.instance.admin();
+        let user = user.name()?;
+        let user_id = user
+            .id::<User, _>(&admin)?
+            .ok_or(bonsaidb_core::Error::UserNotFound)?;
+        self.check_permission(
+            user_resource_name(user_id),
+            &BonsaiAction::Server(ServerAction::ModifyUserTotp),
+        )?;
+        self.instance.confirm_user_totp(user, code)
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let admin = self.instance.admin();
+        let user = user.name()?;
+        let user_id = user
+            .id::<User, _>(&admin)?
+            .ok_or(bonsaidb_core::Error::UserNotFound)?;
+        self.check_permission(
+            user_resource_name(user_id),
+            &BonsaiAction::Server(ServerAction::ModifyUserTotp),
+        )?;
+        self.instance.remove_user_totp(user)
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     #[cfg_attr(not(feature = "token-authentication"), allow(unused_assignments))]
     #[cfg_attr(not(feature = "password-hashing"), allow(unused_mut))]
     fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
     ) -> Result<Self, bonsaidb_core::Error> {
-        let admin = self.admin();
+        let admin = self.instance.admin();
         let mut loaded_user = None;
         match &authentication {
             #[cfg(feature = "token-authentication")]
@@ -1348,6 +2181,10 @@ impl StorageConnection for Storage {
             }
             #[cfg(feature = "token-authentication")]
             bonsaidb_core::connection::Authentication::TokenChallengeResponse(_) => {}
+            #[cfg(feature = "jwt-authentication")]
+            bonsaidb_core::connection::Authentication::Jwt(_) => {}
+            #[cfg(feature = "totp-authentication")]
+            bonsaidb_core::connection::Authentication::Totp(_) => {}
         }
         self.instance.authenticate_inner(
             authentication,
@@ -1365,24 +2202,24 @@ impl StorageConnection for Storage {
     ) -> Result<Self::Authenticated, bonsaidb_core::Error> {
         match identity {
             IdentityReference::User(user) => {
-                let admin = self.admin();
+                let admin = self.instance.admin();
                 let user =
                     User::load(user, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
                 self.check_permission(
                     user_resource_name(user.header.id),
                     &BonsaiAction::Server(ServerAction::AssumeIdentity),
                 )?;
-                self.instance.assume_user(user, &admin)
+                self.instance.assume_user(user, self.scope(), &admin)
             }
             IdentityReference::Role(role) => {
-                let admin = self.admin();
+                let admin = self.instance.admin();
                 let role =
                     Role::load(role, &admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
                 self.check_permission(
                     role_resource_name(role.header.id),
                     &BonsaiAction::Server(ServerAction::AssumeIdentity),
                 )?;
-                self.instance.assume_role(role, &admin)
+                self.instance.assume_role(role, self.scope(), &admin)
             }
 
             _ => Err(bonsaidb_core::Error::InvalidCredentials),
@@ -1668,6 +2505,7 @@ impl StorageNonBlocking for Storage {
             id: authentication_session.id,
             authentication: authentication_session.authentication.clone(),
             permissions: effective_permissions,
+            scope: authentication_session.scope.clone(),
         };
 
         Ok(Self {
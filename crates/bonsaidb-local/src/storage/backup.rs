@@ -2,7 +2,7 @@ use std::fs::DirEntry;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
-use bonsaidb_core::connection::{LowLevelConnection, Range, Sort, StorageConnection};
+use bonsaidb_core::connection::{LowLevelConnection, StorageConnection};
 use bonsaidb_core::document::DocumentId;
 use bonsaidb_core::schema::{Collection, Qualified, SchemaName};
 use bonsaidb_core::transaction::{Operation, Transaction};
@@ -51,9 +51,67 @@ pub trait BackupLocation: Send + Sync {
     ) -> Result<Vec<u8>, Self::Error>;
 }
 
+#[cfg(feature = "encryption")]
+mod encryption;
+
+#[cfg(feature = "encryption")]
+pub use self::encryption::BackupEncryption;
+
+/// The container that stores the information needed to decrypt an encrypted
+/// backup of a database.
+const ENCRYPTION_CONTAINER: &str = "_encryption";
+const ENCRYPTION_HEADER: &str = "header";
+/// The name of the object listing every object stored in an encrypted backup
+/// of a database.
+#[cfg(feature = "encryption")]
+const ENCRYPTION_MANIFEST: &str = "manifest";
+
 impl Storage {
     /// Stores a copy of all data in this instance to `location`.
+    ///
+    /// The contents of collections are stored decrypted, even if they are
+    /// encrypted at-rest. To encrypt the backup, use
+    /// `Storage::backup_encrypted()`.
+    ///
+    /// Documents encrypted with a
+    /// [`DocumentEncryption`](bonsaidb_core::schema::DocumentEncryption) key
+    /// are stored encrypted, and can only be restored to a storage whose vault
+    /// contains the key.
     pub fn backup<L: AnyBackupLocation>(&self, location: &L) -> Result<(), Error> {
+        for database in self.backup_databases()? {
+            Self::backup_database(&database, location)?;
+        }
+
+        Ok(())
+    }
+
+    /// Restores all data from a previously stored backup `location`.
+    ///
+    /// Encrypted backups must be restored using
+    /// `Storage::restore_encrypted()`.
+    ///
+    /// Documents encrypted with a
+    /// [`DocumentEncryption`](bonsaidb_core::schema::DocumentEncryption) key
+    /// that has been deleted are not restored.
+    pub fn restore<L: AnyBackupLocation>(&self, location: &L) -> Result<(), Error> {
+        let databases = Self::stored_databases(location)?;
+        for (schema, database) in &databases {
+            if is_encrypted(location, schema, database)? {
+                return Err(Error::BackupEncryption(format!(
+                    "database {database} in the backup is encrypted"
+                )));
+            }
+        }
+
+        for (schema, database) in databases {
+            let database = self.restore_database_named(&database, schema)?;
+            Self::restore_database(&database, location)?;
+        }
+
+        Ok(())
+    }
+
+    fn backup_databases(&self) -> Result<Vec<Database>, Error> {
         let databases = {
             self.instance
                 .data
@@ -64,37 +122,33 @@ impl Storage {
                 .collect::<Vec<_>>()
         };
 
-        for name in databases {
-            let database = self
-                .instance
-                .database_without_schema(&name, Some(self), None)?;
-            Self::backup_database(&database, location)?;
-        }
-
-        Ok(())
+        databases
+            .into_iter()
+            .map(|name| {
+                self.instance
+                    .database_without_schema(&name, Some(self), None)
+            })
+            .collect()
     }
 
-    /// Restores all data from a previously stored backup `location`.
-    pub fn restore<L: AnyBackupLocation>(&self, location: &L) -> Result<(), Error> {
-        for schema in location
-            .list_schemas()
-            .map_err(|err| Error::Backup(Box::new(err)))?
-        {
-            for database in location
-                .list_databases(&schema)
-                .map_err(|err| Error::Backup(Box::new(err)))?
-            {
-                // The admin database is already going to be created by the process of creating a database.
-                self.create_database_with_schema(&database, schema.clone(), true)?;
-
-                let database =
-                    self.instance
-                        .database_without_schema(&database, Some(self), None)?;
-                Self::restore_database(&database, location)?;
+    fn stored_databases(
+        location: &dyn AnyBackupLocation,
+    ) -> Result<Vec<(SchemaName, String)>, Error> {
+        let mut databases = Vec::new();
+        for schema in location.list_schemas()? {
+            for database in location.list_databases(&schema)? {
+                databases.push((schema.clone(), database));
             }
         }
+        Ok(databases)
+    }
 
-        Ok(())
+    fn restore_database_named(&self, name: &str, schema: SchemaName) -> Result<Database, Error> {
+        // The admin database is already going to be created by the process of creating a database.
+        self.create_database_with_schema(name, schema, true)?;
+
+        self.instance
+            .database_without_schema(name, Some(self), None)
     }
 
     pub(crate) fn backup_database(
@@ -103,12 +157,10 @@ impl Storage {
     ) -> Result<(), Error> {
         let schema = database.schematic().name.clone();
         for collection in database.schematic().collections() {
-            let documents = database.list_from_collection(
-                Range::from(..),
-                Sort::Ascending,
-                None,
-                collection,
-            )?;
+            // Documents encrypted with a document-level key are stored as-is,
+            // so that deleting the key also makes the backed up copies
+            // unreadable.
+            let documents = database.list_stored_documents(collection)?;
             let collection_name = collection.encoded();
             // TODO consider how to best parallelize -- perhaps a location can opt into parallelization?
             for document in documents {
@@ -162,6 +214,12 @@ impl Storage {
             {
                 let contents =
                     location.load(&schema, database.name(), &collection_name, &id_string)?;
+                let Some(contents) = database.decrypt_stored_contents(collection, contents)? else {
+                    log::warn!(
+                        "not restoring document {id} in {collection}: its encryption key has been deleted"
+                    );
+                    continue;
+                };
                 transaction.push(Operation::insert(collection.clone(), Some(id), contents));
             }
         }
@@ -214,6 +272,17 @@ pub trait AnyBackupLocation: Send + Sync {
     ) -> Result<Vec<u8>, Error>;
 }
 
+fn is_encrypted(
+    location: &dyn AnyBackupLocation,
+    schema: &SchemaName,
+    database_name: &str,
+) -> Result<bool, Error> {
+    Ok(location
+        .list_stored(schema, database_name, ENCRYPTION_CONTAINER)?
+        .iter()
+        .any(|name| name == ENCRYPTION_HEADER))
+}
+
 impl<L, E> AnyBackupLocation for L
 where
     L: BackupLocation<Error = E>,
@@ -496,4 +565,102 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(all(feature = "encryption", feature = "password-hashing"))]
+    fn encrypted_backup_restore() -> anyhow::Result<()> {
+        use bonsaidb_core::document::KeyId;
+        use bonsaidb_core::schema::{Collection, Qualified};
+
+        use crate::{BackupEncryption, Error};
+
+        let backup_destination = TestDirectory::new("encrypted-backup-restore.bonsaidb.backup");
+        let vault_backup_destination =
+            TestDirectory::new("encrypted-backup-restore-vault.bonsaidb.backup");
+        let passphrase = BackupEncryption::Passphrase("hunter2".into());
+
+        let test_doc = {
+            let database_directory = TestDirectory::new("encrypted-backup-restore.bonsaidb");
+            let storage = Storage::open(
+                StorageConfiguration::new(&database_directory).with_schema::<Basic>()?,
+            )?;
+
+            let db = storage.create_database::<Basic>("basic", false)?;
+            let test_doc = db.collection::<Basic>().push(&Basic::new("somevalue"))?;
+            db.set_numeric_key("key1", 1_u64).execute()?;
+
+            storage.backup_encrypted(&backup_destination.0, &passphrase)?;
+            storage.backup_encrypted(
+                &vault_backup_destination.0,
+                &BackupEncryption::Vault(KeyId::Master),
+            )?;
+
+            test_doc
+        };
+
+        // The stored document must not contain its contents in plain text.
+        let stored = std::fs::read_dir(&backup_destination.0)?
+            .flat_map(|schema| std::fs::read_dir(schema.unwrap().path().join("basic")))
+            .flatten()
+            .flat_map(|container| std::fs::read_dir(container.unwrap().path()).unwrap())
+            .map(|object| std::fs::read(object.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        assert!(!stored.is_empty());
+        assert!(stored
+            .iter()
+            .all(|object| !object.windows(9).any(|window| window == b"somevalue")));
+
+        let database_directory = TestDirectory::new("encrypted-backup-restore.bonsaidb");
+        let restored_storage =
+            Storage::open(StorageConfiguration::new(&database_directory).with_schema::<Basic>()?)?;
+
+        // Restoring without the correct encryption fails without restoring
+        // anything.
+        assert!(restored_storage.restore(&backup_destination.0).is_err());
+        assert!(restored_storage
+            .restore_encrypted(
+                &backup_destination.0,
+                &BackupEncryption::Passphrase("hunter3".into())
+            )
+            .is_err());
+        assert!(restored_storage
+            .restore_encrypted(
+                &backup_destination.0,
+                &BackupEncryption::Vault(KeyId::Master)
+            )
+            .is_err());
+        // The vault of this storage can't decrypt the backup's key.
+        assert!(restored_storage
+            .restore_encrypted(
+                &vault_backup_destination.0,
+                &BackupEncryption::Vault(KeyId::Master)
+            )
+            .is_err());
+        assert!(restored_storage.database::<Basic>("basic").is_err());
+
+        restored_storage.restore_encrypted(&backup_destination.0, &passphrase)?;
+        let db = restored_storage.database::<Basic>("basic")?;
+        let doc = Basic::get(&test_doc.id, &db)?.expect("Backed up document.not found");
+        assert_eq!(doc.contents.value, "somevalue");
+        assert_eq!(db.get_key("key1").into_u64()?, Some(1));
+
+        // Removing an object from the backup is detected using the manifest.
+        let document_path = std::fs::read_dir(&backup_destination.0)?
+            .map(|schema| schema.unwrap().path().join("basic"))
+            .find(|database| database.exists())
+            .expect("database not found in backup")
+            .join(Basic::collection_name().encoded())
+            .join(test_doc.id.to_string());
+        std::fs::remove_file(document_path)?;
+        let database_directory = TestDirectory::new("encrypted-backup-restore-removed.bonsaidb");
+        let restored_storage =
+            Storage::open(StorageConfiguration::new(&database_directory).with_schema::<Basic>()?)?;
+        assert!(matches!(
+            restored_storage.restore_encrypted(&backup_destination.0, &passphrase),
+            Err(Error::BackupEncryption(_))
+        ));
+        assert!(restored_storage.database::<Basic>("basic").is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,412 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use bonsaidb_core::connection::HasSession;
+#[cfg(feature = "password-hashing")]
+use bonsaidb_core::connection::SensitiveString;
+use bonsaidb_core::document::KeyId;
+use bonsaidb_core::schema::SchemaName;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{AnyBackupLocation, ENCRYPTION_CONTAINER, ENCRYPTION_HEADER, ENCRYPTION_MANIFEST};
+use crate::database::DatabaseNonBlocking;
+use crate::{Error, Storage};
+
+/// The largest Argon2 memory cost, in KiB, accepted from a backup's header.
+/// The parameters used to derive a passphrase's key are read from the backup
+/// being restored, so these limits prevent a tampered backup from requiring an
+/// excessive amount of memory or time to restore.
+#[cfg(feature = "password-hashing")]
+const MAXIMUM_MEMORY_COST: u32 = 1024 * 1024;
+/// The largest Argon2 time cost accepted from a backup's header.
+#[cfg(feature = "password-hashing")]
+const MAXIMUM_TIME_COST: u32 = 16;
+/// The largest Argon2 parallelism accepted from a backup's header.
+#[cfg(feature = "password-hashing")]
+const MAXIMUM_PARALLELISM: u32 = 16;
+
+/// The names of the objects stored for a database, grouped by container.
+type Manifest = BTreeMap<String, BTreeSet<String>>;
+
+/// How to encrypt the contents of a backup.
+///
+/// Each backup is encrypted with a randomly generated key, which is stored
+/// alongside each database in the backup after being encrypted by the option
+/// chosen here. Documents and key-value entries are encrypted using
+/// `XChaCha20Poly1305`, and restoring verifies that each object is stored under
+/// the same name it was backed up with. An encrypted manifest of every object
+/// stored is included in the backup, which allows restoring to detect objects
+/// that have been removed. The names of stored objects, such as document ids
+/// and key-value keys, are not encrypted.
+#[derive(Debug, Clone)]
+pub enum BackupEncryption {
+    /// Protects the backup's key using the vault key `KeyId`. The backup can
+    /// only be restored by a storage with access to the same vault keys, such
+    /// as the storage that created the backup. When restoring, the key
+    /// recorded in the backup is used.
+    Vault(KeyId),
+    /// Protects the backup's key using a key derived from a passphrase using
+    /// Argon2id. The backup can be restored by any storage when given the same
+    /// passphrase.
+    #[cfg(feature = "password-hashing")]
+    Passphrase(SensitiveString),
+}
+
+#[derive(Serialize, Deserialize)]
+enum BackupHeader {
+    Vault {
+        sealed_key: Vec<u8>,
+    },
+    Passphrase {
+        salt: [u8; 16],
+        memory_cost: u32,
+        time_cost: u32,
+        parallelism: u32,
+        sealed_key: Vec<u8>,
+    },
+}
+
+impl Storage {
+    /// Stores a copy of all data in this instance to `location`, encrypting
+    /// the contents of the backup as specified by `encryption`. Use
+    /// [`Storage::restore_encrypted()`] to restore the backup.
+    pub fn backup_encrypted<L: AnyBackupLocation>(
+        &self,
+        location: &L,
+        encryption: &BackupEncryption,
+    ) -> Result<(), Error> {
+        let key = Zeroizing::new(thread_rng().gen::<[u8; 32]>());
+        let header = bincode::serialize(&self.seal_backup_key(&key, encryption)?)?;
+        let encrypted = EncryptedBackupLocation::new(location, &key[..]);
+
+        for database in self.backup_databases()? {
+            let schema = &database.schematic().name;
+            location.store(
+                schema,
+                database.name(),
+                ENCRYPTION_CONTAINER,
+                ENCRYPTION_HEADER,
+                &header,
+            )?;
+            Self::backup_database(&database, &encrypted)?;
+            let manifest = std::mem::take(&mut *encrypted.stored.lock());
+            encrypted.store(
+                schema,
+                database.name(),
+                ENCRYPTION_CONTAINER,
+                ENCRYPTION_MANIFEST,
+                &bincode::serialize(&manifest)?,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Restores all data from a backup stored using
+    /// [`Storage::backup_encrypted()`]. Before any data is restored, every
+    /// database in the backup is checked to be encrypted in a way that can be
+    /// decrypted using `encryption`, and to contain every object listed in its
+    /// manifest.
+    pub fn restore_encrypted<L: AnyBackupLocation>(
+        &self,
+        location: &L,
+        encryption: &BackupEncryption,
+    ) -> Result<(), Error> {
+        let mut databases = Vec::new();
+        for (schema, database) in Self::stored_databases(location)? {
+            if !super::is_encrypted(location, &schema, &database)? {
+                return Err(Error::BackupEncryption(format!(
+                    "database {database} in the backup is not encrypted"
+                )));
+            }
+            let header =
+                location.load(&schema, &database, ENCRYPTION_CONTAINER, ENCRYPTION_HEADER)?;
+            let key = self.open_backup_key(&bincode::deserialize(&header)?, encryption)?;
+            let encrypted = EncryptedBackupLocation::new(location, &key[..]);
+            encrypted.verify_manifest(&schema, &database)?;
+            databases.push((schema, database, encrypted));
+        }
+
+        for (schema, database, encrypted) in databases {
+            let database = self.restore_database_named(&database, schema)?;
+            Self::restore_database(&database, &encrypted)?;
+        }
+
+        Ok(())
+    }
+
+    fn seal_backup_key(
+        &self,
+        key: &[u8; 32],
+        encryption: &BackupEncryption,
+    ) -> Result<BackupHeader, Error> {
+        match encryption {
+            BackupEncryption::Vault(key_id) => Ok(BackupHeader::Vault {
+                sealed_key: self.vault().encrypt_payload(
+                    key_id,
+                    key,
+                    self.session().map(|session| &session.permissions),
+                )?,
+            }),
+            #[cfg(feature = "password-hashing")]
+            BackupEncryption::Passphrase(passphrase) => {
+                let salt: [u8; 16] = thread_rng().gen();
+                let params = argon2::Params::default();
+                let passphrase_key = derive_passphrase_key(passphrase, &salt, &params)?;
+                let nonce = thread_rng().gen::<[u8; 24]>();
+                let mut sealed_key = nonce.to_vec();
+                sealed_key.extend(
+                    XChaCha20Poly1305::new(GenericArray::from_slice(&passphrase_key[..]))
+                        .encrypt(
+                            GenericArray::from_slice(&nonce),
+                            Payload {
+                                msg: key,
+                                aad: &salt,
+                            },
+                        )
+                        .map_err(|_| {
+                            Error::BackupEncryption(String::from("error encrypting backup key"))
+                        })?,
+                );
+                Ok(BackupHeader::Passphrase {
+                    salt,
+                    memory_cost: params.m_cost(),
+                    time_cost: params.t_cost(),
+                    parallelism: params.p_cost(),
+                    sealed_key,
+                })
+            }
+        }
+    }
+
+    fn open_backup_key(
+        &self,
+        header: &BackupHeader,
+        encryption: &BackupEncryption,
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let key = match (header, encryption) {
+            (BackupHeader::Vault { sealed_key }, BackupEncryption::Vault(_)) => {
+                Zeroizing::new(self.vault().decrypt_payload(
+                    sealed_key,
+                    self.session().map(|session| &session.permissions),
+                )?)
+            }
+            #[cfg(feature = "password-hashing")]
+            (
+                BackupHeader::Passphrase {
+                    salt,
+                    memory_cost,
+                    time_cost,
+                    parallelism,
+                    sealed_key,
+                },
+                BackupEncryption::Passphrase(passphrase),
+            ) => {
+                if *memory_cost > MAXIMUM_MEMORY_COST
+                    || *time_cost > MAXIMUM_TIME_COST
+                    || *parallelism > MAXIMUM_PARALLELISM
+                {
+                    return Err(Error::BackupEncryption(String::from(
+                        "backup key derivation parameters exceed the allowed limits",
+                    )));
+                }
+                let params = argon2::Params::new(*memory_cost, *time_cost, *parallelism, None)?;
+                let passphrase_key = derive_passphrase_key(passphrase, salt, &params)?;
+                if sealed_key.len() < 24 {
+                    return Err(Error::BackupEncryption(String::from(
+                        "backup key is corrupt",
+                    )));
+                }
+                let (nonce, sealed_key) = sealed_key.split_at(24);
+                Zeroizing::new(
+                    XChaCha20Poly1305::new(GenericArray::from_slice(&passphrase_key[..]))
+                        .decrypt(
+                            GenericArray::from_slice(nonce),
+                            Payload {
+                                msg: sealed_key,
+                                aad: salt,
+                            },
+                        )
+                        .map_err(|_| {
+                            Error::BackupEncryption(String::from("incorrect backup passphrase"))
+                        })?,
+                )
+            }
+            #[cfg(feature = "password-hashing")]
+            (BackupHeader::Vault { .. }, _) => {
+                return Err(Error::BackupEncryption(String::from(
+                    "backup was encrypted using the vault",
+                )))
+            }
+            (BackupHeader::Passphrase { .. }, _) => {
+                return Err(Error::BackupEncryption(String::from(
+                    "backup was encrypted using a passphrase",
+                )))
+            }
+        };
+
+        if key.len() == 32 {
+            Ok(key)
+        } else {
+            Err(Error::BackupEncryption(String::from(
+                "backup key is corrupt",
+            )))
+        }
+    }
+}
+
+#[cfg(feature = "password-hashing")]
+fn derive_passphrase_key(
+    passphrase: &SensitiveString,
+    salt: &[u8],
+    params: &argon2::Params,
+) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let mut key = Zeroizing::new([0; 32]);
+    argon2::Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        params.clone(),
+    )
+    .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])?;
+    Ok(key)
+}
+
+/// Encrypts objects stored in `location`, and decrypts objects loaded from
+/// it.
+struct EncryptedBackupLocation<'a, L> {
+    location: &'a L,
+    cipher: XChaCha20Poly1305,
+    /// The objects stored since the manifest was last taken.
+    stored: Mutex<Manifest>,
+}
+
+impl<'a, L: AnyBackupLocation> EncryptedBackupLocation<'a, L> {
+    fn new(location: &'a L, key: &[u8]) -> Self {
+        Self {
+            location,
+            cipher: XChaCha20Poly1305::new(GenericArray::from_slice(key)),
+            stored: Mutex::default(),
+        }
+    }
+
+    /// Verifies that every object listed in the manifest of `database_name` is
+    /// present in the backup.
+    fn verify_manifest(&self, schema: &SchemaName, database_name: &str) -> Result<(), Error> {
+        let manifest = self.load(
+            schema,
+            database_name,
+            ENCRYPTION_CONTAINER,
+            ENCRYPTION_MANIFEST,
+        )?;
+        let manifest = bincode::deserialize::<Manifest>(&manifest)?;
+        for (container, names) in manifest {
+            let stored = self
+                .location
+                .list_stored(schema, database_name, &container)?
+                .into_iter()
+                .collect::<HashSet<_>>();
+            if let Some(missing) = names.iter().find(|name| !stored.contains(*name)) {
+                return Err(Error::BackupEncryption(format!(
+                    "{database_name}/{container}/{missing} is missing from the backup"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Binds an encrypted object to the location it was stored at, preventing
+/// objects from being swapped or renamed without detection.
+fn associated_data(
+    schema: &SchemaName,
+    database_name: &str,
+    container: &str,
+    name: &str,
+) -> Vec<u8> {
+    format!("{schema}/{database_name}/{container}/{name}").into_bytes()
+}
+
+impl<'a, L: AnyBackupLocation> AnyBackupLocation for EncryptedBackupLocation<'a, L> {
+    fn store(
+        &self,
+        schema: &SchemaName,
+        database_name: &str,
+        container: &str,
+        name: &str,
+        object: &[u8],
+    ) -> Result<(), Error> {
+        let nonce = thread_rng().gen::<[u8; 24]>();
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(
+            self.cipher
+                .encrypt(
+                    GenericArray::from_slice(&nonce),
+                    Payload {
+                        msg: object,
+                        aad: &associated_data(schema, database_name, container, name),
+                    },
+                )
+                .map_err(|_| Error::BackupEncryption(format!("error encrypting {name}")))?,
+        );
+        self.location
+            .store(schema, database_name, container, name, &encrypted)?;
+        if container != ENCRYPTION_CONTAINER {
+            self.stored
+                .lock()
+                .entry(container.to_string())
+                .or_default()
+                .insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    fn list_schemas(&self) -> Result<Vec<SchemaName>, Error> {
+        self.location.list_schemas()
+    }
+
+    fn list_databases(&self, schema: &SchemaName) -> Result<Vec<String>, Error> {
+        self.location.list_databases(schema)
+    }
+
+    fn list_stored(
+        &self,
+        schema: &SchemaName,
+        database_name: &str,
+        container: &str,
+    ) -> Result<Vec<String>, Error> {
+        self.location.list_stored(schema, database_name, container)
+    }
+
+    fn load(
+        &self,
+        schema: &SchemaName,
+        database_name: &str,
+        container: &str,
+        name: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let encrypted = self.location.load(schema, database_name, container, name)?;
+        let invalid = || {
+            Error::BackupEncryption(format!(
+                "{database_name}/{container}/{name} failed to decrypt"
+            ))
+        };
+        if encrypted.len() < 24 {
+            return Err(invalid());
+        }
+        let (nonce, encrypted) = encrypted.split_at(24);
+        self.cipher
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: encrypted,
+                    aad: &associated_data(schema, database_name, container, name),
+                },
+            )
+            .map_err(|_| invalid())
+    }
+}
@@ -1,10 +1,16 @@
 use std::collections::hash_map::Entry;
+use std::collections::HashSet;
 
-use bonsaidb_core::connection::SessionId;
-use bonsaidb_core::pubsub::Receiver;
+use bonsaidb_core::connection::{HasSession, SessionId};
+use bonsaidb_core::permissions::bonsai::{
+    pubsub_topic_resource_name, BonsaiAction, DatabaseAction, PubSubAction,
+};
+use bonsaidb_core::pubsub::{
+    pattern_delivery_payload, pattern_delivery_topic, Receiver, TopicPattern,
+};
 
 use crate::storage::SessionSubscriber;
-use crate::{Database, Subscriber};
+use crate::{Database, DatabaseNonBlocking, Subscriber};
 
 impl crate::storage::StorageInstance {
     pub(crate) fn register_subscriber(
@@ -22,7 +28,10 @@ impl crate::storage::StorageInstance {
             if matches!(entry, Entry::Vacant(_)) {
                 entry.or_insert(SessionSubscriber {
                     session_id,
+                    session: database.session().cloned(),
+                    database: database.name().to_string(),
                     subscriber: subscriber.clone(),
+                    patterns: HashSet::new(),
                 });
                 break id;
             }
@@ -40,4 +49,73 @@ impl crate::storage::StorageInstance {
         let mut data = self.data.subscribers.write();
         data.unregister(subscriber.id);
     }
+
+    pub(crate) fn subscribe_to_pattern(&self, subscriber_id: u64, pattern: TopicPattern) {
+        let mut data = self.data.subscribers.write();
+        let data = &mut *data;
+        if let Some(registered) = data.subscribers.get_mut(&subscriber_id) {
+            if registered.patterns.is_empty() {
+                // Messages matching a pattern are delivered through a topic
+                // unique to this subscriber.
+                registered
+                    .subscriber
+                    .subscribe_to_raw(pattern_delivery_topic(subscriber_id));
+                data.pattern_subscribers
+                    .entry(registered.database.clone())
+                    .or_default()
+                    .insert(subscriber_id);
+            }
+            registered.patterns.insert(pattern);
+        }
+    }
+
+    pub(crate) fn unsubscribe_from_pattern(&self, subscriber_id: u64, pattern: &TopicPattern) {
+        let mut data = self.data.subscribers.write();
+        if let Some(registered) = data.subscribers.get_mut(&subscriber_id) {
+            if registered.patterns.remove(pattern) && registered.patterns.is_empty() {
+                registered
+                    .subscriber
+                    .unsubscribe_from_raw(&pattern_delivery_topic(subscriber_id));
+                let database = registered.database.clone();
+                data.remove_pattern_subscriber(&database, subscriber_id);
+            }
+        }
+    }
+
+    /// Delivers `payload` to each subscriber with a pattern that matches
+    /// `topic` within `database`. Subscribers whose session isn't allowed to
+    /// subscribe to `topic` directly are skipped.
+    pub(crate) fn publish_to_patterns(&self, database: &str, topic: &[u8], payload: &[u8]) {
+        let data = self.data.subscribers.read();
+        if let Some(subscriber_ids) = data.pattern_subscribers.get(database) {
+            let Ok(topic_name) = pot::from_slice::<String>(topic) else {
+                // Patterns can only match topics serialized from strings.
+                return;
+            };
+            let resource_name = pubsub_topic_resource_name(database, topic);
+            let action = BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::SubscribeTo));
+            for subscriber_id in subscriber_ids {
+                let matches = data
+                    .subscribers
+                    .get(subscriber_id)
+                    .map_or(false, |subscriber| {
+                        subscriber
+                            .patterns
+                            .iter()
+                            .any(|pattern| pattern.matches(&topic_name))
+                            && subscriber.session.as_ref().map_or(true, |session| {
+                                session
+                                    .check_permission(resource_name.as_ref(), &action)
+                                    .is_ok()
+                            })
+                    });
+                if matches {
+                    self.relay().publish_raw(
+                        pattern_delivery_topic(*subscriber_id),
+                        pattern_delivery_payload(topic, payload),
+                    );
+                }
+            }
+        }
+    }
 }
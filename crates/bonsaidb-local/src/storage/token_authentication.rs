@@ -1,3 +1,4 @@
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use bonsaidb_core::admin::{AuthenticationToken, Role, User};
@@ -32,6 +33,9 @@ impl super::StorageInstance {
         }
         let token = AuthenticationToken::get(&id, admin)?
             .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        if token.contents.is_expired(TimestampAsNanoseconds::now()) {
+            return Err(bonsaidb_core::Error::InvalidCredentials);
+        }
         AuthenticationToken::check_request_time(
             request_time,
             request_time_check,
@@ -56,10 +60,13 @@ impl super::StorageInstance {
                 server_timestamp: TimestampAsNanoseconds::now(),
             },
             permissions: Permissions::default(), /* This session will have no permissions until it finishes token authentication */
+            scope: None,
         };
         let authentication = Arc::new(AuthenticatedSession {
             storage: Arc::downgrade(&self.data),
             session: Mutex::new(session.clone()),
+            created_at: TimestampAsNanoseconds::now(),
+            revoked: AtomicBool::new(false),
         });
         sessions.sessions.insert(session_id, authentication.clone());
 
@@ -92,28 +99,41 @@ impl super::StorageInstance {
                 nonce,
                 server_timestamp,
             } => {
-                let token = AuthenticationToken::get(id, admin)?
+                let mut token = AuthenticationToken::get(id, admin)?
                     .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+                let now = TimestampAsNanoseconds::now();
+                if token.contents.is_expired(now) {
+                    return Err(bonsaidb_core::Error::InvalidCredentials);
+                }
                 token
                     .contents
                     .validate_challenge(*algorithm, *server_timestamp, nonce, hash)?;
-                match token.contents.identity {
+                let scope = token.contents.scope.clone().map(Permissions::from);
+                let authenticated = match token.contents.identity {
                     IdentityId::User(id) => {
                         let user = User::get(&id, admin)?
                             .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
-                        self.assume_user(user, admin)
+                        self.assume_user(user, scope, admin)
                     }
                     IdentityId::Role(id) => {
                         let role = Role::get(&id, admin)?
                             .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
-                        self.assume_role(role, admin)
+                        self.assume_role(role, scope, admin)
                     }
                     _ => Err(bonsaidb_core::Error::InvalidCredentials),
+                }?;
+
+                // Recording the time the token was used is best-effort: if
+                // the token was used concurrently, the other update wins.
+                token.contents.last_used_at = Some(now);
+                match token.update(admin) {
+                    Ok(()) | Err(bonsaidb_core::Error::DocumentConflict(..)) => {}
+                    Err(other) => return Err(other),
                 }
+
+                Ok(authenticated)
             }
-            SessionAuthentication::None | SessionAuthentication::Identity(_) => {
-                Err(bonsaidb_core::Error::InvalidCredentials)
-            }
+            _ => Err(bonsaidb_core::Error::InvalidCredentials),
         }
     }
 }
@@ -0,0 +1,193 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bonsaidb_core::admin::{TotpSecret, User, UserTotp};
+use bonsaidb_core::connection::{SensitiveString, Session, SessionAuthentication, SessionId};
+use bonsaidb_core::document::CollectionDocument;
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
+use bonsaidb_core::permissions::Permissions;
+use bonsaidb_core::schema::{Nameable, NamedCollection, SerializedCollection};
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+
+use crate::storage::{AuthenticatedSession, AuthenticatedSessions};
+use crate::{Database, Storage};
+
+/// The amount of time a session created by verifying a user's password has to
+/// be completed using a time-based one-time password.
+const TOTP_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+impl super::StorageInstance {
+    pub(super) fn enroll_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        user: U,
+        admin: &Database,
+    ) -> Result<TotpSecret, bonsaidb_core::Error> {
+        let mut user = User::load(user, admin)?.ok_or(bonsaidb_core::Error::UserNotFound)?;
+        // Replacing a confirmed enrollment would allow bypassing the second
+        // factor, so it must be explicitly removed first.
+        if Self::requires_totp(&user) {
+            return Err(bonsaidb_core::Error::TotpAlreadyEnrolled);
+        }
+        // RFC 4226 recommends a 160-bit secret when using HMAC-SHA1.
+        let secret = TotpSecret::from(thread_rng().gen::<[u8; 20]>().to_vec());
+        user.contents.totp = Some(UserTotp {
+            secret: secret.clone(),
+            confirmed: false,
+            last_used_step: None,
+        });
+        user.update(admin)?;
+        Ok(secret)
+    }
+
+    pub(super) fn confirm_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        user: U,
+        code: &SensitiveString,
+        admin: &Database,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let mut user = User::load(user, admin)?.ok_or(bonsaidb_core::Error::UserNotFound)?;
+        Self::verify_totp(&mut user, code, false)?;
+        user.update(admin)
+    }
+
+    pub(super) fn remove_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        user: U,
+        admin: &Database,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let mut user = User::load(user, admin)?.ok_or(bonsaidb_core::Error::UserNotFound)?;
+        if user.contents.totp.take().is_some() {
+            user.update(admin)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if password authentication for `user` must be completed
+    /// with a time-based one-time password.
+    pub(super) fn requires_totp(user: &CollectionDocument<User>) -> bool {
+        user.contents
+            .totp
+            .as_ref()
+            .map_or(false, |totp| totp.confirmed)
+    }
+
+    pub(super) fn begin_totp_authentication(&self, user: &CollectionDocument<User>) -> Storage {
+        // The password has been verified, but the session has no permissions
+        // until it is completed using a code. The process of finishing TOTP
+        // authentication will remove this session, and sessions that aren't
+        // completed within `TOTP_CHALLENGE_TIMEOUT` are removed the next time
+        // a password is verified.
+        let now = TimestampAsNanoseconds::now();
+        let mut sessions = self.data.sessions.write();
+        let expired = Self::remove_expired_totp_challenges(&mut sessions, now);
+        sessions.last_session_id += 1;
+        let session_id = SessionId(sessions.last_session_id);
+        let session = Session {
+            id: Some(session_id),
+            authentication: SessionAuthentication::TotpChallenge {
+                user_id: user.header.id,
+            },
+            permissions: Permissions::default(),
+            scope: None,
+        };
+        let authentication = Arc::new(AuthenticatedSession {
+            storage: Arc::downgrade(&self.data),
+            session: Mutex::new(session.clone()),
+            created_at: now,
+            revoked: AtomicBool::new(false),
+        });
+        sessions.sessions.insert(session_id, authentication.clone());
+        sessions.totp_challenges.push_back(session_id);
+        drop(sessions);
+        // Dropping the last reference to a session acquires the sessions lock.
+        drop(expired);
+
+        Storage {
+            instance: self.clone(),
+            authentication: Some(authentication),
+            effective_session: Some(Arc::new(session)),
+        }
+    }
+
+    pub(super) fn finish_totp_authentication(
+        &self,
+        session_id: SessionId,
+        code: &SensitiveString,
+        admin: &Database,
+    ) -> Result<Storage, bonsaidb_core::Error> {
+        // Remove the temporary session so that each verified password only
+        // permits a single attempt at entering a code.
+        let session = {
+            let mut sessions = self.data.sessions.write();
+            sessions
+                .sessions
+                .remove(&session_id)
+                .ok_or(bonsaidb_core::Error::InvalidCredentials)?
+        };
+        if totp_challenge_expired(&session, TimestampAsNanoseconds::now()) {
+            return Err(bonsaidb_core::Error::InvalidCredentials);
+        }
+        let session = session.session.lock();
+        match &session.authentication {
+            SessionAuthentication::TotpChallenge { user_id } => {
+                let mut user =
+                    User::get(user_id, admin)?.ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+                Self::verify_totp(&mut user, code, true)?;
+                // Recording the accepted time step must succeed, otherwise
+                // the same code could be used again.
+                user.update(admin)?;
+                self.assume_user(user, None, admin)
+            }
+            _ => Err(bonsaidb_core::Error::InvalidCredentials),
+        }
+    }
+
+    /// Removes the sessions pending a time-based one-time password that have
+    /// expired, returning them so that they can be dropped once the sessions
+    /// lock has been released.
+    fn remove_expired_totp_challenges(
+        sessions: &mut AuthenticatedSessions,
+        now: TimestampAsNanoseconds,
+    ) -> Vec<Arc<AuthenticatedSession>> {
+        let mut expired = Vec::new();
+        while let Some(session_id) = sessions.totp_challenges.front().copied() {
+            // Sessions that were already completed or revoked are skipped.
+            if let Some(session) = sessions.sessions.get(&session_id) {
+                if !totp_challenge_expired(session, now) {
+                    break;
+                }
+                expired.extend(sessions.sessions.remove(&session_id));
+            }
+            sessions.totp_challenges.pop_front();
+        }
+        expired
+    }
+
+    /// Verifies `code` against the user's enrollment, marking the enrollment
+    /// confirmed and recording the accepted time step.
+    fn verify_totp(
+        user: &mut CollectionDocument<User>,
+        code: &SensitiveString,
+        require_confirmed: bool,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let totp = user
+            .contents
+            .totp
+            .as_mut()
+            .filter(|totp| totp.confirmed || !require_confirmed)
+            .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        let step = totp
+            .secret
+            .verify(code, totp.last_used_step)
+            .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        totp.confirmed = true;
+        totp.last_used_step = Some(step);
+        Ok(())
+    }
+}
+
+fn totp_challenge_expired(session: &AuthenticatedSession, now: TimestampAsNanoseconds) -> bool {
+    matches!(
+        now.duration_since(&session.created_at),
+        Ok(Some(elapsed)) if elapsed >= TOTP_CHALLENGE_TIMEOUT
+    )
+}
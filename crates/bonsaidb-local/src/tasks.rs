@@ -12,6 +12,8 @@ use crate::database::Database;
 use crate::tasks::compactor::Compactor;
 use crate::tasks::handle::Handle;
 use crate::tasks::manager::Manager;
+#[cfg(feature = "encryption")]
+use crate::tasks::reencryptor::{Reencryption, Reencryptor};
 use crate::views::integrity_scanner::{IntegrityScan, IntegrityScanner, OptionalViewMapHandle};
 use crate::views::mapper::{Map, Mapper};
 use crate::Error;
@@ -25,8 +27,12 @@ mod traits;
 pub use self::traits::{Job, Keyed};
 
 mod compactor;
+#[cfg(feature = "encryption")]
+mod reencryptor;
 mod task;
 
+#[cfg(feature = "encryption")]
+pub use self::reencryptor::{KeyRotations, PendingKeyRotation};
 pub use task::Task;
 
 #[derive(Debug, Clone)]
@@ -228,4 +234,31 @@ impl TaskManager {
             .lookup_or_enqueue(Compactor::database(database))
             .receive()??)
     }
+
+    #[cfg(feature = "encryption")]
+    pub fn spawn_reencryption(
+        &self,
+        database: &Database,
+        collection: CollectionName,
+    ) -> Handle<(), Error> {
+        self.jobs.lookup_or_enqueue(Reencryptor {
+            database: database.clone(),
+            reencryption: Reencryption {
+                database: database.data.name.clone(),
+                collection,
+            },
+        })
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn spawn_pending_reencryptions(&self, database: &Database) {
+        for collection in database
+            .storage
+            .instance
+            .key_rotations()
+            .pending_collections(&database.data.name)
+        {
+            self.spawn_reencryption(database, collection);
+        }
+    }
 }
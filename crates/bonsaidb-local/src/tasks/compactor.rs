@@ -89,6 +89,10 @@ impl Job for Compactor {
     fn execute(&mut self) -> Result<Self::Output, Error> {
         self.compaction.target.clone().compact(&self.database)
     }
+
+    fn kind(&self) -> &'static str {
+        "compaction"
+    }
 }
 
 impl Keyed<Task> for Compactor {
@@ -97,7 +101,7 @@ impl Keyed<Task> for Compactor {
     }
 }
 
-fn gather_collection_trees(
+pub(super) fn gather_collection_trees(
     database: &Database,
     collection: &CollectionName,
     trees: &mut Vec<Target>,
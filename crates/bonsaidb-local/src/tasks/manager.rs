@@ -4,6 +4,7 @@ use std::sync::Arc;
 use derive_where::derive_where;
 use parking_lot::RwLock;
 
+use crate::metrics::TaskMetrics;
 use crate::tasks::handle::{Handle, Id};
 use crate::tasks::traits::Executable;
 use crate::tasks::{Job, Keyed};
@@ -21,6 +22,7 @@ mod tests;
 pub struct Manager<Key = ()> {
     // #[derive_where(default)]
     pub(crate) jobs: Arc<RwLock<jobs::Jobs<Key>>>,
+    pub(crate) metrics: Arc<TaskMetrics>,
 }
 
 impl<Key> Manager<Key>
@@ -63,6 +63,7 @@ where
     ) -> Handle<J::Output, J::Error> {
         self.last_task_id = self.last_task_id.wrapping_add(1);
         let id = Id(self.last_task_id);
+        manager.metrics.queued.get(job.kind()).increment();
         self.queuer
             .send(Box::new(ManagedJob {
                 id,
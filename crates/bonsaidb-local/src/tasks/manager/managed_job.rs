@@ -1,4 +1,5 @@
 use std::fmt::Debug;
+use std::time::Instant;
 
 use crate::tasks::handle::Id;
 use crate::tasks::manager::Manager;
@@ -19,8 +20,22 @@ where
     Key: Clone + std::hash::Hash + Eq + Send + Sync + Debug + 'static,
 {
     fn execute(&mut self) {
+        let kind = self.job.kind();
+        let metrics = &self.manager.metrics;
+        metrics.queued.get(kind).decrement();
+        let running = metrics.running.get(kind);
+        running.increment();
+        let started_at = Instant::now();
+
         let result = self.job.execute();
 
+        metrics.duration.get(kind).observe(started_at.elapsed());
+        running.decrement();
+        metrics.completed.get(kind).increment();
+        if result.is_err() {
+            metrics.errors.get(kind).increment();
+        }
+
         self.manager
             .job_completed(self.id, self.key.as_ref(), result);
     }
@@ -0,0 +1,293 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bonsaidb_core::connection::Connection;
+use bonsaidb_core::document::KeyId;
+use bonsaidb_core::schema::CollectionName;
+use nebari::tree::{Root, Unversioned, Versioned};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DatabaseNonBlocking;
+use crate::tasks::compactor::{gather_collection_trees, Target};
+use crate::tasks::{Job, Keyed, Task};
+use crate::vault::write_atomically;
+use crate::{Database, Error};
+
+/// Re-encrypts a collection's trees after its encryption key has been rotated.
+#[derive(Debug)]
+pub struct Reencryptor {
+    pub database: Database,
+    pub reencryption: Reencryption,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Reencryption {
+    pub database: Arc<Cow<'static, str>>,
+    pub collection: CollectionName,
+}
+
+impl Job for Reencryptor {
+    type Error = Error;
+    type Output = ();
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn execute(&mut self) -> Result<Self::Output, Error> {
+        let storage = self.database.storage();
+        let rotations = storage.instance.key_rotations();
+        let collection = &self.reencryption.collection;
+        while let Some(tree) = rotations.next_tree(self.database.name(), collection) {
+            match &tree {
+                RotationTree::Versioned(name) => {
+                    reencrypt_tree::<Versioned>(&self.database, collection, name.clone())?;
+                }
+                RotationTree::Unversioned(name) => {
+                    reencrypt_tree::<Unversioned>(&self.database, collection, name.clone())?;
+                }
+            }
+            rotations.complete_tree(self.database.name(), collection, &tree)?;
+        }
+
+        Ok(())
+    }
+
+    fn kind(&self) -> &'static str {
+        "reencryption"
+    }
+}
+
+impl Keyed<Task> for Reencryptor {
+    fn key(&self) -> Task {
+        Task::Reencryption(self.reencryption.clone())
+    }
+}
+
+fn reencrypt_tree<R: Root>(
+    database: &Database,
+    collection: &CollectionName,
+    name: String,
+) -> Result<(), Error> {
+    // Compacting rewrites every chunk of the tree, which encrypts it using the
+    // current version of the collection's key.
+    let tree = database
+        .roots()
+        .tree(database.collection_tree::<R, _>(collection, name)?)?;
+    tree.compact()?;
+    Ok(())
+}
+
+/// The collections whose trees still need to be re-encrypted after their
+/// encryption key was rotated. Progress is persisted after each tree, allowing
+/// re-encryption to resume the next time the database is opened.
+#[derive(Debug)]
+pub struct KeyRotations {
+    path: PathBuf,
+    state: Mutex<KeyRotationState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct KeyRotationState {
+    pending: Vec<KeyRotation>,
+    /// The collections whose re-encryption has finished, which is used to
+    /// determine which versions of a key are no longer in use.
+    reencrypted: Vec<ReencryptedCollection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct KeyRotation {
+    database: String,
+    collection: CollectionName,
+    key: KeyId,
+    /// The version of `key` that was current when re-encryption began. Once
+    /// every tree has been re-encrypted, no data in the collection uses an
+    /// older version.
+    version: u32,
+    remaining_trees: Vec<RotationTree>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ReencryptedCollection {
+    database: String,
+    collection: CollectionName,
+    key: KeyId,
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+enum RotationTree {
+    Versioned(String),
+    Unversioned(String),
+}
+
+/// The progress of re-encrypting a collection after its encryption key was
+/// rotated.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PendingKeyRotation {
+    /// The name of the database containing the collection.
+    pub database: String,
+    /// The collection being re-encrypted.
+    pub collection: CollectionName,
+    /// The number of trees that still need to be re-encrypted.
+    pub remaining_trees: usize,
+}
+
+impl KeyRotations {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let state = if path.exists() {
+            let contents = std::fs::read(&path)?;
+            bincode::deserialize(&contents)?
+        } else {
+            KeyRotationState::default()
+        };
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn save(path: &Path, state: &KeyRotationState) -> Result<(), Error> {
+        let contents = bincode::serialize(state)?;
+        write_atomically(path, &contents)?;
+        Ok(())
+    }
+
+    /// Records that every tree in `collection` needs to be re-encrypted,
+    /// restarting any re-encryption already in progress.
+    pub fn begin(&self, database: &Database, collection: &CollectionName) -> Result<(), Error> {
+        let Some(key) = database.collection_encryption_key(collection).cloned() else {
+            return Err(Error::other(
+                "bonsaidb-local",
+                format!("collection {collection} is not encrypted"),
+            ));
+        };
+        let version = database.storage().vault().current_version(&key)?;
+        let mut targets = Vec::new();
+        gather_collection_trees(database, collection, &mut targets);
+        let remaining_trees = targets
+            .into_iter()
+            .map(|target| match target {
+                Target::VersionedTree(name) => RotationTree::Versioned(name),
+                Target::UnversionedTree(name) => RotationTree::Unversioned(name),
+                _ => unreachable!("collections only contain trees"),
+            })
+            .collect();
+
+        let mut state = self.state.lock();
+        state.pending.retain(|rotation| {
+            rotation.database != database.name() || &rotation.collection != collection
+        });
+        state.pending.push(KeyRotation {
+            database: database.name().to_string(),
+            collection: collection.clone(),
+            key,
+            version,
+            remaining_trees,
+        });
+        Self::save(&self.path, &state)
+    }
+
+    fn next_tree(&self, database: &str, collection: &CollectionName) -> Option<RotationTree> {
+        let state = self.state.lock();
+        state
+            .pending
+            .iter()
+            .find(|rotation| rotation.database == database && &rotation.collection == collection)
+            .and_then(|rotation| rotation.remaining_trees.first().cloned())
+    }
+
+    fn complete_tree(
+        &self,
+        database: &str,
+        collection: &CollectionName,
+        tree: &RotationTree,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock();
+        let KeyRotationState {
+            pending,
+            reencrypted,
+        } = &mut *state;
+        for rotation in pending.iter_mut() {
+            if rotation.database == database && &rotation.collection == collection {
+                rotation
+                    .remaining_trees
+                    .retain(|remaining| remaining != tree);
+                if rotation.remaining_trees.is_empty() {
+                    reencrypted.retain(|existing| {
+                        existing.database != database || &existing.collection != collection
+                    });
+                    reencrypted.push(ReencryptedCollection {
+                        database: rotation.database.clone(),
+                        collection: rotation.collection.clone(),
+                        key: rotation.key.clone(),
+                        version: rotation.version,
+                    });
+                }
+            }
+        }
+        pending.retain(|rotation| !rotation.remaining_trees.is_empty());
+        Self::save(&self.path, &state)
+    }
+
+    /// Returns the version of `key` that `collection` in `database` was most
+    /// recently re-encrypted with, if it has been re-encrypted using `key`.
+    pub fn reencrypted_version(
+        &self,
+        database: &str,
+        collection: &CollectionName,
+        key: &KeyId,
+    ) -> Option<u32> {
+        let state = self.state.lock();
+        state
+            .reencrypted
+            .iter()
+            .find(|reencrypted| {
+                reencrypted.database == database
+                    && &reencrypted.collection == collection
+                    && &reencrypted.key == key
+            })
+            .map(|reencrypted| reencrypted.version)
+    }
+
+    /// Returns the collections in `database` that still need to be
+    /// re-encrypted.
+    pub fn pending_collections(&self, database: &str) -> Vec<CollectionName> {
+        let state = self.state.lock();
+        state
+            .pending
+            .iter()
+            .filter(|rotation| rotation.database == database)
+            .map(|rotation| rotation.collection.clone())
+            .collect()
+    }
+
+    /// Stops tracking re-encryption for `database`.
+    pub fn remove_database(&self, database: &str) -> Result<(), Error> {
+        let mut state = self.state.lock();
+        let count = state.pending.len() + state.reencrypted.len();
+        state
+            .pending
+            .retain(|rotation| rotation.database != database);
+        state
+            .reencrypted
+            .retain(|reencrypted| reencrypted.database != database);
+        if state.pending.len() + state.reencrypted.len() == count {
+            Ok(())
+        } else {
+            Self::save(&self.path, &state)
+        }
+    }
+
+    pub fn progress(&self) -> Vec<PendingKeyRotation> {
+        let state = self.state.lock();
+        state
+            .pending
+            .iter()
+            .map(|rotation| PendingKeyRotation {
+                database: rotation.database.clone(),
+                collection: rotation.collection.clone(),
+                remaining_trees: rotation.remaining_trees.len(),
+            })
+            .collect()
+    }
+}
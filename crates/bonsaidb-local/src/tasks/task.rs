@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::sync::Arc;
 
 use crate::tasks::compactor::Compaction;
+#[cfg(feature = "encryption")]
+use crate::tasks::reencryptor::Reencryption;
 use crate::views::integrity_scanner::IntegrityScan;
 use crate::views::mapper::Map;
 
@@ -11,4 +13,6 @@ pub enum Task {
     ViewMap(Map),
     Compaction(Compaction),
     ExpirationLoader(Arc<Cow<'static, str>>),
+    #[cfg(feature = "encryption")]
+    Reencryption(Reencryption),
 }
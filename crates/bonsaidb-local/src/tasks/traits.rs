@@ -9,6 +9,11 @@ pub trait Job: Debug + Send + Sync + 'static {
 
     /// Executes the job and returns the result.
     fn execute(&mut self) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the name of the kind of job this is, used to label metrics.
+    fn kind(&self) -> &'static str {
+        "job"
+    }
 }
 
 /// Defines a background job that has a unique `key`.
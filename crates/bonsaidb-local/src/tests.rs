@@ -266,6 +266,498 @@ fn encryption() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "encryption")]
+fn document_encryption() -> anyhow::Result<()> {
+    use std::borrow::Cow;
+    use std::path::Path;
+
+    use bonsaidb_core::connection::StorageConnection as _;
+    use bonsaidb_core::document::{CollectionDocument, Emit, KeyId};
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, DocumentEncryption, Qualified, Schema, SchemaName,
+        Schematic, SerializedCollection, View, ViewMapResult, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug)]
+    struct TenantSchema;
+
+    impl Schema for TenantSchema {
+        fn schema_name() -> SchemaName {
+            SchemaName::new("bonsaidb", "document-encryption-tests")
+        }
+
+        fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+            schema.define_collection::<Record>()?;
+            schema.define_document_encryption::<Record>()
+        }
+    }
+
+    #[derive(Debug)]
+    struct ViewSchema;
+
+    impl Schema for ViewSchema {
+        fn schema_name() -> SchemaName {
+            SchemaName::new("bonsaidb", "document-encryption-view-tests")
+        }
+
+        fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+            schema.define_collection::<Record>()?;
+            schema.define_document_encryption::<Record>()?;
+            schema.define_view(RecordsByTenant)
+        }
+    }
+
+    #[derive(Collection, Serialize, Deserialize, Debug, Clone)]
+    #[collection(name = "records", core = bonsaidb_core)]
+    struct Record {
+        tenant: Option<String>,
+        value: String,
+    }
+
+    impl Record {
+        fn new(tenant: Option<&str>, value: &str) -> Self {
+            Self {
+                tenant: tenant.map(String::from),
+                value: String::from(value),
+            }
+        }
+    }
+
+    impl DocumentEncryption for Record {
+        fn document_encryption_key(document: &CollectionDocument<Self>) -> Option<KeyId> {
+            document
+                .contents
+                .tenant
+                .as_ref()
+                .map(|tenant| KeyId::Id(Cow::Owned(format!("tenant-{tenant}"))))
+        }
+    }
+
+    #[derive(Debug, Clone, View, ViewSchema)]
+    #[view(collection = Record, key = Option<String>, value = (), name = "by-tenant", core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct RecordsByTenant;
+
+    impl CollectionMapReduce for RecordsByTenant {
+        fn map<'doc>(&self, document: CollectionDocument<Record>) -> ViewMapResult<'doc, Self> {
+            document.header.emit_key(document.contents.tenant)
+        }
+    }
+
+    fn contains_bytes(path: &Path, bytes: &[u8]) -> std::io::Result<bool> {
+        if path.is_dir() {
+            for entry in std::fs::read_dir(path)? {
+                if contains_bytes(&entry?.path(), bytes)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        } else {
+            Ok(std::fs::read(path)?
+                .windows(bytes.len())
+                .any(|window| window == bytes))
+        }
+    }
+
+    // View entries aren't encrypted, so views can't be defined on collections
+    // using document encryption.
+    assert!(matches!(
+        Schematic::from_schema::<ViewSchema>(),
+        Err(bonsaidb_core::Error::ViewsWithDocumentEncryption(_))
+    ));
+
+    let path = TestDirectory::new("document-encryption");
+    let (a, b, shared) = {
+        let db = Database::open::<TenantSchema>(StorageConfiguration::new(&path))?;
+        let a = Record::new(Some("a"), "a's data").push_into(&db)?;
+        let b = Record::new(Some("b"), "b's data").push_into(&db)?;
+        let shared = Record::new(None, "shared").push_into(&db)?;
+        assert_eq!(
+            Record::get(&a.header.id, &db)?.unwrap().contents.value,
+            "a's data"
+        );
+        (a, b, shared)
+    };
+
+    // Keys are persisted, allowing the documents to be read after reopening.
+    let db = Database::open::<TenantSchema>(StorageConfiguration::new(&path))?;
+    assert_eq!(Record::all(&db).query()?.len(), 3);
+
+    // Documents are backed up without being decrypted.
+    let backup = path.join("backup");
+    db.storage().backup(&backup)?;
+    assert!(!contains_bytes(&backup, b"a's data")?);
+    assert!(contains_bytes(&backup, b"shared")?);
+
+    // Deleting a tenant's key hides their documents without affecting others.
+    db.storage().delete_encryption_key("tenant-a")?;
+    assert!(Record::get(&a.header.id, &db)?.is_none());
+    assert_eq!(
+        Record::get(&b.header.id, &db)?.unwrap().contents.value,
+        "b's data"
+    );
+    assert_eq!(
+        Record::get(&shared.header.id, &db)?.unwrap().contents.value,
+        "shared"
+    );
+    assert_eq!(Record::all(&db).query()?.len(), 2);
+
+    // Shredded documents can still be removed.
+    db.collection::<Record>().delete(&a.header)?;
+    assert_eq!(Record::all(&db).headers()?.len(), 2);
+
+    // Restoring the backup skips documents whose key has been deleted, rather
+    // than creating the key again.
+    let storage = db.storage().clone();
+    drop(db);
+    storage.delete_database("default")?;
+    storage.restore(&backup)?;
+    let db = storage.database::<TenantSchema>("default")?;
+    assert!(Record::get(&a.header.id, &db)?.is_none());
+    assert_eq!(
+        Record::get(&b.header.id, &db)?.unwrap().contents.value,
+        "b's data"
+    );
+    assert_eq!(Record::all(&db).query()?.len(), 2);
+
+    assert!(matches!(
+        db.storage().delete_encryption_key("tenant-a"),
+        Err(crate::Error::Vault(
+            crate::vault::Error::EncryptionKeyNotFound(_)
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn encryption_keys_in_use_are_not_deleted() -> anyhow::Result<()> {
+    use bonsaidb_core::document::KeyId;
+    use bonsaidb_core::schema::{Collection, SerializedCollection};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Serialize, Deserialize, Debug, Clone)]
+    #[collection(name = "secrets", encryption_key = Some(KeyId::Id("secrets".into())), core = bonsaidb_core)]
+    struct Secret {
+        value: String,
+    }
+
+    let path = TestDirectory::new("encryption-keys-in-use");
+    let db = Database::open::<Secret>(
+        StorageConfiguration::new(&path).default_encryption_key(KeyId::Id("default".into())),
+    )?;
+    Secret {
+        value: String::from("hello"),
+    }
+    .push_into(&db)?;
+    db.storage()
+        .vault()
+        .encrypt_payload(&KeyId::Id("unused".into()), b"hi", None)?;
+
+    for key in ["secrets", "default"] {
+        assert!(matches!(
+            db.storage().delete_encryption_key(key),
+            Err(crate::Error::Vault(
+                crate::vault::Error::EncryptionKeyInUse(_)
+            ))
+        ));
+    }
+    db.storage().delete_encryption_key("unused")?;
+    assert_eq!(Secret::all(&db).query()?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn key_rotation() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::{Collection, SerializedCollection};
+
+    let path = TestDirectory::new("key-rotation");
+    let collection = EncryptedBasic::collection_name();
+    let header = {
+        let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+        let header = EncryptedBasic::new("hello").push_into(&db)?.header;
+
+        // Rotating the vault key re-seals the master keys, which must still be
+        // able to be unsealed after reopening.
+        db.storage().rotate_vault_key()?;
+        header
+    };
+
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    assert_eq!(
+        EncryptedBasic::get(&header.id, &db)?
+            .unwrap()
+            .contents
+            .value,
+        "hello"
+    );
+
+    db.rotate_collection_encryption_key(&collection)?;
+    db.storage
+        .instance
+        .tasks()
+        .spawn_reencryption(&db, collection.clone())
+        .receive()??;
+    assert!(db.storage().pending_key_rotations().is_empty());
+    EncryptedBasic::new("world").push_into(&db)?;
+    assert_eq!(
+        EncryptedBasic::get(&header.id, &db)?
+            .unwrap()
+            .contents
+            .value,
+        "hello"
+    );
+    assert_eq!(
+        db.view::<bonsaidb_core::test_util::EncryptedBasicCount>()
+            .reduce()?,
+        2
+    );
+
+    // Interrupted re-encryption resumes when the database is next opened.
+    db.storage
+        .instance
+        .key_rotations()
+        .begin(&db, &collection)?;
+    assert_eq!(db.storage().pending_key_rotations().len(), 1);
+    drop(db);
+
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    db.storage
+        .instance
+        .tasks()
+        .spawn_reencryption(&db, collection)
+        .receive()??;
+    assert!(db.storage().pending_key_rotations().is_empty());
+    assert_eq!(EncryptedBasic::all(&db).query()?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn retiring_unused_key_versions() -> anyhow::Result<()> {
+    use bonsaidb_core::document::KeyId;
+    use bonsaidb_core::schema::{Collection, SerializedCollection};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Serialize, Deserialize, Debug, Clone)]
+    #[collection(name = "secrets", encryption_key = Some(KeyId::Id("secrets".into())), core = bonsaidb_core)]
+    struct Secret {
+        value: String,
+    }
+
+    let path = TestDirectory::new("key-rotation-retirement");
+    let db = Database::open::<Secret>(StorageConfiguration::new(&path))?;
+    let key = KeyId::Id("secrets".into());
+    let header = Secret {
+        value: String::from("hello"),
+    }
+    .push_into(&db)?
+    .header;
+    let encrypted_with_first_version = db.storage().vault().encrypt_payload(&key, b"hi", None)?;
+
+    db.rotate_collection_encryption_key(&Secret::collection_name())?;
+    db.storage
+        .instance
+        .tasks()
+        .spawn_reencryption(&db, Secret::collection_name())
+        .receive()??;
+
+    // Previous versions are kept until they are explicitly retired.
+    assert_eq!(db.storage().vault().current_version(&key)?, 1);
+    assert_eq!(
+        db.storage()
+            .vault()
+            .decrypt_payload(&encrypted_with_first_version, None)?,
+        b"hi"
+    );
+
+    // Once the only collection using the key has been re-encrypted, the
+    // previous version can be removed.
+    assert_eq!(db.storage().retire_encryption_key_versions("secrets")?, 1);
+    assert!(db
+        .storage()
+        .vault()
+        .decrypt_payload(&encrypted_with_first_version, None)
+        .is_err());
+    assert_eq!(db.storage().retire_encryption_key_versions("secrets")?, 0);
+    assert_eq!(
+        Secret::get(&header.id, &db)?.unwrap().contents.value,
+        "hello"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn shamir_vault_key_storage() -> anyhow::Result<()> {
+    use std::sync::Arc;
+
+    use bonsaidb_core::schema::SerializedCollection;
+
+    use crate::vault::{KeyPair, LocalVaultKeyStorage, ShamirVaultKeyStorage, VaultKeyStorage};
+
+    let path = TestDirectory::new("shamir-vault-key-storage");
+    let share_paths = ["keys-a", "keys-b", "keys-c"].map(|name| path.join(name));
+    let configuration = || -> anyhow::Result<StorageConfiguration> {
+        let mut key_storage = ShamirVaultKeyStorage::new(2);
+        for share_path in &share_paths {
+            key_storage = key_storage.with_storage(LocalVaultKeyStorage::new(share_path)?);
+        }
+        Ok(StorageConfiguration::new(&path).vault_key_storage(key_storage))
+    };
+
+    let header = {
+        let db = Database::open::<BasicSchema>(configuration()?)?;
+        EncryptedBasic::new("hello").push_into(&db)?.header
+    };
+
+    // Each storage only contains a share of the vault key.
+    let storage_id = Storage::open(configuration()?)?.unique_id();
+    let share = LocalVaultKeyStorage::new(&share_paths[0])?
+        .vault_key_for(storage_id)?
+        .unwrap();
+    assert!(matches!(share, KeyPair::P256Share { .. }));
+
+    // Any two of the three shares can unseal the vault.
+    std::fs::remove_dir_all(&share_paths[0])?;
+    let db = Database::open::<BasicSchema>(configuration()?)?;
+    assert_eq!(
+        EncryptedBasic::get(&header.id, &db)?
+            .unwrap()
+            .contents
+            .value,
+        "hello"
+    );
+    drop(db);
+
+    std::fs::remove_dir_all(&share_paths[1])?;
+    assert!(Database::open::<BasicSchema>(configuration()?).is_err());
+
+    // The remaining share can't be counted twice, and can't be combined using
+    // a lower threshold than it was split with.
+    let remaining = Arc::new(LocalVaultKeyStorage::new(&share_paths[2])?);
+    let duplicated = ShamirVaultKeyStorage::new(2)
+        .with_shared_storage(remaining.clone())
+        .with_shared_storage(remaining.clone());
+    assert!(Storage::open(StorageConfiguration::new(&path).vault_key_storage(duplicated)).is_err());
+    let lowered = ShamirVaultKeyStorage::new(1).with_shared_storage(remaining);
+    assert!(Storage::open(StorageConfiguration::new(&path).vault_key_storage(lowered)).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn shamir_vault_key_rotation_failure() -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use bonsaidb_core::schema::SerializedCollection;
+
+    use crate::storage::StorageId;
+    use crate::vault::{
+        KeyPair, LocalVaultKeyStorage, LocalVaultKeyStorageError, ShamirVaultKeyStorage,
+        VaultKeyStorage,
+    };
+
+    /// Fails to store keys once `remaining_writes` reaches 0.
+    #[derive(Debug)]
+    struct FlakyKeyStorage {
+        storage: LocalVaultKeyStorage,
+        remaining_writes: Arc<AtomicUsize>,
+    }
+
+    impl VaultKeyStorage for FlakyKeyStorage {
+        type Error = LocalVaultKeyStorageError;
+
+        fn set_vault_key_for(
+            &self,
+            storage_id: StorageId,
+            key: KeyPair,
+        ) -> Result<(), Self::Error> {
+            self.remaining_writes
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                    remaining.checked_sub(1)
+                })
+                .map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::Other, "storage unavailable")
+                })?;
+            self.storage.set_vault_key_for(storage_id, key)
+        }
+
+        fn vault_key_for(&self, storage_id: StorageId) -> Result<Option<KeyPair>, Self::Error> {
+            self.storage.vault_key_for(storage_id)
+        }
+    }
+
+    let path = TestDirectory::new("shamir-vault-key-rotation-failure");
+    let share_paths = ["keys-a", "keys-b", "keys-c"].map(|name| path.join(name));
+    let remaining_writes = [(); 3].map(|_| Arc::new(AtomicUsize::new(usize::MAX)));
+    let configuration = || -> anyhow::Result<StorageConfiguration> {
+        // Every share is required to recover the key.
+        let mut key_storage = ShamirVaultKeyStorage::new(3);
+        for (share_path, remaining_writes) in share_paths.iter().zip(&remaining_writes) {
+            key_storage = key_storage.with_storage(FlakyKeyStorage {
+                storage: LocalVaultKeyStorage::new(share_path)?,
+                remaining_writes: remaining_writes.clone(),
+            });
+        }
+        Ok(StorageConfiguration::new(&path).vault_key_storage(key_storage))
+    };
+    let rotate_with_remaining_writes = |remaining: [usize; 3]| -> anyhow::Result<()> {
+        let db = Database::open::<BasicSchema>(configuration()?)?;
+        for (remaining_writes, remaining) in remaining_writes.iter().zip(remaining) {
+            remaining_writes.store(remaining, Ordering::SeqCst);
+        }
+        let result = db.storage().rotate_vault_key();
+        for remaining_writes in &remaining_writes {
+            remaining_writes.store(usize::MAX, Ordering::SeqCst);
+        }
+        result?;
+        Ok(())
+    };
+    let assert_readable = |header: &bonsaidb_core::document::Header| -> anyhow::Result<()> {
+        let db = Database::open::<BasicSchema>(configuration()?)?;
+        assert_eq!(
+            EncryptedBasic::get(&header.id, &db)?
+                .unwrap()
+                .contents
+                .value,
+            "hello"
+        );
+        Ok(())
+    };
+
+    let header = {
+        let db = Database::open::<BasicSchema>(configuration()?)?;
+        EncryptedBasic::new("hello").push_into(&db)?.header
+    };
+
+    // The third storage fails after the first two have stored their new
+    // shares, which are then replaced with their previous shares.
+    assert!(rotate_with_remaining_writes([usize::MAX, usize::MAX, 0]).is_err());
+    assert_readable(&header)?;
+
+    // The first two storages also fail to restore their previous shares, so
+    // the previous key must be recovered from the shares kept alongside the
+    // new shares.
+    assert!(rotate_with_remaining_writes([1, 1, 0]).is_err());
+    assert_readable(&header)?;
+
+    // A rotation that succeeds replaces the key.
+    rotate_with_remaining_writes([usize::MAX; 3])?;
+    assert_readable(&header)?;
+
+    Ok(())
+}
+
 #[test]
 fn expiration_after_close() -> anyhow::Result<()> {
     use bonsaidb_core::keyvalue::KeyValue;
@@ -318,3 +810,712 @@ fn expiration_after_close() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn durable_pubsub() -> anyhow::Result<()> {
+    use bonsaidb_core::pubsub::durable::{self, DurablePubSub};
+    use bonsaidb_core::schema::{Qualified, Schema, SchemaName, Schematic};
+
+    #[derive(Debug)]
+    struct DurableSchema;
+
+    impl Schema for DurableSchema {
+        fn schema_name() -> SchemaName {
+            SchemaName::new("bonsaidb", "durable-pubsub-tests")
+        }
+
+        fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+            durable::define_collections(schema)
+        }
+    }
+
+    let path = TestDirectory::new("durable-pubsub");
+    let db = Database::open::<DurableSchema>(StorageConfiguration::new(&path))?;
+
+    // Publish messages before any subscribers exist.
+    for value in 0_u32..3 {
+        db.publish_durable(&"topic", &value)?;
+    }
+    db.publish_durable(&"other", &u32::MAX)?;
+
+    let mut subscriber = db.subscribe_as_consumer("worker", &"topic")?;
+    let first = subscriber.receive()?;
+    assert_eq!(first.payload::<u32>()?, 0);
+    let second = subscriber.receive()?;
+    assert_eq!(second.payload::<u32>()?, 1);
+    db.acknowledge_durable("worker", &"topic", second.offset)?;
+    assert_eq!(
+        db.durable_consumer_offset("worker", &"topic")?,
+        Some(second.offset)
+    );
+    drop(subscriber);
+
+    // Resuming as the same consumer should start after the acknowledged
+    // message, and then deliver new messages as they are published.
+    let mut subscriber = db.subscribe_as_consumer("worker", &"topic")?;
+    assert_eq!(subscriber.receive()?.payload::<u32>()?, 2);
+    assert!(subscriber.try_receive()?.is_none());
+    db.publish_durable(&"topic", &3_u32)?;
+    assert_eq!(subscriber.receive()?.payload::<u32>()?, 3);
+
+    // Replaying from an explicit offset returns every message from that point.
+    let mut subscriber = db.subscribe_from(&"topic", first.offset)?;
+    for expected in 0_u32..4 {
+        assert_eq!(subscriber.receive()?.payload::<u32>()?, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn document_policies() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::Session;
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, DocumentPolicy, Qualified, ReduceResult, Schema,
+        SchemaName, Schematic, SerializedCollection, View, ViewMapResult, ViewMappedValue,
+        ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug)]
+    struct PolicySchema;
+
+    impl Schema for PolicySchema {
+        fn schema_name() -> SchemaName {
+            SchemaName::new("bonsaidb", "policy-tests")
+        }
+
+        fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb_core::Error> {
+            schema.define_collection::<Note>()?;
+            schema.define_policy::<Note>();
+            Ok(())
+        }
+    }
+
+    #[derive(Collection, Serialize, Deserialize, Debug, Clone)]
+    #[collection(name = "notes", views = [NotesByTopic], core = bonsaidb_core)]
+    struct Note {
+        topic: String,
+        public: bool,
+        locked: bool,
+        secret: String,
+    }
+
+    impl Note {
+        fn new(public: bool, locked: bool) -> Self {
+            Self {
+                topic: String::from("notes"),
+                public,
+                locked,
+                secret: String::from("secret"),
+            }
+        }
+    }
+
+    impl DocumentPolicy for Note {
+        fn can_read(_session: &Session, document: &mut CollectionDocument<Self>) -> bool {
+            document.contents.secret.clear();
+            document.contents.public
+        }
+
+        fn can_insert(_session: &Session, _id: Option<&u64>, contents: &Self) -> bool {
+            !contents.locked
+        }
+
+        fn can_update(_session: &Session, existing: &CollectionDocument<Self>, _: &Self) -> bool {
+            !existing.contents.locked
+        }
+
+        fn can_delete(_session: &Session, existing: &CollectionDocument<Self>) -> bool {
+            !existing.contents.locked
+        }
+    }
+
+    #[derive(Clone, View, ViewSchema, Debug)]
+    #[view(collection = Note, key = String, value = u32, core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct NotesByTopic;
+
+    impl CollectionMapReduce for NotesByTopic {
+        fn map<'doc>(&self, document: CollectionDocument<Note>) -> ViewMapResult<'doc, Self> {
+            document
+                .header
+                .emit_key_and_value(document.contents.topic, 1)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self::View>],
+            _rereduce: bool,
+        ) -> ReduceResult<Self::View> {
+            Ok(mappings.iter().map(|map| map.value).sum())
+        }
+    }
+
+    let path = TestDirectory::new("document-policies");
+    let db = Database::open::<PolicySchema>(StorageConfiguration::new(&path))?;
+    let public = Note::new(true, false).push_into(&db)?;
+    let private = Note::new(false, false).push_into(&db)?;
+    let locked = Note::new(true, true).push_into(&db)?;
+
+    // Policies aren't evaluated without a session.
+    assert_eq!(Note::all(&db).count()?, 3);
+    assert_eq!(db.view::<NotesByTopic>().reduce()?, 3);
+    assert_eq!(
+        Note::get(&public.header.id, &db)?.unwrap().contents.secret,
+        "secret"
+    );
+
+    let restricted = db
+        .with_effective_permissions(Permissions::from(vec![
+            Statement::allow_all_for_any_resource(),
+        ]))
+        .unwrap();
+
+    // Unreadable documents are hidden, and readable documents are redacted.
+    assert!(Note::get(&private.header.id, &restricted)?.is_none());
+    let readable = Note::get(&public.header.id, &restricted)?.unwrap();
+    assert!(readable.contents.secret.is_empty());
+    let all = Note::all(&restricted).query()?;
+    assert_eq!(all.len(), 2);
+    assert!(all.iter().all(|note| note.contents.public));
+    assert_eq!(Note::all(&restricted).count()?, 2);
+    assert_eq!(Note::all(&restricted).headers()?.len(), 2);
+    assert_eq!(restricted.view::<NotesByTopic>().query()?.len(), 2);
+    assert_eq!(restricted.view::<NotesByTopic>().reduce()?, 2);
+    assert_eq!(
+        restricted
+            .view::<NotesByTopic>()
+            .query_with_collection_docs()?
+            .documents
+            .len(),
+        2
+    );
+
+    // Writes rejected by the policy fail with a permission error.
+    assert!(matches!(
+        Note::new(true, true).push_into(&restricted),
+        Err(bonsaidb_core::schema::InsertError {
+            error: bonsaidb_core::Error::PermissionDenied(_),
+            ..
+        })
+    ));
+    let mut locked = Note::get(&locked.header.id, &restricted)?.unwrap();
+    locked.contents.topic = String::from("changed");
+    assert!(matches!(
+        locked.update(&restricted),
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        locked.delete(&restricted),
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+    // Overwrites are evaluated as updates of the stored document, and as
+    // inserts when no document exists.
+    assert!(matches!(
+        Note::new(true, false).overwrite_into(&locked.header.id, &restricted),
+        Err(bonsaidb_core::schema::InsertError {
+            error: bonsaidb_core::Error::PermissionDenied(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        Note::new(true, true).overwrite_into(&u64::MAX, &restricted),
+        Err(bonsaidb_core::schema::InsertError {
+            error: bonsaidb_core::Error::PermissionDenied(_),
+            ..
+        })
+    ));
+    let overwritten = Note::new(true, false).overwrite_into(&u64::MAX, &restricted)?;
+    overwritten.delete(&restricted)?;
+
+    let mut public = readable;
+    public.contents.topic = String::from("changed");
+    public.update(&restricted)?;
+    public.delete(&restricted)?;
+    assert_eq!(Note::all(&db).count()?, 2);
+
+    Ok(())
+}
+
+#[test]
+fn effective_permissions() -> anyhow::Result<()> {
+    use bonsaidb_core::admin::User;
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::KeyValue;
+    use bonsaidb_core::permissions::bonsai::{
+        BonsaiAction, DatabaseAction, DocumentAction, PubSubAction, ViewAction,
+    };
+    use bonsaidb_core::pubsub::{PubSub, Subscriber};
+    use bonsaidb_core::schema::SerializedCollection;
+
+    fn is_denied<T>(result: Result<T, bonsaidb_core::Error>) -> bool {
+        matches!(result, Err(bonsaidb_core::Error::PermissionDenied(_)))
+    }
+
+    let path = TestDirectory::new("effective-permissions");
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    let doc = Basic::new("initial").with_parent_id(1).push_into(&db)?;
+    db.set_key("key", &1_u32).execute()?;
+
+    let read_only = db
+        .with_effective_permissions(Permissions::from(
+            Statement::for_any()
+                .allowing(&BonsaiAction::Database(DatabaseAction::Document(
+                    DocumentAction::Get,
+                )))
+                .allowing(&BonsaiAction::Database(DatabaseAction::View(
+                    ViewAction::Query,
+                )))
+                .allowing(&BonsaiAction::Database(DatabaseAction::PubSub(
+                    PubSubAction::CreateSuscriber,
+                ))),
+        ))
+        .unwrap();
+
+    assert!(Basic::get(&doc.header.id, &read_only)?.is_some());
+    assert_eq!(read_only.view::<BasicByParentId>().query()?.len(), 1);
+    assert!(is_denied(Basic::all(&read_only).query()));
+    assert!(is_denied(Basic::all(&read_only).count()));
+    assert!(is_denied(read_only.view::<BasicByParentId>().reduce()));
+    assert!(is_denied(
+        read_only.collection::<Basic>().push(&Basic::new("denied"))
+    ));
+    assert!(is_denied(read_only.get_key("key").query()));
+    assert!(is_denied(read_only.publish(&"topic", &())));
+    let subscriber = read_only.create_subscriber()?;
+    assert!(is_denied(subscriber.subscribe_to(&"topic")));
+
+    // Deleting documents through a view requires its own permission, even
+    // when each document could be deleted individually.
+    let deleter = db
+        .with_effective_permissions(Permissions::from(
+            Statement::for_any()
+                .allowing(&BonsaiAction::Database(DatabaseAction::Document(
+                    DocumentAction::Delete,
+                )))
+                .allowing(&BonsaiAction::Database(DatabaseAction::View(
+                    ViewAction::Query,
+                ))),
+        ))
+        .unwrap();
+    assert!(is_denied(deleter.view::<BasicByParentId>().delete_docs()));
+    assert_eq!(Basic::all(&db).count()?, 1);
+
+    // Databases opened from a restricted storage, including the admin
+    // database, inherit its permissions.
+    let restricted_storage = db
+        .storage()
+        .with_effective_permissions(Permissions::default())
+        .unwrap();
+    let restricted_db = restricted_storage.database::<BasicSchema>("default")?;
+    assert!(is_denied(Basic::get(&doc.header.id, &restricted_db)));
+    assert!(is_denied(User::all(&restricted_storage.admin()).query()));
+
+    Ok(())
+}
+
+#[test]
+fn audit_log() -> anyhow::Result<()> {
+    use bonsaidb_core::admin::{AuditLogEntry, AuditOutcome};
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::key::time::TimestampAsNanoseconds;
+    use bonsaidb_core::permissions::bonsai::{
+        collection_resource_name, BonsaiAction, DatabaseAction, DocumentAction,
+    };
+    use bonsaidb_core::permissions::Action;
+    use bonsaidb_core::schema::{Collection, SerializedCollection};
+    use itertools::Itertools;
+
+    use crate::config::AuditLog;
+    use crate::DatabaseNonBlocking;
+
+    let path = TestDirectory::new("audit-log");
+    let db = Database::open::<BasicSchema>(
+        StorageConfiguration::new(&path).audit_log(AuditLog::Modifications),
+    )?;
+    // Operations without a session aren't audited.
+    let unaudited = Basic::new("unaudited").push_into(&db)?;
+
+    let insert = BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Insert));
+    let restricted = db
+        .with_effective_permissions(Permissions::from(vec![
+            Statement::for_any().allowing(&insert),
+            Statement::for_any().allowing(&BonsaiAction::Database(DatabaseAction::Document(
+                DocumentAction::Get,
+            ))),
+        ]))
+        .unwrap();
+    let inserted = Basic::new("audited").push_into(&restricted)?;
+    // Reads aren't included in `AuditLog::Modifications`.
+    assert!(Basic::get(&inserted.header.id, &restricted)?.is_some());
+    assert!(matches!(
+        unaudited.delete(&restricted),
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+
+    // Entries are written by a background thread, and flushing writes any
+    // that are still queued.
+    db.storage().flush_audit_log()?;
+    let admin = db.storage().admin();
+    let beginning = TimestampAsNanoseconds::from_representation(i64::MIN);
+    let now = TimestampAsNanoseconds::now();
+    let entries = AuditLogEntry::recorded_between(beginning, now, 10, &admin)?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].contents.action, insert.name().to_string());
+    assert_eq!(entries[0].contents.outcome, AuditOutcome::Allowed);
+    assert_eq!(
+        entries[1].contents.action,
+        BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Delete))
+            .name()
+            .to_string()
+    );
+    assert_eq!(entries[1].contents.outcome, AuditOutcome::Denied);
+
+    let first_page = AuditLogEntry::recorded_between(beginning, now, 1, &admin)?;
+    assert_eq!(first_page.len(), 1);
+    assert_eq!(first_page[0].header.id, entries[0].header.id);
+
+    let collection = collection_resource_name(db.name(), &Basic::collection_name())
+        .as_ref()
+        .iter()
+        .join(".");
+    let for_collection = AuditLogEntry::for_resource(&collection, beginning, now, 10, &admin)?;
+    assert_eq!(for_collection.len(), 1);
+    assert_eq!(for_collection[0].header.id, entries[0].header.id);
+
+    Ok(())
+}
+
+#[test]
+fn database_quotas() -> anyhow::Result<()> {
+    use bonsaidb_core::admin::{DatabaseQuota, QuotaLimit};
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::KeyValue;
+    use bonsaidb_core::schema::SerializedCollection;
+
+    let path = TestDirectory::new("database-quotas");
+    {
+        let storage =
+            Storage::open(StorageConfiguration::new(&path).with_schema::<BasicSchema>()?)?;
+        let db = storage.create_database::<BasicSchema>("limited", false)?;
+        storage.set_database_quota("limited", DatabaseQuota::unlimited().with_max_documents(2))?;
+
+        Basic::new("a").push_into(&db)?;
+        let second = Basic::new("b").push_into(&db)?;
+        assert!(matches!(
+            Basic::new("c").push_into(&db),
+            Err(err) if matches!(
+                err.error,
+                bonsaidb_core::Error::QuotaExceeded { limit: QuotaLimit::Documents(2), .. }
+            )
+        ));
+        // Removing data is always allowed, and frees up space.
+        second.delete(&db)?;
+        Basic::new("c").push_into(&db)?;
+        // The running usage counters match the stored data.
+        assert_eq!(db.storage_usage(), db.count_storage_usage()?.totals(0));
+
+        assert!(matches!(
+            storage.set_database_quota("missing", DatabaseQuota::unlimited()),
+            Err(bonsaidb_core::Error::DatabaseNotFound(_))
+        ));
+    }
+
+    // Quotas are persisted.
+    let storage = Storage::open(StorageConfiguration::new(&path).with_schema::<BasicSchema>()?)?;
+    let db = storage.database::<BasicSchema>("limited")?;
+    let usage = storage.list_database_usage()?;
+    let limited = usage.iter().find(|usage| usage.name == "limited").unwrap();
+    assert_eq!(limited.documents, 2);
+    assert!(limited.bytes > 0);
+    assert_eq!(limited.quota.max_documents, Some(2));
+
+    storage.set_database_quota(
+        "limited",
+        DatabaseQuota::unlimited().with_max_bytes(limited.bytes + 16),
+    )?;
+    assert!(matches!(
+        db.set_key("big", &vec![0_u8; 64]).execute(),
+        Err(bonsaidb_core::Error::QuotaExceeded {
+            limit: QuotaLimit::Bytes(_),
+            ..
+        })
+    ));
+    db.set_key("small", &0_u8).execute()?;
+
+    // Key-value writes count towards the quota before they are persisted.
+    let exceeded = (0..16)
+        .map(|index| db.set_key(format!("pending-{index}"), &[0_u8; 8]).execute())
+        .find_map(Result::err);
+    assert!(matches!(
+        exceeded,
+        Some(bonsaidb_core::Error::QuotaExceeded {
+            limit: QuotaLimit::Bytes(_),
+            ..
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "token-authentication")]
+fn assume_identity_keeps_token_scope() -> anyhow::Result<()> {
+    use bonsaidb_core::admin::{AuthenticationToken, AuthenticationTokenOptions};
+    use bonsaidb_core::connection::{HasSession, IdentityReference, StorageConnection};
+    use bonsaidb_core::permissions::bonsai::{BonsaiAction, ServerAction};
+
+    let path = TestDirectory::new("assume-identity-keeps-token-scope");
+    let storage = Storage::open(
+        StorageConfiguration::new(&path).authenticated_permissions(Permissions::allow_all()),
+    )?;
+    storage.create_user("scoped")?;
+    storage.create_user("other")?;
+
+    let token = AuthenticationToken::create_with_options(
+        &IdentityReference::user("scoped")?,
+        &AuthenticationTokenOptions::default().scope(vec![Statement::for_any()
+            .allowing(&BonsaiAction::Server(ServerAction::AssumeIdentity))
+            .allowing(&BonsaiAction::Server(ServerAction::ListDatabases))]),
+        &storage.admin(),
+    )?;
+    let as_scoped = storage.authenticate_with_token(token.header.id, &token.contents.token)?;
+
+    // Assuming another identity can't be used to escape the token's scope.
+    let as_other = as_scoped.assume_identity(IdentityReference::user("other")?)?;
+    assert!(as_other
+        .session()
+        .map_or(false, |session| session.scope.is_some()));
+    as_other.list_databases()?;
+    assert!(matches!(
+        as_other.create_user("escaped"),
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn revoked_sessions_are_denied() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::{HasSession, IdentityReference, StorageConnection};
+    use bonsaidb_core::schema::SerializedCollection;
+
+    let path = TestDirectory::new("revoked-sessions-are-denied");
+    let storage = Storage::open(
+        StorageConfiguration::new(&path)
+            .with_schema::<BasicSchema>()?
+            .authenticated_permissions(Permissions::allow_all()),
+    )?;
+    storage.create_database::<BasicSchema>("revoked", false)?;
+    storage.create_user("revoked")?;
+
+    let as_user = storage.assume_identity(IdentityReference::user("revoked")?)?;
+    let session_id = as_user.session().and_then(|session| session.id).unwrap();
+    let db = as_user.database::<BasicSchema>("revoked")?;
+    Basic::new("a").push_into(&db)?;
+
+    // Handles that outlive the revocation can no longer be used.
+    storage.revoke_session(session_id)?;
+    assert!(matches!(
+        as_user.list_databases(),
+        Err(bonsaidb_core::Error::SessionNotFound(_))
+    ));
+    assert!(matches!(
+        Basic::all(&db).query(),
+        Err(bonsaidb_core::Error::SessionNotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn metrics() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::schema::SerializedCollection;
+
+    use crate::metrics::PrometheusEncoder;
+
+    let path = TestDirectory::new("metrics");
+    let storage = Storage::open(StorageConfiguration::new(&path).with_schema::<BasicSchema>()?)?;
+    let db = storage.create_database::<BasicSchema>("metrics", false)?;
+
+    let applied = storage.metrics().transactions_applied();
+    Basic::new("a").push_into(&db)?;
+    Basic::new("b").push_into(&db)?;
+    assert_eq!(storage.metrics().transactions_applied(), applied + 2);
+
+    let mapped = storage.metrics().tasks_completed("view_map");
+    db.view::<BasicByParentId>().query()?;
+    assert!(storage.metrics().tasks_completed("view_map") > mapped);
+
+    let mut output = String::new();
+    storage
+        .metrics()
+        .encode(&mut PrometheusEncoder::new(&mut output));
+    assert!(output.contains("# TYPE bonsaidb_transactions_total counter\n"));
+    assert!(output.contains(&format!("bonsaidb_transactions_total {}\n", applied + 2)));
+    assert!(output.contains("bonsaidb_task_duration_seconds_bucket{task=\"view_map\",le=\"+Inf\"}"));
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+mod custom_api {
+    use bonsaidb_core::api::{Api, ApiName};
+    use bonsaidb_core::async_trait::async_trait;
+    use bonsaidb_core::connection::{AsyncStorageConnection, StorageConnection};
+    use bonsaidb_core::schema::Qualified;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::api::{ApiError, Handler, HandlerError, HandlerResult, HandlerSession};
+    use crate::AsyncStorage;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct CreateTestDatabase {
+        name: String,
+        privileged: bool,
+    }
+
+    #[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize)]
+    #[error("database name is empty")]
+    struct EmptyName;
+
+    impl Api for CreateTestDatabase {
+        type Error = EmptyName;
+        type Response = ();
+
+        fn name() -> ApiName {
+            ApiName::private("create-test-database")
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Unregistered;
+
+    impl Api for Unregistered {
+        type Error = bonsaidb_core::api::Infallible;
+        type Response = ();
+
+        fn name() -> ApiName {
+            ApiName::private("unregistered")
+        }
+    }
+
+    #[derive(Debug)]
+    struct CreateTestDatabaseHandler;
+
+    #[async_trait]
+    impl Handler<CreateTestDatabase, AsyncStorage> for CreateTestDatabaseHandler {
+        async fn handle(
+            session: HandlerSession<'_>,
+            request: CreateTestDatabase,
+        ) -> HandlerResult<CreateTestDatabase> {
+            if request.name.is_empty() {
+                return Err(HandlerError::Api(EmptyName));
+            }
+
+            let storage = if request.privileged {
+                session.storage.clone()
+            } else {
+                session.as_client
+            };
+            storage
+                .create_database::<BasicSchema>(&request.name, false)
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn send_api_request() -> anyhow::Result<()> {
+        let directory = TestDirectory::new("custom-api");
+        let storage = AsyncStorage::open(
+            StorageConfiguration::new(&directory)
+                .with_schema::<BasicSchema>()?
+                .with_api::<CreateTestDatabaseHandler, CreateTestDatabase>(),
+        )
+        .await?;
+
+        storage
+            .send_api_request(&CreateTestDatabase {
+                name: String::from("a"),
+                privileged: false,
+            })
+            .await?;
+        assert!(matches!(
+            storage
+                .send_api_request(&CreateTestDatabase {
+                    name: String::new(),
+                    privileged: false,
+                })
+                .await,
+            Err(ApiError::Api(EmptyName))
+        ));
+        assert!(matches!(
+            storage.send_api_request(&Unregistered).await,
+            Err(ApiError::Storage(crate::Error::Core(
+                bonsaidb_core::Error::ApiNotFound(_)
+            )))
+        ));
+
+        // The handler's `as_client` is limited to the caller's permissions,
+        // while `storage` is not.
+        let restricted = storage
+            .with_effective_permissions(Permissions::default())
+            .unwrap();
+        assert!(matches!(
+            restricted
+                .send_api_request(&CreateTestDatabase {
+                    name: String::from("b"),
+                    privileged: false,
+                })
+                .await,
+            Err(ApiError::Storage(crate::Error::Core(
+                bonsaidb_core::Error::PermissionDenied(_)
+            )))
+        ));
+        restricted
+            .send_api_request(&CreateTestDatabase {
+                name: String::from("b"),
+                privileged: true,
+            })
+            .await?;
+        storage.database::<BasicSchema>("b").await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn send_blocking_api_request() -> anyhow::Result<()> {
+        let directory = TestDirectory::new("custom-api-blocking");
+        let storage = Storage::open(
+            StorageConfiguration::new(&directory)
+                .with_schema::<BasicSchema>()?
+                .with_api::<CreateTestDatabaseHandler, CreateTestDatabase>(),
+        )?;
+
+        storage.send_api_request(&CreateTestDatabase {
+            name: String::from("a"),
+            privileged: false,
+        })?;
+        storage.database::<BasicSchema>("a")?;
+        assert!(matches!(
+            storage.send_api_request(&CreateTestDatabase {
+                name: String::new(),
+                privileged: false,
+            }),
+            Err(ApiError::Api(EmptyName))
+        ));
+        assert!(matches!(
+            storage.send_api_request(&Unregistered),
+            Err(ApiError::Storage(crate::Error::Core(
+                bonsaidb_core::Error::ApiNotFound(_)
+            )))
+        ));
+
+        Ok(())
+    }
+}
@@ -34,6 +34,17 @@
 //! Eventually, other BonsaiDb servers will be able to operate as key storage
 //! for each other.
 //!
+//! ## Named Keys
+//!
+//! In addition to the master keys, the vault manages keys referenced by
+//! [`KeyId::Id`]. Named keys are created randomly the first time they are used
+//! and are stored in the `vault-named-keys` file, encrypted with the current
+//! master key. Deleting a named key using
+//! [`Storage::delete_encryption_key`](crate::Storage::delete_encryption_key)
+//! makes all data encrypted with it permanently unreadable. This allows
+//! crypto-shredding individual users' or tenants' documents when used with
+//! [`DocumentEncryption`](bonsaidb_core::schema::DocumentEncryption).
+//!
 //! ## Encryption Algorithms Used
 //!
 //! BonsaiDb uses the [`hpke`](https://github.com/rozbb/rust-hpke) crate to
@@ -68,12 +79,14 @@ use hpke::kdf::HkdfSha256;
 use hpke::kem::DhP256HkdfSha256;
 use hpke::{self, Deserializable, Kem, OpModeS, Serializable};
 use lockedbox::LockedBox;
+use parking_lot::RwLock;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use zeroize::{Zeroize, Zeroizing};
 
 /// A private encryption key.
 #[derive(Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum KeyPair {
     /// A P256 keypair.
     P256 {
@@ -82,6 +95,13 @@ pub enum KeyPair {
         /// The public key.
         public: <DhP256HkdfSha256 as Kem>::PublicKey,
     },
+    /// A share of a P256 private key, created by [`ShamirVaultKeyStorage`].
+    P256Share {
+        /// The public key of the complete keypair.
+        public: <DhP256HkdfSha256 as Kem>::PublicKey,
+        /// The share of the private key.
+        share: KeyShare,
+    },
 }
 
 impl KeyPair {
@@ -118,30 +138,101 @@ impl PublicKey {
 impl<'a> From<&'a KeyPair> for PublicKey {
     fn from(key: &'a KeyPair) -> Self {
         match key {
-            KeyPair::P256 { public, .. } => PublicKey::P256(public.clone()),
+            KeyPair::P256 { public, .. } | KeyPair::P256Share { public, .. } => {
+                PublicKey::P256(public.clone())
+            }
         }
     }
 }
 
+pub use self::shamir::{KeyShare, ShamirVaultKeyStorage};
 use crate::storage::StorageId;
 
+mod shamir;
+
 pub(crate) struct Vault {
-    _vault_public_key: PublicKey,
-    master_keys: HashMap<u32, EncryptionKey>,
-    current_master_key_id: u32,
+    master_keys: RwLock<MasterKeys>,
     master_key_storage: Arc<dyn AnyVaultKeyStorage>,
+    named_keys: RwLock<NamedKeys>,
 }
 
 impl Debug for Vault {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Vault")
             .field("master_keys", &self.master_keys)
-            .field("current_master_key_id", &self.current_master_key_id)
             .field("master_key_storage", &self.master_key_storage)
+            .field("named_keys", &self.named_keys)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The master keys, sealed using the vault key.
+struct MasterKeys {
+    vault_public_key: <DhP256HkdfSha256 as Kem>::PublicKey,
+    /// The location the sealed keys are persisted to. In-memory only if
+    /// `None`.
+    path: Option<PathBuf>,
+    keys: HashMap<u32, EncryptionKey>,
+    current: u32,
+}
+
+impl Debug for MasterKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterKeys")
+            .field("path", &self.path)
+            .field("keys", &self.keys)
+            .field("current", &self.current)
             .finish_non_exhaustive()
     }
 }
 
+impl MasterKeys {
+    fn current_key(&self) -> &EncryptionKey {
+        self.keys.get(&self.current).unwrap()
+    }
+
+    /// Seals the keys with the vault key and writes them to `path`.
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let sealed = seal_master_keys(&self.vault_public_key, &self.keys)?;
+        write_atomically(path, &sealed)
+            .map_err(|err| Error::Encryption(format!("error saving master keys: {err:?}")))
+    }
+}
+
+/// The keys referenced by [`KeyId::Id`].
+#[derive(Debug, Default)]
+struct NamedKeys {
+    /// The location the keys are persisted to. In-memory only if `None`.
+    path: Option<PathBuf>,
+    keys: HashMap<String, NamedKey>,
+}
+
+/// Every version of a named key. Previous versions are kept so that data
+/// encrypted before the key was rotated can still be decrypted.
+#[derive(Debug, Serialize, Deserialize)]
+struct NamedKey {
+    versions: HashMap<u32, EncryptionKey>,
+    current: u32,
+}
+
+impl NamedKey {
+    fn random() -> Self {
+        let mut versions = HashMap::new();
+        versions.insert(0, EncryptionKey::random());
+        Self {
+            versions,
+            current: 0,
+        }
+    }
+
+    fn current_key(&self) -> &EncryptionKey {
+        self.versions.get(&self.current).unwrap()
+    }
+}
+
 /// Errors relating to encryption and/or secret storage.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -158,6 +249,13 @@ pub enum Error {
     /// doesn't contain the key.
     #[error("vault key not found")]
     VaultKeyNotFound,
+    /// The named encryption key was not found. It may have been deleted.
+    #[error("encryption key not found: {0}")]
+    EncryptionKeyNotFound(String),
+    /// The named encryption key can't be deleted, because it is the storage's
+    /// default encryption key or a collection's encryption key.
+    #[error("encryption key is in use: {0}")]
+    EncryptionKeyInUse(String),
 }
 
 impl From<chacha20poly1305::aead::Error> for Error {
@@ -185,11 +283,223 @@ impl Vault {
         master_key_storage: Arc<dyn AnyVaultKeyStorage>,
     ) -> Result<Self, Error> {
         let master_keys_path = server_directory.join("master-keys");
-        if master_keys_path.exists() {
-            Self::unseal(&master_keys_path, server_id, master_key_storage)
+        let vault = if master_keys_path.exists() {
+            Self::unseal(&master_keys_path, server_id, master_key_storage)?
         } else {
-            Self::initialize_vault_key_storage(&master_keys_path, server_id, master_key_storage)
+            Self::initialize_vault_key_storage(&master_keys_path, server_id, master_key_storage)?
+        };
+        vault.load_named_keys(server_directory.join("vault-named-keys"))?;
+        Ok(vault)
+    }
+
+    fn load_named_keys(&self, path: PathBuf) -> Result<(), Error> {
+        let mut named_keys = self.named_keys.write();
+        if path.exists() {
+            let encrypted = std::fs::read(&path)
+                .map_err(|err| Error::Initializing(format!("error reading named keys: {err:?}")))?;
+            let payload = VaultPayload::from_slice(&encrypted)?;
+            let master_keys = self.master_keys.read();
+            let key = master_keys.keys.get(&payload.key_version).ok_or_else(|| {
+                Error::Initializing(String::from("named keys use an unknown master key"))
+            })?;
+            let mut decrypted = key.decrypt_payload(&payload)?;
+            named_keys.keys = bincode::deserialize(&decrypted)?;
+            decrypted.zeroize();
+        }
+        named_keys.path = Some(path);
+        Ok(())
+    }
+
+    fn save_named_keys(&self, named_keys: &NamedKeys) -> Result<(), Error> {
+        let Some(path) = &named_keys.path else {
+            return Ok(());
+        };
+        let mut serialized = bincode::serialize(&named_keys.keys)?;
+        let payload = {
+            let master_keys = self.master_keys.read();
+            master_keys
+                .current_key()
+                .encrypt_payload(KeyId::Master, master_keys.current, &serialized)
+                .to_vec()
+        };
+        serialized.zeroize();
+
+        write_atomically(path, &payload)
+            .map_err(|err| Error::Encryption(format!("error saving named keys: {err:?}")))
+    }
+
+    /// Invokes `callback` with the current version of the key named `name`,
+    /// creating the key if it doesn't exist.
+    fn with_named_key<R>(
+        &self,
+        name: &str,
+        callback: impl FnOnce(&EncryptionKey, u32) -> R,
+    ) -> Result<R, Error> {
+        let named_keys = self.named_keys.read();
+        if let Some(key) = named_keys.keys.get(name) {
+            return Ok(callback(key.current_key(), key.current));
+        }
+        drop(named_keys);
+
+        let mut named_keys = self.named_keys.write();
+        if !named_keys.keys.contains_key(name) {
+            named_keys.keys.insert(name.to_string(), NamedKey::random());
+            if let Err(err) = self.save_named_keys(&named_keys) {
+                named_keys.keys.remove(name);
+                return Err(err);
+            }
+        }
+        let key = &named_keys.keys[name];
+        Ok(callback(key.current_key(), key.current))
+    }
+
+    /// Deletes the key named `name`. All data encrypted with the key will no
+    /// longer be able to be decrypted.
+    pub fn delete_named_key(&self, name: &str) -> Result<(), Error> {
+        let mut named_keys = self.named_keys.write();
+        let key = named_keys
+            .keys
+            .remove(name)
+            .ok_or_else(|| Error::EncryptionKeyNotFound(name.to_string()))?;
+        if let Err(err) = self.save_named_keys(&named_keys) {
+            named_keys.keys.insert(name.to_string(), key);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Creates a new version of `key_id`, which is used to encrypt all data
+    /// from now on. Previous versions are kept so that existing data can still
+    /// be decrypted.
+    pub fn rotate_key(&self, key_id: &KeyId) -> Result<(), Error> {
+        match key_id {
+            KeyId::Master => self.rotate_master_key(),
+            KeyId::Id(name) => self.rotate_named_key(name),
+            KeyId::None => unreachable!(),
+        }
+    }
+
+    fn rotate_master_key(&self) -> Result<(), Error> {
+        {
+            let mut master_keys = self.master_keys.write();
+            let previous = master_keys.current;
+            let new_version = master_keys.keys.keys().max().copied().unwrap_or_default() + 1;
+            master_keys
+                .keys
+                .insert(new_version, EncryptionKey::random());
+            master_keys.current = new_version;
+            if let Err(err) = master_keys.save() {
+                master_keys.keys.remove(&new_version);
+                master_keys.current = previous;
+                return Err(err);
+            }
         }
+
+        // The named keys are encrypted with the current master key. The
+        // previous master key is retained, so a failure here leaves the named
+        // keys readable.
+        let named_keys = self.named_keys.read();
+        self.save_named_keys(&named_keys)
+    }
+
+    fn rotate_named_key(&self, name: &str) -> Result<(), Error> {
+        let mut named_keys = self.named_keys.write();
+        let key = named_keys
+            .keys
+            .get_mut(name)
+            .ok_or_else(|| Error::EncryptionKeyNotFound(name.to_string()))?;
+        let previous = key.current;
+        let new_version = key.versions.keys().max().copied().unwrap_or_default() + 1;
+        key.versions.insert(new_version, EncryptionKey::random());
+        key.current = new_version;
+        if let Err(err) = self.save_named_keys(&named_keys) {
+            let key = named_keys.keys.get_mut(name).unwrap();
+            key.versions.remove(&new_version);
+            key.current = previous;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Returns the version of `key_id` currently used to encrypt new data.
+    pub fn current_version(&self, key_id: &KeyId) -> Result<u32, Error> {
+        match key_id {
+            KeyId::Master => Ok(self.master_keys.read().current),
+            KeyId::Id(name) => self.with_named_key(name, |_, version| version),
+            KeyId::None => unreachable!(),
+        }
+    }
+
+    /// Removes every version of the key named `name` older than `version`,
+    /// returning the number of versions removed. Data encrypted with the
+    /// removed versions can no longer be decrypted.
+    pub fn retire_named_key_versions_before(
+        &self,
+        name: &str,
+        version: u32,
+    ) -> Result<usize, Error> {
+        let mut named_keys = self.named_keys.write();
+        let key = named_keys
+            .keys
+            .get_mut(name)
+            .ok_or_else(|| Error::EncryptionKeyNotFound(name.to_string()))?;
+        let retired = key
+            .versions
+            .keys()
+            .copied()
+            .filter(|existing| *existing < version && *existing != key.current)
+            .collect::<Vec<_>>();
+        if retired.is_empty() {
+            return Ok(0);
+        }
+
+        let retired = retired
+            .into_iter()
+            .filter_map(|retired| key.versions.remove(&retired).map(|key| (retired, key)))
+            .collect::<Vec<_>>();
+        if let Err(err) = self.save_named_keys(&named_keys) {
+            let key = named_keys.keys.get_mut(name).unwrap();
+            key.versions.extend(retired);
+            return Err(err);
+        }
+        Ok(retired.len())
+    }
+
+    /// Replaces the vault key with a newly generated key and re-seals the
+    /// master keys with it.
+    ///
+    /// The re-sealed master keys are written next to the existing file before
+    /// the new vault key is stored. If the process is interrupted after
+    /// storing the new vault key, the re-sealed file is picked up when the
+    /// vault is next unsealed.
+    pub fn rotate_vault_key(&self, server_id: StorageId) -> Result<(), Error> {
+        let mut master_keys = self.master_keys.write();
+        let (private, public) = DhP256HkdfSha256::gen_keypair(&mut thread_rng());
+        let sealed = seal_master_keys(&public, &master_keys.keys)?;
+        let pending_path = master_keys
+            .path
+            .as_ref()
+            .map(|path| path.with_extension("new"));
+        if let Some(pending_path) = &pending_path {
+            write_atomically(pending_path, &sealed).map_err(|err| {
+                Error::Encryption(format!("error saving re-sealed master keys: {err:?}"))
+            })?;
+        }
+
+        store_vault_key(
+            self.master_key_storage.as_ref(),
+            server_id,
+            private,
+            &public,
+        )?;
+
+        if let (Some(pending_path), Some(path)) = (pending_path, &master_keys.path) {
+            fs::rename(pending_path, path).map_err(|err| {
+                Error::Encryption(format!("error replacing master keys: {err:?}"))
+            })?;
+        }
+        master_keys.vault_public_key = public;
+        Ok(())
     }
 
     fn initialize_vault_key_storage(
@@ -200,67 +510,27 @@ impl Vault {
         let master_key = EncryptionKey::random();
         let (private, public) = DhP256HkdfSha256::gen_keypair(&mut thread_rng());
 
-        master_key_storage
-            .set_vault_key_for(
-                server_id,
-                KeyPair::P256 {
-                    private,
-                    public: public.clone(),
-                },
-            )
-            .map_err(|err| Error::VaultKeyStorage(err.to_string()))?;
-        let mut master_keys = HashMap::new();
-        master_keys.insert(0_u32, master_key);
-        // Beacuse this is such a critical step, let's verify that we can
-        // retrieve the key before we store the sealing key.
-        let retrieved = master_key_storage
-            .vault_key_for(server_id)
-            .map_err(|err| Error::VaultKeyStorage(err.to_string()))?;
-        let expected_public_key_bytes = PublicKey::P256(public.clone()).to_bytes().unwrap();
-        let retrieved_key_matches = retrieved
-            .map(|r| PublicKey::from(&r).to_bytes().ok() == Some(expected_public_key_bytes))
-            .unwrap_or_default();
-        if retrieved_key_matches {
-            let mut serialized_master_keys = bincode::serialize(&master_keys)?;
-
-            let (encapsulated_key, aead_tag) = hpke::single_shot_seal_in_place_detached::<
-                ChaCha20Poly1305,
-                HkdfSha256,
-                DhP256HkdfSha256,
-                _,
-            >(
-                &OpModeS::Base,
-                &public,
-                b"",
-                &mut serialized_master_keys,
-                b"",
-                &mut thread_rng(),
-            )?;
-            let mut tag = [0_u8; 16];
-            tag.copy_from_slice(&aead_tag.to_bytes());
-
-            let encrypted_master_keys_payload = bincode::serialize(&HpkePayload {
-                encryption: PublicKeyEncryption::DhP256HkdfSha256ChaCha20,
-                payload: Bytes::from(serialized_master_keys),
-                encapsulated_key,
-                tag,
-            })?;
-
-            File::create(master_keys_path)
-                .and_then(move |mut file| file.write_all(&encrypted_master_keys_payload))
-                .map_err(|err| Error::Initializing(format!("error saving vault key: {err:?}")))?;
-
-            Ok(Self {
-                _vault_public_key: PublicKey::P256(public),
-                master_keys,
-                current_master_key_id: 0,
-                master_key_storage,
-            })
-        } else {
-            Err(Error::VaultKeyStorage(String::from(
-                "vault key storage failed to return the same stored key during initialization",
-            )))
-        }
+        // Beacuse this is such a critical step, the key is verified to be
+        // retrievable before we store the sealed master keys.
+        store_vault_key(master_key_storage.as_ref(), server_id, private, &public)?;
+
+        let mut keys = HashMap::new();
+        keys.insert(0_u32, master_key);
+        let master_keys = MasterKeys {
+            vault_public_key: public,
+            path: Some(master_keys_path.to_path_buf()),
+            keys,
+            current: 0,
+        };
+        master_keys
+            .save()
+            .map_err(|err| Error::Initializing(format!("error saving vault key: {err:?}")))?;
+
+        Ok(Self {
+            master_keys: RwLock::new(master_keys),
+            master_key_storage,
+            named_keys: RwLock::default(),
+        })
     }
 
     fn unseal(
@@ -268,56 +538,56 @@ impl Vault {
         server_id: StorageId,
         master_key_storage: Arc<dyn AnyVaultKeyStorage>,
     ) -> Result<Self, Error> {
-        // The vault has been initilized previously. Do not overwrite this file voluntarily.
-        let encrypted_master_keys = std::fs::read(master_keys_path)
-            .map_err(|err| Error::Initializing(format!("error reading master keys: {err:?}")))?;
-        let mut encrypted_master_keys =
-            bincode::deserialize::<HpkePayload>(&encrypted_master_keys)?;
-        let PublicKeyEncryption::DhP256HkdfSha256ChaCha20 = &encrypted_master_keys.encryption;
-        if let Some(vault_key) = master_key_storage
+        let Some(vault_key) = master_key_storage
             .vault_key_for(server_id)
             .map_err(|err| Error::VaultKeyStorage(err.to_string()))?
-        {
-            let master_keys = match &vault_key {
-                KeyPair::P256 { private, .. } => {
-                    let mut decryption_context =
-                        hpke::setup_receiver::<ChaCha20Poly1305, HkdfSha256, DhP256HkdfSha256>(
-                            &hpke::OpModeR::Base,
-                            private,
-                            &encrypted_master_keys.encapsulated_key,
-                            b"",
-                        )
-                        .unwrap();
-
-                    decryption_context
-                        .open_in_place_detached(
-                            &mut encrypted_master_keys.payload.0,
-                            b"",
-                            &AeadTag::<ChaCha20Poly1305>::from_bytes(&encrypted_master_keys.tag)
-                                .unwrap(),
-                        )
-                        .unwrap();
-
-                    bincode::deserialize::<HashMap<u32, EncryptionKey>>(
-                        &encrypted_master_keys.payload,
-                    )?
-                }
-            };
-
-            let current_master_key_id = *master_keys.keys().max().unwrap();
-            Ok(Self {
-                _vault_public_key: PublicKey::from(&vault_key),
-                master_keys,
-                current_master_key_id,
-                master_key_storage,
-            })
-        } else {
-            Err(Error::VaultKeyNotFound)
+        else {
+            return Err(Error::VaultKeyNotFound);
+        };
+
+        // If a vault key rotation was interrupted, the master keys re-sealed
+        // with the new key may not have replaced the original file yet.
+        let pending_path = master_keys_path.with_extension("new");
+        let mut keys = None;
+        if pending_path.exists() {
+            if let Ok(unsealed) = std::fs::read(&pending_path)
+                .map_err(|err| Error::Initializing(format!("error reading master keys: {err:?}")))
+                .and_then(|sealed| open_master_keys(&vault_key, &sealed))
+            {
+                fs::rename(&pending_path, master_keys_path).map_err(|err| {
+                    Error::Initializing(format!("error replacing master keys: {err:?}"))
+                })?;
+                keys = Some(unsealed);
+            } else {
+                // The new vault key was never stored.
+                let _ = fs::remove_file(&pending_path);
+            }
         }
-    }
 
-    fn current_master_key(&self) -> &EncryptionKey {
-        self.master_keys.get(&self.current_master_key_id).unwrap()
+        let keys = match keys {
+            Some(keys) => keys,
+            None => {
+                // The vault has been initilized previously. Do not overwrite
+                // this file voluntarily.
+                let sealed = std::fs::read(master_keys_path).map_err(|err| {
+                    Error::Initializing(format!("error reading master keys: {err:?}"))
+                })?;
+                open_master_keys(&vault_key, &sealed)?
+            }
+        };
+
+        let current = *keys.keys().max().unwrap();
+        let (KeyPair::P256 { public, .. } | KeyPair::P256Share { public, .. }) = vault_key;
+        Ok(Self {
+            master_keys: RwLock::new(MasterKeys {
+                vault_public_key: public,
+                path: Some(master_keys_path.to_path_buf()),
+                keys,
+                current,
+            }),
+            master_key_storage,
+            named_keys: RwLock::default(),
+        })
     }
 
     pub fn encrypt_payload(
@@ -333,12 +603,20 @@ impl Vault {
             )?;
         }
 
-        let (key, version) = match key_id {
-            KeyId::Master => (self.current_master_key(), self.current_master_key_id),
-            KeyId::Id(_) => todo!(),
+        let payload = match key_id {
+            KeyId::Master => {
+                let master_keys = self.master_keys.read();
+                master_keys.current_key().encrypt_payload(
+                    key_id.clone(),
+                    master_keys.current,
+                    payload,
+                )
+            }
+            KeyId::Id(name) => self.with_named_key(name, |key, version| {
+                key.encrypt_payload(key_id.clone(), version, payload)
+            })?,
             KeyId::None => unreachable!(),
         };
-        let payload = key.encrypt_payload(key_id.clone(), version, payload);
         Ok(payload.to_vec())
     }
 
@@ -370,16 +648,143 @@ impl Vault {
             )?;
         }
 
-        // TODO handle key version
-        let key = match &payload.key_id {
-            KeyId::Master => self.current_master_key(),
-            KeyId::Id(_) => todo!(),
+        match &payload.key_id {
+            KeyId::Master => {
+                let master_keys = self.master_keys.read();
+                let key = master_keys
+                    .keys
+                    .get(&payload.key_version)
+                    .ok_or_else(|| unknown_key_version(&payload.key_id, payload.key_version))?;
+                Ok(key.decrypt_payload(payload)?)
+            }
+            KeyId::Id(name) => {
+                let named_keys = self.named_keys.read();
+                let key = named_keys
+                    .keys
+                    .get(name.as_ref())
+                    .ok_or_else(|| Error::EncryptionKeyNotFound(name.to_string()))?
+                    .versions
+                    .get(&payload.key_version)
+                    .ok_or_else(|| unknown_key_version(&payload.key_id, payload.key_version))?;
+                Ok(key.decrypt_payload(payload)?)
+            }
             KeyId::None => unreachable!(),
-        };
-        Ok(key.decrypt_payload(payload)?)
+        }
     }
 }
 
+fn unknown_key_version(key_id: &KeyId, version: u32) -> Error {
+    Error::Encryption(format!("unknown version {version} of key {key_id:?}"))
+}
+
+/// Stores `private` as the vault key for `server_id`, verifying that the
+/// storage returns the same key.
+fn store_vault_key(
+    master_key_storage: &dyn AnyVaultKeyStorage,
+    server_id: StorageId,
+    private: <DhP256HkdfSha256 as Kem>::PrivateKey,
+    public: &<DhP256HkdfSha256 as Kem>::PublicKey,
+) -> Result<(), Error> {
+    master_key_storage
+        .set_vault_key_for(
+            server_id,
+            KeyPair::P256 {
+                private,
+                public: public.clone(),
+            },
+        )
+        .map_err(|err| Error::VaultKeyStorage(err.to_string()))?;
+    let retrieved = master_key_storage
+        .vault_key_for(server_id)
+        .map_err(|err| Error::VaultKeyStorage(err.to_string()))?;
+    let expected_public_key_bytes = PublicKey::P256(public.clone()).to_bytes()?;
+    let retrieved_key_matches = retrieved
+        .map(|r| PublicKey::from(&r).to_bytes().ok() == Some(expected_public_key_bytes))
+        .unwrap_or_default();
+    if retrieved_key_matches {
+        Ok(())
+    } else {
+        Err(Error::VaultKeyStorage(String::from(
+            "vault key storage failed to return the same stored key",
+        )))
+    }
+}
+
+fn seal_master_keys(
+    public: &<DhP256HkdfSha256 as Kem>::PublicKey,
+    master_keys: &HashMap<u32, EncryptionKey>,
+) -> Result<Vec<u8>, Error> {
+    let mut serialized_master_keys = bincode::serialize(master_keys)?;
+
+    let (encapsulated_key, aead_tag) = hpke::single_shot_seal_in_place_detached::<
+        ChaCha20Poly1305,
+        HkdfSha256,
+        DhP256HkdfSha256,
+        _,
+    >(
+        &OpModeS::Base,
+        public,
+        b"",
+        &mut serialized_master_keys,
+        b"",
+        &mut thread_rng(),
+    )?;
+    let mut tag = [0_u8; 16];
+    tag.copy_from_slice(&aead_tag.to_bytes());
+
+    Ok(bincode::serialize(&HpkePayload {
+        encryption: PublicKeyEncryption::DhP256HkdfSha256ChaCha20,
+        payload: Bytes::from(serialized_master_keys),
+        encapsulated_key,
+        tag,
+    })?)
+}
+
+fn open_master_keys(
+    vault_key: &KeyPair,
+    sealed: &[u8],
+) -> Result<HashMap<u32, EncryptionKey>, Error> {
+    let mut encrypted_master_keys = bincode::deserialize::<HpkePayload>(sealed)?;
+    let PublicKeyEncryption::DhP256HkdfSha256ChaCha20 = &encrypted_master_keys.encryption;
+    match vault_key {
+        KeyPair::P256 { private, .. } => {
+            let mut decryption_context =
+                hpke::setup_receiver::<ChaCha20Poly1305, HkdfSha256, DhP256HkdfSha256>(
+                    &hpke::OpModeR::Base,
+                    private,
+                    &encrypted_master_keys.encapsulated_key,
+                    b"",
+                )?;
+
+            decryption_context.open_in_place_detached(
+                &mut encrypted_master_keys.payload.0,
+                b"",
+                &AeadTag::<ChaCha20Poly1305>::from_bytes(&encrypted_master_keys.tag)?,
+            )?;
+
+            let master_keys = bincode::deserialize::<HashMap<u32, EncryptionKey>>(
+                &encrypted_master_keys.payload,
+            )?;
+            encrypted_master_keys.payload.0.zeroize();
+            Ok(master_keys)
+        }
+        KeyPair::P256Share { .. } => Err(Error::VaultKeyStorage(String::from(
+            "vault key storage returned a key share instead of a key",
+        ))),
+    }
+}
+
+/// Writes `contents` alongside `path` before replacing it, preventing a
+/// partial write from losing the existing contents.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let temporary_path = path.with_extension("tmp");
+    File::create(&temporary_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    })?;
+    fs::rename(&temporary_path, path)
+}
+
 /// Stores encrypted keys for a vault.
 pub trait VaultKeyStorage: Send + Sync + Debug + 'static {
     /// The error type that the functions return.
@@ -654,16 +1059,20 @@ mod tests {
     }
 
     fn random_null_vault() -> Vault {
-        let mut master_keys = HashMap::new();
-        master_keys.insert(0, EncryptionKey::random());
+        let mut keys = HashMap::new();
+        keys.insert(0, EncryptionKey::random());
 
         let (_, public_key) = <DhP256HkdfSha256 as Kem>::gen_keypair(&mut thread_rng());
 
         Vault {
-            _vault_public_key: PublicKey::P256(public_key),
-            master_keys,
-            current_master_key_id: 0,
+            master_keys: RwLock::new(MasterKeys {
+                vault_public_key: public_key,
+                path: None,
+                keys,
+                current: 0,
+            }),
             master_key_storage: Arc::new(NullKeyStorage),
+            named_keys: RwLock::default(),
         }
     }
 
@@ -678,6 +1087,67 @@ mod tests {
         assert_eq!(decrypted, b"hello");
     }
 
+    #[test]
+    fn vault_named_key_test() {
+        let vault = random_null_vault();
+        let tenant_a = KeyId::Id(Cow::Borrowed("tenant-a"));
+        let tenant_b = KeyId::Id(Cow::Borrowed("tenant-b"));
+        let encrypted_a = vault.encrypt_payload(&tenant_a, b"hello", None).unwrap();
+        let encrypted_b = vault.encrypt_payload(&tenant_b, b"world", None).unwrap();
+        assert_eq!(vault.decrypt_payload(&encrypted_a, None).unwrap(), b"hello");
+
+        vault.delete_named_key("tenant-a").unwrap();
+        assert!(matches!(
+            vault.decrypt_payload(&encrypted_a, None),
+            Err(crate::Error::Vault(Error::EncryptionKeyNotFound(_)))
+        ));
+        assert_eq!(vault.decrypt_payload(&encrypted_b, None).unwrap(), b"world");
+        assert!(matches!(
+            vault.delete_named_key("tenant-a"),
+            Err(Error::EncryptionKeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn vault_key_rotation_test() {
+        let vault = random_null_vault();
+        let tenant = KeyId::Id(Cow::Borrowed("tenant"));
+        let master_before = vault
+            .encrypt_payload(&KeyId::Master, b"hello", None)
+            .unwrap();
+        let tenant_before = vault.encrypt_payload(&tenant, b"world", None).unwrap();
+
+        vault.rotate_key(&KeyId::Master).unwrap();
+        vault.rotate_key(&tenant).unwrap();
+        assert!(matches!(
+            vault.rotate_key(&KeyId::Id(Cow::Borrowed("missing"))),
+            Err(Error::EncryptionKeyNotFound(_))
+        ));
+
+        let master_after = vault
+            .encrypt_payload(&KeyId::Master, b"hello", None)
+            .unwrap();
+        let tenant_after = vault.encrypt_payload(&tenant, b"world", None).unwrap();
+        assert_eq!(
+            VaultPayload::from_slice(&master_after).unwrap().key_version,
+            1
+        );
+        assert_eq!(
+            VaultPayload::from_slice(&tenant_after).unwrap().key_version,
+            1
+        );
+
+        // Data encrypted with previous versions remains readable.
+        for (encrypted, expected) in [
+            (master_before, b"hello"),
+            (master_after, b"hello"),
+            (tenant_before, b"world"),
+            (tenant_after, b"world"),
+        ] {
+            assert_eq!(vault.decrypt_payload(&encrypted, None).unwrap(), expected);
+        }
+    }
+
     #[test]
     fn vault_permissions_test() {
         let vault = random_null_vault();
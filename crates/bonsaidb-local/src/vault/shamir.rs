@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use hpke::kem::DhP256HkdfSha256;
+use hpke::{Deserializable, Kem, Serializable};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{AnyVaultKeyStorage, Error, KeyPair, VaultKeyStorage};
+use crate::storage::StorageId;
+
+/// A [`VaultKeyStorage`] that splits the vault key across multiple inner
+/// storages using [Shamir's secret
+/// sharing](https://en.wikipedia.org/wiki/Shamir%27s_secret_sharing).
+///
+/// Each inner storage receives one share of the vault key. Any `threshold`
+/// shares can recover the key, while fewer shares reveal nothing about it.
+/// This ensures that compromising a single key storage does not expose the
+/// vault key, and allows the vault to be unsealed while some key storages are
+/// unavailable.
+///
+/// Storing a key requires every inner storage to succeed. Each inner storage
+/// keeps its share of the previous key until the next key is stored, and if
+/// storing a key fails partway through, the previous key is still returned.
+/// Retrieving a key succeeds as long as `threshold` shares from the same split
+/// can be retrieved.
+///
+/// ```rust
+/// # use bonsaidb_local::config::{Builder, StorageConfiguration};
+/// # use bonsaidb_local::vault::{LocalVaultKeyStorage, ShamirVaultKeyStorage};
+/// # fn test() -> anyhow::Result<()> {
+/// let configuration = StorageConfiguration::new("my-db.bonsaidb").vault_key_storage(
+///     ShamirVaultKeyStorage::new(2)
+///         .with_storage(LocalVaultKeyStorage::new("/mnt/a/vault-keys")?)
+///         .with_storage(LocalVaultKeyStorage::new("/mnt/b/vault-keys")?)
+///         .with_storage(LocalVaultKeyStorage::new("/mnt/c/vault-keys")?),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct ShamirVaultKeyStorage {
+    threshold: u8,
+    storages: Vec<Arc<dyn AnyVaultKeyStorage>>,
+}
+
+impl ShamirVaultKeyStorage {
+    /// Returns a new instance that requires `threshold` shares to recover the
+    /// vault key. Inner storages are added using [`Self::with_storage()`].
+    pub fn new(threshold: u8) -> Self {
+        Self {
+            threshold,
+            storages: Vec::new(),
+        }
+    }
+
+    /// Adds `storage` as a location to store a share of the vault key.
+    pub fn with_storage<S: AnyVaultKeyStorage>(self, storage: S) -> Self {
+        self.with_shared_storage(Arc::new(storage))
+    }
+
+    /// Adds `storage` as a location to store a share of the vault key.
+    pub fn with_shared_storage(mut self, storage: Arc<dyn AnyVaultKeyStorage>) -> Self {
+        self.storages.push(storage);
+        self
+    }
+
+    fn validate(&self) -> Result<u8, Error> {
+        let share_count = u8::try_from(self.storages.len())
+            .map_err(|_| Error::VaultKeyStorage(String::from("too many key storages")))?;
+        if self.threshold == 0 || self.threshold > share_count {
+            return Err(Error::VaultKeyStorage(format!(
+                "threshold {} requires between 1 and {share_count} key storages",
+                self.threshold
+            )));
+        }
+        Ok(share_count)
+    }
+}
+
+impl VaultKeyStorage for ShamirVaultKeyStorage {
+    type Error = Error;
+
+    fn set_vault_key_for(&self, storage_id: StorageId, key: KeyPair) -> Result<(), Self::Error> {
+        let share_count = self.validate()?;
+        let KeyPair::P256 { private, public } = key else {
+            return Err(Error::VaultKeyStorage(String::from(
+                "a key share can't be split further",
+            )));
+        };
+
+        // Each new share is stored alongside the storage's current share. If
+        // any storage fails to store its new share, the existing split can
+        // still be recovered.
+        let existing = self
+            .storages
+            .iter()
+            .map(|storage| storage.vault_key_for(storage_id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let secret = Zeroizing::new(private.to_bytes().to_vec());
+        let split_id = thread_rng().gen();
+        let shares = split(&secret, self.threshold, share_count);
+        let mut stored = 0;
+        let mut result = Ok(());
+        for ((storage, (index, value)), existing) in self.storages.iter().zip(shares).zip(&existing)
+        {
+            let previous = match existing {
+                Some(KeyPair::P256Share {
+                    public: previous_public,
+                    share,
+                }) => Some(Box::new(PreviousKeyShare {
+                    public: previous_public.clone(),
+                    share: share.without_previous(),
+                })),
+                _ => None,
+            };
+            result = storage.set_vault_key_for(
+                storage_id,
+                KeyPair::P256Share {
+                    public: public.clone(),
+                    share: KeyShare {
+                        split_id,
+                        threshold: self.threshold,
+                        index,
+                        value,
+                        previous,
+                    },
+                },
+            );
+            if result.is_err() {
+                break;
+            }
+            stored += 1;
+        }
+
+        if result.is_err() {
+            // Restore the shares that were replaced. If this fails, the
+            // previous split is still recoverable from the new shares.
+            for (storage, existing) in self.storages.iter().zip(existing).take(stored) {
+                if let Some(existing) = existing {
+                    if let Err(err) = storage.set_vault_key_for(storage_id, existing) {
+                        log::error!("error restoring previous vault key share: {err}");
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn vault_key_for(&self, storage_id: StorageId) -> Result<Option<KeyPair>, Self::Error> {
+        self.validate()?;
+        let mut first_error = None;
+        let mut found_shares = false;
+        let mut split_order = Vec::new();
+        let mut splits = HashMap::<u64, (_, Vec<KeyShare>)>::new();
+        // Pairs of (split_id, previous split_id) for each retrieved share.
+        let mut replacements = Vec::new();
+        for storage in &self.storages {
+            match storage.vault_key_for(storage_id) {
+                Ok(Some(KeyPair::P256Share { public, mut share })) => {
+                    found_shares = true;
+                    let previous = share.previous.take();
+                    if let Some(previous) = &previous {
+                        replacements.push((share.split_id, previous.share.split_id));
+                    }
+                    for (public, share) in
+                        std::iter::once((public, share)).chain(previous.map(|previous| {
+                            let PreviousKeyShare { public, share } = *previous;
+                            (public, share)
+                        }))
+                    {
+                        if let Err(err) =
+                            self.add_share(public, share, &mut splits, &mut split_order)
+                        {
+                            first_error.get_or_insert(err);
+                        }
+                    }
+                }
+                Ok(Some(KeyPair::P256 { .. })) => {
+                    first_error.get_or_insert_with(|| {
+                        Error::VaultKeyStorage(String::from(
+                            "key storage returned a key instead of a key share",
+                        ))
+                    });
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        // A split that has been replaced by a recoverable split is only used
+        // if the rotation that replaced it didn't complete.
+        let threshold = usize::from(self.threshold);
+        let recoverable = |split_id: u64| {
+            splits
+                .get(&split_id)
+                .is_some_and(|(_, shares)| shares.len() >= threshold)
+        };
+        let current = split_order.iter().copied().find(|&split_id| {
+            recoverable(split_id)
+                && !replacements.iter().any(|&(replacement, replaced)| {
+                    replaced == split_id && recoverable(replacement)
+                })
+        });
+        if let Some(split_id) = current {
+            let (public, shares) = splits.remove(&split_id).unwrap();
+            let secret = combine(&shares);
+            let private = <DhP256HkdfSha256 as Kem>::PrivateKey::from_bytes(&secret)?;
+            return Ok(Some(KeyPair::P256 { private, public }));
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None if found_shares => Err(Error::VaultKeyStorage(format!(
+                "{} key shares are required to recover the vault key",
+                self.threshold
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl ShamirVaultKeyStorage {
+    fn add_share(
+        &self,
+        public: <DhP256HkdfSha256 as Kem>::PublicKey,
+        share: KeyShare,
+        splits: &mut HashMap<u64, (<DhP256HkdfSha256 as Kem>::PublicKey, Vec<KeyShare>)>,
+        split_order: &mut Vec<u64>,
+    ) -> Result<(), Error> {
+        // Combining shares from a split with a different threshold would
+        // silently produce the wrong key.
+        if share.threshold != self.threshold {
+            return Err(Error::VaultKeyStorage(format!(
+                "key share requires {} shares, but the threshold is {}",
+                share.threshold, self.threshold
+            )));
+        }
+        let (_, shares) = splits.entry(share.split_id).or_insert_with(|| {
+            split_order.push(share.split_id);
+            (public, Vec::new())
+        });
+        // The same share returned by multiple storages must only be counted
+        // once.
+        if shares.iter().any(|existing| existing.index == share.index) {
+            return Err(Error::VaultKeyStorage(format!(
+                "multiple key storages returned key share {}",
+                share.index
+            )));
+        }
+        shares.push(share);
+        Ok(())
+    }
+}
+
+/// One share of a key split by [`ShamirVaultKeyStorage`].
+#[derive(Serialize, Deserialize)]
+pub struct KeyShare {
+    split_id: u64,
+    threshold: u8,
+    index: u8,
+    value: Vec<u8>,
+    previous: Option<Box<PreviousKeyShare>>,
+}
+
+impl KeyShare {
+    fn without_previous(&self) -> Self {
+        Self {
+            split_id: self.split_id,
+            threshold: self.threshold,
+            index: self.index,
+            value: self.value.clone(),
+            previous: None,
+        }
+    }
+}
+
+/// The share this storage held before the current share was stored.
+#[derive(Serialize, Deserialize)]
+struct PreviousKeyShare {
+    public: <DhP256HkdfSha256 as Kem>::PublicKey,
+    share: KeyShare,
+}
+
+impl Debug for KeyShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyShare")
+            .field("split_id", &self.split_id)
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .field(
+                "previous_split_id",
+                &self
+                    .previous
+                    .as_ref()
+                    .map(|previous| previous.share.split_id),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for KeyShare {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Splits `secret` into `share_count` shares, any `threshold` of which can be
+/// combined to recover it. Each byte of the secret is the constant term of a
+/// random polynomial of degree `threshold - 1` over GF(256), and each share
+/// contains the polynomials evaluated at the share's nonzero index.
+fn split(secret: &[u8], threshold: u8, share_count: u8) -> Vec<(u8, Vec<u8>)> {
+    let mut rng = thread_rng();
+    let mut shares = (1..=share_count)
+        .map(|index| (index, Vec::with_capacity(secret.len())))
+        .collect::<Vec<_>>();
+    let mut coefficients = Zeroizing::new(vec![0_u8; usize::from(threshold)]);
+    for &byte in secret {
+        coefficients[0] = byte;
+        rng.fill(&mut coefficients[1..]);
+        for (index, value) in &mut shares {
+            // Horner's method, starting with the highest degree.
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0, |y, &coefficient| gf_mul(y, *index) ^ coefficient);
+            value.push(y);
+        }
+    }
+    shares
+}
+
+/// Recovers the secret from `shares` by evaluating the Lagrange interpolating
+/// polynomial at zero.
+fn combine(shares: &[KeyShare]) -> Zeroizing<Vec<u8>> {
+    let length = shares
+        .iter()
+        .map(|share| share.value.len())
+        .min()
+        .unwrap_or(0);
+    let mut secret = Zeroizing::new(vec![0_u8; length]);
+    for share in shares {
+        // In GF(256), subtraction is the same as addition: xor.
+        let basis = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold(1, |basis, other| {
+                gf_mul(basis, gf_div(other.index, other.index ^ share.index))
+            });
+        for (secret, &value) in secret.iter_mut().zip(&share.value) {
+            *secret ^= gf_mul(basis, value);
+        }
+    }
+    secret
+}
+
+/// Multiplies in GF(256) using the polynomial x^8 + x^4 + x^3 + x + 1.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    // The multiplicative group has order 255, so b^254 is b's inverse.
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shares(secret: &[u8], threshold: u8, share_count: u8) -> Vec<KeyShare> {
+        split(secret, threshold, share_count)
+            .into_iter()
+            .map(|(index, value)| KeyShare {
+                split_id: 0,
+                threshold,
+                index,
+                value,
+                previous: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_and_combine() {
+        let secret = b"a secret that is 32 bytes long!!";
+        let mut shares = shares(secret, 3, 5);
+        assert!(shares.iter().all(|share| &share.value[..] != secret));
+
+        // Any three shares recover the secret.
+        assert_eq!(&combine(&shares[..3])[..], secret);
+        assert_eq!(&combine(&shares[2..])[..], secret);
+        shares.swap(0, 4);
+        assert_eq!(&combine(&shares[1..4])[..], secret);
+
+        // Two shares do not.
+        assert_ne!(&combine(&shares[..2])[..], secret);
+    }
+}
@@ -132,6 +132,10 @@ impl Job for IntegrityScanner {
 
         Ok(task)
     }
+
+    fn kind(&self) -> &'static str {
+        "integrity_scan"
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
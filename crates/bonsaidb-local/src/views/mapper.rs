@@ -99,6 +99,10 @@ impl Job for Mapper {
 
         Ok(transaction_id)
     }
+
+    fn kind(&self) -> &'static str {
+        "view_map"
+    }
 }
 
 fn map_view(
@@ -2,6 +2,8 @@ use std::convert::Infallible;
 use std::fmt::Debug;
 
 use async_trait::async_trait;
+#[cfg(any(feature = "jwt-authentication", feature = "certificate-authentication"))]
+use bonsaidb_core::connection::IdentityReference;
 use bonsaidb_core::connection::Session;
 use bonsaidb_core::permissions::PermissionDenied;
 use bonsaidb_core::schema::{InsertError, InvalidNameError};
@@ -86,6 +88,36 @@ pub trait Backend: Debug + Send + Sync + Sized + 'static {
         Ok(())
     }
 
+    /// Returns the identity a client authenticating with a verified JSON Web
+    /// Token should be authenticated as. Returning `None` rejects the
+    /// authentication attempt.
+    ///
+    /// By default, all tokens are rejected.
+    #[cfg(feature = "jwt-authentication")]
+    #[allow(unused_variables)]
+    async fn identity_for_jwt_claims(
+        &self,
+        claims: &crate::jwt::JwtClaims,
+        server: &CustomServer<Self>,
+    ) -> Result<Option<IdentityReference<'static>>, BackendError<Self::Error>> {
+        Ok(None)
+    }
+
+    /// Returns the identity a client that presented a verified TLS client
+    /// certificate should be authenticated as. Returning `None` rejects the
+    /// connection.
+    ///
+    /// By default, all certificates are rejected.
+    #[cfg(feature = "certificate-authentication")]
+    #[allow(unused_variables)]
+    async fn identity_for_client_certificate(
+        &self,
+        certificate: &crate::client_certificate::ClientCertificate,
+        server: &CustomServer<Self>,
+    ) -> Result<Option<IdentityReference<'static>>, BackendError<Self::Error>> {
+        Ok(None)
+    }
+
     /// A client's session has ended.
     ///
     /// If `disconnecting` is true, the session is ending because the client is
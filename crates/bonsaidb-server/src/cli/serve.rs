@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 #[cfg(any(feature = "websockets", feature = "acme"))]
-use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::net::{Ipv6Addr, SocketAddrV6};
 #[cfg(feature = "acme")]
 use std::time::Duration;
 
@@ -33,6 +34,12 @@ pub struct Serve<B: Backend> {
     #[clap(long = "https")]
     pub https_port: Option<SocketAddr>,
 
+    #[cfg(feature = "hyper")]
+    /// The bind port and address for serving Prometheus metrics over HTTP.
+    /// Metrics are only served when this option is specified.
+    #[clap(long = "metrics")]
+    pub metrics_address: Option<SocketAddr>,
+
     #[clap(skip)]
     _backend: PhantomData<B>,
 }
@@ -95,6 +102,14 @@ impl<B: Backend> Serve<B> {
             }
         }
 
+        #[cfg(feature = "hyper")]
+        if let Some(metrics_address) = self.metrics_address {
+            let task_server = server.clone();
+            tokio::task::spawn(
+                async move { task_server.listen_for_metrics_on(metrics_address).await },
+            );
+        }
+
         let task_server = server.clone();
         tokio::task::spawn(async move { task_server.listen_on(config).await });
 
@@ -0,0 +1,172 @@
+//! Authentication using TLS client certificates.
+//!
+//! When [`ServerConfiguration::client_certificates`] is set, the QUIC listener
+//! and secure TCP/`WebSocket` listeners request a certificate from each client
+//! during the TLS handshake. When a client presents a certificate, the server:
+//!
+//! 1. Verifies the certificate chain against
+//!    [`ClientCertificateConfiguration::roots`].
+//! 2. Invokes
+//!    [`Backend::identity_for_client_certificate`](crate::Backend::identity_for_client_certificate)
+//!    to map the certificate to a [`User`](bonsaidb_core::admin::User) or
+//!    [`Role`](bonsaidb_core::admin::Role).
+//! 3. Checks that the identity is permitted to
+//!    [`Authenticate`](bonsaidb_core::permissions::bonsai::ServerAction::Authenticate)
+//!    using
+//!    [`AuthenticationMethod::ClientCertificate`](bonsaidb_core::connection::AuthenticationMethod::ClientCertificate).
+//!
+//! If these steps succeed, the client's unauthenticated session is replaced
+//! with a session authenticated as the identity. If any step fails, the
+//! connection is rejected. Clients that don't present a certificate are
+//! rejected if [`ClientCertificateConfiguration::required`] is true, and
+//! otherwise connect using the server's default permissions.
+//!
+//! [`ServerConfiguration::client_certificates`]: crate::ServerConfiguration#structfield.client_certificates
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use rustls::server::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, ClientCertVerifier,
+};
+use rustls::{Certificate, RootCertStore};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+
+use crate::Error;
+
+/// The certificate authorities that client certificates are verified against.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct ClientCertificateConfiguration {
+    /// The certificate authorities trusted to issue client certificates.
+    pub roots: RootCertStore,
+    /// If true, clients that don't present a certificate are rejected during
+    /// the TLS handshake. Default value is false.
+    pub required: bool,
+}
+
+impl ClientCertificateConfiguration {
+    /// Returns a configuration that verifies client certificates using
+    /// `roots`.
+    pub const fn new(roots: RootCertStore) -> Self {
+        Self {
+            roots,
+            required: false,
+        }
+    }
+
+    /// Adds `certificate` to the trusted certificate authorities and returns
+    /// self.
+    pub fn trusted_root(mut self, certificate: &fabruic::Certificate) -> Result<Self, Error> {
+        self.roots
+            .add(&Certificate(certificate.as_ref().to_vec()))
+            .map_err(|err| Error::other("client-certificates", err))?;
+        Ok(self)
+    }
+
+    /// Sets [`Self::required`](Self#structfield.required) to true and returns
+    /// self.
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub(crate) fn verifier(&self) -> ClientCertificateVerifier {
+        let verifier = if self.required {
+            AllowAnyAuthenticatedClient::new(self.roots.clone()).boxed()
+        } else {
+            AllowAnyAnonymousOrAuthenticatedClient::new(self.roots.clone()).boxed()
+        };
+        ClientCertificateVerifier(verifier)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ClientCertificateVerifier(pub Arc<dyn ClientCertVerifier>);
+
+impl Debug for ClientCertificateVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ClientCertificateVerifier").finish()
+    }
+}
+
+impl ClientCertificateVerifier {
+    pub fn required(&self) -> bool {
+        self.0.client_auth_mandatory()
+    }
+
+    /// Verifies `chain`, whose first certificate is the client's certificate,
+    /// and parses the client's certificate.
+    pub fn verify(&self, chain: Vec<Certificate>) -> Result<ClientCertificate, Error> {
+        let (end_entity, intermediates) = chain
+            .split_first()
+            .ok_or_else(|| Error::other("client-certificates", "empty certificate chain"))?;
+        self.0
+            .verify_client_cert(end_entity, intermediates, SystemTime::now())
+            .map_err(|err| Error::other("client-certificates", err))?;
+        ClientCertificate::parse(chain)
+    }
+}
+
+/// A verified certificate presented by a client.
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    /// The certificate chain presented by the client, starting with the
+    /// client's certificate.
+    pub chain: Vec<Certificate>,
+    /// The certificate's subject distinguished name, formatted as a string,
+    /// e.g., `CN=alice, O=Example`.
+    pub subject: String,
+    /// The common names (`CN`) contained in the certificate's subject.
+    pub common_names: Vec<String>,
+    /// The DNS names contained in the certificate's subject alternative names.
+    pub dns_names: Vec<String>,
+    /// The email addresses contained in the certificate's subject alternative
+    /// names.
+    pub email_addresses: Vec<String>,
+    /// The URIs contained in the certificate's subject alternative names.
+    pub uris: Vec<String>,
+}
+
+impl ClientCertificate {
+    fn parse(chain: Vec<Certificate>) -> Result<Self, Error> {
+        let (_, parsed) = X509Certificate::from_der(&chain[0].0)
+            .map_err(|err| Error::other("client-certificates", err))?;
+        let subject = parsed.subject().to_string();
+        let common_names = parsed
+            .subject()
+            .iter_common_name()
+            .filter_map(|name| name.as_str().ok())
+            .map(String::from)
+            .collect();
+
+        let mut dns_names = Vec::new();
+        let mut email_addresses = Vec::new();
+        let mut uris = Vec::new();
+        if let Some(names) = parsed
+            .subject_alternative_name()
+            .map_err(|err| Error::other("client-certificates", err))?
+        {
+            for name in &names.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) => dns_names.push((*name).to_string()),
+                    GeneralName::RFC822Name(email) => email_addresses.push((*email).to_string()),
+                    GeneralName::URI(uri) => uris.push((*uri).to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            chain,
+            subject,
+            common_names,
+            dns_names,
+            email_addresses,
+            uris,
+        })
+    }
+}
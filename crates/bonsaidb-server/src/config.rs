@@ -17,7 +17,14 @@ use bonsaidb_local::config::{Builder, KeyValuePersistence, StorageConfiguration}
 use bonsaidb_local::vault::AnyVaultKeyStorage;
 
 use crate::api::{AnyHandler, AnyWrapper, Handler};
-use crate::{Backend, Error, NoBackend};
+#[cfg(feature = "certificate-authentication")]
+use crate::client_certificate::ClientCertificateConfiguration;
+#[cfg(feature = "jwt-authentication")]
+use crate::jwt::JwtConfiguration;
+use crate::middleware::Middleware;
+use crate::rate_limit::RateLimits;
+use crate::schedule::{RegisteredTask, Schedule, ScheduledTask};
+use crate::{Backend, CustomServer, Error, NoBackend};
 
 /// Configuration options for [`Server`](crate::Server)
 #[derive(Debug, Clone)]
@@ -33,6 +40,9 @@ pub struct ServerConfiguration<B: Backend = NoBackend> {
     /// relative to `request_workers` such that one client cannot overwhelm the
     /// entire queue.
     pub client_simultaneous_request_limit: usize,
+    /// The rate limits enforced on client requests. By default, requests are
+    /// not rate limited.
+    pub rate_limits: RateLimits,
     /// Number of simultaneous requests to be processed. Default value is 16.
     pub request_workers: usize,
     /// Configuration options for individual databases.
@@ -42,8 +52,18 @@ pub struct ServerConfiguration<B: Backend = NoBackend> {
     /// The ACME settings for automatic TLS certificate management.
     #[cfg(feature = "acme")]
     pub acme: AcmeConfiguration,
+    /// The keys and validation rules used to verify JSON Web Tokens. If
+    /// `None`, JWT authentication is rejected.
+    #[cfg(feature = "jwt-authentication")]
+    pub jwt: Option<JwtConfiguration>,
+    /// The certificate authorities used to verify client certificates. If
+    /// `None`, clients are not asked to present a certificate.
+    #[cfg(feature = "certificate-authentication")]
+    pub client_certificates: Option<ClientCertificateConfiguration>,
 
     pub(crate) custom_apis: HashMap<ApiName, Arc<dyn AnyHandler<B>>>,
+    pub(crate) scheduled_tasks: Vec<RegisteredTask<B>>,
+    pub(crate) middleware: Vec<Arc<dyn Middleware<B>>>,
 }
 
 impl<B: Backend> ServerConfiguration<B> {
@@ -53,14 +73,21 @@ impl<B: Backend> ServerConfiguration<B> {
             backend,
             server_name: String::from("bonsaidb"),
             client_simultaneous_request_limit: 16,
+            rate_limits: RateLimits::default(),
             // TODO this was arbitrarily picked, it probably should be higher,
             // but it also should probably be based on the cpu's capabilities
             request_workers: 16,
             storage: bonsaidb_local::config::StorageConfiguration::default(),
             default_permissions: DefaultPermissions::Permissions(Permissions::default()),
             custom_apis: HashMap::default(),
+            scheduled_tasks: Vec::new(),
+            middleware: Vec::new(),
             #[cfg(feature = "acme")]
             acme: AcmeConfiguration::default(),
+            #[cfg(feature = "jwt-authentication")]
+            jwt: None,
+            #[cfg(feature = "certificate-authentication")]
+            client_certificates: None,
         }
     }
 
@@ -81,6 +108,12 @@ impl<B: Backend> ServerConfiguration<B> {
         self
     }
 
+    /// Sets [`Self::rate_limits`](Self#structfield.rate_limits) to `rate_limits` and returns self.
+    pub fn rate_limits(mut self, rate_limits: RateLimits) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
     /// Sets [`Self::request_workers`](Self#structfield.request_workers) to `workers` and returns self.
     pub const fn request_workers(mut self, workers: usize) -> Self {
         self.request_workers = workers;
@@ -110,9 +143,30 @@ impl<B: Backend> ServerConfiguration<B> {
         self
     }
 
+    /// Sets [`Self::jwt`](Self#structfield.jwt) to `jwt` and returns self.
+    #[cfg(feature = "jwt-authentication")]
+    pub fn jwt(mut self, jwt: JwtConfiguration) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    /// Sets [`Self::client_certificates`](Self#structfield.client_certificates)
+    /// to `client_certificates` and returns self.
+    #[cfg(feature = "certificate-authentication")]
+    pub fn client_certificates(
+        mut self,
+        client_certificates: ClientCertificateConfiguration,
+    ) -> Self {
+        self.client_certificates = Some(client_certificates);
+        self
+    }
+
     /// Registers a `handler` for a [`Api`][api::Api]. When an [`Api`][api::Api] is
     /// received by the server, the handler will be invoked
-    pub fn register_custom_api<Dispatcher: Handler<Api, B> + 'static, Api: api::Api>(
+    pub fn register_custom_api<
+        Dispatcher: Handler<Api, CustomServer<B>> + 'static,
+        Api: api::Api,
+    >(
         &mut self,
     ) -> Result<(), Error> {
         // TODO this should error on duplicate registration.
@@ -124,12 +178,62 @@ impl<B: Backend> ServerConfiguration<B> {
     }
 
     /// Registers the custom api dispatcher and returns self.
-    pub fn with_api<Dispatcher: Handler<Api, B> + 'static, Api: api::Api>(
+    pub fn with_api<Dispatcher: Handler<Api, CustomServer<B>> + 'static, Api: api::Api>(
         mut self,
     ) -> Result<Self, Error> {
         self.register_custom_api::<Dispatcher, Api>()?;
         Ok(self)
     }
+
+    /// Registers `task` to be executed by the server according to `schedule`.
+    /// `name` uniquely identifies the task, and is used to persist the time of
+    /// the task's most recent execution. If the server was not running when an
+    /// execution was scheduled, the task will be executed once when the server
+    /// is opened.
+    pub fn register_scheduled_task<Task: ScheduledTask<B>>(
+        &mut self,
+        name: impl Into<String>,
+        schedule: Schedule,
+        task: Task,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        if self.scheduled_tasks.iter().any(|task| task.name == name) {
+            return Err(Error::ScheduledTaskAlreadyRegistered(name));
+        }
+
+        self.scheduled_tasks.push(RegisteredTask {
+            name,
+            schedule,
+            task: Arc::new(task),
+        });
+        Ok(())
+    }
+
+    /// Registers `task` to be executed by the server according to `schedule`
+    /// and returns self.
+    pub fn with_scheduled_task<Task: ScheduledTask<B>>(
+        mut self,
+        name: impl Into<String>,
+        schedule: Schedule,
+        task: Task,
+    ) -> Result<Self, Error> {
+        self.register_scheduled_task(name, schedule, task)?;
+        Ok(self)
+    }
+
+    /// Registers `middleware` to be invoked for every API request clients
+    /// send. Middleware is invoked in the order it is registered. See the
+    /// [`middleware`](crate::middleware) module for more information.
+    pub fn register_middleware<M: Middleware<B>>(&mut self, middleware: M) {
+        self.middleware.push(Arc::new(middleware));
+    }
+
+    /// Registers `middleware` to be invoked for every API request clients
+    /// send and returns self.
+    pub fn with_middleware<M: Middleware<B>>(mut self, middleware: M) -> Self {
+        self.register_middleware(middleware);
+        self
+    }
 }
 
 impl<B> Default for ServerConfiguration<B>
@@ -279,6 +383,11 @@ impl<B: Backend> Builder for ServerConfiguration<B> {
         self.storage.argon = argon;
         self
     }
+
+    fn audit_log(mut self, audit_log: bonsaidb_local::config::AuditLog) -> Self {
+        self.storage.audit_log = audit_log;
+        self
+    }
 }
 
 /// Configuration for the BonsaiDb network protocol.
@@ -1,26 +1,59 @@
+use std::time::Instant;
+
 use bonsaidb_core::api::ApiName;
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::async_trait::async_trait;
 use bonsaidb_core::connection::{
     AsyncConnection, AsyncLowLevelConnection, AsyncStorageConnection, HasSession,
 };
+#[cfg(any(
+    feature = "password-hashing",
+    feature = "token-authentication",
+    feature = "jwt-authentication"
+))]
+use bonsaidb_core::connection::{Authentication, AuthenticationMethod};
 use bonsaidb_core::keyvalue::AsyncKeyValue;
+#[cfg(any(
+    feature = "password-hashing",
+    feature = "token-authentication",
+    feature = "jwt-authentication"
+))]
+use bonsaidb_core::networking::Authenticate;
+#[cfg(feature = "password-hashing")]
+use bonsaidb_core::networking::SetUserPassword;
 use bonsaidb_core::networking::{
     AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction, AssumeIdentity,
     Compact, CompactCollection, CompactKeyValueStore, Count, CreateDatabase, CreateSubscriber,
     CreateUser, DeleteDatabase, DeleteDocs, DeleteUser, ExecuteKeyOperation, Get, GetMultiple,
-    LastTransactionId, List, ListAvailableSchemas, ListDatabases, ListExecutedTransactions,
-    ListHeaders, LogOutSession, Publish, PublishToAll, Query, QueryWithDocs, Reduce, ReduceGrouped,
-    SubscribeTo, UnregisterSubscriber, UnsubscribeFrom,
+    LastTransactionId, List, ListAvailableSchemas, ListDatabaseUsage, ListDatabases,
+    ListExecutedTransactions, ListHeaders, ListSessions, LogOutSession, Publish, PublishToAll,
+    Query, QueryWithDocs, Reduce, ReduceGrouped, RevokeSession, SetDatabaseQuota, SubscribeTo,
+    SubscribeToPattern, UnregisterSubscriber, UnsubscribeFrom, UnsubscribeFromPattern,
 };
-#[cfg(feature = "password-hashing")]
-use bonsaidb_core::networking::{Authenticate, SetUserPassword};
+#[cfg(feature = "totp-authentication")]
+use bonsaidb_core::networking::{ConfirmUserTotp, EnrollUserTotp, RemoveUserTotp};
+#[cfg(feature = "token-authentication")]
+use bonsaidb_core::networking::{ListAuthenticationTokens, RevokeAuthenticationToken};
+use bonsaidb_core::permissions::bonsai::{
+    BonsaiAction, DatabaseAction, DocumentAction, KeyValueAction, PubSubAction, ServerAction,
+    TransactionAction, ViewAction,
+};
+use bonsaidb_core::permissions::Action;
 use bonsaidb_core::pubsub::AsyncPubSub;
+use bonsaidb_core::transaction::Command;
 
 use crate::api::{Handler, HandlerError, HandlerResult, HandlerSession};
-use crate::{Backend, Error, ServerConfiguration};
+use crate::middleware::ApiRequest;
+use crate::{Backend, CustomServer, Error, ServerConfiguration};
 
-#[cfg_attr(not(feature = "password-hashing"), allow(unused_mut))]
+#[cfg_attr(
+    not(any(
+        feature = "password-hashing",
+        feature = "token-authentication",
+        feature = "jwt-authentication"
+    )),
+    allow(unused_mut)
+)]
 pub fn register_api_handlers<B: Backend>(
     config: ServerConfiguration<B>,
 ) -> Result<ServerConfiguration<B>, Error> {
@@ -47,7 +80,9 @@ pub fn register_api_handlers<B: Backend>(
         .with_api::<ServerDispatcher, ListHeaders>()?
         .with_api::<ServerDispatcher, ListAvailableSchemas>()?
         .with_api::<ServerDispatcher, ListDatabases>()?
+        .with_api::<ServerDispatcher, ListDatabaseUsage>()?
         .with_api::<ServerDispatcher, ListExecutedTransactions>()?
+        .with_api::<ServerDispatcher, ListSessions>()?
         .with_api::<ServerDispatcher, LogOutSession>()?
         .with_api::<ServerDispatcher, Publish>()?
         .with_api::<ServerDispatcher, PublishToAll>()?
@@ -55,15 +90,41 @@ pub fn register_api_handlers<B: Backend>(
         .with_api::<ServerDispatcher, QueryWithDocs>()?
         .with_api::<ServerDispatcher, Reduce>()?
         .with_api::<ServerDispatcher, ReduceGrouped>()?
+        .with_api::<ServerDispatcher, RevokeSession>()?
+        .with_api::<ServerDispatcher, SetDatabaseQuota>()?
         .with_api::<ServerDispatcher, SubscribeTo>()?
+        .with_api::<ServerDispatcher, SubscribeToPattern>()?
         .with_api::<ServerDispatcher, UnregisterSubscriber>()?
-        .with_api::<ServerDispatcher, UnsubscribeFrom>()?;
+        .with_api::<ServerDispatcher, UnsubscribeFrom>()?
+        .with_api::<ServerDispatcher, UnsubscribeFromPattern>()?;
+
+    #[cfg(any(
+        feature = "password-hashing",
+        feature = "token-authentication",
+        feature = "jwt-authentication"
+    ))]
+    {
+        config = config.with_api::<ServerDispatcher, Authenticate>()?;
+    }
 
     #[cfg(feature = "password-hashing")]
+    {
+        config = config.with_api::<ServerDispatcher, SetUserPassword>()?;
+    }
+
+    #[cfg(feature = "totp-authentication")]
     {
         config = config
-            .with_api::<ServerDispatcher, Authenticate>()?
-            .with_api::<ServerDispatcher, SetUserPassword>()?;
+            .with_api::<ServerDispatcher, EnrollUserTotp>()?
+            .with_api::<ServerDispatcher, ConfirmUserTotp>()?
+            .with_api::<ServerDispatcher, RemoveUserTotp>()?;
+    }
+
+    #[cfg(feature = "token-authentication")]
+    {
+        config = config
+            .with_api::<ServerDispatcher, ListAuthenticationTokens>()?
+            .with_api::<ServerDispatcher, RevokeAuthenticationToken>()?;
     }
 
     Ok(config)
@@ -77,20 +138,101 @@ impl ServerDispatcher {
         name: &ApiName,
         request: Bytes,
     ) -> Result<Bytes, Error> {
+        let server = session.server;
+        let started_at = Instant::now();
+        let result = Self::dispatch_through_middleware(session, name, request).await;
+        // Clients can send any name, so only registered names are recorded to
+        // keep the number of labels bounded.
+        let registered = server.custom_api_dispatcher(name).is_some();
+        server.metrics().record_request(
+            registered.then_some(name),
+            started_at.elapsed(),
+            result.is_ok(),
+        );
+        result
+    }
+
+    async fn dispatch_through_middleware<B: Backend>(
+        session: HandlerSession<'_, B>,
+        name: &ApiName,
+        request: Bytes,
+    ) -> Result<Bytes, Error> {
+        let server = session.server;
+        let middleware = server.middleware();
+        if middleware.is_empty() {
+            return Self::dispatch_to_handler(session, name, &request).await;
+        }
+
+        let mut api_request = ApiRequest::new(
+            server,
+            session.client,
+            session.as_client.session().cloned(),
+            name,
+            request,
+        );
+        let mut allowed_by = 0;
+        let mut rejection = None;
+        for middleware in middleware {
+            match middleware.before_request(&mut api_request).await {
+                Ok(()) => allowed_by += 1,
+                Err(err) => {
+                    rejection = Some(err);
+                    break;
+                }
+            }
+        }
+
+        let result = match rejection {
+            Some(err) => Err(err),
+            None => Self::dispatch_to_handler(session, name, api_request.bytes()).await,
+        };
+
+        for middleware in middleware[..allowed_by].iter().rev() {
+            middleware.after_request(&api_request, &result).await;
+        }
+
+        result
+    }
+
+    async fn dispatch_to_handler<B: Backend>(
+        session: HandlerSession<'_, B>,
+        name: &ApiName,
+        request: &[u8],
+    ) -> Result<Bytes, Error> {
+        session
+            .server
+            .rate_limiter()
+            .limit_request(session.client.rate_limit_key(), session.as_client.session())
+            .await?;
         if let Some(dispatcher) = session.server.custom_api_dispatcher(name) {
-            dispatcher.handle(session, &request).await
+            dispatcher.handle(session, request).await
         } else {
             Err(Error::from(bonsaidb_core::Error::ApiNotFound(name.clone())))
         }
     }
 }
 
+/// Consumes a token from the connection's rate limit for `action`, if one is
+/// configured.
+async fn limit_action<B: Backend>(
+    session: &HandlerSession<'_, B>,
+    action: BonsaiAction,
+) -> Result<(), bonsaidb_core::Error> {
+    let action = action.name();
+    session
+        .server
+        .rate_limiter()
+        .limit_actions(session.client.rate_limit_key(), [&action])
+        .await
+}
+
 #[async_trait]
-impl<B: Backend> Handler<CreateDatabase, B> for ServerDispatcher {
+impl<B: Backend> Handler<CreateDatabase, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         request: CreateDatabase,
     ) -> HandlerResult<CreateDatabase> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::CreateDatabase)).await?;
         session
             .as_client
             .create_database_with_schema(
@@ -104,22 +246,24 @@ impl<B: Backend> Handler<CreateDatabase, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<DeleteDatabase, B> for ServerDispatcher {
+impl<B: Backend> Handler<DeleteDatabase, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: DeleteDatabase,
     ) -> HandlerResult<DeleteDatabase> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::DeleteDatabase)).await?;
         session.as_client.delete_database(&command.name).await?;
         Ok(())
     }
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ListDatabases, B> for ServerDispatcher {
+impl<B: Backend> Handler<ListDatabases, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         _command: ListDatabases,
     ) -> HandlerResult<ListDatabases> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::ListDatabases)).await?;
         session
             .as_client
             .list_databases()
@@ -129,11 +273,124 @@ impl<B: Backend> Handler<ListDatabases, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ListAvailableSchemas, B> for ServerDispatcher {
+impl<B: Backend> Handler<SetDatabaseQuota, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: SetDatabaseQuota,
+    ) -> HandlerResult<SetDatabaseQuota> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::SetDatabaseQuota),
+        )
+        .await?;
+        session
+            .as_client
+            .set_database_quota(&command.name, command.quota)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<ListDatabaseUsage, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        _command: ListDatabaseUsage,
+    ) -> HandlerResult<ListDatabaseUsage> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::ListDatabaseUsage),
+        )
+        .await?;
+        session
+            .as_client
+            .list_database_usage()
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<ListSessions, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        _command: ListSessions,
+    ) -> HandlerResult<ListSessions> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::ListSessions)).await?;
+        session
+            .as_client
+            .list_sessions()
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<RevokeSession, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RevokeSession,
+    ) -> HandlerResult<RevokeSession> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::RevokeSession)).await?;
+        session
+            .as_client
+            .revoke_session(command.id)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[cfg(feature = "token-authentication")]
+#[async_trait]
+impl<B: Backend> Handler<ListAuthenticationTokens, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: ListAuthenticationTokens,
+    ) -> HandlerResult<ListAuthenticationTokens> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::ListAuthenticationTokens),
+        )
+        .await?;
+        session
+            .as_client
+            .list_authentication_tokens(command.identity)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[cfg(feature = "token-authentication")]
+#[async_trait]
+impl<B: Backend> Handler<RevokeAuthenticationToken, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RevokeAuthenticationToken,
+    ) -> HandlerResult<RevokeAuthenticationToken> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::RevokeAuthenticationToken),
+        )
+        .await?;
+        session
+            .as_client
+            .revoke_authentication_token(command.id)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<ListAvailableSchemas, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         _command: ListAvailableSchemas,
     ) -> HandlerResult<ListAvailableSchemas> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::ListAvailableSchemas),
+        )
+        .await?;
         session
             .as_client
             .list_available_schemas()
@@ -143,11 +400,12 @@ impl<B: Backend> Handler<ListAvailableSchemas, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<CreateUser, B> for ServerDispatcher {
+impl<B: Backend> Handler<CreateUser, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: CreateUser,
     ) -> HandlerResult<CreateUser> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::CreateUser)).await?;
         session
             .as_client
             .create_user(&command.username)
@@ -157,11 +415,12 @@ impl<B: Backend> Handler<CreateUser, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<DeleteUser, B> for ServerDispatcher {
+impl<B: Backend> Handler<DeleteUser, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: DeleteUser,
     ) -> HandlerResult<DeleteUser> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::DeleteUser)).await?;
         session
             .as_client
             .delete_user(command.user)
@@ -172,11 +431,12 @@ impl<B: Backend> Handler<DeleteUser, B> for ServerDispatcher {
 
 #[cfg(feature = "password-hashing")]
 #[async_trait]
-impl<B: Backend> Handler<SetUserPassword, B> for ServerDispatcher {
+impl<B: Backend> Handler<SetUserPassword, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: SetUserPassword,
     ) -> HandlerResult<SetUserPassword> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::SetPassword)).await?;
         session
             .as_client
             .set_user_password(command.user, command.password)
@@ -185,13 +445,83 @@ impl<B: Backend> Handler<SetUserPassword, B> for ServerDispatcher {
     }
 }
 
-#[cfg(feature = "password-hashing")]
+#[cfg(feature = "totp-authentication")]
+#[async_trait]
+impl<B: Backend> Handler<EnrollUserTotp, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: EnrollUserTotp,
+    ) -> HandlerResult<EnrollUserTotp> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::ModifyUserTotp)).await?;
+        session
+            .as_client
+            .enroll_user_totp(command.user)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[cfg(feature = "totp-authentication")]
+#[async_trait]
+impl<B: Backend> Handler<ConfirmUserTotp, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: ConfirmUserTotp,
+    ) -> HandlerResult<ConfirmUserTotp> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::ModifyUserTotp)).await?;
+        session
+            .as_client
+            .confirm_user_totp(command.user, command.code)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[cfg(feature = "totp-authentication")]
+#[async_trait]
+impl<B: Backend> Handler<RemoveUserTotp, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RemoveUserTotp,
+    ) -> HandlerResult<RemoveUserTotp> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::ModifyUserTotp)).await?;
+        session
+            .as_client
+            .remove_user_totp(command.user)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[cfg(any(
+    feature = "password-hashing",
+    feature = "token-authentication",
+    feature = "jwt-authentication"
+))]
 #[async_trait]
-impl<B: Backend> Handler<Authenticate, B> for ServerDispatcher {
+impl<B: Backend> Handler<Authenticate, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: Authenticate,
     ) -> HandlerResult<Authenticate> {
+        let method = match &command.authentication {
+            #[cfg(feature = "token-authentication")]
+            Authentication::Token { .. } | Authentication::TokenChallengeResponse(_) => {
+                AuthenticationMethod::Token
+            }
+            #[cfg(feature = "password-hashing")]
+            Authentication::Password { .. } => AuthenticationMethod::PasswordHash,
+            // The second factor completes a password authentication.
+            #[cfg(feature = "totp-authentication")]
+            Authentication::Totp(_) => AuthenticationMethod::PasswordHash,
+            #[cfg(feature = "jwt-authentication")]
+            Authentication::Jwt(_) => AuthenticationMethod::Jwt,
+        };
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::Authenticate(method)),
+        )
+        .await?;
         let authenticated = session
             .as_client
             .authenticate(command.authentication)
@@ -205,11 +535,12 @@ impl<B: Backend> Handler<Authenticate, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<AssumeIdentity, B> for ServerDispatcher {
+impl<B: Backend> Handler<AssumeIdentity, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: AssumeIdentity,
     ) -> HandlerResult<AssumeIdentity> {
+        limit_action(&session, BonsaiAction::Server(ServerAction::AssumeIdentity)).await?;
         let authenticated = session.as_client.assume_identity(command.0).await?;
         let new_session = authenticated.session().cloned().unwrap();
 
@@ -220,12 +551,13 @@ impl<B: Backend> Handler<AssumeIdentity, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<LogOutSession, B> for ServerDispatcher {
+impl<B: Backend> Handler<LogOutSession, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: LogOutSession,
     ) -> HandlerResult<LogOutSession> {
         if let Some(logged_out) = session.client.log_out(command.0) {
+            session.server.end_session(command.0).await;
             if let Err(err) = session
                 .server
                 .backend()
@@ -241,11 +573,16 @@ impl<B: Backend> Handler<LogOutSession, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<AlterUserPermissionGroupMembership, B> for ServerDispatcher {
+impl<B: Backend> Handler<AlterUserPermissionGroupMembership, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: AlterUserPermissionGroupMembership,
     ) -> HandlerResult<AlterUserPermissionGroupMembership> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::ModifyUserPermissionGroups),
+        )
+        .await?;
         if command.should_be_member {
             session
                 .as_client
@@ -263,11 +600,16 @@ impl<B: Backend> Handler<AlterUserPermissionGroupMembership, B> for ServerDispat
 }
 
 #[async_trait]
-impl<B: Backend> Handler<AlterUserRoleMembership, B> for ServerDispatcher {
+impl<B: Backend> Handler<AlterUserRoleMembership, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: AlterUserRoleMembership,
     ) -> HandlerResult<AlterUserRoleMembership> {
+        limit_action(
+            &session,
+            BonsaiAction::Server(ServerAction::ModifyUserRoles),
+        )
+        .await?;
         if command.should_be_member {
             session
                 .as_client
@@ -285,8 +627,13 @@ impl<B: Backend> Handler<AlterUserRoleMembership, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Get, B> for ServerDispatcher {
+impl<B: Backend> Handler<Get, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Get) -> HandlerResult<Get> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -299,11 +646,16 @@ impl<B: Backend> Handler<Get, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<GetMultiple, B> for ServerDispatcher {
+impl<B: Backend> Handler<GetMultiple, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: GetMultiple,
     ) -> HandlerResult<GetMultiple> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -316,8 +668,13 @@ impl<B: Backend> Handler<GetMultiple, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<List, B> for ServerDispatcher {
+impl<B: Backend> Handler<List, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: List) -> HandlerResult<List> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::List)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -335,11 +692,16 @@ impl<B: Backend> Handler<List, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ListHeaders, B> for ServerDispatcher {
+impl<B: Backend> Handler<ListHeaders, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: ListHeaders,
     ) -> HandlerResult<ListHeaders> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::ListHeaders)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.0.database)
@@ -357,8 +719,13 @@ impl<B: Backend> Handler<ListHeaders, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Count, B> for ServerDispatcher {
+impl<B: Backend> Handler<Count, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Count) -> HandlerResult<Count> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Count)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -371,8 +738,13 @@ impl<B: Backend> Handler<Count, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Query, B> for ServerDispatcher {
+impl<B: Backend> Handler<Query, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Query) -> HandlerResult<Query> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -391,11 +763,16 @@ impl<B: Backend> Handler<Query, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<QueryWithDocs, B> for ServerDispatcher {
+impl<B: Backend> Handler<QueryWithDocs, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: QueryWithDocs,
     ) -> HandlerResult<QueryWithDocs> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.0.database)
@@ -414,8 +791,13 @@ impl<B: Backend> Handler<QueryWithDocs, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Reduce, B> for ServerDispatcher {
+impl<B: Backend> Handler<Reduce, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Reduce) -> HandlerResult<Reduce> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::Reduce)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -429,11 +811,16 @@ impl<B: Backend> Handler<Reduce, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ReduceGrouped, B> for ServerDispatcher {
+impl<B: Backend> Handler<ReduceGrouped, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: ReduceGrouped,
     ) -> HandlerResult<ReduceGrouped> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::Reduce)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.0.database)
@@ -446,11 +833,31 @@ impl<B: Backend> Handler<ReduceGrouped, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ApplyTransaction, B> for ServerDispatcher {
+impl<B: Backend> Handler<ApplyTransaction, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: ApplyTransaction,
     ) -> HandlerResult<ApplyTransaction> {
+        let actions = command
+            .transaction
+            .operations
+            .iter()
+            .filter_map(|op| {
+                let action = match &op.command {
+                    Command::Insert { .. } => DocumentAction::Insert,
+                    Command::Update { .. } => DocumentAction::Update,
+                    Command::Overwrite { .. } => DocumentAction::Overwrite,
+                    Command::Delete { .. } => DocumentAction::Delete,
+                    Command::Check { .. } => return None,
+                };
+                Some(BonsaiAction::Database(DatabaseAction::Document(action)).name())
+            })
+            .collect::<Vec<_>>();
+        session
+            .server
+            .rate_limiter()
+            .limit_actions(session.client.rate_limit_key(), &actions)
+            .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -463,11 +870,16 @@ impl<B: Backend> Handler<ApplyTransaction, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<DeleteDocs, B> for ServerDispatcher {
+impl<B: Backend> Handler<DeleteDocs, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: DeleteDocs,
     ) -> HandlerResult<DeleteDocs> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::View(ViewAction::DeleteDocs)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -480,11 +892,16 @@ impl<B: Backend> Handler<DeleteDocs, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ListExecutedTransactions, B> for ServerDispatcher {
+impl<B: Backend> Handler<ListExecutedTransactions, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: ListExecutedTransactions,
     ) -> HandlerResult<ListExecutedTransactions> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Transaction(TransactionAction::ListExecuted)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -497,11 +914,16 @@ impl<B: Backend> Handler<ListExecutedTransactions, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<LastTransactionId, B> for ServerDispatcher {
+impl<B: Backend> Handler<LastTransactionId, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: LastTransactionId,
     ) -> HandlerResult<LastTransactionId> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::Transaction(TransactionAction::GetLastId)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -514,11 +936,16 @@ impl<B: Backend> Handler<LastTransactionId, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<CreateSubscriber, B> for ServerDispatcher {
+impl<B: Backend> Handler<CreateSubscriber, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: CreateSubscriber,
     ) -> HandlerResult<CreateSubscriber> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::CreateSuscriber)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -536,8 +963,13 @@ impl<B: Backend> Handler<CreateSubscriber, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Publish, B> for ServerDispatcher {
+impl<B: Backend> Handler<Publish, CustomServer<B>> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Publish) -> HandlerResult<Publish> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::Publish)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -550,11 +982,16 @@ impl<B: Backend> Handler<Publish, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<PublishToAll, B> for ServerDispatcher {
+impl<B: Backend> Handler<PublishToAll, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: PublishToAll,
     ) -> HandlerResult<PublishToAll> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::Publish)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -570,11 +1007,16 @@ impl<B: Backend> Handler<PublishToAll, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<SubscribeTo, B> for ServerDispatcher {
+impl<B: Backend> Handler<SubscribeTo, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: SubscribeTo,
     ) -> HandlerResult<SubscribeTo> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::SubscribeTo)),
+        )
+        .await?;
         session
             .client
             .subscribe_by_id(
@@ -587,11 +1029,16 @@ impl<B: Backend> Handler<SubscribeTo, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<UnsubscribeFrom, B> for ServerDispatcher {
+impl<B: Backend> Handler<UnsubscribeFrom, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: UnsubscribeFrom,
     ) -> HandlerResult<UnsubscribeFrom> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::UnsubscribeFrom)),
+        )
+        .await?;
         session
             .client
             .unsubscribe_by_id(
@@ -604,7 +1051,51 @@ impl<B: Backend> Handler<UnsubscribeFrom, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<UnregisterSubscriber, B> for ServerDispatcher {
+impl<B: Backend> Handler<SubscribeToPattern, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: SubscribeToPattern,
+    ) -> HandlerResult<SubscribeToPattern> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::SubscribeToPattern)),
+        )
+        .await?;
+        session
+            .client
+            .subscribe_to_pattern_by_id(
+                command.subscriber_id,
+                &command.pattern,
+                session.as_client.session().and_then(|session| session.id),
+            )
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<UnsubscribeFromPattern, CustomServer<B>> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: UnsubscribeFromPattern,
+    ) -> HandlerResult<UnsubscribeFromPattern> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::PubSub(PubSubAction::UnsubscribeFromPattern)),
+        )
+        .await?;
+        session
+            .client
+            .unsubscribe_from_pattern_by_id(
+                command.subscriber_id,
+                &command.pattern,
+                session.as_client.session().and_then(|session| session.id),
+            )
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<UnregisterSubscriber, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: UnregisterSubscriber,
@@ -620,11 +1111,16 @@ impl<B: Backend> Handler<UnregisterSubscriber, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<ExecuteKeyOperation, B> for ServerDispatcher {
+impl<B: Backend> Handler<ExecuteKeyOperation, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: ExecuteKeyOperation,
     ) -> HandlerResult<ExecuteKeyOperation> {
+        limit_action(
+            &session,
+            BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )
+        .await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -637,11 +1133,12 @@ impl<B: Backend> Handler<ExecuteKeyOperation, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<CompactCollection, B> for ServerDispatcher {
+impl<B: Backend> Handler<CompactCollection, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: CompactCollection,
     ) -> HandlerResult<CompactCollection> {
+        limit_action(&session, BonsaiAction::Database(DatabaseAction::Compact)).await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -654,11 +1151,12 @@ impl<B: Backend> Handler<CompactCollection, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<CompactKeyValueStore, B> for ServerDispatcher {
+impl<B: Backend> Handler<CompactKeyValueStore, CustomServer<B>> for ServerDispatcher {
     async fn handle(
         session: HandlerSession<'_, B>,
         command: CompactKeyValueStore,
     ) -> HandlerResult<CompactKeyValueStore> {
+        limit_action(&session, BonsaiAction::Database(DatabaseAction::Compact)).await?;
         let database = session
             .as_client
             .database_without_schema(&command.database)
@@ -671,8 +1169,9 @@ impl<B: Backend> Handler<CompactKeyValueStore, B> for ServerDispatcher {
 }
 
 #[async_trait]
-impl<B: Backend> Handler<Compact, B> for ServerDispatcher {
+impl<B: Backend> Handler<Compact, CustomServer<B>> for ServerDispatcher {
     async fn handle(client: HandlerSession<'_, B>, command: Compact) -> HandlerResult<Compact> {
+        limit_action(&client, BonsaiAction::Database(DatabaseAction::Compact)).await?;
         let database = client
             .as_client
             .database_without_schema(&command.database)
@@ -56,6 +56,15 @@ pub enum Error {
     /// An error occurred during tls signing.
     #[error("an error occurred during tls signing")]
     TlsSigningError,
+
+    /// An error occurred parsing a JSON Web Key Set.
+    #[error("an invalid JSON Web Key Set: {0}")]
+    #[cfg(feature = "jwt-authentication")]
+    Jwks(#[from] serde_json::Error),
+
+    /// A scheduled task with the same name has already been registered.
+    #[error("a scheduled task named '{0}' is already registered")]
+    ScheduledTaskAlreadyRegistered(String),
 }
 
 impl Error {
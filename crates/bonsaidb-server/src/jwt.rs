@@ -0,0 +1,184 @@
+//! Authentication using signed JSON Web Tokens (JWTs).
+//!
+//! When a client authenticates using
+//! [`Authentication::Jwt`](bonsaidb_core::connection::Authentication::Jwt),
+//! the server:
+//!
+//! 1. Verifies the token's signature using the key from
+//!    [`JwtConfiguration::keys`] whose id matches the token's `kid` header.
+//!    The token's `alg` header must be one of
+//!    [`JwtConfiguration::algorithms`], and must match the key's `alg` if the
+//!    key declares one.
+//! 2. Validates the token's expiration, and its issuer and audience if any
+//!    were configured.
+//! 3. Invokes
+//!    [`Backend::identity_for_jwt_claims`](crate::Backend::identity_for_jwt_claims)
+//!    to map the token's claims to a [`User`](bonsaidb_core::admin::User) or
+//!    [`Role`](bonsaidb_core::admin::Role).
+//! 4. Checks that the identity is permitted to
+//!    [`Authenticate`](bonsaidb_core::permissions::bonsai::ServerAction::Authenticate)
+//!    using [`AuthenticationMethod::Jwt`](bonsaidb_core::connection::AuthenticationMethod::Jwt).
+//!
+//! If any step fails,
+//! [`InvalidCredentials`](bonsaidb_core::Error::InvalidCredentials) is
+//! returned to the client.
+
+use std::path::Path;
+
+pub use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::jwk::KeyAlgorithm;
+pub use jsonwebtoken::Algorithm;
+use jsonwebtoken::{DecodingKey, Validation};
+use serde::Deserialize;
+pub use serde_json::Value;
+
+use crate::Error;
+
+/// The keys and validation rules used to verify JSON Web Tokens.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct JwtConfiguration {
+    /// The keys that tokens can be signed with.
+    pub keys: JwkSet,
+    /// The signing algorithms accepted. Tokens signed using any other
+    /// algorithm are rejected, regardless of the algorithm they claim to use.
+    pub algorithms: Vec<Algorithm>,
+    /// The accepted values of the `iss` claim. If empty, the issuer is not
+    /// validated.
+    pub issuers: Vec<String>,
+    /// The accepted values of the `aud` claim. If empty, the audience is not
+    /// validated.
+    pub audiences: Vec<String>,
+    /// The number of seconds of clock skew tolerated when validating the `exp`
+    /// and `nbf` claims. Default value is 60.
+    pub leeway: u64,
+}
+
+impl JwtConfiguration {
+    /// Returns a configuration that verifies tokens signed by `keys` using one
+    /// of `algorithms`.
+    pub fn new(keys: JwkSet, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        Self {
+            keys,
+            algorithms: algorithms.into_iter().collect(),
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            leeway: 60,
+        }
+    }
+
+    /// Loads the keys from a JSON Web Key Set (JWKS) file at `path`, accepting
+    /// tokens signed using one of `algorithms`.
+    pub fn from_jwks_file(
+        path: impl AsRef<Path>,
+        algorithms: impl IntoIterator<Item = Algorithm>,
+    ) -> Result<Self, Error> {
+        let contents = std::fs::read(path)?;
+        let keys = serde_json::from_slice(&contents)?;
+        Ok(Self::new(keys, algorithms))
+    }
+
+    /// Adds `issuer` to the accepted values of the `iss` claim and returns
+    /// self.
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    /// Adds `audience` to the accepted values of the `aud` claim and returns
+    /// self.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Sets [`Self::leeway`](Self#structfield.leeway) to `seconds` and returns
+    /// self.
+    pub const fn leeway(mut self, seconds: u64) -> Self {
+        self.leeway = seconds;
+        self
+    }
+
+    /// Verifies `token` and returns its claims.
+    pub(crate) fn verify(&self, token: &str) -> Result<JwtClaims, jsonwebtoken::errors::Error> {
+        use jsonwebtoken::errors::ErrorKind;
+
+        let header = jsonwebtoken::decode_header(token)?;
+        if !self.algorithms.contains(&header.alg) {
+            return Err(ErrorKind::InvalidAlgorithm.into());
+        }
+
+        let key = match &header.kid {
+            Some(kid) => self.keys.find(kid),
+            // Tokens without a key id can only be verified when there is no
+            // ambiguity about which key signed them.
+            None if self.keys.keys.len() == 1 => self.keys.keys.first(),
+            None => None,
+        }
+        .ok_or(ErrorKind::InvalidKeyFormat)?;
+
+        // If the key declares its algorithm, never accept a token claiming to
+        // use a different one.
+        if let Some(key_algorithm) = key.common.key_algorithm {
+            if signing_algorithm(key_algorithm) != Some(header.alg) {
+                return Err(ErrorKind::InvalidAlgorithm.into());
+            }
+        }
+
+        let decoding_key = DecodingKey::from_jwk(key)?;
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway;
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+        }
+        if self.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audiences);
+        }
+
+        jsonwebtoken::decode::<JwtClaims>(token, &decoding_key, &validation).map(|data| data.claims)
+    }
+}
+
+/// Returns the signing algorithm identified by a JWK's `alg`, or `None` if it
+/// identifies an encryption algorithm.
+const fn signing_algorithm(algorithm: KeyAlgorithm) -> Option<Algorithm> {
+    match algorithm {
+        KeyAlgorithm::HS256 => Some(Algorithm::HS256),
+        KeyAlgorithm::HS384 => Some(Algorithm::HS384),
+        KeyAlgorithm::HS512 => Some(Algorithm::HS512),
+        KeyAlgorithm::ES256 => Some(Algorithm::ES256),
+        KeyAlgorithm::ES384 => Some(Algorithm::ES384),
+        KeyAlgorithm::RS256 => Some(Algorithm::RS256),
+        KeyAlgorithm::RS384 => Some(Algorithm::RS384),
+        KeyAlgorithm::RS512 => Some(Algorithm::RS512),
+        KeyAlgorithm::PS256 => Some(Algorithm::PS256),
+        KeyAlgorithm::PS384 => Some(Algorithm::PS384),
+        KeyAlgorithm::PS512 => Some(Algorithm::PS512),
+        KeyAlgorithm::EdDSA => Some(Algorithm::EdDSA),
+        KeyAlgorithm::RSA1_5 | KeyAlgorithm::RSA_OAEP | KeyAlgorithm::RSA_OAEP_256 => None,
+    }
+}
+
+/// The claims of a verified JSON Web Token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtClaims {
+    /// The subject (`sub`) of the token.
+    #[serde(rename = "sub", default)]
+    pub subject: Option<String>,
+    /// The issuer (`iss`) of the token.
+    #[serde(rename = "iss", default)]
+    pub issuer: Option<String>,
+    /// All other claims contained in the token.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, Value>,
+}
+
+impl JwtClaims {
+    /// Returns the claim named `name`, if present.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.other.get(name)
+    }
+}
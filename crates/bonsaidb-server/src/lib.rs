@@ -22,10 +22,18 @@ mod backend;
 /// Command-line interface for the server.
 #[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "certificate-authentication")]
+pub mod client_certificate;
 mod config;
 mod dispatch;
 mod error;
 pub(crate) mod hosted;
+#[cfg(feature = "jwt-authentication")]
+pub mod jwt;
+pub mod middleware;
+pub mod rate_limit;
+/// Types for defining tasks executed by the server on a schedule.
+pub mod schedule;
 mod server;
 
 #[cfg(feature = "acme")]
@@ -36,6 +44,8 @@ pub use config::{
 pub use self::backend::{Backend, BackendError, ConnectionHandling, NoBackend};
 pub use self::config::{BonsaiListenConfig, DefaultPermissions, ServerConfiguration};
 pub use self::error::Error;
+#[cfg(feature = "json-pubsub")]
+pub use self::server::JSON_PUBSUB_PROTOCOL;
 pub use self::server::{
     ApplicationProtocols, ConnectedClient, CustomServer, HttpService, LockedClientDataGuard, Peer,
     Server, ServerDatabase, StandardTcpProtocols, TcpService, Transport,
@@ -0,0 +1,162 @@
+//! Middleware that intercepts API requests.
+//!
+//! Middleware is registered using
+//! [`ServerConfiguration::register_middleware`](crate::ServerConfiguration::register_middleware).
+//! Every API request a client sends passes through the registered middleware,
+//! including the APIs built into BonsaiDb and custom APIs registered using
+//! [`ServerConfiguration::register_custom_api`](crate::ServerConfiguration::register_custom_api):
+//!
+//! 1. [`Middleware::before_request`] is invoked for each middleware in the
+//!    order it was registered. Each middleware can inspect the request,
+//!    replace it, or reject it by returning an error. Once a request is
+//!    rejected, no further middleware is invoked and the request is not
+//!    dispatched.
+//! 2. The request is dispatched to its handler. Rate limits are enforced at
+//!    this step.
+//! 3. [`Middleware::after_request`] is invoked with the result for each
+//!    middleware whose `before_request` succeeded, in the reverse order of
+//!    registration.
+//!
+//! ```rust
+//! use bonsaidb_core::arc_bytes::serde::Bytes;
+//! use bonsaidb_server::middleware::{ApiRequest, Middleware};
+//! use bonsaidb_server::{Backend, Error};
+//!
+//! #[derive(Debug)]
+//! struct LogRequests;
+//!
+//! #[bonsaidb_core::async_trait::async_trait]
+//! impl<B: Backend> Middleware<B> for LogRequests {
+//!     async fn after_request(&self, request: &ApiRequest<'_, B>, result: &Result<Bytes, Error>) {
+//!         log::info!(
+//!             "{} from {}: {}",
+//!             request.name(),
+//!             request.client.address(),
+//!             if result.is_ok() { "ok" } else { "error" }
+//!         );
+//!     }
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use bonsaidb_core::api::{self, ApiName};
+use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::connection::Session;
+
+use crate::{Backend, ConnectedClient, CustomServer, Error, NoBackend};
+
+/// Logic that is executed for every API request a client sends.
+#[async_trait]
+pub trait Middleware<B: Backend = NoBackend>: Send + Sync + Debug + 'static {
+    /// Invoked before `request` is dispatched. The request can be replaced
+    /// using [`ApiRequest::replace()`]. Returning an error rejects the
+    /// request, and the error is returned to the client.
+    ///
+    /// By default, all requests are allowed.
+    #[allow(unused_variables)]
+    async fn before_request(&self, request: &mut ApiRequest<'_, B>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked after `request` has been handled, or rejected by a middleware
+    /// registered after this one. `result` contains the serialized response
+    /// that will be sent to the client, which can be decoded using
+    /// [`ApiRequest::decode_response()`].
+    #[allow(unused_variables)]
+    async fn after_request(&self, request: &ApiRequest<'_, B>, result: &Result<Bytes, Error>) {}
+}
+
+/// An API request received from a connected client.
+#[derive(Debug)]
+pub struct ApiRequest<'a, B: Backend = NoBackend> {
+    /// The server the request was received by. This server instance is not
+    /// limited to the permissions of the connected client.
+    pub server: &'a CustomServer<B>,
+    /// The connected client making the request.
+    pub client: &'a ConnectedClient<B>,
+    session: Option<Session>,
+    name: &'a ApiName,
+    request: Bytes,
+}
+
+impl<'a, B: Backend> ApiRequest<'a, B> {
+    pub(crate) fn new(
+        server: &'a CustomServer<B>,
+        client: &'a ConnectedClient<B>,
+        session: Option<Session>,
+        name: &'a ApiName,
+        request: Bytes,
+    ) -> Self {
+        Self {
+            server,
+            client,
+            session,
+            name,
+            request,
+        }
+    }
+
+    /// Returns the session the request is being made with.
+    #[must_use]
+    pub const fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Returns the name of the requested [`Api`](api::Api).
+    #[must_use]
+    pub const fn name(&self) -> &ApiName {
+        self.name
+    }
+
+    /// Returns the serialized request.
+    #[must_use]
+    pub const fn bytes(&self) -> &Bytes {
+        &self.request
+    }
+
+    /// Returns true if this is a request for `Api`.
+    #[must_use]
+    pub fn is<Api: api::Api>(&self) -> bool {
+        self.name == &Api::name()
+    }
+
+    /// Returns the decoded request if this is a request for `Api`.
+    pub fn decode<Api: api::Api>(&self) -> Result<Option<Api>, Error> {
+        if self.is::<Api>() {
+            Ok(Some(pot::from_slice(&self.request)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Replaces the request with `request`. Returns an error if this is not a
+    /// request for `Api`.
+    pub fn replace<Api: api::Api>(&mut self, request: &Api) -> Result<(), Error> {
+        if !self.is::<Api>() {
+            return Err(Error::other(
+                "middleware",
+                format!("a {} request can't replace {}", Api::name(), self.name),
+            ));
+        }
+
+        self.request = Bytes::from(pot::to_vec(request)?);
+        Ok(())
+    }
+
+    /// Returns the decoded response if this is a request for `Api`. `response`
+    /// should be the successful result passed to
+    /// [`Middleware::after_request()`].
+    #[allow(clippy::type_complexity)]
+    pub fn decode_response<Api: api::Api>(
+        &self,
+        response: &Bytes,
+    ) -> Result<Option<Result<Api::Response, Api::Error>>, Error> {
+        if self.is::<Api>() {
+            Ok(Some(pot::from_slice(response)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Token-bucket rate limiting of client requests.
+//!
+//! Rate limits are configured using
+//! [`ServerConfiguration::rate_limits`](crate::ServerConfiguration::rate_limits).
+//! Every request a client sends consumes one token from each bucket that
+//! applies to it:
+//!
+//! - [`RateLimits::per_connection`]: a bucket for each connected client. Each
+//!   HTTP gateway request is handled as a new client, so all gateway requests
+//!   from the same IP address share a bucket.
+//! - [`RateLimits::per_user`]: a bucket for each authenticated user, shared
+//!   across all of the user's connections.
+//! - [`RateLimits::per_action`]: a bucket for each connected client and
+//!   configured [`BonsaiAction`](bonsaidb_core::permissions::bonsai::BonsaiAction).
+//!   A transaction consumes one token for each distinct action its operations
+//!   perform. Like [`RateLimits::per_connection`], HTTP gateway requests share
+//!   these buckets by IP address.
+//!
+//! When a bucket is empty, the request is either delayed until a token is
+//! available or rejected with
+//! [`bonsaidb_core::Error::RateLimited`], depending on
+//! [`RateLimits::response`].
+
+use std::collections::HashMap;
+#[cfg(feature = "http-gateway")]
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use bonsaidb_core::connection::{Identity, Session};
+use bonsaidb_core::permissions::{Action, ActionName};
+use parking_lot::Mutex;
+
+/// A token-bucket rate limit.
+///
+/// A bucket holds up to `capacity` tokens and starts full. One token is
+/// restored each `refill_interval`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimit {
+    /// The maximum number of tokens the bucket can hold. This is the largest
+    /// burst of requests that can be made without being limited. A capacity
+    /// of 0 is treated as 1.
+    pub capacity: u32,
+    /// The amount of time it takes to restore a single token.
+    pub refill_interval: Duration,
+}
+
+impl RateLimit {
+    /// Returns a limit that holds up to `capacity` tokens and restores one
+    /// token each `refill_interval`.
+    #[must_use]
+    pub const fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity,
+            refill_interval,
+        }
+    }
+
+    /// Returns a limit that allows `requests` per second, allowing bursts of
+    /// up to `requests`.
+    #[must_use]
+    pub fn per_second(requests: u32) -> Self {
+        let requests = requests.max(1);
+        Self::new(requests, Duration::from_secs(1) / requests)
+    }
+
+    fn burst_tolerance(&self) -> Duration {
+        self.refill_interval * self.capacity.saturating_sub(1)
+    }
+}
+
+/// How the server responds to a request that exceeds a rate limit.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum RateLimitResponse {
+    /// The request is delayed until tokens are available. A delayed request
+    /// counts towards
+    /// [`ServerConfiguration::client_simultaneous_request_limit`](crate::ServerConfiguration::client_simultaneous_request_limit)
+    /// while it waits.
+    Delay,
+    /// The request is rejected with [`bonsaidb_core::Error::RateLimited`].
+    #[default]
+    Reject,
+}
+
+/// The rate limits enforced by the server. By default, no limits are enforced.
+#[derive(Debug, Default, Clone)]
+#[must_use]
+pub struct RateLimits {
+    /// The limit applied to each connected client. HTTP gateway requests from
+    /// the same IP address share a single limit.
+    pub per_connection: Option<RateLimit>,
+    /// The limit applied to each authenticated user. Requests made by
+    /// unauthenticated sessions or sessions authenticated as a role are not
+    /// subject to this limit.
+    pub per_user: Option<RateLimit>,
+    /// The limits applied to each connected client when it performs a
+    /// specific action.
+    pub per_action: Vec<(ActionName, RateLimit)>,
+    /// How requests that exceed a limit are handled.
+    pub response: RateLimitResponse,
+}
+
+impl RateLimits {
+    /// Sets [`Self::per_connection`](Self#structfield.per_connection) to `limit` and returns self.
+    pub const fn per_connection(mut self, limit: RateLimit) -> Self {
+        self.per_connection = Some(limit);
+        self
+    }
+
+    /// Sets [`Self::per_user`](Self#structfield.per_user) to `limit` and returns self.
+    pub const fn per_user(mut self, limit: RateLimit) -> Self {
+        self.per_user = Some(limit);
+        self
+    }
+
+    /// Limits how often each connected client can perform `action`, and
+    /// returns self. Any existing limit for `action` is replaced.
+    pub fn action(mut self, action: &impl Action, limit: RateLimit) -> Self {
+        let name = action.name();
+        self.per_action.retain(|(existing, _)| existing.0 != name.0);
+        self.per_action.push((name, limit));
+        self
+    }
+
+    /// Sets [`Self::response`](Self#structfield.response) to `response` and returns self.
+    pub const fn response(mut self, response: RateLimitResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.per_connection.is_none() && self.per_user.is_none() && self.per_action.is_empty()
+    }
+}
+
+/// Identifies the client that connection and action buckets are tracked for.
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub(crate) enum ConnectionKey {
+    /// A connected client.
+    Client(u32),
+    /// All HTTP gateway requests from an IP address. Each gateway request is
+    /// handled by a new client, so tracking buckets by client would allow
+    /// every request to start with a full bucket.
+    #[cfg(feature = "http-gateway")]
+    Peer(IpAddr),
+}
+
+impl From<u32> for ConnectionKey {
+    fn from(client: u32) -> Self {
+        Self::Client(client)
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+enum BucketKey {
+    Connection(ConnectionKey),
+    User(u64),
+    Action {
+        client: ConnectionKey,
+        action: usize,
+    },
+}
+
+/// A token bucket, tracked as the time at which the bucket will be full again.
+/// This is equivalent to counting tokens, but doesn't require refilling the
+/// bucket on a timer.
+#[derive(Debug)]
+struct TokenBucket {
+    full_at: Instant,
+}
+
+impl TokenBucket {
+    /// Returns how long a request must wait before a token is available.
+    fn wait_time(&self, limit: &RateLimit, now: Instant) -> Duration {
+        self.full_at
+            .saturating_duration_since(now)
+            .saturating_sub(limit.burst_tolerance())
+    }
+
+    fn take(&mut self, limit: &RateLimit, now: Instant) {
+        self.full_at = self.full_at.max(now) + limit.refill_interval;
+    }
+}
+
+/// How often buckets that have refilled completely are forgotten.
+const IDLE_BUCKET_EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Buckets {
+    buckets: HashMap<BucketKey, TokenBucket>,
+    last_eviction: Instant,
+}
+
+impl Buckets {
+    /// Removes all buckets that are full. A full bucket behaves identically to
+    /// a bucket that isn't tracked, so this only frees memory used by clients
+    /// and users that haven't made requests recently.
+    fn evict_idle(&mut self, now: Instant) {
+        self.buckets.retain(|_, bucket| bucket.full_at > now);
+        self.last_eviction = now;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                last_eviction: Instant::now(),
+            }),
+        }
+    }
+
+    /// Consumes a token for a request made by `client` using `session`.
+    pub async fn limit_request(
+        &self,
+        client: impl Into<ConnectionKey>,
+        session: Option<&Session>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        if self.limits.is_empty() {
+            return Ok(());
+        }
+
+        let client = client.into();
+        let user = session
+            .and_then(Session::identity)
+            .and_then(|identity| match identity {
+                Identity::User { id, .. } => Some(*id),
+                _ => None,
+            });
+        let buckets = self
+            .limits
+            .per_connection
+            .map(|limit| (BucketKey::Connection(client), limit))
+            .into_iter()
+            .chain(
+                self.limits
+                    .per_user
+                    .zip(user)
+                    .map(|(limit, user)| (BucketKey::User(user), limit)),
+            );
+        self.acquire(buckets).await
+    }
+
+    /// Consumes a token for each of `actions` performed by `client`.
+    pub async fn limit_actions<'a>(
+        &self,
+        client: impl Into<ConnectionKey>,
+        actions: impl IntoIterator<Item = &'a ActionName>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        if self.limits.per_action.is_empty() {
+            return Ok(());
+        }
+
+        let client = client.into();
+        let mut buckets = Vec::new();
+        for action in actions {
+            if let Some((index, (_, limit))) = self
+                .limits
+                .per_action
+                .iter()
+                .enumerate()
+                .find(|(_, (name, _))| name.0 == action.0)
+            {
+                let key = BucketKey::Action {
+                    client,
+                    action: index,
+                };
+                if !buckets.iter().any(|(existing, _)| existing == &key) {
+                    buckets.push((key, *limit));
+                }
+            }
+        }
+        self.acquire(buckets).await
+    }
+
+    /// Removes the buckets tracked for `client`. Buckets shared by HTTP
+    /// gateway requests are kept until they are evicted for being idle.
+    pub fn client_disconnected(&self, client: u32) {
+        if self.limits.is_empty() {
+            return;
+        }
+
+        let mut buckets = self.buckets.lock();
+        let client = ConnectionKey::Client(client);
+        buckets.buckets.retain(|key, _| match key {
+            BucketKey::Connection(key) | BucketKey::Action { client: key, .. } => *key != client,
+            BucketKey::User(_) => true,
+        });
+    }
+
+    /// Forgets all buckets that have refilled completely.
+    #[cfg(test)]
+    pub fn evict_idle_buckets(&self) {
+        self.buckets.lock().evict_idle(Instant::now());
+    }
+
+    /// Returns the number of buckets currently tracked.
+    #[cfg(test)]
+    pub fn tracked_buckets(&self) -> usize {
+        self.buckets.lock().buckets.len()
+    }
+
+    async fn acquire(
+        &self,
+        limits: impl IntoIterator<Item = (BucketKey, RateLimit)>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let wait_time = {
+            let mut buckets = self.buckets.lock();
+            let now = Instant::now();
+            if now.saturating_duration_since(buckets.last_eviction) >= IDLE_BUCKET_EVICTION_INTERVAL
+            {
+                buckets.evict_idle(now);
+            }
+            let limits = limits.into_iter().collect::<Vec<_>>();
+            let wait_time = limits
+                .iter()
+                .filter_map(|(key, limit)| {
+                    buckets
+                        .buckets
+                        .get(key)
+                        .map(|bucket| bucket.wait_time(limit, now))
+                })
+                .max()
+                .unwrap_or_default();
+            if self.limits.response == RateLimitResponse::Reject && !wait_time.is_zero() {
+                return Err(bonsaidb_core::Error::RateLimited {
+                    retry_after: wait_time,
+                });
+            }
+
+            for (key, limit) in &limits {
+                buckets
+                    .buckets
+                    .entry(*key)
+                    .or_insert(TokenBucket { full_at: now })
+                    .take(limit, now);
+            }
+            wait_time
+        };
+
+        if !wait_time.is_zero() {
+            tokio::time::sleep(wait_time).await;
+        }
+
+        Ok(())
+    }
+}
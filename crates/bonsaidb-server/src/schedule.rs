@@ -0,0 +1,316 @@
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
+use derive_where::derive_where;
+
+use crate::{Backend, BackendError, CustomServer, NoBackend};
+
+/// A task executed by the server according to a [`Schedule`].
+///
+/// Tasks are registered using
+/// [`ServerConfiguration::register_scheduled_task`](crate::ServerConfiguration::register_scheduled_task),
+/// typically from [`Backend::configure`]. The time of each execution is
+/// stored in the admin database. When the server is opened, any execution
+/// missed while the server was not running is performed once, regardless of
+/// how many executions were missed.
+#[async_trait]
+pub trait ScheduledTask<B: Backend = NoBackend>: Send + Sync + Debug + 'static {
+    /// Executes the task. `scheduled_for` is the time this execution was
+    /// scheduled for, which will be in the past when catching up on missed
+    /// executions.
+    async fn execute(
+        &self,
+        server: &CustomServer<B>,
+        scheduled_for: TimestampAsNanoseconds,
+    ) -> Result<(), BackendError<B::Error>>;
+}
+
+#[derive_where(Clone, Debug)]
+pub(crate) struct RegisteredTask<B: Backend> {
+    pub name: String,
+    pub schedule: Schedule,
+    pub task: Arc<dyn ScheduledTask<B>>,
+}
+
+/// A cron-style schedule, evaluated in UTC.
+///
+/// Schedules are parsed from five whitespace-separated fields: minute (0-59),
+/// hour (0-23), day of month (1-31), month (1-12), and day of week (0-7, where
+/// both 0 and 7 are Sunday). Each field can be:
+///
+/// - `*`: every value.
+/// - `5`: a single value.
+/// - `1-5`: an inclusive range of values.
+/// - `*/15`, `1-30/5`, `10/5`: every `n`th value within a range.
+/// - `1,15,30`: a comma-separated list of any of the above.
+///
+/// When both day of month and day of week are restricted, a day matches if
+/// either field matches. The shorthands `@yearly`, `@annually`, `@monthly`,
+/// `@weekly`, `@daily`, `@midnight`, and `@hourly` are also accepted.
+///
+/// ```rust
+/// use bonsaidb_server::schedule::Schedule;
+///
+/// // Every 15 minutes during business hours on weekdays.
+/// let schedule: Schedule = "*/15 9-17 * * 1-5".parse().unwrap();
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Schedule {
+    expression: String,
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+/// The maximum number of days to search for the next matching time. A
+/// schedule for February 29th may need to search almost eight years when a
+/// century year is skipped.
+const MAXIMUM_DAYS_SEARCHED: u64 = 366 * 8;
+
+impl Schedule {
+    /// Parses `expression` as a cron-style schedule.
+    pub fn new(expression: &str) -> Result<Self, InvalidSchedule> {
+        let trimmed = expression.trim();
+        let fields = match trimmed {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields = fields.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(InvalidSchedule::new(
+                expression,
+                "expected five fields: minute, hour, day of month, month, and day of week",
+            ));
+        };
+
+        let parse = |field: &str, min: u32, max: u32| {
+            parse_field(field, min, max).map_err(|reason| InvalidSchedule::new(expression, reason))
+        };
+        let mut days_of_week_mask = parse(days_of_week, 0, 7)?;
+        // Both 0 and 7 represent Sunday.
+        if days_of_week_mask & (1 << 7) != 0 {
+            days_of_week_mask = (days_of_week_mask | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expression: trimmed.to_string(),
+            minutes: parse(minutes, 0, 59)?,
+            hours: u32::try_from(parse(hours, 0, 23)?).expect("hours fit in u32"),
+            days_of_month: u32::try_from(parse(days_of_month, 1, 31)?)
+                .expect("days of month fit in u32"),
+            months: u16::try_from(parse(months, 1, 12)?).expect("months fit in u16"),
+            days_of_week: u8::try_from(days_of_week_mask).expect("days of week fit in u8"),
+            day_of_month_restricted: days_of_month != "*",
+            day_of_week_restricted: days_of_week != "*",
+        })
+    }
+
+    /// Returns the expression this schedule was parsed from.
+    #[must_use]
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Returns the first time matching this schedule that is strictly after
+    /// `time`. Returns `None` if no matching time can be found.
+    #[must_use]
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        // Schedules have a resolution of one minute.
+        let start = (seconds / 60 + 1) * 60;
+        let mut day = start / 86_400;
+        let mut first_minute = start % 86_400 / 60;
+        for _ in 0..MAXIMUM_DAYS_SEARCHED {
+            if self.matches_day(day) {
+                for minute_of_day in first_minute..24 * 60 {
+                    let hour = minute_of_day / 60;
+                    let minute = minute_of_day % 60;
+                    if self.hours & (1 << hour) != 0 && self.minutes & (1 << minute) != 0 {
+                        return UNIX_EPOCH
+                            .checked_add(Duration::from_secs(day * 86_400 + minute_of_day * 60));
+                    }
+                }
+            }
+            day += 1;
+            first_minute = 0;
+        }
+
+        None
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (month, day_of_month) = month_and_day(days_since_epoch);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+
+        // January 1, 1970 was a Thursday.
+        let day_of_week = (days_since_epoch + 4) % 7;
+        let day_of_month_matches = self.days_of_month & (1 << day_of_month) != 0;
+        let day_of_week_matches = self.days_of_week & (1 << day_of_week) != 0;
+        if self.day_of_month_restricted && self.day_of_week_restricted {
+            day_of_month_matches || day_of_week_matches
+        } else {
+            day_of_month_matches && day_of_week_matches
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = InvalidSchedule;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// Parses a single cron field into a bitmask of the values it matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step: {part}"))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let parse_value = |value: &str| {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| format!("{value} is not between {min} and {max}"))
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start)?, parse_value(end)?)
+        } else {
+            let start = parse_value(range)?;
+            // `10/5` repeats from 10 through the maximum value.
+            (start, if step.is_some() { max } else { start })
+        };
+        if start > end {
+            return Err(format!("invalid range: {range}"));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+/// Returns the month (1-12) and day of month (1-31) for a number of days
+/// since the unix epoch.
+fn month_and_day(days_since_epoch: u64) -> (u64, u64) {
+    // Adapted from Howard Hinnant's `civil_from_days` algorithm.
+    let z = days_since_epoch + 719_468;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (month, day)
+}
+
+/// An error parsing a [`Schedule`].
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+#[error("invalid schedule '{expression}': {reason}")]
+pub struct InvalidSchedule {
+    /// The expression that failed to parse.
+    pub expression: String,
+    /// The reason the expression is invalid.
+    pub reason: String,
+}
+
+impl InvalidSchedule {
+    fn new(expression: &str, reason: impl Into<String>) -> Self {
+        Self {
+            expression: expression.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[test]
+fn schedule_parsing() {
+    assert_eq!(Schedule::new("* * * * *").unwrap().minutes, u64::MAX >> 4);
+    assert_eq!(
+        Schedule::new("*/15 * * * *").unwrap().minutes,
+        1 | 1 << 15 | 1 << 30 | 1 << 45
+    );
+    assert_eq!(
+        Schedule::new("0 1-3,5 * * *").unwrap().hours,
+        1 << 1 | 1 << 2 | 1 << 3 | 1 << 5
+    );
+    assert_eq!(Schedule::new("0 0 * * 7").unwrap().days_of_week, 1);
+    assert_eq!(
+        Schedule::new("@daily").unwrap(),
+        Schedule::new("0 0 * * *").unwrap()
+    );
+    assert!(Schedule::new("* * * *").is_err());
+    assert!(Schedule::new("60 * * * *").is_err());
+    assert!(Schedule::new("*/0 * * * *").is_err());
+    assert!(Schedule::new("5-1 * * * *").is_err());
+}
+
+#[test]
+fn schedule_next_after() {
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    // 2023-01-01T00:00:00Z was a Sunday.
+    const JAN_1_2023: u64 = 1_672_531_200;
+    let hourly = Schedule::new("@hourly").unwrap();
+    assert_eq!(
+        hourly.next_after(at(JAN_1_2023)),
+        Some(at(JAN_1_2023 + 3600))
+    );
+    assert_eq!(
+        hourly.next_after(at(JAN_1_2023 + 1)),
+        Some(at(JAN_1_2023 + 3600))
+    );
+
+    // The next Monday at 09:30 is 2023-01-02.
+    let weekdays = Schedule::new("30 9 * * 1-5").unwrap();
+    assert_eq!(
+        weekdays.next_after(at(JAN_1_2023)),
+        Some(at(JAN_1_2023 + 86_400 + 9 * 3600 + 30 * 60))
+    );
+
+    // The next February 29th is in 2024.
+    let leap_day = Schedule::new("0 0 29 2 *").unwrap();
+    assert_eq!(leap_day.next_after(at(JAN_1_2023)), Some(at(1_709_164_800)));
+}
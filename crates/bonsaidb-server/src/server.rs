@@ -5,10 +5,10 @@ use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
-use bonsaidb_core::admin::{Admin, ADMIN_DATABASE_NAME};
+use bonsaidb_core::admin::{Admin, DatabaseQuota, ScheduledTaskRun, ADMIN_DATABASE_NAME};
 use bonsaidb_core::api;
 use bonsaidb_core::api::ApiName;
 use bonsaidb_core::arc_bytes::serde::Bytes;
@@ -16,6 +16,7 @@ use bonsaidb_core::connection::{
     self, AsyncConnection, AsyncStorageConnection, HasSession, IdentityReference, Session,
     SessionId,
 };
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
 use bonsaidb_core::networking::{self, Payload, CURRENT_PROTOCOL_VERSION};
 use bonsaidb_core::permissions::bonsai::{bonsaidb_resource_name, BonsaiAction, ServerAction};
 use bonsaidb_core::permissions::Permissions;
@@ -29,7 +30,7 @@ use flume::Sender;
 use futures::{Future, StreamExt};
 use parking_lot::{Mutex, RwLock};
 use rustls::sign::CertifiedKey;
-use schema::SchemaName;
+use schema::{SchemaName, SerializedCollection};
 #[cfg(not(windows))]
 use signal_hook::consts::SIGQUIT;
 use signal_hook::consts::{SIGINT, SIGTERM};
@@ -42,6 +43,9 @@ use crate::config::AcmeConfiguration;
 use crate::dispatch::{register_api_handlers, ServerDispatcher};
 use crate::error::Error;
 use crate::hosted::{Hosted, SerializablePrivateKey, TlsCertificate, TlsCertificatesByDomain};
+use crate::middleware::Middleware;
+use crate::rate_limit::RateLimiter;
+use crate::schedule::RegisteredTask;
 use crate::server::shutdown::{Shutdown, ShutdownState, ShutdownStateWatcher};
 use crate::{Backend, BackendError, BonsaiListenConfig, NoBackend, ServerConfiguration};
 
@@ -49,6 +53,9 @@ use crate::{Backend, BackendError, BonsaiListenConfig, NoBackend, ServerConfigur
 pub mod acme;
 mod connected_client;
 mod database;
+#[cfg(feature = "http-gateway")]
+mod http_gateway;
+mod metrics;
 
 mod shutdown;
 mod tcp;
@@ -58,10 +65,19 @@ mod websockets;
 use self::connected_client::OwnedClient;
 pub use self::connected_client::{ConnectedClient, LockedClientDataGuard, Transport};
 pub use self::database::ServerDatabase;
+use self::metrics::ServerMetrics;
 pub use self::tcp::{ApplicationProtocols, HttpService, Peer, StandardTcpProtocols, TcpService};
+#[cfg(feature = "json-pubsub")]
+pub use self::websockets::JSON_PUBSUB_PROTOCOL;
 
 static CONNECTED_CLIENT_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
 
+/// The delay before retrying a scheduled task whose schedule couldn't be
+/// loaded or whose execution couldn't be recorded. The delay doubles after each
+/// consecutive failure, up to [`MAX_SCHEDULED_TASK_RETRY_DELAY`].
+const MIN_SCHEDULED_TASK_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_SCHEDULED_TASK_RETRY_DELAY: Duration = Duration::from_secs(300);
+
 /// A BonsaiDb server.
 #[derive(Debug)]
 #[derive_where(Clone)]
@@ -92,13 +108,20 @@ struct Data<B: Backend = NoBackend> {
     request_processor: flume::Sender<ClientRequest<B>>,
     default_session: Session,
     client_simultaneous_request_limit: usize,
+    rate_limiter: RateLimiter,
     primary_tls_key: CachedCertifiedKey,
     primary_domain: String,
     custom_apis: RwLock<HashMap<ApiName, Arc<dyn AnyHandler<B>>>>,
+    middleware: Vec<Arc<dyn Middleware<B>>>,
+    metrics: ServerMetrics,
     #[cfg(feature = "acme")]
     acme: AcmeConfiguration,
     #[cfg(feature = "acme")]
     alpn_keys: AlpnKeys,
+    #[cfg(feature = "jwt-authentication")]
+    jwt: Option<crate::jwt::JwtConfiguration>,
+    #[cfg(feature = "certificate-authentication")]
+    client_certificates: Option<crate::client_certificate::ClientCertificateVerifier>,
     shutdown: Shutdown,
 }
 
@@ -176,21 +199,168 @@ impl<B: Backend> CustomServer<B> {
                     ..Session::default()
                 },
                 client_simultaneous_request_limit: configuration.client_simultaneous_request_limit,
+                rate_limiter: RateLimiter::new(configuration.rate_limits),
                 primary_tls_key: CachedCertifiedKey::default(),
                 primary_domain: configuration.server_name,
                 custom_apis: parking_lot::RwLock::new(configuration.custom_apis),
+                middleware: configuration.middleware,
+                metrics: ServerMetrics::default(),
                 #[cfg(feature = "acme")]
                 acme: configuration.acme,
                 #[cfg(feature = "acme")]
                 alpn_keys: AlpnKeys::default(),
+                #[cfg(feature = "jwt-authentication")]
+                jwt: configuration.jwt,
+                #[cfg(feature = "certificate-authentication")]
+                client_certificates: configuration
+                    .client_certificates
+                    .as_ref()
+                    .map(crate::client_certificate::ClientCertificateConfiguration::verifier),
                 shutdown: Shutdown::new(),
             }),
         };
 
         server.data.backend.initialize(&server).await?;
+
+        for task in configuration.scheduled_tasks {
+            server.spawn_scheduled_task(task).await;
+        }
+
         Ok(server)
     }
 
+    async fn spawn_scheduled_task(&self, task: RegisteredTask<B>) {
+        let Some(mut shutdown) = self.data.shutdown.watcher().await else {
+            return;
+        };
+        let server = self.clone();
+        tokio::task::spawn(async move {
+            let mut retry_delay = MIN_SCHEDULED_TASK_RETRY_DELAY;
+            loop {
+                let next_run = match server.next_scheduled_run(&task).await {
+                    Ok(Some(next_run)) => next_run,
+                    Ok(None) => {
+                        log::warn!(
+                            "[server] scheduled task {} has no future executions",
+                            task.name
+                        );
+                        break;
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "[server] error loading schedule for task {}, retrying in {retry_delay:?}: {err:?}",
+                            task.name
+                        );
+                        if Self::wait_to_retry_scheduled_task(&mut retry_delay, &mut shutdown).await
+                        {
+                            continue;
+                        }
+                        break;
+                    }
+                };
+
+                let delay = next_run
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                tokio::select! {
+                    () = tokio::time::sleep(delay) => {}
+                    _ = shutdown.wait_for_shutdown() => break,
+                }
+
+                match server.execute_scheduled_task(&task, next_run).await {
+                    Ok(Ok(())) => retry_delay = MIN_SCHEDULED_TASK_RETRY_DELAY,
+                    Ok(Err(err)) => {
+                        // The failed execution was recorded, so the task isn't
+                        // executed again until its next scheduled time.
+                        retry_delay = MIN_SCHEDULED_TASK_RETRY_DELAY;
+                        log::error!(
+                            "[server] error executing scheduled task {}: {err:?}",
+                            task.name
+                        );
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "[server] error recording execution of scheduled task {}, retrying in {retry_delay:?}: {err:?}",
+                            task.name
+                        );
+                        if !Self::wait_to_retry_scheduled_task(&mut retry_delay, &mut shutdown)
+                            .await
+                        {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Waits `retry_delay` before retrying a failed scheduled task operation,
+    /// and doubles `retry_delay` for the next failure. Returns false if the
+    /// server began shutting down while waiting.
+    async fn wait_to_retry_scheduled_task(
+        retry_delay: &mut Duration,
+        shutdown: &mut ShutdownStateWatcher,
+    ) -> bool {
+        let delay = *retry_delay;
+        *retry_delay = (delay * 2).min(MAX_SCHEDULED_TASK_RETRY_DELAY);
+        tokio::select! {
+            () = tokio::time::sleep(delay) => true,
+            _ = shutdown.wait_for_shutdown() => false,
+        }
+    }
+
+    /// Returns the next time `task` should be executed. If the previous
+    /// execution is further in the past than the schedule allows, the
+    /// returned time will be in the past.
+    async fn next_scheduled_run(
+        &self,
+        task: &RegisteredTask<B>,
+    ) -> Result<Option<SystemTime>, Error> {
+        let admin = self.admin().await;
+        let last_run = match ScheduledTaskRun::get_async(&task.name, &admin).await? {
+            Some(run) => {
+                SystemTime::try_from(run.contents.last_run).map_err(bonsaidb_core::Error::from)?
+            }
+            None => {
+                // Newly registered tasks are first executed at the next
+                // scheduled time.
+                let now = SystemTime::now();
+                ScheduledTaskRun {
+                    last_run: TimestampAsNanoseconds::try_from(now)
+                        .map_err(bonsaidb_core::Error::from)?,
+                }
+                .overwrite_into_async(&task.name, &admin)
+                .await?;
+                now
+            }
+        };
+
+        Ok(task.schedule.next_after(last_run))
+    }
+
+    async fn execute_scheduled_task(
+        &self,
+        task: &RegisteredTask<B>,
+        scheduled_for: SystemTime,
+    ) -> Result<Result<(), BackendError<B::Error>>, bonsaidb_core::Error> {
+        let result = task
+            .task
+            .execute(self, TimestampAsNanoseconds::try_from(scheduled_for)?)
+            .await;
+
+        // The execution is recorded regardless of whether the task succeeded,
+        // ensuring a failing task is not retried until its next scheduled time.
+        // Recording the current time rather than `scheduled_for` causes
+        // multiple missed executions to be caught up with a single execution.
+        ScheduledTaskRun {
+            last_run: TimestampAsNanoseconds::now(),
+        }
+        .overwrite_into_async(&task.name, &self.admin().await)
+        .await?;
+
+        Ok(result)
+    }
+
     /// Returns the path to the public pinned certificate, if this server has
     /// one. Note: this function will always succeed, but the file may not
     /// exist.
@@ -228,6 +398,99 @@ impl<B: Backend> CustomServer<B> {
         }
     }
 
+    pub(crate) fn rate_limiter(&self) -> &RateLimiter {
+        &self.data.rate_limiter
+    }
+
+    #[cfg(feature = "jwt-authentication")]
+    async fn authenticate_jwt(&self, token: &str) -> Result<Self, bonsaidb_core::Error> {
+        let jwt = self
+            .data
+            .jwt
+            .as_ref()
+            .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        let claims = jwt.verify(token).map_err(|err| {
+            log::debug!("rejecting JSON Web Token: {err}");
+            bonsaidb_core::Error::InvalidCredentials
+        })?;
+        let identity = self
+            .data
+            .backend
+            .identity_for_jwt_claims(&claims, self)
+            .await
+            .map_err(|err| match err {
+                BackendError::Server(err) => bonsaidb_core::Error::from(err),
+                BackendError::Backend(err) => {
+                    bonsaidb_core::Error::other("jwt-authentication", err)
+                }
+            })?
+            .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        let storage = self
+            .storage
+            .authenticate_as(
+                identity,
+                bonsaidb_core::connection::AuthenticationMethod::Jwt,
+            )
+            .await?;
+        Ok(Self {
+            data: self.data.clone(),
+            storage,
+        })
+    }
+
+    /// Authenticates `client` using the certificate chain it presented during
+    /// the TLS handshake, replacing its unauthenticated session.
+    #[cfg(feature = "certificate-authentication")]
+    async fn authenticate_client_certificate(
+        &self,
+        client: &ConnectedClient<B>,
+        client_certificates: Option<Vec<rustls::Certificate>>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let Some(verifier) = &self.data.client_certificates else {
+            return Ok(());
+        };
+        let chain = match client_certificates {
+            Some(chain) if !chain.is_empty() => chain,
+            _ if verifier.required() => return Err(bonsaidb_core::Error::InvalidCredentials),
+            _ => return Ok(()),
+        };
+        let certificate = verifier.verify(chain).map_err(|err| {
+            log::debug!("rejecting client certificate: {err}");
+            bonsaidb_core::Error::InvalidCredentials
+        })?;
+        let identity = self
+            .data
+            .backend
+            .identity_for_client_certificate(&certificate, self)
+            .await
+            .map_err(|err| match err {
+                BackendError::Server(err) => bonsaidb_core::Error::from(err),
+                BackendError::Backend(err) => {
+                    bonsaidb_core::Error::other("certificate-authentication", err)
+                }
+            })?
+            .ok_or(bonsaidb_core::Error::InvalidCredentials)?;
+        let storage = self
+            .storage
+            .authenticate_as(
+                identity,
+                bonsaidb_core::connection::AuthenticationMethod::ClientCertificate,
+            )
+            .await?;
+        if let Some(session) = storage.session() {
+            client.replace_unauthenticated_session(session.clone());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn middleware(&self) -> &[Arc<dyn Middleware<B>>] {
+        &self.data.middleware
+    }
+
+    pub(crate) fn metrics(&self) -> &ServerMetrics {
+        &self.data.metrics
+    }
+
     pub(crate) fn custom_api_dispatcher(&self, name: &ApiName) -> Option<Arc<dyn AnyHandler<B>>> {
         let dispatchers = self.data.custom_apis.read();
         dispatchers.get(name).cloned()
@@ -442,10 +705,15 @@ impl<B: Backend> CustomServer<B> {
         }
     }
 
+    #[cfg_attr(
+        not(feature = "certificate-authentication"),
+        allow(unused_variables, clippy::needless_pass_by_value)
+    )]
     async fn initialize_client(
         &self,
         transport: Transport,
         address: SocketAddr,
+        client_certificates: Option<Vec<rustls::Certificate>>,
         sender: Sender<(Option<SessionId>, ApiName, Bytes)>,
     ) -> Option<OwnedClient<B>> {
         if !self.data.default_session.allowed_to(
@@ -472,6 +740,15 @@ impl<B: Backend> CustomServer<B> {
             }
         };
 
+        #[cfg(feature = "certificate-authentication")]
+        if let Err(err) = self
+            .authenticate_client_certificate(&client, client_certificates)
+            .await
+        {
+            log::error!("[server] Rejecting connection due to client certificate: {err:?}");
+            return None;
+        }
+
         match self.data.backend.client_connected(&client, self).await {
             Ok(ConnectionHandling::Accept) => Some(client),
             Ok(ConnectionHandling::Reject) => None,
@@ -490,9 +767,14 @@ impl<B: Backend> CustomServer<B> {
             clients.remove(&id)
         };
 
+        self.data.rate_limiter.client_disconnected(id);
+
         if let Some(client) = removed_client {
             client.set_disconnected();
             for session in client.all_sessions::<Vec<_>>() {
+                if let Some(id) = session.id {
+                    self.end_session(id).await;
+                }
                 if let Err(err) = self
                     .data
                     .backend
@@ -509,6 +791,15 @@ impl<B: Backend> CustomServer<B> {
         }
     }
 
+    /// Removes session `id` from storage after it has been logged out of the
+    /// only client using it.
+    pub(crate) async fn end_session(&self, id: SessionId) {
+        match self.storage.revoke_session(id).await {
+            Ok(()) | Err(bonsaidb_core::Error::SessionNotFound(_)) => {}
+            Err(err) => log::error!("[server] Error ending session: {err:?}"),
+        }
+    }
+
     async fn handle_bonsai_connection(
         &self,
         mut connection: fabruic::Connection<()>,
@@ -528,10 +819,17 @@ impl<B: Backend> CustomServer<B> {
             {
                 Ok((sender, receiver)) => {
                     let (api_response_sender, api_response_receiver) = flume::unbounded();
+                    let client_certificates = connection.peer_identity().map(|chain| {
+                        chain
+                            .iter()
+                            .map(|certificate| rustls::Certificate(certificate.as_ref().to_vec()))
+                            .collect()
+                    });
                     if let Some(disconnector) = self
                         .initialize_client(
                             Transport::Bonsai,
                             connection.remote_address(),
+                            client_certificates,
                             api_response_sender,
                         )
                         .await
@@ -686,6 +984,35 @@ impl<B: Backend> CustomServer<B> {
         Ok(())
     }
 
+    /// Dispatches `request` on behalf of `client` through the request workers,
+    /// exactly as if `client` had sent it using `session_id`.
+    #[cfg(any(feature = "http-gateway", feature = "json-pubsub"))]
+    async fn dispatch_for_client<Api: api::Api<Error = bonsaidb_core::Error>>(
+        &self,
+        client: ConnectedClient<B>,
+        session_id: Option<SessionId>,
+        request: &Api,
+    ) -> Result<Api::Response, bonsaidb_core::Error> {
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.handle_request_through_worker(
+            Payload {
+                session_id,
+                id: None,
+                name: Api::name(),
+                value: Ok(Bytes::from(pot::to_vec(request)?)),
+            },
+            move |_name, result| async move {
+                drop(result_sender.send(result));
+                Ok(())
+            },
+            client,
+        )?;
+        let response = result_receiver
+            .await
+            .map_err(|_| Error::InternalCommunication)??;
+        pot::from_slice::<Result<Api::Response, bonsaidb_core::Error>>(&response)?
+    }
+
     async fn handle_stream(
         &self,
         client: OwnedClient<B>,
@@ -929,6 +1256,67 @@ impl<B: Backend> AsyncStorageConnection for CustomServer<B> {
         self.storage.list_databases().await
     }
 
+    async fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.storage.set_database_quota(name, quota).await
+    }
+
+    async fn list_database_usage(
+        &self,
+    ) -> Result<Vec<connection::DatabaseUsage>, bonsaidb_core::Error> {
+        self.storage.list_database_usage().await
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<connection::AuthenticationTokenSummary>, bonsaidb_core::Error> {
+        self.storage.list_authentication_tokens(identity).await
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        self.storage.revoke_authentication_token(id).await
+    }
+
+    async fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        let mut sessions = self.storage.list_sessions().await?;
+        let clients = self.connected_clients();
+        for summary in &mut sessions {
+            if let Some(client) = clients
+                .iter()
+                .find(|client| client.session(Some(summary.id)).is_some())
+            {
+                summary.address = Some(*client.address());
+                summary.transport = Some(client.transport().to_string());
+            }
+        }
+        Ok(sessions)
+    }
+
+    async fn revoke_session(&self, id: SessionId) -> Result<(), bonsaidb_core::Error> {
+        self.storage.revoke_session(id).await?;
+        for client in self.connected_clients() {
+            if let Some(session) = client.log_out(id) {
+                if let Err(err) = self
+                    .data
+                    .backend
+                    .client_session_ended(session, &client, false, self)
+                    .await
+                {
+                    log::error!("[server] Error in `client_session_ended`: {err:?}");
+                }
+            }
+        }
+        Ok(())
+    }
+
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         self.storage.list_available_schemas().await
     }
@@ -953,11 +1341,45 @@ impl<B: Backend> AsyncStorageConnection for CustomServer<B> {
         self.storage.set_user_password(user, password).await
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    async fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<bonsaidb_core::admin::TotpSecret, bonsaidb_core::Error> {
+        self.storage.enroll_user_totp(user).await
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.storage.confirm_user_totp(user, code).await
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.storage.remove_user_totp(user).await
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     async fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
     ) -> Result<Self::Authenticated, bonsaidb_core::Error> {
+        #[cfg(feature = "jwt-authentication")]
+        if let bonsaidb_core::connection::Authentication::Jwt(token) = &authentication {
+            return self.authenticate_jwt(&token.0).await;
+        }
+
         let storage = self.storage.authenticate(authentication).await?;
         Ok(Self {
             data: self.data.clone(),
@@ -6,7 +6,7 @@ use std::sync::Arc;
 
 use async_lock::{Mutex, MutexGuard};
 use bonsaidb_core::api;
-use bonsaidb_core::api::ApiName;
+use bonsaidb_core::api::{Api as _, ApiName};
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::connection::{Session, SessionId};
 use bonsaidb_core::networking::MessageReceived;
@@ -14,9 +14,10 @@ use bonsaidb_core::pubsub::{Receiver, Subscriber as _};
 use bonsaidb_local::Subscriber;
 use bonsaidb_utils::fast_async_lock;
 use derive_where::derive_where;
-use flume::Sender;
+use flume::{Sender, TrySendError};
 use parking_lot::RwLock;
 
+use crate::rate_limit::ConnectionKey;
 use crate::{Backend, CustomServer, Error, NoBackend};
 
 /// The ways a client can be connected to the server.
@@ -27,6 +28,21 @@ pub enum Transport {
     /// A connection over WebSockets.
     #[cfg(feature = "websockets")]
     WebSocket,
+    /// A request to the HTTP JSON gateway.
+    #[cfg(feature = "http-gateway")]
+    Http,
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bonsai => f.write_str("bonsai"),
+            #[cfg(feature = "websockets")]
+            Self::WebSocket => f.write_str("websocket"),
+            #[cfg(feature = "http-gateway")]
+            Self::Http => f.write_str("http"),
+        }
+    }
 }
 
 /// A connected database client.
@@ -54,6 +70,24 @@ struct ClientSession {
 }
 
 impl<B: Backend> ConnectedClient<B> {
+    pub(crate) fn id(&self) -> u32 {
+        self.data.id
+    }
+
+    /// Returns the key that this client's rate limits are tracked by. Each
+    /// HTTP gateway request is handled by a new client, so gateway requests
+    /// are tracked by their peer's IP address instead.
+    pub(crate) fn rate_limit_key(&self) -> ConnectionKey {
+        #[cfg(feature = "http-gateway")]
+        {
+            if self.data.transport == Transport::Http {
+                return ConnectionKey::Peer(self.data.address.ip());
+            }
+        }
+
+        ConnectionKey::Client(self.data.id)
+    }
+
     /// Returns the address of the connected client.
     #[must_use]
     pub fn address(&self) -> &SocketAddr {
@@ -87,24 +121,59 @@ impl<B: Backend> ConnectedClient<B> {
         );
     }
 
+    /// Replaces the session used for requests that don't specify a session,
+    /// such as after the client was authenticated by its TLS certificate.
+    pub(crate) fn replace_unauthenticated_session(&self, session: Session) {
+        let mut sessions = self.data.sessions.write();
+        sessions.insert(
+            None,
+            ClientSession {
+                session,
+                subscribers: HashMap::default(),
+            },
+        );
+    }
+
     pub(crate) fn log_out(&self, session: SessionId) -> Option<Session> {
         let mut sessions = self.data.sessions.write();
         sessions.remove(&Some(session)).map(|cs| cs.session)
     }
 
     /// Sends a custom API response to the client.
+    ///
+    /// If the client's transport bounds the number of queued responses and the
+    /// client isn't keeping up, an error is returned and the client is
+    /// disconnected.
     pub fn send<Api: api::Api>(
         &self,
         session: Option<&Session>,
         response: &Api::Response,
     ) -> Result<(), Error> {
-        let encoded = pot::to_vec(&Result::<&Api::Response, Api::Error>::Ok(response))?;
-        self.data.response_sender.send((
-            session.and_then(|session| session.id),
+        self.send_to_session_id::<Api>(session.and_then(|session| session.id), response)
+    }
+
+    fn send_to_session_id<Api: api::Api>(
+        &self,
+        session_id: Option<SessionId>,
+        response: &Api::Response,
+    ) -> Result<(), Error> {
+        match self.data.response_sender.try_send((
+            session_id,
             Api::name(),
-            Bytes::from(encoded),
-        ))?;
-        Ok(())
+            encode_response::<Api>(response)?,
+        )) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                // The transport disconnects the client once it observes that
+                // it is no longer connected.
+                self.set_disconnected();
+                Err(Error::other(
+                    "bonsaidb-server",
+                    "client is not keeping up with its responses",
+                ))
+            }
+            Err(TrySendError::Disconnected(_)) => Err(Error::InternalCommunication),
+        }
     }
 
     /// Returns a locked reference to the stored client data.
@@ -166,17 +235,24 @@ impl<B: Backend> ConnectedClient<B> {
         subscriber_id: u64,
         receiver: Receiver,
     ) {
-        let session = self.session(session_id);
+        // The client identifies the subscriber by the session id it
+        // subscribed with, which is `None` even when the unauthenticated
+        // session was replaced by an authenticated one.
         while let Ok(message) = receiver.receive_async().await {
+            let Ok(encoded) = encode_response::<MessageReceived>(&MessageReceived {
+                subscriber_id,
+                topic: Bytes::from(message.topic.0.into_vec()),
+                payload: Bytes::from(&message.payload[..]),
+            }) else {
+                break;
+            };
+            // Waiting rather than blocking allows transports to bound the
+            // number of responses queued for a client.
             if self
-                .send::<MessageReceived>(
-                    session.as_ref(),
-                    &MessageReceived {
-                        subscriber_id,
-                        topic: Bytes::from(message.topic.0.into_vec()),
-                        payload: Bytes::from(&message.payload[..]),
-                    },
-                )
+                .data
+                .response_sender
+                .send_async((session_id, MessageReceived::name(), encoded))
+                .await
                 .is_err()
             {
                 break;
@@ -228,6 +304,50 @@ impl<B: Backend> ConnectedClient<B> {
         }
     }
 
+    pub(crate) fn subscribe_to_pattern_by_id(
+        &self,
+        subscriber_id: u64,
+        pattern: &str,
+        check_session_id: Option<SessionId>,
+    ) -> Result<(), crate::Error> {
+        let mut sessions = self.data.sessions.write();
+        if let Some(client_session) = sessions.get_mut(&check_session_id) {
+            if let Some(subscriber) = client_session.subscribers.get(&subscriber_id) {
+                subscriber.subscribe_to_pattern(pattern)?;
+                Ok(())
+            } else {
+                Err(Error::other(
+                    "bonsaidb-server pubsub",
+                    "invalid subscriber id",
+                ))
+            }
+        } else {
+            Err(Error::other("bonsaidb-server auth", "invalid session id"))
+        }
+    }
+
+    pub(crate) fn unsubscribe_from_pattern_by_id(
+        &self,
+        subscriber_id: u64,
+        pattern: &str,
+        check_session_id: Option<SessionId>,
+    ) -> Result<(), crate::Error> {
+        let mut sessions = self.data.sessions.write();
+        if let Some(client_session) = sessions.get_mut(&check_session_id) {
+            if let Some(subscriber) = client_session.subscribers.get(&subscriber_id) {
+                subscriber.unsubscribe_from_pattern(pattern)?;
+                Ok(())
+            } else {
+                Err(Error::other(
+                    "bonsaidb-server pubsub",
+                    "invalid subscriber id",
+                ))
+            }
+        } else {
+            Err(Error::other("bonsaidb-server auth", "invalid session id"))
+        }
+    }
+
     pub(crate) fn unregister_subscriber_by_id(
         &self,
         subscriber_id: u64,
@@ -312,6 +432,11 @@ impl<B: Backend> OwnedClient<B> {
     }
 }
 
+fn encode_response<Api: api::Api>(response: &Api::Response) -> Result<Bytes, Error> {
+    let encoded = pot::to_vec(&Result::<&Api::Response, Api::Error>::Ok(response))?;
+    Ok(Bytes::from(encoded))
+}
+
 impl<B: Backend> Drop for OwnedClient<B> {
     fn drop(&mut self) {
         let id = self.client.data.id;
@@ -0,0 +1,941 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use bonsaidb_core::api;
+use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::connection::{AccessPolicy, Bound, Range, SerializedQueryKey, SessionId, Sort};
+use bonsaidb_core::document::{DocumentId, Header, OwnedDocument, Revision};
+use bonsaidb_core::keyvalue::{
+    Command as KeyCommand, KeyOperation, KeyStatus, Numeric, Output, SetCommand, Value,
+};
+#[cfg(any(feature = "password-hashing", feature = "jwt-authentication"))]
+use bonsaidb_core::networking::Authenticate;
+use bonsaidb_core::networking::{
+    ApplyTransaction, ExecuteKeyOperation, Get, List, Publish, Query, QueryWithDocs,
+};
+use bonsaidb_core::schema::view::map::{MappedSerializedDocuments, Serialized};
+use bonsaidb_core::schema::{CollectionName, ViewName};
+use bonsaidb_core::transaction::{OperationResult, Transaction};
+use hyper::body::HttpBody;
+use hyper::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH,
+    RETRY_AFTER,
+};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::json;
+
+use crate::server::connected_client::OwnedClient;
+use crate::{Backend, CustomServer, Error, Transport};
+
+/// The maximum length of a request body accepted by the HTTP gateway.
+const MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+
+impl<B: Backend> CustomServer<B> {
+    /// Handles a request to the HTTP JSON gateway. Requires feature
+    /// `http-gateway` to be enabled.
+    ///
+    /// The gateway allows services that don't speak BonsaiDb's protocol to
+    /// access databases using JSON over HTTP. Each request is handled as if a
+    /// client connected, sent the equivalent request, and disconnected.
+    /// Permissions, rate limits, and
+    /// [`Middleware`](crate::middleware::Middleware) are applied exactly as
+    /// they are for other clients. Because each request is handled by a new
+    /// client, per-connection and per-action rate limits are shared by all
+    /// requests from the same IP address.
+    ///
+    /// Requests are authenticated using the `Authorization` header:
+    ///
+    /// - `Basic <base64 username:password>`: Requires feature
+    ///   `password-hashing`.
+    /// - `Bearer <token>`: A JSON Web Token. Requires feature
+    ///   `jwt-authentication`.
+    ///
+    /// Requests without an `Authorization` header use the server's default
+    /// permissions.
+    ///
+    /// Because each request is handled independently, `Basic` credentials are
+    /// verified on every request. Password hashes are intentionally expensive
+    /// to verify, so services that send many requests should prefer `Bearer`
+    /// tokens.
+    ///
+    /// Requests are authenticated before their body is read, and bodies
+    /// larger than 16 megabytes are rejected with `413 Payload Too Large`.
+    ///
+    /// | Method   | Path                                           | Operation |
+    /// |----------|------------------------------------------------|-----------|
+    /// | `GET`    | `/databases/{db}/collections/{collection}/documents`      | List documents. Accepts `start`, `end`, `order`, and `limit`. |
+    /// | `POST`   | `/databases/{db}/collections/{collection}/documents`      | Insert a document. Accepts `id`. |
+    /// | `GET`    | `/databases/{db}/collections/{collection}/documents/{id}` | Get a document. |
+    /// | `PUT`    | `/databases/{db}/collections/{collection}/documents/{id}` | Update the document whose revision matches `If-Match`, or overwrite the document if `If-Match` isn't present. |
+    /// | `DELETE` | `/databases/{db}/collections/{collection}/documents/{id}` | Delete a document. If `If-Match` is present, the revision must match. |
+    /// | `GET`    | `/databases/{db}/views/{view}`                 | Query a view. Accepts `key`, `start`, `end`, `order`, `limit`, `access_policy`, and `docs`. |
+    /// | `GET`    | `/databases/{db}/kv/{key}`                     | Get a key's value. Accepts `namespace`. |
+    /// | `PUT`    | `/databases/{db}/kv/{key}`                     | Set a key's value. Accepts `namespace`. |
+    /// | `DELETE` | `/databases/{db}/kv/{key}`                     | Delete a key. Accepts `namespace`. |
+    /// | `POST`   | `/databases/{db}/pubsub/{topic}`               | Publish the body to a topic. |
+    ///
+    /// Collections are identified by their encoded
+    /// [`CollectionName`](bonsaidb_core::schema::CollectionName) and views by
+    /// their encoded [`ViewName`](bonsaidb_core::schema::ViewName), e.g.,
+    /// `khonsulabs.blog-post` and `khonsulabs.blog-post.by-author`. See
+    /// [`Qualified::encoded()`](bonsaidb_core::schema::Qualified::encoded).
+    /// Document ids and view keys use the same textual representation as
+    /// [`DocumentId`]'s `Display` and `FromStr` implementations.
+    ///
+    /// Document contents, view values, key-value values, and `PubSub`
+    /// payloads are converted between JSON and
+    /// [Pot](https://github.com/khonsulabs/pot), the default format of
+    /// [`SerializedCollection`](bonsaidb_core::schema::SerializedCollection).
+    /// `PubSub` topics are serialized as strings. Numeric JSON values stored
+    /// in the key-value store are stored as
+    /// [`Numeric`](bonsaidb_core::keyvalue::Numeric) values.
+    ///
+    /// Errors are returned with an appropriate status code and a body of
+    /// `{"error": "<message>"}`.
+    pub async fn handle_http_gateway_request(
+        &self,
+        peer_address: SocketAddr,
+        request: Request<Body>,
+    ) -> Response<Body> {
+        match self.handle_gateway_request(peer_address, request).await {
+            Ok(response) => response,
+            Err(err) => err.into_response(),
+        }
+    }
+
+    async fn handle_gateway_request(
+        &self,
+        peer_address: SocketAddr,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, GatewayError> {
+        let (parts, body) = request.into_parts();
+        let route = Route::parse(&parts.method, parts.uri.path(), parts.uri.query())?;
+
+        // Responses pushed to clients, such as PubSub messages, can't be
+        // delivered over HTTP.
+        let (response_sender, _) = flume::unbounded();
+        let client = self
+            .initialize_client(Transport::Http, peer_address, None, response_sender)
+            .await
+            .ok_or(GatewayError::ConnectionRejected)?;
+        let mut gateway = GatewayClient {
+            server: self,
+            client,
+            session_id: None,
+        };
+        gateway
+            .authenticate(parts.headers.get(AUTHORIZATION))
+            .await?;
+        let body = read_body(&parts.headers, body).await?;
+        route.execute(&gateway, &parts.headers, &body).await
+    }
+}
+
+/// Reads `body`, rejecting it without reading it if `Content-Length` exceeds
+/// [`MAX_BODY_LENGTH`], and stopping as soon as more than
+/// [`MAX_BODY_LENGTH`] bytes have been received.
+async fn read_body(headers: &HeaderMap, mut body: Body) -> Result<Vec<u8>, GatewayError> {
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+    if content_length.map_or(false, |length| length > MAX_BODY_LENGTH) {
+        return Err(GatewayError::PayloadTooLarge);
+    }
+
+    let mut contents = Vec::with_capacity(content_length.unwrap_or_default());
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| GatewayError::BadRequest(err.to_string()))?;
+        if contents.len() + chunk.len() > MAX_BODY_LENGTH {
+            return Err(GatewayError::PayloadTooLarge);
+        }
+        contents.extend_from_slice(&chunk);
+    }
+    Ok(contents)
+}
+
+struct GatewayClient<'a, B: Backend> {
+    server: &'a CustomServer<B>,
+    client: OwnedClient<B>,
+    session_id: Option<SessionId>,
+}
+
+impl<B: Backend> GatewayClient<'_, B> {
+    #[cfg_attr(
+        not(any(feature = "password-hashing", feature = "jwt-authentication")),
+        allow(clippy::unused_async, clippy::unused_self)
+    )]
+    async fn authenticate(
+        &mut self,
+        authorization: Option<&HeaderValue>,
+    ) -> Result<(), GatewayError> {
+        let Some(authorization) = authorization else {
+            return Ok(());
+        };
+
+        #[cfg(any(feature = "password-hashing", feature = "jwt-authentication"))]
+        {
+            let session = self
+                .send(&Authenticate {
+                    authentication: parse_authorization(authorization)?,
+                })
+                .await?;
+            self.session_id = session.id;
+            Ok(())
+        }
+
+        #[cfg(not(any(feature = "password-hashing", feature = "jwt-authentication")))]
+        {
+            let _ = authorization;
+            Err(GatewayError::Core(bonsaidb_core::Error::InvalidCredentials))
+        }
+    }
+
+    /// Sends `request` through the same request workers and dispatcher used
+    /// for all other clients.
+    async fn send<Api: api::Api<Error = bonsaidb_core::Error>>(
+        &self,
+        request: &Api,
+    ) -> Result<Api::Response, GatewayError> {
+        self.server
+            .dispatch_for_client(self.client.clone(), self.session_id, request)
+            .await
+            .map_err(GatewayError::Core)
+    }
+
+    async fn apply_transaction(
+        &self,
+        database: String,
+        transaction: Transaction,
+    ) -> Result<Vec<OperationResult>, GatewayError> {
+        self.send(&ApplyTransaction {
+            database,
+            transaction,
+        })
+        .await
+    }
+
+    async fn execute_key_operation(
+        &self,
+        database: String,
+        namespace: Option<String>,
+        key: String,
+        command: KeyCommand,
+    ) -> Result<Output, GatewayError> {
+        self.send(&ExecuteKeyOperation {
+            database,
+            op: KeyOperation {
+                namespace,
+                key,
+                command,
+            },
+        })
+        .await
+    }
+}
+
+#[cfg(any(feature = "password-hashing", feature = "jwt-authentication"))]
+fn parse_authorization(
+    authorization: &HeaderValue,
+) -> Result<bonsaidb_core::connection::Authentication, GatewayError> {
+    let authorization = authorization
+        .to_str()
+        .map_err(|_| GatewayError::Core(bonsaidb_core::Error::InvalidCredentials))?;
+    let (scheme, credentials) = authorization
+        .split_once(' ')
+        .ok_or(GatewayError::Core(bonsaidb_core::Error::InvalidCredentials))?;
+    match scheme.to_ascii_lowercase().as_str() {
+        #[cfg(feature = "password-hashing")]
+        "basic" => {
+            use base64::engine::general_purpose::STANDARD as BASE64;
+            use base64::Engine;
+
+            let credentials = BASE64
+                .decode(credentials.trim())
+                .ok()
+                .and_then(|credentials| String::from_utf8(credentials).ok())
+                .ok_or(GatewayError::Core(bonsaidb_core::Error::InvalidCredentials))?;
+            let (username, password) = credentials
+                .split_once(':')
+                .ok_or(GatewayError::Core(bonsaidb_core::Error::InvalidCredentials))?;
+            bonsaidb_core::connection::Authentication::password(
+                username,
+                bonsaidb_core::connection::SensitiveString(password.to_string()),
+            )
+            .map_err(GatewayError::Core)
+        }
+        #[cfg(feature = "jwt-authentication")]
+        "bearer" => Ok(bonsaidb_core::connection::Authentication::jwt(
+            credentials.trim(),
+        )),
+        _ => Err(GatewayError::Core(bonsaidb_core::Error::InvalidCredentials)),
+    }
+}
+
+enum Route {
+    ListDocuments {
+        database: String,
+        collection: CollectionName,
+        ids: Range<DocumentId>,
+        order: Sort,
+        limit: Option<u32>,
+    },
+    InsertDocument {
+        database: String,
+        collection: CollectionName,
+        id: Option<DocumentId>,
+    },
+    GetDocument {
+        database: String,
+        collection: CollectionName,
+        id: DocumentId,
+    },
+    UpdateDocument {
+        database: String,
+        collection: CollectionName,
+        id: DocumentId,
+    },
+    DeleteDocument {
+        database: String,
+        collection: CollectionName,
+        id: DocumentId,
+    },
+    QueryView {
+        query: Query,
+        with_docs: bool,
+    },
+    GetKey {
+        database: String,
+        namespace: Option<String>,
+        key: String,
+    },
+    SetKey {
+        database: String,
+        namespace: Option<String>,
+        key: String,
+    },
+    DeleteKey {
+        database: String,
+        namespace: Option<String>,
+        key: String,
+    },
+    Publish {
+        database: String,
+        topic: String,
+    },
+}
+
+impl Route {
+    fn parse(method: &Method, path: &str, query: Option<&str>) -> Result<Self, GatewayError> {
+        let segments = path
+            .trim_matches('/')
+            .split('/')
+            .map(percent_decode)
+            .collect::<Result<Vec<_>, _>>()?;
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut parameters = QueryParameters::parse(query)?;
+        let method = method.as_str();
+
+        match segments.as_slice() {
+            ["databases", database, "collections", collection, "documents"] => {
+                let database = (*database).to_string();
+                let collection =
+                    CollectionName::from_str(collection).map_err(bonsaidb_core::Error::from)?;
+                match method {
+                    "GET" => Ok(Self::ListDocuments {
+                        database,
+                        collection,
+                        ids: parameters.range()?,
+                        order: parameters.order()?,
+                        limit: parameters.limit()?,
+                    }),
+                    "POST" => Ok(Self::InsertDocument {
+                        database,
+                        collection,
+                        id: parameters.document_id("id")?,
+                    }),
+                    _ => Err(GatewayError::MethodNotAllowed),
+                }
+            }
+            ["databases", database, "collections", collection, "documents", id] => {
+                let database = (*database).to_string();
+                let collection =
+                    CollectionName::from_str(collection).map_err(bonsaidb_core::Error::from)?;
+                let id = DocumentId::from_str(id)?;
+                match method {
+                    "GET" => Ok(Self::GetDocument {
+                        database,
+                        collection,
+                        id,
+                    }),
+                    "PUT" => Ok(Self::UpdateDocument {
+                        database,
+                        collection,
+                        id,
+                    }),
+                    "DELETE" => Ok(Self::DeleteDocument {
+                        database,
+                        collection,
+                        id,
+                    }),
+                    _ => Err(GatewayError::MethodNotAllowed),
+                }
+            }
+            ["databases", database, "views", view] => {
+                if method != "GET" {
+                    return Err(GatewayError::MethodNotAllowed);
+                }
+                let view = ViewName::from_str(view).map_err(bonsaidb_core::Error::from)?;
+                let key = if let Some(key) = parameters.document_id("key")? {
+                    Some(SerializedQueryKey::Matches(Bytes::from(key.to_vec())))
+                } else {
+                    let range = parameters.range()?;
+                    if matches!(
+                        (&range.start, &range.end),
+                        (Bound::Unbounded, Bound::Unbounded)
+                    ) {
+                        None
+                    } else {
+                        Some(SerializedQueryKey::Range(
+                            range.map(|id| Bytes::from(id.to_vec())),
+                        ))
+                    }
+                };
+                Ok(Self::QueryView {
+                    query: Query {
+                        database: (*database).to_string(),
+                        view,
+                        key,
+                        order: parameters.order()?,
+                        limit: parameters.limit()?,
+                        access_policy: parameters.access_policy()?,
+                    },
+                    with_docs: parameters.flag("docs")?,
+                })
+            }
+            ["databases", database, "kv", key] => {
+                let database = (*database).to_string();
+                let namespace = parameters.take("namespace");
+                let key = (*key).to_string();
+                match method {
+                    "GET" => Ok(Self::GetKey {
+                        database,
+                        namespace,
+                        key,
+                    }),
+                    "PUT" => Ok(Self::SetKey {
+                        database,
+                        namespace,
+                        key,
+                    }),
+                    "DELETE" => Ok(Self::DeleteKey {
+                        database,
+                        namespace,
+                        key,
+                    }),
+                    _ => Err(GatewayError::MethodNotAllowed),
+                }
+            }
+            ["databases", database, "pubsub", topic] => {
+                if method == "POST" {
+                    Ok(Self::Publish {
+                        database: (*database).to_string(),
+                        topic: (*topic).to_string(),
+                    })
+                } else {
+                    Err(GatewayError::MethodNotAllowed)
+                }
+            }
+            _ => Err(GatewayError::NotFound),
+        }
+    }
+
+    async fn execute<B: Backend>(
+        self,
+        gateway: &GatewayClient<'_, B>,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Response<Body>, GatewayError> {
+        match self {
+            Self::ListDocuments {
+                database,
+                collection,
+                ids,
+                order,
+                limit,
+            } => {
+                let documents = gateway
+                    .send(&List {
+                        database,
+                        collection,
+                        ids,
+                        order,
+                        limit,
+                    })
+                    .await?;
+                let documents = documents
+                    .iter()
+                    .map(document_json)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(json_response(StatusCode::OK, &json!(documents)))
+            }
+            Self::InsertDocument {
+                database,
+                collection,
+                id,
+            } => {
+                let contents = json_to_pot(body)?;
+                let results = gateway
+                    .apply_transaction(database, Transaction::insert(collection, id, contents))
+                    .await?;
+                let header = updated_header(results)?;
+                Ok(json_response(StatusCode::CREATED, &header_json(&header)))
+            }
+            Self::GetDocument {
+                database,
+                collection,
+                id,
+            } => {
+                let document = gateway
+                    .send(&Get {
+                        database,
+                        collection: collection.clone(),
+                        id: id.clone(),
+                    })
+                    .await?
+                    .ok_or_else(|| {
+                        bonsaidb_core::Error::DocumentNotFound(collection, Box::new(id))
+                    })?;
+                let mut response = json_response(StatusCode::OK, &document_json(&document)?);
+                response
+                    .headers_mut()
+                    .insert(ETAG, entity_tag(&document.header.revision));
+                Ok(response)
+            }
+            Self::UpdateDocument {
+                database,
+                collection,
+                id,
+            } => {
+                let contents = json_to_pot(body)?;
+                let transaction = if let Some(revision) = headers.get(IF_MATCH) {
+                    let revision = parse_revision(revision)?;
+                    Transaction::update(collection, Header { id, revision }, contents)
+                } else {
+                    Transaction::overwrite(collection, id, contents)
+                };
+                let results = gateway.apply_transaction(database, transaction).await?;
+                let header = updated_header(results)?;
+                Ok(json_response(StatusCode::OK, &header_json(&header)))
+            }
+            Self::DeleteDocument {
+                database,
+                collection,
+                id,
+            } => {
+                let header = if let Some(revision) = headers.get(IF_MATCH) {
+                    Header {
+                        id,
+                        revision: parse_revision(revision)?,
+                    }
+                } else {
+                    gateway
+                        .send(&Get {
+                            database: database.clone(),
+                            collection: collection.clone(),
+                            id: id.clone(),
+                        })
+                        .await?
+                        .ok_or_else(|| {
+                            bonsaidb_core::Error::DocumentNotFound(collection.clone(), Box::new(id))
+                        })?
+                        .header
+                };
+                gateway
+                    .apply_transaction(database, Transaction::delete(collection, header))
+                    .await?;
+                Ok(empty_response(StatusCode::NO_CONTENT))
+            }
+            Self::QueryView { query, with_docs } => {
+                let results = if with_docs {
+                    let MappedSerializedDocuments {
+                        mappings,
+                        documents,
+                    } = gateway.send(&QueryWithDocs(query)).await?;
+                    mappings
+                        .iter()
+                        .map(|mapping| {
+                            let mut result = mapping_json(mapping)?;
+                            result["document"] = documents
+                                .get(&mapping.source.id)
+                                .map(document_json)
+                                .transpose()?
+                                .unwrap_or_default();
+                            Ok(result)
+                        })
+                        .collect::<Result<Vec<_>, GatewayError>>()?
+                } else {
+                    gateway
+                        .send(&query)
+                        .await?
+                        .iter()
+                        .map(mapping_json)
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                Ok(json_response(StatusCode::OK, &json!(results)))
+            }
+            Self::GetKey {
+                database,
+                namespace,
+                key,
+            } => match gateway
+                .execute_key_operation(database, namespace, key, KeyCommand::Get { delete: false })
+                .await?
+            {
+                Output::Value(Some(value)) => {
+                    Ok(json_response(StatusCode::OK, &key_value_json(&value)?))
+                }
+                Output::Value(None) | Output::Status(_) => Err(GatewayError::NotFound),
+            },
+            Self::SetKey {
+                database,
+                namespace,
+                key,
+            } => {
+                gateway
+                    .execute_key_operation(
+                        database,
+                        namespace,
+                        key,
+                        KeyCommand::Set(SetCommand {
+                            value: json_to_key_value(body)?,
+                            expiration: None,
+                            keep_existing_expiration: false,
+                            check: None,
+                            return_previous_value: false,
+                        }),
+                    )
+                    .await?;
+                Ok(empty_response(StatusCode::NO_CONTENT))
+            }
+            Self::DeleteKey {
+                database,
+                namespace,
+                key,
+            } => match gateway
+                .execute_key_operation(database, namespace, key, KeyCommand::Delete)
+                .await?
+            {
+                Output::Status(KeyStatus::Deleted) => Ok(empty_response(StatusCode::NO_CONTENT)),
+                _ => Err(GatewayError::NotFound),
+            },
+            Self::Publish { database, topic } => {
+                gateway
+                    .send(&Publish {
+                        database,
+                        topic: Bytes::from(pot::to_vec(&topic).map_err(Error::from)?),
+                        payload: Bytes::from(json_to_pot(body)?),
+                    })
+                    .await?;
+                Ok(empty_response(StatusCode::NO_CONTENT))
+            }
+        }
+    }
+}
+
+struct QueryParameters(HashMap<String, String>);
+
+impl QueryParameters {
+    fn parse(query: Option<&str>) -> Result<Self, GatewayError> {
+        let mut parameters = HashMap::new();
+        for pair in query.unwrap_or_default().split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            parameters.insert(
+                percent_decode(&name.replace('+', " "))?,
+                percent_decode(&value.replace('+', " "))?,
+            );
+        }
+        Ok(Self(parameters))
+    }
+
+    fn take(&mut self, name: &str) -> Option<String> {
+        self.0.remove(name)
+    }
+
+    fn document_id(&mut self, name: &str) -> Result<Option<DocumentId>, GatewayError> {
+        self.take(name)
+            .map(|id| DocumentId::from_str(&id))
+            .transpose()
+            .map_err(GatewayError::from)
+    }
+
+    fn range(&mut self) -> Result<Range<DocumentId>, GatewayError> {
+        Ok(Range {
+            start: self
+                .document_id("start")?
+                .map_or(Bound::Unbounded, Bound::Included),
+            end: self
+                .document_id("end")?
+                .map_or(Bound::Unbounded, Bound::Excluded),
+        })
+    }
+
+    fn order(&mut self) -> Result<Sort, GatewayError> {
+        match self.take("order").as_deref() {
+            None | Some("ascending") => Ok(Sort::Ascending),
+            Some("descending") => Ok(Sort::Descending),
+            Some(other) => Err(GatewayError::BadRequest(format!("invalid order: {other}"))),
+        }
+    }
+
+    fn limit(&mut self) -> Result<Option<u32>, GatewayError> {
+        self.take("limit")
+            .map(|limit| {
+                limit
+                    .parse()
+                    .map_err(|_| GatewayError::BadRequest(format!("invalid limit: {limit}")))
+            })
+            .transpose()
+    }
+
+    fn access_policy(&mut self) -> Result<AccessPolicy, GatewayError> {
+        match self.take("access_policy").as_deref() {
+            None | Some("update-before") => Ok(AccessPolicy::UpdateBefore),
+            Some("update-after") => Ok(AccessPolicy::UpdateAfter),
+            Some("no-update") => Ok(AccessPolicy::NoUpdate),
+            Some(other) => Err(GatewayError::BadRequest(format!(
+                "invalid access policy: {other}"
+            ))),
+        }
+    }
+
+    fn flag(&mut self, name: &str) -> Result<bool, GatewayError> {
+        match self.take(name).as_deref() {
+            None | Some("false") => Ok(false),
+            Some("" | "true") => Ok(true),
+            Some(other) => Err(GatewayError::BadRequest(format!(
+                "invalid value for {name}: {other}"
+            ))),
+        }
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String, GatewayError> {
+    fn hex_digit(byte: Option<u8>) -> Option<u8> {
+        char::from(byte?)
+            .to_digit(16)
+            .and_then(|digit| u8::try_from(digit).ok())
+    }
+
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let (Some(high), Some(low)) = (hex_digit(bytes.next()), hex_digit(bytes.next())) else {
+                return Err(GatewayError::BadRequest(format!(
+                    "invalid percent-encoding: {value}"
+                )));
+            };
+            decoded.push(high << 4 | low);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded)
+        .map_err(|_| GatewayError::BadRequest(format!("invalid percent-encoding: {value}")))
+}
+
+fn parse_revision(value: &HeaderValue) -> Result<Revision, GatewayError> {
+    let invalid = || GatewayError::BadRequest(String::from("invalid revision in If-Match"));
+    let (id, digest) = value
+        .to_str()
+        .map_err(|_| invalid())?
+        .trim()
+        .trim_matches('"')
+        .split_once('-')
+        .ok_or_else(invalid)?;
+    let id = id.parse().map_err(|_| invalid())?;
+    if digest.len() != 64 || !digest.is_ascii() {
+        return Err(invalid());
+    }
+    let mut sha256 = [0; 32];
+    for (byte, hex) in sha256.iter_mut().zip(digest.as_bytes().chunks_exact(2)) {
+        let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+        *byte = u8::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    }
+    Ok(Revision { id, sha256 })
+}
+
+fn entity_tag(revision: &Revision) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{revision}\"")).expect("revisions are valid header values")
+}
+
+fn updated_header(results: Vec<OperationResult>) -> Result<Header, GatewayError> {
+    results
+        .into_iter()
+        .find_map(|result| match result {
+            OperationResult::DocumentUpdated { header, .. } => Some(header),
+            _ => None,
+        })
+        .ok_or_else(|| GatewayError::from(Error::other("http-gateway", "no document updated")))
+}
+
+fn json_to_pot(body: &[u8]) -> Result<Vec<u8>, GatewayError> {
+    let value = serde_json::from_slice::<serde_json::Value>(body)
+        .map_err(|err| GatewayError::BadRequest(err.to_string()))?;
+    Ok(pot::to_vec(&value).map_err(Error::from)?)
+}
+
+fn pot_to_json(bytes: &[u8]) -> Result<serde_json::Value, GatewayError> {
+    let value =
+        pot::from_slice::<pot::Value<'_>>(bytes).map_err(|_| GatewayError::UnsupportedContents)?;
+    serde_json::to_value(value).map_err(|_| GatewayError::UnsupportedContents)
+}
+
+fn json_to_key_value(body: &[u8]) -> Result<Value, GatewayError> {
+    let value = serde_json::from_slice::<serde_json::Value>(body)
+        .map_err(|err| GatewayError::BadRequest(err.to_string()))?;
+    if let serde_json::Value::Number(number) = &value {
+        let numeric = if let Some(value) = number.as_u64() {
+            Numeric::UnsignedInteger(value)
+        } else if let Some(value) = number.as_i64() {
+            Numeric::Integer(value)
+        } else {
+            Numeric::Float(number.as_f64().unwrap_or_default())
+        };
+        Ok(Value::Numeric(numeric))
+    } else {
+        Ok(Value::Bytes(Bytes::from(
+            pot::to_vec(&value).map_err(Error::from)?,
+        )))
+    }
+}
+
+fn key_value_json(value: &Value) -> Result<serde_json::Value, GatewayError> {
+    match value {
+        Value::Bytes(bytes) => pot_to_json(bytes),
+        Value::Numeric(Numeric::Integer(value)) => Ok(json!(value)),
+        Value::Numeric(Numeric::UnsignedInteger(value)) => Ok(json!(value)),
+        Value::Numeric(Numeric::Float(value)) => Ok(json!(value)),
+    }
+}
+
+fn header_json(header: &Header) -> serde_json::Value {
+    json!({
+        "id": header.id.to_string(),
+        "revision": header.revision.to_string(),
+    })
+}
+
+fn document_json(document: &OwnedDocument) -> Result<serde_json::Value, GatewayError> {
+    let mut json = header_json(&document.header);
+    json["contents"] = pot_to_json(&document.contents)?;
+    Ok(json)
+}
+
+fn mapping_json(mapping: &Serialized) -> Result<serde_json::Value, GatewayError> {
+    Ok(json!({
+        "source": header_json(&mapping.source),
+        "key": DocumentId::try_from(&mapping.key[..])?.to_string(),
+        "value": pot_to_json(&mapping.value)?,
+    }))
+}
+
+fn json_response(status: StatusCode, value: &serde_json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(value.to_string()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[derive(Debug)]
+enum GatewayError {
+    BadRequest(String),
+    NotFound,
+    MethodNotAllowed,
+    PayloadTooLarge,
+    ConnectionRejected,
+    UnsupportedContents,
+    Core(bonsaidb_core::Error),
+}
+
+impl GatewayError {
+    fn into_response(self) -> Response<Body> {
+        let (status, message) = match self {
+            Self::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            Self::NotFound => (StatusCode::NOT_FOUND, String::from("not found")),
+            Self::MethodNotAllowed => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                String::from("method not allowed"),
+            ),
+            Self::PayloadTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                String::from("request body too large"),
+            ),
+            Self::ConnectionRejected => {
+                (StatusCode::FORBIDDEN, String::from("connection rejected"))
+            }
+            Self::UnsupportedContents => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("contents can't be represented as JSON"),
+            ),
+            Self::Core(err) => {
+                let status = match &err {
+                    bonsaidb_core::Error::InvalidCredentials => StatusCode::UNAUTHORIZED,
+                    bonsaidb_core::Error::PermissionDenied(_) => StatusCode::FORBIDDEN,
+                    bonsaidb_core::Error::DatabaseNotFound(_)
+                    | bonsaidb_core::Error::DocumentNotFound(..)
+                    | bonsaidb_core::Error::CollectionNotFound
+                    | bonsaidb_core::Error::ViewNotFound
+                    | bonsaidb_core::Error::SchemaNotRegistered(_) => StatusCode::NOT_FOUND,
+                    bonsaidb_core::Error::DocumentConflict(..)
+                    | bonsaidb_core::Error::UniqueKeyViolation { .. } => StatusCode::CONFLICT,
+                    bonsaidb_core::Error::InvalidName(_)
+                    | bonsaidb_core::Error::InvalidDatabaseName(_)
+                    | bonsaidb_core::Error::DocumentIdTooLong
+                    | bonsaidb_core::Error::InvalidUnicode(_)
+                    | bonsaidb_core::Error::NotANumber => StatusCode::BAD_REQUEST,
+                    bonsaidb_core::Error::QuotaExceeded { .. } => StatusCode::INSUFFICIENT_STORAGE,
+                    bonsaidb_core::Error::RateLimited { retry_after } => {
+                        let mut response = json_response(
+                            StatusCode::TOO_MANY_REQUESTS,
+                            &json!({ "error": err.to_string() }),
+                        );
+                        response
+                            .headers_mut()
+                            .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs() + 1));
+                        return response;
+                    }
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                (status, err.to_string())
+            }
+        };
+
+        json_response(status, &json!({ "error": message }))
+    }
+}
+
+impl From<bonsaidb_core::Error> for GatewayError {
+    fn from(err: bonsaidb_core::Error) -> Self {
+        Self::Core(err)
+    }
+}
+
+impl From<Error> for GatewayError {
+    fn from(err: Error) -> Self {
+        Self::Core(bonsaidb_core::Error::from(err))
+    }
+}
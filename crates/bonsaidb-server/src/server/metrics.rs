@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use bonsaidb_core::api::ApiName;
+use bonsaidb_local::metrics::{Counter, Family, Histogram, PrometheusEncoder};
+#[cfg(feature = "hyper")]
+use hyper::{Body, Method, Request, Response, StatusCode};
+#[cfg(feature = "hyper")]
+use tokio::net::TcpListener;
+
+#[cfg(feature = "hyper")]
+use crate::Error;
+use crate::{Backend, CustomServer};
+
+/// The label used for requests whose [`ApiName`] isn't registered with the
+/// server.
+const UNKNOWN_API_LABEL: &str = "unknown";
+
+/// The maximum amount of time to wait for a metrics request's headers before
+/// the connection is closed.
+#[cfg(feature = "hyper")]
+const REQUEST_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Metrics collected by the server while dispatching requests.
+#[derive(Debug)]
+pub(crate) struct ServerMetrics {
+    requests: Family<Counter>,
+    request_errors: Family<Counter>,
+    request_duration: Family<Histogram>,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self {
+            requests: Family::new("api"),
+            request_errors: Family::new("api"),
+            request_duration: Family::new("api"),
+        }
+    }
+}
+
+impl ServerMetrics {
+    /// Records a dispatched request. `api` should be `None` when the request's
+    /// name isn't registered, because clients can send arbitrary names.
+    pub fn record_request(&self, api: Option<&ApiName>, elapsed: Duration, succeeded: bool) {
+        let api = api.map_or_else(|| String::from(UNKNOWN_API_LABEL), ToString::to_string);
+        self.requests.get(&api).increment();
+        if !succeeded {
+            self.request_errors.get(&api).increment();
+        }
+        self.request_duration.get(&api).observe(elapsed);
+    }
+
+    fn encode(&self, encoder: &mut PrometheusEncoder<'_>) {
+        encoder.family(
+            "bonsaidb_server_requests_total",
+            "Requests dispatched by the server.",
+            &self.requests,
+        );
+        encoder.family(
+            "bonsaidb_server_request_errors_total",
+            "Requests dispatched by the server that returned an error.",
+            &self.request_errors,
+        );
+        encoder.family(
+            "bonsaidb_server_request_duration_seconds",
+            "Time spent dispatching requests.",
+            &self.request_duration,
+        );
+    }
+}
+
+impl<B: Backend> CustomServer<B> {
+    /// Returns the metrics collected by this server and its storage in the
+    /// [Prometheus text exposition
+    /// format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+    ///
+    /// This includes request counts and latencies for each registered
+    /// [`ApiName`], with requests for unregistered names grouped under the
+    /// `unknown` label. It also includes the number of requests waiting to be
+    /// dispatched, transactions applied, and the depth of the background task
+    /// queue, such as the view mapping backlog.
+    #[must_use]
+    pub fn encode_metrics(&self) -> String {
+        let mut output = String::new();
+        let mut encoder = PrometheusEncoder::new(&mut output);
+        self.storage.as_blocking().metrics().encode(&mut encoder);
+        self.data.metrics.encode(&mut encoder);
+        encoder.gauge_value(
+            "bonsaidb_server_requests_queued",
+            "Requests waiting for a request worker.",
+            i64::try_from(self.data.request_processor.len()).unwrap_or(i64::MAX),
+        );
+        encoder.gauge_value(
+            "bonsaidb_server_connected_clients",
+            "Clients currently connected to the server.",
+            i64::try_from(self.data.clients.read().len()).unwrap_or(i64::MAX),
+        );
+        output
+    }
+
+    /// Listens for HTTP requests on `addr`, responding to `GET /metrics` with
+    /// the result of [`encode_metrics()`](Self::encode_metrics). This allows
+    /// Prometheus to scrape this server's metrics. Requires feature `hyper` to
+    /// be enabled.
+    ///
+    /// This listener doesn't authenticate requests, and should only be
+    /// reachable by trusted networks.
+    #[cfg(feature = "hyper")]
+    pub async fn listen_for_metrics_on<T: tokio::net::ToSocketAddrs + Send + Sync>(
+        &self,
+        addr: T,
+    ) -> Result<(), Error> {
+        let listener = TcpListener::bind(&addr).await?;
+        let mut shutdown_watcher = self
+            .data
+            .shutdown
+            .watcher()
+            .await
+            .expect("server already shutdown");
+
+        loop {
+            tokio::select! {
+                _ = shutdown_watcher.wait_for_shutdown() => {
+                    break;
+                }
+                incoming = listener.accept() => {
+                    let Ok((connection, remote_addr)) = incoming else {
+                        continue;
+                    };
+
+                    let task_self = self.clone();
+                    tokio::spawn(async move {
+                        let service = hyper::service::service_fn(move |request| {
+                            let response = task_self.metrics_response(&request);
+                            async move { Ok::<_, std::convert::Infallible>(response) }
+                        });
+                        if let Err(err) = hyper::server::conn::Http::new()
+                            .http1_only(true)
+                            .http1_header_read_timeout(REQUEST_HEADER_TIMEOUT)
+                            .serve_connection(connection, service)
+                            .await
+                        {
+                            log::error!("[server] error serving metrics to {}: {:?}", remote_addr, err);
+                        }
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "hyper")]
+    fn metrics_response(&self, request: &Request<Body>) -> Response<Body> {
+        let (status, body) = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/metrics") => (StatusCode::OK, Body::from(self.encode_metrics())),
+            (&Method::GET, _) => (StatusCode::NOT_FOUND, Body::empty()),
+            _ => (StatusCode::METHOD_NOT_ALLOWED, Body::empty()),
+        };
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        response.headers_mut().insert(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+        );
+        response
+    }
+}
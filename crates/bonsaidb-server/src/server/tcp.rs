@@ -43,7 +43,7 @@ impl<B: Backend> CustomServer<B> {
                     let task_self = self.clone();
                     let task_service = service.clone();
                     tokio::spawn(async move {
-                        if let Err(err) = task_self.handle_tcp_connection(connection, peer, &task_service).await {
+                        if let Err(err) = task_self.handle_tcp_connection(connection, peer, None, &task_service).await {
                             log::error!("[server] closing connection {}: {:?}", remote_addr, err);
                         }
                     });
@@ -82,10 +82,16 @@ impl<B: Backend> CustomServer<B> {
             });
         }
 
-        let mut config = rustls::ServerConfig::builder()
-            .with_safe_defaults()
-            .with_no_client_auth()
-            .with_cert_resolver(Arc::new(self.clone()));
+        let config = rustls::ServerConfig::builder().with_safe_defaults();
+        #[cfg(feature = "certificate-authentication")]
+        let config = if let Some(verifier) = &self.data.client_certificates {
+            config.with_client_cert_verifier(verifier.0.clone())
+        } else {
+            config.with_no_client_auth()
+        };
+        #[cfg(not(feature = "certificate-authentication"))]
+        let config = config.with_no_client_auth();
+        let mut config = config.with_cert_resolver(Arc::new(self.clone()));
         config.alpn_protocols = service
             .available_protocols()
             .iter()
@@ -110,9 +116,9 @@ impl<B: Backend> CustomServer<B> {
                 };
 
                 let available_protocols = task_service.available_protocols();
-                let protocol = stream
-                    .get_ref()
-                    .1
+                let tls_connection = stream.get_ref().1;
+                let client_certificates = tls_connection.peer_certificates().map(<[_]>::to_vec);
+                let protocol = tls_connection
                     .alpn_protocol()
                     .and_then(|protocol| {
                         available_protocols
@@ -127,7 +133,7 @@ impl<B: Backend> CustomServer<B> {
                     protocol,
                 };
                 if let Err(err) = task_self
-                    .handle_tcp_connection(stream, peer, &task_service)
+                    .handle_tcp_connection(stream, peer, client_certificates, &task_service)
                     .await
                 {
                     log::error!("[server] error for client {}: {:?}", peer_addr, err);
@@ -144,6 +150,7 @@ impl<B: Backend> CustomServer<B> {
         &self,
         connection: C,
         peer: Peer<S::ApplicationProtocols>,
+        client_certificates: Option<Vec<rustls::Certificate>>,
         service: &S,
     ) -> Result<(), Error> {
         // For ACME, don't send any traffic over the connection.
@@ -156,7 +163,7 @@ impl<B: Backend> CustomServer<B> {
         if let Err(connection) = service.handle_connection(connection, &peer).await {
             #[cfg(feature = "websockets")]
             if let Err(err) = self
-                .handle_raw_websocket_connection(connection, peer.address)
+                .handle_raw_websocket_connection(connection, peer.address, client_certificates)
                 .await
             {
                 log::error!(
@@ -7,6 +7,12 @@ use crate::server::connected_client::OwnedClient;
 use crate::server::shutdown::{ShutdownState, ShutdownStateWatcher};
 use crate::{Backend, CustomServer, Error, Transport};
 
+#[cfg(feature = "json-pubsub")]
+mod json_pubsub;
+
+#[cfg(feature = "json-pubsub")]
+pub use self::json_pubsub::JSON_PUBSUB_PROTOCOL;
+
 impl<B: Backend> CustomServer<B> {
     /// Listens for websocket connections on `addr`.
     pub async fn listen_for_websockets_on<T: tokio::net::ToSocketAddrs + Send + Sync>(
@@ -27,9 +33,31 @@ impl<B: Backend> CustomServer<B> {
         &self,
         connection: S,
         peer_address: std::net::SocketAddr,
+        client_certificates: Option<Vec<rustls::Certificate>>,
     ) -> Result<(), Error> {
-        let stream = tokio_tungstenite::accept_hdr_async(connection, VersionChecker).await?;
-        self.handle_websocket(stream, peer_address).await;
+        let mut protocol = WebSocketProtocol::Bonsai;
+        let stream = tokio_tungstenite::accept_hdr_async(
+            connection,
+            VersionChecker {
+                protocol: &mut protocol,
+            },
+        )
+        .await?;
+        match protocol {
+            WebSocketProtocol::Bonsai => {
+                self.handle_websocket_with_certificates(stream, peer_address, client_certificates)
+                    .await;
+            }
+            #[cfg(feature = "json-pubsub")]
+            WebSocketProtocol::JsonPubSub => {
+                self.handle_json_pubsub_websocket_with_certificates(
+                    stream,
+                    peer_address,
+                    client_certificates,
+                )
+                .await;
+            }
+        }
         Ok(())
     }
 
@@ -37,12 +65,35 @@ impl<B: Backend> CustomServer<B> {
     /// on the upgrade `request`. Requires feature `hyper` to be enabled.
     #[cfg(feature = "hyper")]
     pub fn upgrade_websocket(
+        &self,
+        peer_address: std::net::SocketAddr,
+        request: hyper::Request<hyper::Body>,
+    ) -> hyper::Response<hyper::Body> {
+        self.upgrade_websocket_with_protocol(peer_address, request, WebSocketProtocol::Bonsai)
+    }
+
+    /// Handles upgrading an HTTP connection to the JSON `PubSub` protocol
+    /// described in [`JSON_PUBSUB_PROTOCOL`] based on the upgrade `request`.
+    /// Requires features `hyper` and `json-pubsub` to be enabled.
+    #[cfg(all(feature = "hyper", feature = "json-pubsub"))]
+    pub fn upgrade_json_pubsub_websocket(
+        &self,
+        peer_address: std::net::SocketAddr,
+        request: hyper::Request<hyper::Body>,
+    ) -> hyper::Response<hyper::Body> {
+        self.upgrade_websocket_with_protocol(peer_address, request, WebSocketProtocol::JsonPubSub)
+    }
+
+    #[cfg(feature = "hyper")]
+    fn upgrade_websocket_with_protocol(
         &self,
         peer_address: std::net::SocketAddr,
         mut request: hyper::Request<hyper::Body>,
+        protocol: WebSocketProtocol,
     ) -> hyper::Response<hyper::Body> {
         use hyper::header::{
-            HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+            HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY,
+            SEC_WEBSOCKET_PROTOCOL, UPGRADE,
         };
         use hyper::StatusCode;
         use tokio_tungstenite::tungstenite::protocol::Role;
@@ -61,12 +112,34 @@ impl<B: Backend> CustomServer<B> {
             return response;
         };
 
+        // Browsers reject the connection unless a requested subprotocol is
+        // echoed back.
+        let requested_protocol = protocol.name().filter(|name| {
+            request
+                .headers()
+                .get_all(SEC_WEBSOCKET_PROTOCOL)
+                .iter()
+                .filter_map(|protocols| protocols.to_str().ok())
+                .flat_map(|protocols| protocols.split(',').map(str::trim))
+                .any(|requested| requested == *name)
+        });
+
         let task_self = self.clone();
         tokio::spawn(async move {
             match hyper::upgrade::on(&mut request).await {
                 Ok(upgraded) => {
                     let ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
-                    task_self.handle_websocket(ws, peer_address).await;
+                    match protocol {
+                        WebSocketProtocol::Bonsai => {
+                            task_self.handle_websocket(ws, peer_address).await;
+                        }
+                        #[cfg(feature = "json-pubsub")]
+                        WebSocketProtocol::JsonPubSub => {
+                            task_self
+                                .handle_json_pubsub_websocket(ws, peer_address)
+                                .await;
+                        }
+                    }
                 }
                 Err(err) => {
                     log::error!("Error upgrading websocket: {:?}", err);
@@ -85,6 +158,11 @@ impl<B: Backend> CustomServer<B> {
             SEC_WEBSOCKET_ACCEPT,
             compute_websocket_accept_header(sec_websocket_key.as_bytes()),
         );
+        if let Some(protocol) = requested_protocol {
+            response
+                .headers_mut()
+                .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(protocol));
+        }
 
         response
     }
@@ -100,6 +178,24 @@ impl<B: Backend> CustomServer<B> {
         &self,
         connection: S,
         peer_address: std::net::SocketAddr,
+    ) {
+        self.handle_websocket_with_certificates(connection, peer_address, None)
+            .await;
+    }
+
+    /// Handles an established `WebSocket` stream whose client presented
+    /// `client_certificates` during the TLS handshake.
+    async fn handle_websocket_with_certificates<
+        S: futures::Stream<Item = Result<tokio_tungstenite::tungstenite::Message, E>>
+            + futures::Sink<tokio_tungstenite::tungstenite::Message>
+            + Send
+            + 'static,
+        E: std::fmt::Debug + Send,
+    >(
+        &self,
+        connection: S,
+        peer_address: std::net::SocketAddr,
+        client_certificates: Option<Vec<rustls::Certificate>>,
     ) {
         let mut shutdown = self
             .data
@@ -114,7 +210,12 @@ impl<B: Backend> CustomServer<B> {
 
         let (api_response_sender, api_response_receiver) = flume::unbounded();
         let Some(client) = self
-            .initialize_client(Transport::WebSocket, peer_address, api_response_sender)
+            .initialize_client(
+                Transport::WebSocket,
+                peer_address,
+                client_certificates,
+                api_response_sender,
+            )
             .await
         else {
             return;
@@ -236,9 +337,33 @@ fn compute_websocket_accept_header(key: &[u8]) -> hyper::header::HeaderValue {
     hyper::header::HeaderValue::from_str(&encoded).expect("base64 is a valid value")
 }
 
-struct VersionChecker;
+#[derive(Clone, Copy)]
+enum WebSocketProtocol {
+    Bonsai,
+    #[cfg(feature = "json-pubsub")]
+    JsonPubSub,
+}
+
+impl WebSocketProtocol {
+    /// Returns the subprotocol clients must request to use this protocol, if
+    /// any.
+    #[cfg_attr(not(feature = "hyper"), allow(dead_code))]
+    const fn name(self) -> Option<&'static str> {
+        match self {
+            // The Bonsai protocol is negotiated by `VersionChecker` for raw
+            // connections, and upgrades using hyper have never required it.
+            Self::Bonsai => None,
+            #[cfg(feature = "json-pubsub")]
+            Self::JsonPubSub => Some(JSON_PUBSUB_PROTOCOL),
+        }
+    }
+}
+
+struct VersionChecker<'a> {
+    protocol: &'a mut WebSocketProtocol,
+}
 
-impl tokio_tungstenite::tungstenite::handshake::server::Callback for VersionChecker {
+impl tokio_tungstenite::tungstenite::handshake::server::Callback for VersionChecker<'_> {
     fn on_request(
         self,
         request: &tokio_tungstenite::tungstenite::handshake::server::Request,
@@ -255,6 +380,17 @@ impl tokio_tungstenite::tungstenite::handshake::server::Callback for VersionChec
                             "Sec-WebSocket-Protocol",
                             CURRENT_PROTOCOL_VERSION.try_into().unwrap(),
                         );
+                        *self.protocol = WebSocketProtocol::Bonsai;
+                        return Ok(response);
+                    }
+
+                    #[cfg(feature = "json-pubsub")]
+                    if protocol == JSON_PUBSUB_PROTOCOL {
+                        response.headers_mut().insert(
+                            "Sec-WebSocket-Protocol",
+                            JSON_PUBSUB_PROTOCOL.try_into().unwrap(),
+                        );
+                        *self.protocol = WebSocketProtocol::JsonPubSub;
                         return Ok(response);
                     }
                 }
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use bonsaidb_core::api::{Api, ApiName};
+use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::connection::SessionId;
+#[cfg(any(feature = "password-hashing", feature = "jwt-authentication"))]
+use bonsaidb_core::networking::{Authenticate, UnregisterSubscriber};
+use bonsaidb_core::networking::{
+    CreateSubscriber, MessageReceived, Publish, SubscribeTo, UnsubscribeFrom,
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::server::connected_client::OwnedClient;
+use crate::server::shutdown::ShutdownState;
+use crate::{Backend, CustomServer, Transport};
+
+/// The `WebSocket` subprotocol that selects the JSON `PubSub` protocol. Browsers
+/// can connect using `new WebSocket(url, "bonsaidb-pubsub-json")`.
+///
+/// Each request is a text message containing a JSON object whose `type` field
+/// identifies the request. An optional `id` field is echoed in the response:
+///
+/// - `{"type": "subscribe", "database": "chat", "topic": "general"}`
+/// - `{"type": "unsubscribe", "database": "chat", "topic": "general"}`
+/// - `{"type": "publish", "database": "chat", "topic": "general", "payload": {"text": "hi"}}`
+/// - `{"type": "authenticate_with_password", "username": "ecton", "password": "..."}`:
+///   Requires feature `password-hashing`.
+/// - `{"type": "authenticate_with_jwt", "token": "..."}`: Requires feature
+///   `jwt-authentication`.
+///
+/// Each request receives either `{"type": "ok"}` or `{"type": "error",
+/// "error": "<message>"}`. Messages published to subscribed topics are sent
+/// as `{"type": "message_received", "database": "chat", "topic": "general",
+/// "payload": {"text": "hi"}}`.
+///
+/// Topics and payloads may be any JSON value, and are converted to and from
+/// [Pot](https://github.com/khonsulabs/pot), the format used by
+/// [`AsyncPubSub::publish()`](bonsaidb_core::pubsub::AsyncPubSub::publish).
+/// This allows JSON clients and clients using `bonsaidb-client` to exchange
+/// messages. Messages whose topic or payload can't be represented as JSON are
+/// not delivered to JSON clients.
+///
+/// Requests are dispatched through the same handlers as other clients, so
+/// permissions and rate limits apply identically.
+pub const JSON_PUBSUB_PROTOCOL: &str = "bonsaidb-pubsub-json";
+
+/// The maximum number of messages queued to be sent to a JSON `PubSub` client.
+/// Clients that fall further behind are disconnected.
+const MAX_QUEUED_MESSAGES: usize = 1024;
+
+#[derive(Deserialize)]
+struct JsonRequest {
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    request: JsonPubSubRequest,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonPubSubRequest {
+    Subscribe {
+        database: String,
+        topic: serde_json::Value,
+    },
+    Unsubscribe {
+        database: String,
+        topic: serde_json::Value,
+    },
+    Publish {
+        database: String,
+        topic: serde_json::Value,
+        payload: serde_json::Value,
+    },
+    #[cfg(feature = "password-hashing")]
+    AuthenticateWithPassword { username: String, password: String },
+    #[cfg(feature = "jwt-authentication")]
+    AuthenticateWithJwt { token: String },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonPubSubResponse<'a> {
+    Ok {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<serde_json::Value>,
+    },
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<serde_json::Value>,
+        error: String,
+    },
+    MessageReceived {
+        database: &'a str,
+        topic: serde_json::Value,
+        payload: serde_json::Value,
+    },
+}
+
+impl JsonPubSubResponse<'_> {
+    fn into_message(self) -> Message {
+        Message::Text(serde_json::to_string(&self).expect("json serialization failed"))
+    }
+}
+
+struct JsonPubSubClient<'a, B: Backend> {
+    server: &'a CustomServer<B>,
+    client: OwnedClient<B>,
+    session_id: Option<SessionId>,
+    subscribers: HashMap<String, u64>,
+    subscriber_databases: HashMap<u64, String>,
+}
+
+impl<B: Backend> JsonPubSubClient<'_, B> {
+    async fn handle_request(&mut self, request: &str) -> Message {
+        let (id, request) = match serde_json::from_str::<JsonRequest>(request) {
+            Ok(JsonRequest { id, request }) => (id, request),
+            Err(err) => {
+                return JsonPubSubResponse::Error {
+                    id: None,
+                    error: err.to_string(),
+                }
+                .into_message()
+            }
+        };
+        match self.execute(request).await {
+            Ok(()) => JsonPubSubResponse::Ok { id },
+            Err(err) => JsonPubSubResponse::Error {
+                id,
+                error: err.to_string(),
+            },
+        }
+        .into_message()
+    }
+
+    async fn execute(&mut self, request: JsonPubSubRequest) -> Result<(), bonsaidb_core::Error> {
+        match request {
+            JsonPubSubRequest::Subscribe { database, topic } => {
+                let subscriber_id = self.subscriber_for(database.clone()).await?;
+                self.send(&SubscribeTo {
+                    database,
+                    subscriber_id,
+                    topic: Bytes::from(pot::to_vec(&topic)?),
+                })
+                .await
+            }
+            JsonPubSubRequest::Unsubscribe { database, topic } => {
+                let Some(&subscriber_id) = self.subscribers.get(&database) else {
+                    return Ok(());
+                };
+                self.send(&UnsubscribeFrom {
+                    database,
+                    subscriber_id,
+                    topic: Bytes::from(pot::to_vec(&topic)?),
+                })
+                .await
+            }
+            JsonPubSubRequest::Publish {
+                database,
+                topic,
+                payload,
+            } => {
+                self.send(&Publish {
+                    database,
+                    topic: Bytes::from(pot::to_vec(&topic)?),
+                    payload: Bytes::from(pot::to_vec(&payload)?),
+                })
+                .await
+            }
+            #[cfg(feature = "password-hashing")]
+            JsonPubSubRequest::AuthenticateWithPassword { username, password } => {
+                self.authenticate(bonsaidb_core::connection::Authentication::password(
+                    username,
+                    bonsaidb_core::connection::SensitiveString(password),
+                )?)
+                .await
+            }
+            #[cfg(feature = "jwt-authentication")]
+            JsonPubSubRequest::AuthenticateWithJwt { token } => {
+                self.authenticate(bonsaidb_core::connection::Authentication::jwt(token))
+                    .await
+            }
+        }
+    }
+
+    #[cfg(any(feature = "password-hashing", feature = "jwt-authentication"))]
+    async fn authenticate(
+        &mut self,
+        authentication: bonsaidb_core::connection::Authentication,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let session = self.send(&Authenticate { authentication }).await?;
+        // Subscribers belong to the session that created them, so they are
+        // unregistered using the previous session before switching sessions.
+        for (database, subscriber_id) in std::mem::take(&mut self.subscribers) {
+            if let Err(err) = self
+                .send(&UnregisterSubscriber {
+                    database,
+                    subscriber_id,
+                })
+                .await
+            {
+                log::warn!("[server] error unregistering json pubsub subscriber: {err}");
+            }
+        }
+        self.subscriber_databases.clear();
+        self.session_id = session.id;
+        Ok(())
+    }
+
+    async fn subscriber_for(&mut self, database: String) -> Result<u64, bonsaidb_core::Error> {
+        if let Some(subscriber_id) = self.subscribers.get(&database) {
+            return Ok(*subscriber_id);
+        }
+
+        let subscriber_id = self
+            .send(&CreateSubscriber {
+                database: database.clone(),
+            })
+            .await?;
+        self.subscribers.insert(database.clone(), subscriber_id);
+        self.subscriber_databases.insert(subscriber_id, database);
+        Ok(subscriber_id)
+    }
+
+    async fn send<Request: Api<Error = bonsaidb_core::Error>>(
+        &self,
+        request: &Request,
+    ) -> Result<Request::Response, bonsaidb_core::Error> {
+        self.server
+            .dispatch_for_client(self.client.clone(), self.session_id, request)
+            .await
+    }
+
+    /// Converts a response pushed to the client into a JSON message, if it is
+    /// a [`MessageReceived`] that can be represented as JSON.
+    fn pushed_response(&self, name: &ApiName, response: &[u8]) -> Option<Message> {
+        if name != &MessageReceived::name() {
+            return None;
+        }
+
+        let message =
+            pot::from_slice::<Result<MessageReceived, bonsaidb_core::Error>>(response).ok()?;
+        let message = message.ok()?;
+        let database = self.subscriber_databases.get(&message.subscriber_id)?;
+        match (pot_to_json(&message.topic), pot_to_json(&message.payload)) {
+            (Some(topic), Some(payload)) => Some(
+                JsonPubSubResponse::MessageReceived {
+                    database,
+                    topic,
+                    payload,
+                }
+                .into_message(),
+            ),
+            _ => {
+                log::debug!("[server] message can't be represented as JSON");
+                None
+            }
+        }
+    }
+}
+
+fn pot_to_json(bytes: &[u8]) -> Option<serde_json::Value> {
+    let value = pot::from_slice::<pot::Value<'_>>(bytes).ok()?;
+    serde_json::to_value(value).ok()
+}
+
+impl<B: Backend> CustomServer<B> {
+    /// Handles an established `tokio-tungstenite` `WebSocket` stream using the
+    /// JSON `PubSub` protocol described in [`JSON_PUBSUB_PROTOCOL`]. Requires
+    /// feature `json-pubsub` to be enabled.
+    ///
+    /// Connections accepted by
+    /// [`listen_for_websockets_on()`](Self::listen_for_websockets_on) use this
+    /// protocol automatically when the client requests the
+    /// [`JSON_PUBSUB_PROTOCOL`] subprotocol.
+    pub async fn handle_json_pubsub_websocket<
+        S: futures::Stream<Item = Result<Message, E>> + futures::Sink<Message> + Send + 'static,
+        E: std::fmt::Debug + Send,
+    >(
+        &self,
+        connection: S,
+        peer_address: std::net::SocketAddr,
+    ) {
+        self.handle_json_pubsub_websocket_with_certificates(connection, peer_address, None)
+            .await;
+    }
+
+    pub(super) async fn handle_json_pubsub_websocket_with_certificates<
+        S: futures::Stream<Item = Result<Message, E>> + futures::Sink<Message> + Send + 'static,
+        E: std::fmt::Debug + Send,
+    >(
+        &self,
+        connection: S,
+        peer_address: std::net::SocketAddr,
+        client_certificates: Option<Vec<rustls::Certificate>>,
+    ) {
+        let mut shutdown = self
+            .data
+            .shutdown
+            .watcher()
+            .await
+            .expect("watcher shut down");
+
+        let (mut sender, mut receiver) = connection.split();
+        let (message_sender, message_receiver) = flume::bounded(MAX_QUEUED_MESSAGES);
+        let (api_response_sender, api_response_receiver) = flume::bounded(MAX_QUEUED_MESSAGES);
+        let Some(client) = self
+            .initialize_client(
+                Transport::WebSocket,
+                peer_address,
+                client_certificates,
+                api_response_sender,
+            )
+            .await
+        else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            while let Ok(message) = message_receiver.recv_async().await {
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = JsonPubSubClient {
+            server: self,
+            client,
+            session_id: None,
+            subscribers: HashMap::new(),
+            subscriber_databases: HashMap::new(),
+        };
+        loop {
+            tokio::select! {
+                payload = receiver.next() => {
+                    match payload {
+                        Some(Ok(Message::Text(request))) => {
+                            let response = client.handle_request(&request).await;
+                            if message_sender.send_async(response).await.is_err() {
+                                return;
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            if message_sender.send_async(Message::Pong(payload)).await.is_err() {
+                                return;
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => return,
+                        other => {
+                            log::error!("[server] unexpected message: {:?}", other);
+                            return;
+                        }
+                    }
+                },
+                response = api_response_receiver.recv_async() => {
+                    let Ok((_session_id, name, response)) = response else {
+                        return;
+                    };
+                    if !client.client.connected() {
+                        log::warn!(
+                            "[server] disconnecting json pubsub client {peer_address} that isn't keeping up"
+                        );
+                        return;
+                    }
+                    if let Some(message) = client.pushed_response(&name, &response) {
+                        if message_sender.try_send(message).is_err() {
+                            log::warn!(
+                                "[server] disconnecting json pubsub client {peer_address} that isn't keeping up"
+                            );
+                            return;
+                        }
+                    }
+                },
+                shutdown = shutdown.wait_for_shutdown() => {
+                    if matches!(shutdown, ShutdownState::Shutdown) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,22 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use async_trait::async_trait;
 use bonsaidb_core::actionable::{Permissions, Statement};
+use bonsaidb_core::admin::ScheduledTaskRun;
 use bonsaidb_core::connection::AsyncStorageConnection;
+use bonsaidb_core::key::time::TimestampAsNanoseconds;
+use bonsaidb_core::permissions::bonsai::{BonsaiAction, DatabaseAction};
+use bonsaidb_core::permissions::Action;
+use bonsaidb_core::schema::SerializedCollection;
 use bonsaidb_core::test_util::{self, BasicSchema, HarnessTest, TestDirectory};
+use bonsaidb_local::config::{Builder, StorageConfiguration};
+use bonsaidb_local::AsyncStorage;
 
+use crate::rate_limit::{RateLimit, RateLimitResponse, RateLimiter, RateLimits};
+use crate::schedule::{Schedule, ScheduledTask};
 use crate::server::ServerDatabase;
 use crate::test_util::initialize_basic_server;
-use crate::Server;
+use crate::{BackendError, Server, ServerConfiguration};
 
 #[tokio::test]
 async fn simple_test() -> anyhow::Result<()> {
@@ -35,6 +47,619 @@ async fn install_self_signed_certificate_tests() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[derive(Debug)]
+struct SendOnExecute(flume::Sender<TimestampAsNanoseconds>);
+
+#[async_trait]
+impl ScheduledTask for SendOnExecute {
+    async fn execute(
+        &self,
+        _server: &Server,
+        scheduled_for: TimestampAsNanoseconds,
+    ) -> Result<(), BackendError> {
+        drop(self.0.send(scheduled_for));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn scheduled_task_catch_up_test() -> anyhow::Result<()> {
+    let test_dir = TestDirectory::new("scheduled-task-catch-up");
+    let two_hours_ago = SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+    {
+        // Record an execution that happened before the server was "offline".
+        let storage = AsyncStorage::open(StorageConfiguration::new(&test_dir)).await?;
+        ScheduledTaskRun {
+            last_run: TimestampAsNanoseconds::try_from(two_hours_ago)?,
+        }
+        .overwrite_into_async(&String::from("catch-up"), &storage.admin().await)
+        .await
+        .map_err(|err| err.error)?;
+    }
+
+    let (sender, receiver) = flume::unbounded();
+    let server = Server::open(ServerConfiguration::new(&test_dir).with_scheduled_task(
+        "catch-up",
+        Schedule::new("@hourly")?,
+        SendOnExecute(sender),
+    )?)
+    .await?;
+
+    // The missed executions are caught up with a single execution.
+    let scheduled_for =
+        tokio::time::timeout(Duration::from_secs(10), receiver.recv_async()).await??;
+    assert!(SystemTime::try_from(scheduled_for)? < SystemTime::now());
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), receiver.recv_async())
+            .await
+            .is_err()
+    );
+
+    let last_run = ScheduledTaskRun::get_async(&String::from("catch-up"), &server.admin().await)
+        .await?
+        .expect("last run not recorded");
+    assert!(SystemTime::try_from(last_run.contents.last_run)? > two_hours_ago);
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
+#[test]
+fn duplicate_scheduled_task_test() {
+    let configuration = ServerConfiguration::default()
+        .with_scheduled_task(
+            "task",
+            Schedule::new("@daily").unwrap(),
+            SendOnExecute(flume::unbounded().0),
+        )
+        .unwrap();
+    assert!(matches!(
+        configuration.with_scheduled_task(
+            "task",
+            Schedule::new("@hourly").unwrap(),
+            SendOnExecute(flume::unbounded().0),
+        ),
+        Err(crate::Error::ScheduledTaskAlreadyRegistered(name)) if name == "task"
+    ));
+}
+
+#[tokio::test]
+async fn rate_limit_reject_test() {
+    let compact = BonsaiAction::Database(DatabaseAction::Compact);
+    let limiter = RateLimiter::new(
+        RateLimits::default()
+            .per_connection(RateLimit::new(2, Duration::from_secs(60)))
+            .action(&compact, RateLimit::new(1, Duration::from_secs(60))),
+    );
+
+    limiter.limit_request(0, None).await.unwrap();
+    limiter.limit_request(0, None).await.unwrap();
+    match limiter.limit_request(0, None).await {
+        Err(bonsaidb_core::Error::RateLimited { retry_after }) => {
+            assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(60));
+        }
+        other => unreachable!("expected rate limit error, got {other:?}"),
+    }
+
+    // Other connections have their own buckets.
+    limiter.limit_request(1, None).await.unwrap();
+
+    let compact = compact.name();
+    limiter.limit_actions(1, [&compact]).await.unwrap();
+    assert!(matches!(
+        limiter.limit_actions(1, [&compact]).await,
+        Err(bonsaidb_core::Error::RateLimited { .. })
+    ));
+    limiter.limit_actions(0, [&compact]).await.unwrap();
+
+    // Disconnecting forgets the client's buckets.
+    limiter.client_disconnected(0);
+    limiter.limit_request(0, None).await.unwrap();
+}
+
+#[tokio::test]
+async fn rate_limit_delay_test() {
+    let limiter = RateLimiter::new(
+        RateLimits::default()
+            .per_connection(RateLimit::new(1, Duration::from_millis(100)))
+            .response(RateLimitResponse::Delay),
+    );
+
+    let start = Instant::now();
+    limiter.limit_request(0, None).await.unwrap();
+    limiter.limit_request(0, None).await.unwrap();
+    limiter.limit_request(0, None).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn rate_limit_eviction_test() {
+    let limiter = RateLimiter::new(
+        RateLimits::default().per_connection(RateLimit::new(1, Duration::from_millis(10))),
+    );
+
+    limiter.limit_request(0, None).await.unwrap();
+    limiter.limit_request(1, None).await.unwrap();
+    assert_eq!(limiter.tracked_buckets(), 2);
+
+    // Once a bucket has refilled, it no longer needs to be tracked.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    limiter.evict_idle_buckets();
+    assert_eq!(limiter.tracked_buckets(), 0);
+}
+
+#[cfg(feature = "jwt-authentication")]
+#[derive(Debug, Default)]
+struct JwtBackend;
+
+#[cfg(feature = "jwt-authentication")]
+#[async_trait]
+impl crate::Backend for JwtBackend {
+    type ClientData = ();
+    type Error = std::convert::Infallible;
+
+    async fn identity_for_jwt_claims(
+        &self,
+        claims: &crate::jwt::JwtClaims,
+        _server: &crate::CustomServer<Self>,
+    ) -> Result<
+        Option<bonsaidb_core::connection::IdentityReference<'static>>,
+        BackendError<Self::Error>,
+    > {
+        Ok(claims
+            .subject
+            .clone()
+            .filter(|subject| subject == "jwt-user")
+            .map(bonsaidb_core::connection::IdentityReference::user)
+            .transpose()?)
+    }
+}
+
+#[cfg(feature = "jwt-authentication")]
+#[tokio::test]
+async fn jwt_authentication_test() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::{HasSession, Identity};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+
+    const SECRET: &[u8] = b"bonsaidb-jwt-test-secret";
+
+    fn sign(subject: &str, secret: &[u8]) -> String {
+        sign_with(Algorithm::HS256, subject, secret)
+    }
+
+    fn sign_with(algorithm: Algorithm, subject: &str, secret: &[u8]) -> String {
+        let expiration = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+        let mut header = Header::new(algorithm);
+        header.kid = Some(String::from("test"));
+        jsonwebtoken::encode(
+            &header,
+            &serde_json::json!({ "sub": subject, "iss": "bonsaidb-tests", "exp": expiration }),
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    let test_dir = TestDirectory::new("jwt-authentication");
+    let jwks_path = test_dir.as_ref().join("jwks.json");
+    std::fs::create_dir_all(test_dir.as_ref())?;
+    std::fs::write(
+        &jwks_path,
+        r#"{"keys":[{"kty":"oct","kid":"test","alg":"HS256","k":"Ym9uc2FpZGItand0LXRlc3Qtc2VjcmV0"}]}"#,
+    )?;
+    let server = crate::CustomServer::<JwtBackend>::open(
+        ServerConfiguration::new_with_backend(test_dir.as_ref().join("server"), JwtBackend).jwt(
+            crate::jwt::JwtConfiguration::from_jwks_file(&jwks_path, [Algorithm::HS256])?
+                .issuer("bonsaidb-tests"),
+        ),
+    )
+    .await?;
+    let user_id = server.create_user("jwt-user").await?;
+
+    let authenticated = server
+        .authenticate_with_jwt(sign("jwt-user", SECRET))
+        .await?;
+    assert!(matches!(
+        authenticated.session().and_then(|session| session.identity()),
+        Some(Identity::User { id, .. }) if *id == user_id
+    ));
+
+    // Tokens signed with another key are rejected.
+    assert!(matches!(
+        server
+            .authenticate_with_jwt(sign("jwt-user", b"another-secret"))
+            .await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+    // Tokens signed with an algorithm that isn't allowed are rejected.
+    assert!(matches!(
+        server
+            .authenticate_with_jwt(sign_with(Algorithm::HS512, "jwt-user", SECRET))
+            .await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+    // Tokens the backend doesn't map to an identity are rejected.
+    assert!(matches!(
+        server
+            .authenticate_with_jwt(sign("someone-else", SECRET))
+            .await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
+#[cfg(feature = "certificate-authentication")]
+#[test]
+fn client_certificate_verification_test() -> anyhow::Result<()> {
+    use crate::client_certificate::ClientCertificateConfiguration;
+
+    fn chain(keypair: &fabruic::KeyPair) -> Vec<rustls::Certificate> {
+        keypair
+            .certificate_chain()
+            .iter()
+            .map(|certificate| rustls::Certificate(certificate.as_ref().to_vec()))
+            .collect()
+    }
+
+    let trusted = fabruic::KeyPair::new_self_signed("cert-user.example");
+    let untrusted = fabruic::KeyPair::new_self_signed("cert-user.example");
+    let verifier = ClientCertificateConfiguration::new(rustls::RootCertStore::empty())
+        .trusted_root(trusted.end_entity_certificate())?
+        .required()
+        .verifier();
+    assert!(verifier.required());
+
+    let certificate = verifier.verify(chain(&trusted))?;
+    assert_eq!(
+        certificate.dns_names,
+        vec![String::from("cert-user.example")]
+    );
+    assert_eq!(certificate.chain, chain(&trusted));
+
+    // Certificates not issued by a trusted root are rejected, even if their
+    // subjects match.
+    assert!(verifier.verify(chain(&untrusted)).is_err());
+    assert!(verifier.verify(Vec::new()).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "http-gateway")]
+#[tokio::test]
+async fn http_gateway_test() -> anyhow::Result<()> {
+    use std::str::FromStr;
+
+    use bonsaidb_core::document::DocumentId;
+    use bonsaidb_core::keyvalue::AsyncKeyValue;
+    use bonsaidb_core::schema::{Collection, Qualified};
+    use bonsaidb_core::test_util::Basic;
+    use hyper::{Body, Method, Request, StatusCode};
+    use serde_json::json;
+
+    async fn send(
+        server: &Server,
+        method: Method,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = server
+            .handle_http_gateway_request("127.0.0.1:12345".parse().unwrap(), request)
+            .await;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap()
+        };
+        (status, body)
+    }
+
+    let test_dir = TestDirectory::new("http-gateway");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+    let db = server.database::<BasicSchema>("tests").await?;
+    let collection = Basic::collection_name().encoded();
+    let documents = format!("/databases/tests/collections/{collection}/documents");
+
+    let (status, inserted) = send(
+        &server,
+        Method::POST,
+        &documents,
+        Some(json!({"value": "gateway", "category": "http", "parent_id": null, "tags": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = inserted["id"].as_str().unwrap().to_string();
+
+    // Documents inserted through the gateway are readable by native clients.
+    let native_id = DocumentId::from_str(&id)?.deserialize::<u64>()?;
+    let native = Basic::get_async(&native_id, &db).await?.unwrap();
+    assert_eq!(native.contents.value, "gateway");
+
+    let (status, overwritten) = send(
+        &server,
+        Method::PUT,
+        &format!("{documents}/{id}"),
+        Some(json!({"value": "updated", "category": "http", "parent_id": null, "tags": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(overwritten["revision"], inserted["revision"]);
+
+    let (status, document) = send(&server, Method::GET, &format!("{documents}/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(document["contents"]["value"], "updated");
+
+    let (status, listed) = send(&server, Method::GET, &documents, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let (status, mappings) = send(
+        &server,
+        Method::GET,
+        &format!("/databases/tests/views/{collection}.by-category?key=http&docs=true"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mappings[0]["key"], "http");
+    assert_eq!(mappings[0]["value"], 1);
+    assert_eq!(mappings[0]["document"]["contents"]["value"], "updated");
+
+    let (status, _) = send(&server, Method::DELETE, &format!("{documents}/{id}"), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&server, Method::GET, &format!("{documents}/{id}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        &server,
+        Method::PUT,
+        "/databases/tests/kv/counter",
+        Some(json!(1)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(db.get_key("counter").into_u64().await?, Some(1));
+    let (status, value) = send(&server, Method::GET, "/databases/tests/kv/counter", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value, json!(1));
+
+    let (status, _) = send(
+        &server,
+        Method::POST,
+        "/databases/tests/pubsub/gateway",
+        Some(json!({"hello": "world"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&server, Method::GET, "/databases/missing/kv/counter", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&server, Method::PATCH, &documents, None).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    let (status, _) = send(&server, Method::GET, "/unknown", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Bodies that are too large are rejected before being read.
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri("/databases/tests/kv/counter")
+        .header(hyper::header::CONTENT_LENGTH, 1024 * 1024 * 1024)
+        .body(Body::empty())
+        .unwrap();
+    let response = server
+        .handle_http_gateway_request("127.0.0.1:12345".parse().unwrap(), request)
+        .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
+#[cfg(feature = "http-gateway")]
+#[tokio::test]
+async fn http_gateway_rate_limit_test() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::{Collection, Qualified};
+    use bonsaidb_core::test_util::Basic;
+    use hyper::{Body, Method, Request, Response};
+
+    use crate::DefaultPermissions;
+
+    async fn list(server: &Server, peer_address: &str) -> Response<Body> {
+        let collection = Basic::collection_name().encoded();
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!(
+                "/databases/tests/collections/{collection}/documents"
+            ))
+            .body(Body::empty())
+            .unwrap();
+        server
+            .handle_http_gateway_request(peer_address.parse().unwrap(), request)
+            .await
+    }
+
+    let test_dir = TestDirectory::new("http-gateway-rate-limit");
+    let server = Server::open(
+        ServerConfiguration::new(&test_dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .rate_limits(
+                RateLimits::default().per_connection(RateLimit::new(2, Duration::from_secs(60))),
+            )
+            .with_schema::<BasicSchema>()?,
+    )
+    .await?;
+    server
+        .create_database::<BasicSchema>("tests", false)
+        .await?;
+
+    // Each request is handled by a new client, but requests from the same IP
+    // address share their buckets, regardless of the port they are sent from.
+    assert_eq!(
+        list(&server, "127.0.0.1:12345").await.status(),
+        hyper::StatusCode::OK
+    );
+    assert_eq!(
+        list(&server, "127.0.0.1:12346").await.status(),
+        hyper::StatusCode::OK
+    );
+    let limited = list(&server, "127.0.0.1:12347").await;
+    assert_eq!(limited.status(), hyper::StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited.headers().contains_key(hyper::header::RETRY_AFTER));
+    let body = hyper::body::to_bytes(limited.into_body()).await?;
+    let body = serde_json::from_slice::<serde_json::Value>(&body)?;
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("rate limit exceeded"));
+
+    // Other addresses have their own buckets.
+    assert_eq!(
+        list(&server, "127.0.0.2:12345").await.status(),
+        hyper::StatusCode::OK
+    );
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
+#[cfg(feature = "json-pubsub")]
+#[tokio::test]
+async fn json_pubsub_websocket_test() -> anyhow::Result<()> {
+    use bonsaidb_core::pubsub::AsyncPubSub;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::json;
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::WebSocketStream;
+
+    async fn receive(
+        client: &mut WebSocketStream<tokio::io::DuplexStream>,
+    ) -> anyhow::Result<serde_json::Value> {
+        match client.next().await {
+            Some(Ok(Message::Text(text))) => Ok(serde_json::from_str(&text)?),
+            other => anyhow::bail!("unexpected message: {other:?}"),
+        }
+    }
+
+    let test_dir = TestDirectory::new("json-pubsub-websocket");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+    let db = server.database::<BasicSchema>("tests").await?;
+
+    let (client_stream, server_stream) = tokio::io::duplex(4096);
+    let task_server = server.clone();
+    tokio::spawn(async move {
+        let stream = WebSocketStream::from_raw_socket(server_stream, Role::Server, None).await;
+        task_server
+            .handle_json_pubsub_websocket(stream, "127.0.0.1:12345".parse().unwrap())
+            .await;
+    });
+    let mut client = WebSocketStream::from_raw_socket(client_stream, Role::Client, None).await;
+
+    client
+        .send(Message::Text(
+            json!({"id": 1, "type": "subscribe", "database": "tests", "topic": "chat"}).to_string(),
+        ))
+        .await?;
+    assert_eq!(receive(&mut client).await?, json!({"type": "ok", "id": 1}));
+
+    // Messages published by native clients are delivered as JSON.
+    db.publish(&"chat", &json!({"text": "hi"})).await?;
+    assert_eq!(
+        receive(&mut client).await?,
+        json!({"type": "message_received", "database": "tests", "topic": "chat", "payload": {"text": "hi"}})
+    );
+
+    // Messages published by JSON clients are delivered to native subscribers.
+    let subscriber = db.create_subscriber().await?;
+    subscriber.subscribe_to(&"chat").await?;
+    client
+        .send(Message::Text(
+            json!({"type": "publish", "database": "tests", "topic": "chat", "payload": [1, 2]})
+                .to_string(),
+        ))
+        .await?;
+    assert_eq!(receive(&mut client).await?, json!({"type": "ok"}));
+    let message = subscriber.receiver().receive_async().await?;
+    assert_eq!(message.payload::<Vec<u64>>()?, vec![1, 2]);
+    // The JSON client is also subscribed to the topic.
+    assert_eq!(receive(&mut client).await?["payload"], json!([1, 2]));
+
+    client
+        .send(Message::Text(
+            json!({"type": "subscribe", "database": "missing", "topic": "chat"}).to_string(),
+        ))
+        .await?;
+    assert_eq!(receive(&mut client).await?["type"], "error");
+    client.send(Message::Text(String::from("{}"))).await?;
+    assert_eq!(receive(&mut client).await?["type"], "error");
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg(feature = "hyper")]
+async fn metrics_endpoint_test() -> anyhow::Result<()> {
+    use bonsaidb_core::test_util::Basic;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn get(path: &str) -> anyhow::Result<String> {
+        let mut attempts = 0;
+        let mut connection = loop {
+            match TcpStream::connect("127.0.0.1:7025").await {
+                Ok(connection) => break connection,
+                Err(err) if attempts < 100 => {
+                    log::debug!("waiting for metrics listener: {err}");
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+        connection
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await?;
+        let mut response = String::new();
+        connection.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    let test_dir = TestDirectory::new("metrics-endpoint");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+    let db = server.database::<BasicSchema>("tests").await?;
+    Basic::new("metrics").push_into_async(&db).await?;
+
+    let task_server = server.clone();
+    tokio::spawn(async move { task_server.listen_for_metrics_on("127.0.0.1:7025").await });
+
+    let response = get("/metrics").await?;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\nbonsaidb_transactions_total "));
+    assert!(response.contains("# TYPE bonsaidb_server_request_duration_seconds histogram\n"));
+    assert!(response.contains("\nbonsaidb_server_connected_clients 0\n"));
+    assert!(get("/missing")
+        .await?
+        .starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    server.shutdown(None).await?;
+    Ok(())
+}
+
 struct TestHarness {
     _directory: TestDirectory,
     server: Server,
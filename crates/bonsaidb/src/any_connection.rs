@@ -1,4 +1,5 @@
 use bonsaidb_client::{AsyncClient, AsyncRemoteDatabase};
+use bonsaidb_core::admin::DatabaseQuota;
 use bonsaidb_core::async_trait::async_trait;
 use bonsaidb_core::connection::{
     self, AccessPolicy, AsyncConnection, AsyncLowLevelConnection, AsyncStorageConnection,
@@ -91,6 +92,64 @@ impl<B: Backend> AsyncStorageConnection for AnyServerConnection<B> {
         }
     }
 
+    async fn set_database_quota(
+        &self,
+        name: &str,
+        quota: DatabaseQuota,
+    ) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.set_database_quota(name, quota).await,
+            Self::Networked(client) => client.set_database_quota(name, quota).await,
+        }
+    }
+
+    async fn list_database_usage(
+        &self,
+    ) -> Result<Vec<connection::DatabaseUsage>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.list_database_usage().await,
+            Self::Networked(client) => client.list_database_usage().await,
+        }
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn list_authentication_tokens(
+        &self,
+        identity: IdentityReference<'_>,
+    ) -> Result<Vec<connection::AuthenticationTokenSummary>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.list_authentication_tokens(identity).await,
+            Self::Networked(client) => client.list_authentication_tokens(identity).await,
+        }
+    }
+
+    #[cfg(feature = "token-authentication")]
+    async fn revoke_authentication_token(&self, id: u64) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.revoke_authentication_token(id).await,
+            Self::Networked(client) => client.revoke_authentication_token(id).await,
+        }
+    }
+
+    async fn list_sessions(
+        &self,
+    ) -> Result<Vec<bonsaidb_core::connection::SessionSummary>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.list_sessions().await,
+            Self::Networked(client) => client.list_sessions().await,
+        }
+    }
+
+    async fn revoke_session(
+        &self,
+        id: bonsaidb_core::connection::SessionId,
+    ) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.revoke_session(id).await,
+            Self::Networked(client) => client.revoke_session(id).await,
+        }
+    }
+
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         match self {
             Self::Local(server) => server.list_available_schemas().await,
@@ -127,7 +186,45 @@ impl<B: Backend> AsyncStorageConnection for AnyServerConnection<B> {
         }
     }
 
-    #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
+    #[cfg(feature = "totp-authentication")]
+    async fn enroll_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<bonsaidb_core::admin::TotpSecret, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.enroll_user_totp(user).await,
+            Self::Networked(client) => client.enroll_user_totp(user).await,
+        }
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn confirm_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+        code: bonsaidb_core::connection::SensitiveString,
+    ) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.confirm_user_totp(user, code).await,
+            Self::Networked(client) => client.confirm_user_totp(user, code).await,
+        }
+    }
+
+    #[cfg(feature = "totp-authentication")]
+    async fn remove_user_totp<'user, U: Nameable<'user, u64> + Send + Sync>(
+        &self,
+        user: U,
+    ) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.remove_user_totp(user).await,
+            Self::Networked(client) => client.remove_user_totp(user).await,
+        }
+    }
+
+    #[cfg(any(
+        feature = "token-authentication",
+        feature = "password-hashing",
+        feature = "jwt-authentication"
+    ))]
     async fn authenticate(
         &self,
         authentication: bonsaidb_core::connection::Authentication,
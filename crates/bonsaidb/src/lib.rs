@@ -34,8 +34,11 @@ mod any_connection;
 pub mod cli;
 
 /// `VaultKeyStorage` implementors.
-#[cfg(feature = "keystorage-s3")]
+#[cfg(any(feature = "keystorage-command", feature = "keystorage-s3"))]
 pub mod keystorage {
+    #[cfg(feature = "keystorage-command")]
+    #[doc(inline)]
+    pub use bonsaidb_keystorage_command as command;
     #[cfg(feature = "keystorage-s3")]
     #[doc(inline)]
     pub use bonsaidb_keystorage_s3 as s3;
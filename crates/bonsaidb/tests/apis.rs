@@ -1,5 +1,7 @@
 //! Tests invoking an API defined in a custom backend.
 
+use std::sync::{Arc, Mutex};
+
 use bonsaidb::client::url::Url;
 use bonsaidb::client::AsyncClient;
 use bonsaidb::core::api::{Api, Infallible};
@@ -9,8 +11,10 @@ use bonsaidb::local::config::Builder;
 use bonsaidb::server::api::Handler;
 use bonsaidb::server::{Backend, CustomServer, DefaultPermissions, ServerConfiguration};
 use bonsaidb_core::api::ApiName;
+use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::schema::Qualified;
 use bonsaidb_server::api::{HandlerResult, HandlerSession};
+use bonsaidb_server::middleware::{ApiRequest, Middleware};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default)]
@@ -36,7 +40,8 @@ async fn custom_api() -> anyhow::Result<()> {
         .certificate_chain()
         .await?
         .into_end_entity_certificate();
-    tokio::spawn(async move { server.listen_on(12346).await });
+    let task_server = server.clone();
+    tokio::spawn(async move { task_server.listen_on(12346).await });
 
     let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12346")?)
         .with_api::<SetValue>()
@@ -48,9 +53,100 @@ async fn custom_api() -> anyhow::Result<()> {
     let old_value = client.send_api_request(&SetValue { new_value: 2 }).await?;
     assert_eq!(old_value, Some(1));
 
+    // Requests for APIs the server doesn't know about are recorded under a
+    // single label rather than the name the client sent.
+    assert!(client.send_api_request(&Unregistered).await.is_err());
+    let metrics = server.encode_metrics();
+    assert!(metrics.contains("api=\"unknown\""));
+    assert!(!metrics.contains("unregistered"));
+
     Ok(())
 }
 
+#[tokio::test]
+async fn custom_api_middleware() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("custom_api_middleware.bonsaidb");
+    let middleware = SetValueMiddleware::default();
+    let server = CustomServer::<CustomBackend>::open(
+        ServerConfiguration::new(&dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .with_api::<SetValueHandler, _>()?
+            .with_middleware(middleware.clone())
+            .with_schema::<Basic>()?,
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    tokio::spawn(async move { server.listen_on(12347).await });
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12347")?)
+        .with_api::<SetValue>()
+        .with_certificate(certificate)
+        .build()?;
+
+    // The middleware doubles each value before it is handled.
+    let old_value = client.send_api_request(&SetValue { new_value: 1 }).await?;
+    assert_eq!(old_value, None);
+    let old_value = client.send_api_request(&SetValue { new_value: 2 }).await?;
+    assert_eq!(old_value, Some(2));
+
+    // The middleware rejects 0, which prevents the handler from being invoked.
+    assert!(client
+        .send_api_request(&SetValue { new_value: 0 })
+        .await
+        .is_err());
+    let old_value = client.send_api_request(&SetValue { new_value: 3 }).await?;
+    assert_eq!(old_value, Some(4));
+
+    assert_eq!(
+        &*middleware.responses.lock().unwrap(),
+        &[None, Some(2), Some(4)]
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone)]
+struct SetValueMiddleware {
+    responses: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+#[async_trait]
+impl Middleware<CustomBackend> for SetValueMiddleware {
+    async fn before_request(
+        &self,
+        request: &mut ApiRequest<'_, CustomBackend>,
+    ) -> Result<(), bonsaidb_server::Error> {
+        if let Some(set_value) = request.decode::<SetValue>()? {
+            if set_value.new_value == 0 {
+                return Err(bonsaidb_server::Error::from(bonsaidb_core::Error::other(
+                    "middleware",
+                    "0 is not allowed",
+                )));
+            }
+            request.replace(&SetValue {
+                new_value: set_value.new_value * 2,
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn after_request(
+        &self,
+        request: &ApiRequest<'_, CustomBackend>,
+        result: &Result<Bytes, bonsaidb_server::Error>,
+    ) {
+        if let Ok(response) = result {
+            if let Some(Ok(old_value)) = request.decode_response::<SetValue>(response).unwrap() {
+                self.responses.lock().unwrap().push(old_value);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SetValue {
     new_value: u64,
@@ -65,11 +161,23 @@ impl Api for SetValue {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct Unregistered;
+
+impl Api for Unregistered {
+    type Error = Infallible;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::private("unregistered")
+    }
+}
+
 #[derive(Debug)]
 struct SetValueHandler;
 
 #[async_trait]
-impl Handler<SetValue, CustomBackend> for SetValueHandler {
+impl Handler<SetValue, CustomServer<CustomBackend>> for SetValueHandler {
     async fn handle(
         session: HandlerSession<'_, CustomBackend>,
         request: SetValue,
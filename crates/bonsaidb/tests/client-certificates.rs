@@ -0,0 +1,110 @@
+//! Tests authenticating QUIC clients using TLS client certificates.
+
+use std::time::Duration;
+
+use bonsaidb::client::url::Url;
+use bonsaidb::client::AsyncClient;
+use bonsaidb::core::actionable::Permissions;
+use bonsaidb::core::async_trait::async_trait;
+use bonsaidb::core::connection::{AsyncStorageConnection, AuthenticationMethod, IdentityReference};
+use bonsaidb::core::permissions::bonsai::{BonsaiAction, ServerAction};
+use bonsaidb::core::permissions::Statement;
+use bonsaidb::core::test_util::TestDirectory;
+use bonsaidb::local::config::Builder;
+use bonsaidb::server::client_certificate::{ClientCertificate, ClientCertificateConfiguration};
+use bonsaidb::server::fabruic::KeyPair;
+use bonsaidb::server::{
+    Backend, BackendError, CustomServer, DefaultPermissions, ServerConfiguration,
+};
+
+#[derive(Debug, Default)]
+struct CertificateBackend;
+
+#[async_trait]
+impl Backend for CertificateBackend {
+    type ClientData = ();
+    type Error = bonsaidb::core::api::Infallible;
+
+    async fn identity_for_client_certificate(
+        &self,
+        certificate: &ClientCertificate,
+        _server: &CustomServer<Self>,
+    ) -> Result<Option<IdentityReference<'static>>, BackendError<Self::Error>> {
+        if certificate
+            .dns_names
+            .iter()
+            .any(|name| name == "ecton.example")
+        {
+            Ok(Some(IdentityReference::user("ecton")?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[tokio::test]
+async fn quic() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("client-certificates-quic.bonsaidb");
+    let trusted = KeyPair::new_self_signed("ecton.example");
+    let untrusted = KeyPair::new_self_signed("ecton.example");
+    let server = CustomServer::<CertificateBackend>::open(
+        ServerConfiguration::new(&dir)
+            .default_permissions(Permissions::from(
+                Statement::for_any()
+                    .allowing(&BonsaiAction::Server(ServerAction::Connect))
+                    .allowing(&BonsaiAction::Server(ServerAction::Authenticate(
+                        AuthenticationMethod::ClientCertificate,
+                    ))),
+            ))
+            .authenticated_permissions(DefaultPermissions::AllowAll)
+            .client_certificates(
+                ClientCertificateConfiguration::new(rustls::RootCertStore::empty())
+                    .trusted_root(trusted.end_entity_certificate())?,
+            ),
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    server.create_user("ecton").await?;
+    tokio::spawn(async move {
+        server.listen_on(6010).await?;
+        Result::<(), anyhow::Error>::Ok(())
+    });
+    // Give the server time to listen
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let url = Url::parse("bonsaidb://localhost:6010")?;
+
+    // Without a certificate, the client connects using the default
+    // permissions.
+    let anonymous = AsyncClient::build(url.clone())
+        .with_certificate(certificate.clone())
+        .build()?;
+    match anonymous.create_user("anonymous").await {
+        Err(bonsaidb::core::Error::PermissionDenied(_)) => {}
+        other => unreachable!("anonymous clients should not be able to create users: {other:?}"),
+    }
+
+    // A certificate issued by a trusted root authenticates the client as the
+    // user returned by the backend.
+    let authenticated = AsyncClient::build(url.clone())
+        .with_certificate(certificate.clone())
+        .with_client_certificate(trusted)
+        .build()?;
+    authenticated
+        .create_user("authenticated")
+        .await
+        .expect("client certificate should authenticate the connection");
+
+    // A certificate that isn't issued by a trusted root rejects the connection.
+    let rejected = AsyncClient::build(url)
+        .with_certificate(certificate)
+        .with_client_certificate(untrusted)
+        .build()?;
+    assert!(rejected.create_user("rejected").await.is_err());
+
+    Ok(())
+}
@@ -426,6 +426,108 @@ async fn authenticated_permissions_test() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "totp-authentication")]
+#[tokio::test]
+async fn totp_authentication_test() -> anyhow::Result<()> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use bonsaidb::core::admin::TotpSecret;
+    use bonsaidb_core::connection::{AsyncStorageConnection, HasSession, SessionAuthentication};
+
+    let database_path = TestDirectory::new("totp-authentication");
+    let server = Server::open(
+        ServerConfiguration::new(&database_path)
+            .default_permissions(Permissions::from(
+                Statement::for_any()
+                    .allowing(&BonsaiAction::Server(ServerAction::Connect))
+                    .allowing(&BonsaiAction::Server(ServerAction::Authenticate(
+                        AuthenticationMethod::PasswordHash,
+                    ))),
+            ))
+            .authenticated_permissions(DefaultPermissions::AllowAll),
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+
+    server.create_user("ecton").await?;
+    server
+        .set_user_password("ecton", SensitiveString::from("hunter2"))
+        .await?;
+    let secret = server.enroll_user_totp("ecton").await?;
+    let step = TotpSecret::step_at(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+    server
+        .confirm_user_totp("ecton", SensitiveString(secret.code_at(step)))
+        .await?;
+    // A confirmed enrollment can't be replaced without removing it first.
+    assert!(matches!(
+        server.enroll_user_totp("ecton").await,
+        Err(bonsaidb_core::Error::TotpAlreadyEnrolled)
+    ));
+    tokio::spawn(async move {
+        server.listen_on(6004).await?;
+        Result::<(), anyhow::Error>::Ok(())
+    });
+    // Give the server time to listen
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let url = Url::parse("bonsaidb://localhost:6004")?;
+    let client = AsyncClient::build(url)
+        .with_certificate(certificate)
+        .build()?;
+
+    // The password alone only yields a session pending the second factor.
+    let pending = client
+        .authenticate_with_password("ecton", SensitiveString::from("hunter2"))
+        .await?;
+    assert!(matches!(
+        pending.session().map(|session| &session.authentication),
+        Some(SessionAuthentication::TotpChallenge { .. })
+    ));
+    assert!(matches!(
+        pending.create_user("otheruser").await,
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+
+    // An invalid code consumes the pending session.
+    assert!(matches!(
+        pending
+            .authenticate_with_totp(SensitiveString::from("invalid"))
+            .await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+    let code = SensitiveString(secret.code_at(step + 1));
+    assert!(matches!(
+        pending.authenticate_with_totp(code.clone()).await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+
+    let authenticated = client
+        .authenticate_with_password("ecton", SensitiveString::from("hunter2"))
+        .await?
+        .authenticate_with_totp(code.clone())
+        .await?;
+    authenticated
+        .create_user("otheruser")
+        .await
+        .expect("should be able to create user after completing authentication");
+
+    // A code can only be used once.
+    assert!(matches!(
+        client
+            .authenticate_with_password("ecton", SensitiveString::from("hunter2"))
+            .await?
+            .authenticate_with_totp(code)
+            .await,
+        Err(bonsaidb_core::Error::InvalidCredentials)
+    ));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn client_disconnection() -> anyhow::Result<()> {
     use bonsaidb_core::connection::AsyncStorageConnection;
@@ -0,0 +1,104 @@
+//! Tests replaying durable `PubSub` topics after a client reconnects.
+
+use std::time::Duration;
+
+use bonsaidb::client::url::Url;
+use bonsaidb::client::AsyncClient;
+use bonsaidb::core::connection::AsyncStorageConnection;
+use bonsaidb::core::pubsub::durable::{self, AsyncDurablePubSub};
+use bonsaidb::core::schema::{Qualified, Schema, SchemaName, Schematic};
+use bonsaidb::core::test_util::TestDirectory;
+use bonsaidb::local::config::Builder;
+use bonsaidb::server::{BonsaiListenConfig, DefaultPermissions, Server, ServerConfiguration};
+
+#[derive(Debug)]
+struct DurableSchema;
+
+impl Schema for DurableSchema {
+    fn schema_name() -> SchemaName {
+        SchemaName::new("bonsaidb", "durable-pubsub-tests")
+    }
+
+    fn define_collections(schema: &mut Schematic) -> Result<(), bonsaidb::core::Error> {
+        durable::define_collections(schema)
+    }
+}
+
+async fn open_server(dir: &TestDirectory) -> anyhow::Result<Server> {
+    let server = Server::open(
+        ServerConfiguration::new(dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .with_schema::<DurableSchema>()?,
+    )
+    .await?;
+    let listener = server.clone();
+    tokio::spawn(async move {
+        listener
+            .listen_on(BonsaiListenConfig::from(6011).reuse_address(true))
+            .await
+            .unwrap();
+    });
+    // Give the listener a moment to become established.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    Ok(server)
+}
+
+#[tokio::test]
+async fn replay_after_reconnect() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("durable-pubsub-reconnect.bonsaidb");
+    let server = Server::open(ServerConfiguration::new(&dir)).await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    server.shutdown(None).await?;
+    drop(server);
+
+    let server = open_server(&dir).await?;
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:6011")?)
+        .with_certificate(certificate)
+        .build()?;
+    let db = client
+        .create_database::<DurableSchema>("durable", true)
+        .await?;
+
+    db.publish_durable(&"topic", &0_u32).await?;
+    let mut subscriber = db.subscribe_as_consumer("worker", &"topic").await?;
+    let first = subscriber.receive().await?;
+    assert_eq!(first.payload::<u32>()?, 0);
+    db.acknowledge_durable("worker", &"topic", first.offset)
+        .await?;
+
+    // Restart the server, publishing messages while the client is
+    // disconnected. Regular subscribers would never receive these messages.
+    server.shutdown(None).await?;
+    drop(server);
+    // Give time for the endpoint to completely close.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let server = open_server(&dir).await?;
+    let server_db = server.database::<DurableSchema>("durable").await?;
+    for value in 1_u32..3 {
+        server_db.publish_durable(&"topic", &value).await?;
+    }
+
+    drop(subscriber);
+
+    // Once reconnected, the consumer resumes after its acknowledged offset
+    // and receives the messages published while it was disconnected.
+    let mut subscriber = db.subscribe_as_consumer("worker", &"topic").await?;
+    for expected in 1_u32..3 {
+        let message = subscriber.receive().await?;
+        assert_eq!(message.payload::<u32>()?, expected);
+        db.acknowledge_durable("worker", &"topic", message.offset)
+            .await?;
+    }
+    assert!(subscriber.try_receive().await?.is_none());
+
+    // New messages continue to be delivered to the resumed subscriber.
+    server_db.publish_durable(&"topic", &3_u32).await?;
+    assert_eq!(subscriber.receive().await?.payload::<u32>()?, 3);
+
+    server.shutdown(None).await?;
+    Ok(())
+}
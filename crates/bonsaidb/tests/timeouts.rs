@@ -101,7 +101,7 @@ fn blocking_quic_connect_timeout() -> anyhow::Result<()> {
 struct LongCall;
 
 #[async_trait]
-impl Handler<LongCall> for LongCall {
+impl Handler<LongCall, Server> for LongCall {
     async fn handle(_session: HandlerSession<'_>, _request: LongCall) -> HandlerResult<LongCall> {
         tokio::time::sleep(Duration::from_secs(10)).await;
         Ok(())
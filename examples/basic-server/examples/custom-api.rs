@@ -48,7 +48,7 @@ pub struct ExampleHandler;
 /// causes `PingHandler` to be generated with a single method and no implicit
 /// permission handling.
 #[async_trait]
-impl Handler<Ping> for ExampleHandler {
+impl Handler<Ping, Server> for ExampleHandler {
     async fn handle(_session: HandlerSession<'_>, _request: Ping) -> HandlerResult<Ping> {
         Ok(Pong)
     }
@@ -75,7 +75,7 @@ pub async fn increment_counter<S: AsyncStorageConnection<Database = C>, C: Async
 }
 
 #[async_trait]
-impl Handler<IncrementCounter> for ExampleHandler {
+impl Handler<IncrementCounter, Server> for ExampleHandler {
     async fn handle(
         session: HandlerSession<'_>,
         request: IncrementCounter,
@@ -68,6 +68,7 @@ impl publish::Config for Config {
             String::from("crates/bonsaidb-local"),
             String::from("crates/bonsaidb-server"),
             String::from("crates/bonsaidb-client"),
+            String::from("crates/bonsaidb-keystorage-command"),
             String::from("crates/bonsaidb-keystorage-s3"),
             String::from("crates/bonsaidb"),
         ]
@@ -197,6 +198,10 @@ fn all_tests() -> &'static [TestSuite] {
             cargo_args: "--package bonsaidb-files --no-default-features",
             toolchain: "stable",
         },
+        TestSuite {
+            cargo_args: "--package bonsaidb-jobs --no-default-features",
+            toolchain: "stable",
+        },
         TestSuite {
             cargo_args: "--package bonsaidb-macros",
             toolchain: "stable",